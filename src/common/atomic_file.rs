@@ -0,0 +1,43 @@
+use std::{io::Result, path::Path};
+
+use tokio::fs;
+
+/// Writes the bytes into a temporary file next to the file and renames it over the file, so
+/// that readers never see a partially written file and a failed write keeps the old one.
+pub async fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+
+    fs::write(&temp_path, bytes).await?;
+    fs::rename(&temp_path, path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_write_atomic() {
+        let path = std::env::temp_dir().join(format!("tunnelize-atomic-{}.json", Uuid::new_v4()));
+
+        write_atomic(&path, b"first").await.unwrap();
+        write_atomic(&path, b"second").await.unwrap();
+
+        let written = fs::read(&path).await.unwrap();
+        fs::remove_file(&path).await.unwrap();
+
+        assert_eq!(written, b"second");
+        assert!(!PathBuf::from(format!("{}.tmp", path.display())).exists());
+    }
+
+    #[tokio::test]
+    async fn test_write_atomic_to_missing_directory() {
+        let path = std::env::temp_dir()
+            .join(format!("tunnelize-missing-{}", Uuid::new_v4()))
+            .join("file.json");
+
+        assert!(write_atomic(&path, b"data").await.is_err());
+    }
+}
@@ -1,6 +1,8 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use uuid::Uuid;
 
+use super::config_format::ConfigFormat;
+
 #[derive(Parser, Debug)]
 #[command(
     name = "Tunnelize",
@@ -20,6 +22,16 @@ pub enum Commands {
     Init {
         #[command(subcommand)]
         command: Option<InitCommands>,
+        #[arg(
+            short = 'f',
+            long,
+            global = true,
+            value_enum,
+            default_value_t = ConfigFormat::Json,
+            help = "Format of the configuration file",
+            long_help = "Format in which the configuration file is written. File is named tunnelize.json, tunnelize.toml or tunnelize.yaml accordingly."
+        )]
+        format: ConfigFormat,
     },
     #[command(about = "Start the Tunnelize server")]
     Server {
@@ -40,6 +52,13 @@ pub enum Commands {
             long_help = "Specify a custom path to the tunnel configuration file. If not provided, default configuration will be used."
         )]
         config: Option<String>,
+        #[arg(
+            long,
+            env = "TUNNELIZE_PROFILE",
+            help = "Name of the tunnel profile to use",
+            long_help = "Name of the tunnel profile from the configuration file to use. Required if the configuration file has more than one profile and no tunnel section."
+        )]
+        profile: Option<String>,
         #[arg(
             long,
             short = 'v',
@@ -48,6 +67,40 @@ pub enum Commands {
             long_help = "Enable detailed logging output for debugging purposes"
         )]
         verbose: bool,
+        #[command(flatten)]
+        run: TunnelRunArgs,
+        #[command(subcommand)]
+        command: Option<TunnelCommands>,
+    },
+    #[command(
+        about = "Expose a local HTTP server without a configuration file",
+        long_about = "Start a tunnel for a single local HTTP server. Endpoint is picked from the server's public endpoint configuration."
+    )]
+    Http {
+        #[command(flatten)]
+        tunnel: QuickTunnelArgs,
+        #[arg(short = 'n', long, help = "Desired hostname name on the server")]
+        name: Option<String>,
+    },
+    #[command(
+        about = "Expose a local TCP service without a configuration file",
+        long_about = "Start a tunnel for a single local TCP service. Endpoint is picked from the server's public endpoint configuration."
+    )]
+    Tcp {
+        #[command(flatten)]
+        tunnel: QuickTunnelArgs,
+        #[arg(short = 'p', long, help = "Desired port on the server")]
+        desired_port: Option<u16>,
+    },
+    #[command(
+        about = "Expose a local UDP service without a configuration file",
+        long_about = "Start a tunnel for a single local UDP service. Endpoint is picked from the server's public endpoint configuration."
+    )]
+    Udp {
+        #[command(flatten)]
+        tunnel: QuickTunnelArgs,
+        #[arg(short = 'p', long, help = "Desired port on the server")]
+        desired_port: Option<u16>,
     },
     #[command(about = "Monitor and manage running tunnels and connections")]
     Monitor {
@@ -60,7 +113,134 @@ pub enum Commands {
             long_help = "Specify a custom path to the tunnel configuration file for monitoring operations"
         )]
         config: Option<String>,
+        #[arg(
+            long,
+            env = "TUNNELIZE_PROFILE",
+            help = "Name of the tunnel profile to use",
+            long_help = "Name of the tunnel profile from the configuration file to use. Required if the configuration file has more than one profile and no tunnel section."
+        )]
+        profile: Option<String>,
     },
+    #[command(about = "Check tunnelize configuration")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommands {
+    #[command(
+        about = "Validate configuration and show its effective values",
+        long_about = "Run all validation rules on the configuration and exit with non-zero code if it is invalid. If it is valid, print the configuration with all default values filled in and the listeners the server would bind. Nothing is started or bound."
+    )]
+    Check {
+        #[arg(
+            short = 'c',
+            long,
+            help = "Path to the configuration file",
+            long_help = "Specify a custom path to the configuration file to check. If not provided, default configuration will be used."
+        )]
+        config: Option<String>,
+        #[arg(
+            long,
+            env = "TUNNELIZE_PROFILE",
+            help = "Name of the tunnel profile to check",
+            long_help = "Name of the tunnel profile from the configuration file to check. If not set, all profiles are checked."
+        )]
+        profile: Option<String>,
+        #[arg(
+            short = 'f',
+            long,
+            value_enum,
+            help = "Format of the printed configuration",
+            long_help = "Format in which the effective configuration is printed. Defaults to the format of the configuration file."
+        )]
+        format: Option<ConfigFormat>,
+    },
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct QuickTunnelArgs {
+    #[arg(help = "Local port to forward the traffic to")]
+    pub port: u16,
+    #[arg(
+        short = 'a',
+        long,
+        default_value = "localhost",
+        help = "Local address to forward the traffic to"
+    )]
+    pub address: String,
+    #[arg(
+        short = 's',
+        long,
+        env = "TUNNELIZE_SERVER",
+        default_value = "localhost",
+        help = "Address of the tunnelize server in host[:port] form"
+    )]
+    pub server: String,
+    #[arg(
+        short = 'k',
+        long,
+        env = "TUNNELIZE_KEY",
+        hide_env_values = true,
+        help = "Tunnel key to use to authenticate with server"
+    )]
+    pub key: Option<String>,
+    #[arg(
+        short = 't',
+        long,
+        env = "TUNNELIZE_TLS",
+        help = "Use TLS to connect to server"
+    )]
+    pub tls: bool,
+    #[arg(
+        long,
+        env = "TUNNELIZE_CA",
+        help = "Path to the custom CA certificate file for TLS"
+    )]
+    pub ca: Option<String>,
+    #[arg(
+        short = 'e',
+        long,
+        help = "Name of the server endpoint to use",
+        long_help = "Name of the server endpoint to use. If not provided, first endpoint of the matching type is used."
+    )]
+    pub endpoint: Option<String>,
+    #[arg(
+        long,
+        short = 'v',
+        default_value_t = false,
+        help = "Enable verbose logging"
+    )]
+    pub verbose: bool,
+    #[command(flatten)]
+    pub run: TunnelRunArgs,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct TunnelRunArgs {
+    #[arg(
+        long,
+        help = "Run a command while the tunnel is running",
+        long_help = "Run a command once the tunnel is established and stop the tunnel when it exits. Assigned addresses are exported as TUNNELIZE_URL_<ENDPOINT> environment variables and exit code of the command is passed through."
+    )]
+    pub exec: Option<String>,
+    #[arg(
+        short = 'o',
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Format of the tunnel output",
+        long_help = "Format of the tunnel output. In json mode tunnel status is printed as a single JSON line on every change instead of text messages."
+    )]
+    pub output: OutputFormat,
+    #[arg(
+        long,
+        help = "Path to the file to keep the tunnel status in",
+        long_help = "Path to the file the tunnel writes its status to as JSON on every change. Overrides status_file from the configuration."
+    )]
+    pub status_file: Option<String>,
 }
 
 #[derive(Subcommand, Debug, serde::Serialize, serde::Deserialize, Clone)]
@@ -102,6 +282,14 @@ pub enum InitCommands {
             long_help = "Provide an authentication key for secure tunnel registration with the server"
         )]
         key: Option<String>,
+        #[arg(
+            short = 'g',
+            long,
+            default_value = "false",
+            help = "Generate a key pair to authenticate with server",
+            long_help = "Generate an Ed25519 key pair, set it as private_key_path and print the public key to add to the authorized keys file on the server"
+        )]
+        generate_key: bool,
     },
     #[command(
         about = "Initialize server configuration",
@@ -110,6 +298,73 @@ pub enum InitCommands {
     Server,
 }
 
+#[derive(Subcommand, Debug, Clone)]
+pub enum TunnelCommands {
+    #[command(about = "Show status and assigned addresses of the running tunnel")]
+    Status,
+    #[command(
+        about = "Add a proxy to the running tunnel",
+        long_about = "Add a proxy to the running tunnel without restarting it. Proxy is not saved to the configuration file."
+    )]
+    AddProxy {
+        #[arg(help = "Type of the proxy")]
+        proxy_type: ProxyType,
+        #[arg(help = "Local port to forward the traffic to")]
+        port: u16,
+        #[arg(
+            short = 'a',
+            long,
+            default_value = "localhost",
+            help = "Local address to forward the traffic to"
+        )]
+        address: String,
+        #[arg(
+            short = 'e',
+            long,
+            help = "Name of the server endpoint to use",
+            long_help = "Name of the server endpoint to use. If not provided, proxy type is used as the endpoint name."
+        )]
+        endpoint: Option<String>,
+        #[arg(
+            short = 'n',
+            long,
+            help = "Desired hostname for http proxies",
+            conflicts_with = "desired_port"
+        )]
+        desired_name: Option<String>,
+        #[arg(short = 'p', long, help = "Desired port for tcp and udp proxies")]
+        desired_port: Option<u16>,
+        #[arg(
+            long,
+            help = "Name of the pool to join, sharing the hostname or port with other tunnels",
+            conflicts_with = "desired_name"
+        )]
+        pool: Option<String>,
+    },
+    #[command(about = "Remove a proxy from the running tunnel")]
+    RemoveProxy { id: Uuid },
+    #[command(about = "List HTTP requests captured by the request inspector")]
+    Requests,
+    #[command(about = "Show a captured HTTP request with its response")]
+    Request { id: Uuid },
+    #[command(about = "Send a captured HTTP request to the local server again")]
+    Replay { id: Uuid },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum ProxyType {
+    Http,
+    Tcp,
+    Udp,
+}
+
 #[derive(Subcommand, Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub enum MonitorCommands {
     #[command(about = "Display system information and resources")]
@@ -132,6 +387,17 @@ pub enum MonitorCommands {
     GetLink { id: Uuid },
     #[command(about = "Disconnect and close a specific link")]
     DisconnectLink { id: Uuid },
+    #[command(about = "Create a signed tunnel token which expires")]
+    CreateToken {
+        #[arg(help = "Identity of the tunnel using the token")]
+        identity: String,
+        #[arg(long, default_value_t = 24, help = "Hours until the token expires")]
+        expires_in_hours: u64,
+        #[arg(long, help = "Access policy of the token as JSON")]
+        policy: Option<String>,
+    },
+    #[command(about = "Revoke a tunnel token and disconnect tunnels using it")]
+    RevokeToken { id: Uuid },
 }
 
 pub fn parse_command() -> Option<Commands> {
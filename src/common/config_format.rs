@@ -0,0 +1,200 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::{Serialize, de::DeserializeOwned};
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Detects format from the file extension, files without a known extension are read as JSON.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("toml") => ConfigFormat::Toml,
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            ConfigFormat::Json => "json",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Yaml => "yaml",
+        }
+    }
+
+    /// Parses the text in this format. Errors contain line and column where parsing failed.
+    pub fn parse<T: DeserializeOwned>(&self, text: &str) -> Result<T, String> {
+        match self {
+            ConfigFormat::Json => {
+                serde_json::from_str(&strip_json_comments(text)).map_err(|e| e.to_string())
+            }
+            ConfigFormat::Toml => toml::from_str(text).map_err(|e| e.to_string().trim().to_owned()),
+            ConfigFormat::Yaml => serde_norway::from_str(text).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn serialize<T: Serialize>(&self, value: &T) -> Result<String, String> {
+        match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(value).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::to_string_pretty(value).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_norway::to_string(value).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Replaces `//` and `/* */` comments with spaces. Newlines are kept so that line and column
+/// numbers in parsing errors still match the original text.
+pub fn strip_json_comments(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            result.push(c);
+
+            match c {
+                '\\' => result.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                result.push(c);
+            }
+            ('/', Some('/')) => {
+                result.push(' ');
+
+                while let Some(&next) = chars.peek() {
+                    if next == '\n' {
+                        break;
+                    }
+
+                    chars.next();
+                    result.push(' ');
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                result.push_str("  ");
+
+                let mut previous = ' ';
+
+                for next in chars.by_ref() {
+                    result.push(if next == '\n' { '\n' } else { ' ' });
+
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+
+                    previous = next;
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("tunnelize.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config/tunnelize.YML")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("tunnelize.json")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("tunnelize")),
+            ConfigFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_strip_json_comments() {
+        let text = "{\n  // name\n  \"url\": \"http://a//b\", /* multi\n line */ \"x\": 1\n}";
+        let stripped = strip_json_comments(text);
+
+        assert_eq!(stripped.lines().count(), text.lines().count());
+        assert_eq!(
+            serde_json::from_str::<Value>(&stripped).unwrap(),
+            json!({ "url": "http://a//b", "x": 1 })
+        );
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        let expected = json!({ "tunnel": { "server_address": "localhost", "proxies": [] } });
+
+        let json = "{\n  // tunnel\n  \"tunnel\": { \"server_address\": \"localhost\", \"proxies\": [] }\n}";
+        let toml = "# tunnel\n[tunnel]\nserver_address = \"localhost\"\nproxies = []\n";
+        let yaml = "# tunnel\ntunnel:\n  server_address: localhost\n  proxies: []\n";
+
+        assert_eq!(ConfigFormat::Json.parse::<Value>(json).unwrap(), expected);
+        assert_eq!(ConfigFormat::Toml.parse::<Value>(toml).unwrap(), expected);
+        assert_eq!(ConfigFormat::Yaml.parse::<Value>(yaml).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_errors_contain_line() {
+        let json = "{\n  \"tunnel\": {\n    \"server_port\": \"abc\"\n  }\n}";
+        let toml = "[tunnel]\nserver_address = \n";
+        let yaml = "tunnel:\n  server_address: localhost\n   proxies: []\n";
+
+        assert!(
+            ConfigFormat::Json
+                .parse::<Value>("{\n\n  \"a\": 1,,\n}")
+                .unwrap_err()
+                .contains("line 3")
+        );
+        assert!(
+            ConfigFormat::Json
+                .parse::<std::collections::HashMap<String, std::collections::HashMap<String, u16>>>(
+                    json
+                )
+                .unwrap_err()
+                .contains("line 3")
+        );
+        assert!(
+            ConfigFormat::Toml
+                .parse::<Value>(toml)
+                .unwrap_err()
+                .contains("line 2")
+        );
+        assert!(
+            ConfigFormat::Yaml
+                .parse::<Value>(yaml)
+                .unwrap_err()
+                .contains("line 3")
+        );
+    }
+}
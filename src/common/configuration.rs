@@ -9,7 +9,26 @@ use super::{
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum ServerEncryption {
     None,
-    Tls { cert_path: String, key_path: String },
+    Tls {
+        cert_path: String,
+        key_path: String,
+        /// CA which must sign client certificates. If set, clients without a valid certificate
+        /// are rejected during the TLS handshake.
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        client_ca_path: Option<String>,
+    },
+}
+
+impl ServerEncryption {
+    pub fn requires_client_certificate(&self) -> bool {
+        matches!(
+            self,
+            ServerEncryption::Tls {
+                client_ca_path: Some(_),
+                ..
+            }
+        )
+    }
 }
 
 impl Validatable for ServerEncryption {
@@ -17,10 +36,15 @@ impl Validatable for ServerEncryption {
         if let ServerEncryption::Tls {
             cert_path,
             key_path,
+            client_ca_path,
         } = &self
         {
             result.validate_rule::<FileMustExist>("cert_path", cert_path);
             result.validate_rule::<FileMustExist>("key_path", key_path);
+
+            if let Some(client_ca_path) = client_ca_path {
+                result.validate_rule::<FileMustExist>("client_ca_path", client_ca_path);
+            }
         }
     }
 }
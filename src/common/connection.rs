@@ -7,7 +7,7 @@ use bytes::BytesMut;
 use log::debug;
 use serde::{Serialize, de::DeserializeOwned};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt, Result},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, Result},
     net::TcpStream,
     time::timeout,
 };
@@ -16,6 +16,9 @@ use super::{
     channel_socket::ChannelSocket,
     data_bridge::DataBridge,
     data_request::DataRequest,
+    encryption::get_certificate_names,
+    metered_stream::MeteredStream,
+    multiplexer::MuxStream,
     transport::{MessageError, read_message, write_message},
     udp_client::UdpClient,
 };
@@ -23,6 +26,10 @@ use super::{
 use tokio_rustls::client::TlsStream as ClientTlsStream;
 use tokio_rustls::server::TlsStream as ServerTlsStream;
 
+pub trait AsyncStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> AsyncStream for T {}
+
 #[derive(Debug)]
 pub enum Connection {
     TcpStream(TcpStream),
@@ -30,6 +37,8 @@ pub enum Connection {
     TlsStreamServer(ServerTlsStream<TcpStream>),
     TlsStreamClient(ClientTlsStream<TcpStream>),
     ChannelSocket(ChannelSocket),
+    MuxStream(MuxStream),
+    MeteredStream(MeteredStream),
 }
 
 impl From<TcpStream> for Connection {
@@ -62,12 +71,26 @@ impl From<ChannelSocket> for Connection {
     }
 }
 
+impl From<MuxStream> for Connection {
+    fn from(stream: MuxStream) -> Self {
+        Self::MuxStream(stream)
+    }
+}
+
+impl From<MeteredStream> for Connection {
+    fn from(stream: MeteredStream) -> Self {
+        Self::MeteredStream(stream)
+    }
+}
+
 impl Connection {
     async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         match self {
             Self::TcpStream(stream) => stream.read(buf).await,
             Self::TlsStreamServer(stream) => stream.read(buf).await,
             Self::TlsStreamClient(stream) => stream.read(buf).await,
+            Self::MuxStream(stream) => stream.read(buf).await,
+            Self::MeteredStream(stream) => stream.read(buf).await,
             Self::UdpClient(client) => client.read(buf).await,
             Self::ChannelSocket(socket) => {
                 let data = socket.receive().await?;
@@ -96,6 +119,8 @@ impl Connection {
             Self::TcpStream(stream) => stream.write_all(buf).await,
             Self::TlsStreamServer(stream) => stream.write_all(buf).await,
             Self::TlsStreamClient(stream) => stream.write_all(buf).await,
+            Self::MuxStream(stream) => stream.write_all(buf).await,
+            Self::MeteredStream(stream) => stream.write_all(buf).await,
             Self::UdpClient(client) => client.write(buf).await.map(|_| ()),
             Self::ChannelSocket(socket) => {
                 socket.send(buf.to_vec()).await?;
@@ -112,6 +137,8 @@ impl Connection {
             Self::TcpStream(stream) => read_message(stream).await,
             Self::TlsStreamServer(stream) => read_message(stream).await,
             Self::TlsStreamClient(stream) => read_message(stream).await,
+            Self::MuxStream(stream) => read_message(stream).await,
+            Self::MeteredStream(stream) => read_message(stream).await,
             Self::UdpClient(_) => Err(MessageError::IoError(Error::other(
                 "Reading messages from UDP connection is not supported.",
             ))),
@@ -179,11 +206,13 @@ impl Connection {
             Self::TcpStream(stream) => write_message(stream, &message).await,
             Self::TlsStreamServer(stream) => write_message(stream, &message).await,
             Self::TlsStreamClient(stream) => write_message(stream, &message).await,
+            Self::MuxStream(stream) => write_message(stream, &message).await,
+            Self::MeteredStream(stream) => write_message(stream, &message).await,
             Self::UdpClient(_) => Err(MessageError::IoError(Error::other(
                 "Writing messages to UDP connection is not supported.",
             ))),
             Self::ChannelSocket(socket) => {
-                let data = match rmp_serde::to_vec(&message) {
+                let data = match rmp_serde::to_vec_named(&message) {
                     Ok(data) => data,
                     Err(e) => {
                         debug!("Error while serializing message: {:?}", e);
@@ -259,6 +288,16 @@ impl Connection {
                     debug!("Error while closing stream: {:?}", e);
                 }
             }
+            Self::MuxStream(stream) => {
+                if let Err(e) = stream.shutdown().await {
+                    debug!("Error while closing stream: {:?}", e);
+                }
+            }
+            Self::MeteredStream(stream) => {
+                if let Err(e) = stream.shutdown().await {
+                    debug!("Error while closing stream: {:?}", e);
+                }
+            }
             Self::UdpClient(client) => {
                 client.shutdown();
             }
@@ -269,15 +308,45 @@ impl Connection {
     }
 
     pub async fn close_with_data(&mut self, message: &[u8]) {
-        if !message.is_empty() {
-            if let Err(e) = self.write_all(message).await {
-                debug!("Error while sending message: {:?}", e);
-            }
+        if !message.is_empty()
+            && let Err(e) = self.write_all(message).await
+        {
+            debug!("Error while sending message: {:?}", e);
         }
 
         self.shutdown().await;
     }
 
+    pub fn into_stream(self) -> Result<Box<dyn AsyncStream>> {
+        match self {
+            Self::TcpStream(stream) => Ok(Box::new(stream)),
+            Self::TlsStreamServer(stream) => Ok(Box::new(stream)),
+            Self::TlsStreamClient(stream) => Ok(Box::new(stream)),
+            Self::MuxStream(stream) => Ok(Box::new(stream)),
+            Self::MeteredStream(stream) => Ok(Box::new(stream)),
+            connection => Err(Error::other(format!(
+                "Connection of type '{}' is not a byte stream.",
+                connection.get_protocol()
+            ))),
+        }
+    }
+
+    /// Returns names from the client certificate verified during the TLS handshake. Empty if the
+    /// client did not present a certificate.
+    pub fn get_peer_certificate_names(&self) -> Vec<String> {
+        let Self::TlsStreamServer(stream) = self else {
+            return vec![];
+        };
+
+        stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(|certs| certs.first())
+            .map(get_certificate_names)
+            .unwrap_or_default()
+    }
+
     pub fn get_protocol(&self) -> &str {
         match self {
             Self::TcpStream(_) => "tcp",
@@ -285,6 +354,8 @@ impl Connection {
             Self::TlsStreamClient(_) => "tcp (tls-client)",
             Self::UdpClient(_) => "udp",
             Self::ChannelSocket(_) => "channel socket",
+            Self::MuxStream(_) => "multiplexed stream",
+            Self::MeteredStream(_) => "metered stream",
         }
     }
 }
@@ -317,13 +388,24 @@ impl DataBridge<Connection> for Connection {
             TlsStreamServer -> TcpStream,
             TlsStreamServer -> TlsStreamServer,
             TcpStream -> TlsStreamClient,
+            TlsStreamClient -> TcpStream,
+            TlsStreamClient -> TlsStreamClient,
+            TlsStreamClient -> MuxStream,
             UdpClient -> TcpStream,
             TcpStream -> UdpClient,
             UdpClient -> TlsStreamServer,
             TlsStreamServer -> UdpClient,
             UdpClient -> TlsStreamClient,
             TcpStream -> ChannelSocket,
-            TlsStreamServer -> ChannelSocket
+            TlsStreamServer -> ChannelSocket,
+            MuxStream -> TcpStream,
+            MuxStream -> TlsStreamServer,
+            MuxStream -> ChannelSocket,
+            TcpStream -> MuxStream,
+            UdpClient -> MuxStream,
+            TcpStream -> MeteredStream,
+            TlsStreamClient -> MeteredStream,
+            UdpClient -> MeteredStream
         })
     }
 }
@@ -0,0 +1,390 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{Error, ErrorKind},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use bytes::Bytes;
+use log::debug;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, Result},
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
+    time::timeout,
+};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    connection::Connection,
+    data_request::DataRequest,
+    transport::{MessageError, read_frame, write_frame},
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum FrameHeader {
+    Request { id: u64 },
+    Response { id: u64 },
+}
+
+fn encode_frame<M: ?Sized + Serialize>(
+    header: &FrameHeader,
+    message: &M,
+) -> std::result::Result<Bytes, MessageError> {
+    let mut frame = rmp_serde::to_vec(header)?;
+    frame.extend(rmp_serde::to_vec_named(message)?);
+
+    Ok(Bytes::from(frame))
+}
+
+fn decode_frame(frame: Bytes) -> std::result::Result<(FrameHeader, Bytes), MessageError> {
+    let mut remaining: &[u8] = &frame;
+    let header: FrameHeader = rmp_serde::from_read(&mut remaining)?;
+    let message = frame.slice(frame.len() - remaining.len()..);
+
+    Ok((header, message))
+}
+
+struct Shared {
+    next_id: AtomicU64,
+    pending: Mutex<HashMap<u64, oneshot::Sender<Bytes>>>,
+    frame_tx: UnboundedSender<Bytes>,
+    cancel_token: CancellationToken,
+}
+
+impl Shared {
+    fn send<M: ?Sized + Serialize>(
+        &self,
+        header: &FrameHeader,
+        message: &M,
+    ) -> std::result::Result<(), MessageError> {
+        let frame = encode_frame(header, message)?;
+
+        self.frame_tx.send(frame).map_err(|_| {
+            MessageError::IoError(Error::new(
+                ErrorKind::NotConnected,
+                "Control channel is closed.",
+            ))
+        })
+    }
+}
+
+/// Request/response protocol used by a tunnel session after it is established.
+/// Every request carries an ID which its response refers to, so any number of
+/// requests can be in flight in both directions at the same time.
+#[derive(Clone)]
+pub struct ControlChannel {
+    shared: Arc<Shared>,
+}
+
+impl fmt::Debug for ControlChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ControlChannel")
+            .field("is_closed", &self.is_closed())
+            .finish()
+    }
+}
+
+pub struct IncomingRequest {
+    message: Bytes,
+    pub responder: ControlResponder,
+}
+
+impl IncomingRequest {
+    pub fn decode<Message: DeserializeOwned>(&self) -> std::result::Result<Message, MessageError> {
+        Ok(rmp_serde::from_slice(&self.message)?)
+    }
+}
+
+pub struct ControlResponder {
+    id: u64,
+    shared: Arc<Shared>,
+}
+
+impl ControlResponder {
+    pub fn respond<Message: ?Sized + Serialize>(self, message: &Message) {
+        if let Err(e) = self
+            .shared
+            .send(&FrameHeader::Response { id: self.id }, message)
+        {
+            debug!("Error while sending response: {:?}", e);
+        }
+    }
+}
+
+impl ControlChannel {
+    pub fn start(
+        connection: Connection,
+    ) -> Result<(ControlChannel, UnboundedReceiver<IncomingRequest>)> {
+        let stream = connection.into_stream()?;
+
+        let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+        let (request_tx, request_rx) = mpsc::unbounded_channel();
+
+        let shared = Arc::new(Shared {
+            next_id: AtomicU64::new(1),
+            pending: Mutex::new(HashMap::new()),
+            frame_tx,
+            cancel_token: CancellationToken::new(),
+        });
+
+        let (reader, writer) = tokio::io::split(stream);
+
+        tokio::spawn(read_frames(shared.clone(), reader, request_tx));
+        tokio::spawn(write_frames(shared.cancel_token.clone(), frame_rx, writer));
+
+        Ok((ControlChannel { shared }, request_rx))
+    }
+
+    pub async fn request<RequestMessage>(
+        &self,
+        request: RequestMessage,
+    ) -> Result<RequestMessage::DataResponse>
+    where
+        RequestMessage: Serialize + Into<RequestMessage::DataEnum> + DataRequest,
+    {
+        let id = self.shared.next_id.fetch_add(1, Ordering::Relaxed);
+        let (response_tx, response_rx) = oneshot::channel();
+
+        self.shared.pending.lock().unwrap().insert(id, response_tx);
+
+        if let Err(e) = self
+            .shared
+            .send::<RequestMessage::DataEnum>(&FrameHeader::Request { id }, &request.into())
+        {
+            debug!("Error while sending message: {:?}", e);
+            self.shared.pending.lock().unwrap().remove(&id);
+            return Err(Error::other(e));
+        }
+
+        match timeout(REQUEST_TIMEOUT, response_rx).await {
+            Ok(Ok(response)) => rmp_serde::from_slice(&response).map_err(|e| {
+                debug!("Error while reading response: {:?}", e);
+                Error::other(e)
+            }),
+            Ok(Err(_)) => Err(Error::new(
+                ErrorKind::ConnectionAborted,
+                "Control channel closed before response was received.",
+            )),
+            Err(e) => {
+                debug!("Timeout while waiting for response: {:?}", e);
+                self.shared.pending.lock().unwrap().remove(&id);
+                Err(Error::new(ErrorKind::TimedOut, e))
+            }
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shared.cancel_token.is_cancelled()
+    }
+
+    pub fn shutdown(&self) {
+        self.shared.cancel_token.cancel();
+        self.shared.pending.lock().unwrap().clear();
+    }
+}
+
+async fn read_frames<T: AsyncReadExt + Unpin>(
+    shared: Arc<Shared>,
+    mut reader: T,
+    request_tx: UnboundedSender<IncomingRequest>,
+) {
+    loop {
+        let frame = tokio::select! {
+            _ = shared.cancel_token.cancelled() => {
+                break;
+            }
+            result = read_frame(&mut reader) => result,
+        };
+
+        let (header, message) = match frame.and_then(decode_frame) {
+            Ok(frame) => frame,
+            Err(MessageError::DecodeError(e)) => {
+                debug!("Received invalid control frame: {:?}", e);
+                continue;
+            }
+            Err(e) => {
+                debug!("Control channel connection ended: {:?}", e);
+                break;
+            }
+        };
+
+        match header {
+            FrameHeader::Request { id } => {
+                let request = IncomingRequest {
+                    message,
+                    responder: ControlResponder {
+                        id,
+                        shared: shared.clone(),
+                    },
+                };
+
+                if request_tx.send(request).is_err() {
+                    debug!("Incoming request {} dropped, nobody is listening.", id);
+                }
+            }
+            FrameHeader::Response { id } => {
+                let Some(response_tx) = shared.pending.lock().unwrap().remove(&id) else {
+                    debug!("Received response for unknown request {}", id);
+                    continue;
+                };
+
+                let _ = response_tx.send(message);
+            }
+        }
+    }
+
+    shared.cancel_token.cancel();
+    shared.pending.lock().unwrap().clear();
+}
+
+async fn write_frames<T: AsyncWriteExt + Unpin>(
+    cancel_token: CancellationToken,
+    mut frame_rx: UnboundedReceiver<Bytes>,
+    mut writer: T,
+) {
+    loop {
+        let frame = tokio::select! {
+            _ = cancel_token.cancelled() => {
+                break;
+            }
+            frame = frame_rx.recv() => frame,
+        };
+
+        let Some(frame) = frame else {
+            break;
+        };
+
+        if let Err(e) = write_frame(&mut writer, &frame).await {
+            debug!("Failed to write control frame: {:?}", e);
+            cancel_token.cancel();
+            return;
+        }
+    }
+
+    if let Err(e) = writer.shutdown().await {
+        debug!("Error while closing control channel: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_data_enum;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct EchoRequest {
+        value: u32,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct EchoResponse {
+        value: u32,
+    }
+
+    create_data_enum!(TestRequestMessage, {
+        EchoRequest -> EchoResponse
+    });
+
+    async fn create_pair() -> (
+        (ControlChannel, UnboundedReceiver<IncomingRequest>),
+        (ControlChannel, UnboundedReceiver<IncomingRequest>),
+    ) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let (client, server) =
+            tokio::join!(tokio::net::TcpStream::connect(address), listener.accept());
+
+        (
+            ControlChannel::start(client.unwrap().into()).unwrap(),
+            ControlChannel::start(server.unwrap().0.into()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let frame = encode_frame(&FrameHeader::Request { id: 42 }, &"hello".to_string()).unwrap();
+
+        let (header, message) = decode_frame(frame).unwrap();
+
+        assert_eq!(header, FrameHeader::Request { id: 42 });
+        assert_eq!(
+            rmp_serde::from_slice::<String>(&message).unwrap(),
+            "hello".to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_responses_are_matched_by_id() {
+        let ((left, _), (_, mut right_requests)) = create_pair().await;
+
+        tokio::spawn(async move {
+            let first = right_requests.recv().await.unwrap();
+            let second = right_requests.recv().await.unwrap();
+
+            for request in [second, first] {
+                let TestRequestMessage::EchoRequest(echo) = request.decode().unwrap();
+                request
+                    .responder
+                    .respond(&EchoResponse { value: echo.value });
+            }
+        });
+
+        let (first, second) = tokio::join!(
+            left.request(EchoRequest { value: 1 }),
+            left.request(EchoRequest { value: 2 })
+        );
+
+        assert_eq!(first.unwrap(), EchoResponse { value: 1 });
+        assert_eq!(second.unwrap(), EchoResponse { value: 2 });
+    }
+
+    fn spawn_doubling_handler(mut requests: UnboundedReceiver<IncomingRequest>) {
+        tokio::spawn(async move {
+            while let Some(request) = requests.recv().await {
+                let TestRequestMessage::EchoRequest(echo) = request.decode().unwrap();
+                request.responder.respond(&EchoResponse {
+                    value: echo.value * 2,
+                });
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_requests_in_both_directions() {
+        let ((left, left_requests), (right, right_requests)) = create_pair().await;
+
+        spawn_doubling_handler(left_requests);
+        spawn_doubling_handler(right_requests);
+
+        let (from_left, from_right) = tokio::join!(
+            left.request(EchoRequest { value: 2 }),
+            right.request(EchoRequest { value: 3 })
+        );
+
+        assert_eq!(from_left.unwrap(), EchoResponse { value: 4 });
+        assert_eq!(from_right.unwrap(), EchoResponse { value: 6 });
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_fails_pending_requests() {
+        let ((left, _), (right, _right_requests)) = create_pair().await;
+
+        let request = tokio::spawn(async move { left.request(EchoRequest { value: 1 }).await });
+
+        right.shutdown();
+
+        let error = request.await.unwrap().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ConnectionAborted);
+    }
+}
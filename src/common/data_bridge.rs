@@ -8,7 +8,10 @@ use tokio::{
 };
 use tokio_rustls::{client::TlsStream as ClientTlsStream, server::TlsStream as ServerTlsStream};
 
-use super::{channel_socket::ChannelSocket, udp_client::UdpClient};
+use super::{
+    channel_socket::ChannelSocket, metered_stream::MeteredStream, multiplexer::MuxStream,
+    udp_client::UdpClient,
+};
 
 pub trait DataBridge<To> {
     async fn bridge_to(&mut self, to: &mut To) -> Result<()>;
@@ -74,6 +77,54 @@ impl DataBridge<ClientTlsStream<TcpStream>> for TcpStream {
     }
 }
 
+impl DataBridge<TcpStream> for ClientTlsStream<TcpStream> {
+    async fn bridge_to(&mut self, to: &mut TcpStream) -> Result<()> {
+        match tokio::io::copy_bidirectional(self, to).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                debug!("Client TLS connection ended: {:?}", e);
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to bridge data: {}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl DataBridge<ClientTlsStream<TcpStream>> for ClientTlsStream<TcpStream> {
+    async fn bridge_to(&mut self, to: &mut ClientTlsStream<TcpStream>) -> Result<()> {
+        match tokio::io::copy_bidirectional(self, to).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                debug!("Client TLS connection ended: {:?}", e);
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to bridge data: {}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl DataBridge<MuxStream> for ClientTlsStream<TcpStream> {
+    async fn bridge_to(&mut self, to: &mut MuxStream) -> Result<()> {
+        match tokio::io::copy_bidirectional(self, to).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                debug!("Client TLS connection ended: {:?}", e);
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to bridge data: {}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
 impl DataBridge<TcpStream> for ServerTlsStream<TcpStream> {
     async fn bridge_to(&mut self, to: &mut TcpStream) -> Result<()> {
         match tokio::io::copy_bidirectional(self, to).await {
@@ -86,6 +137,46 @@ impl DataBridge<TcpStream> for ServerTlsStream<TcpStream> {
     }
 }
 
+impl DataBridge<TcpStream> for MuxStream {
+    async fn bridge_to(&mut self, to: &mut TcpStream) -> Result<()> {
+        match tokio::io::copy_bidirectional(self, to).await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("Failed to bridge data: {}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl DataBridge<MuxStream> for TcpStream {
+    async fn bridge_to(&mut self, to: &mut MuxStream) -> Result<()> {
+        match tokio::io::copy_bidirectional(self, to).await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("Failed to bridge data: {}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl DataBridge<ServerTlsStream<TcpStream>> for MuxStream {
+    async fn bridge_to(&mut self, to: &mut ServerTlsStream<TcpStream>) -> Result<()> {
+        match tokio::io::copy_bidirectional(self, to).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                debug!("Server TLS connection ended: {:?}", e);
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to bridge data: {}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
 impl DataBridge<UdpClient> for TcpStream {
     async fn bridge_to(&mut self, to: &mut UdpClient) -> Result<()> {
         bridge_udp_with_writable(to, self).await
@@ -116,6 +207,46 @@ impl DataBridge<ClientTlsStream<TcpStream>> for UdpClient {
     }
 }
 
+impl DataBridge<MuxStream> for UdpClient {
+    async fn bridge_to(&mut self, to: &mut MuxStream) -> Result<()> {
+        bridge_udp_with_writable(self, to).await
+    }
+}
+
+impl DataBridge<MeteredStream> for TcpStream {
+    async fn bridge_to(&mut self, to: &mut MeteredStream) -> Result<()> {
+        match tokio::io::copy_bidirectional(self, to).await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("Failed to bridge data: {}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl DataBridge<MeteredStream> for ClientTlsStream<TcpStream> {
+    async fn bridge_to(&mut self, to: &mut MeteredStream) -> Result<()> {
+        match tokio::io::copy_bidirectional(self, to).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                debug!("Client TLS connection ended: {:?}", e);
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to bridge data: {}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl DataBridge<MeteredStream> for UdpClient {
+    async fn bridge_to(&mut self, to: &mut MeteredStream) -> Result<()> {
+        bridge_udp_with_writable(self, to).await
+    }
+}
+
 async fn bridge_udp_with_writable<T: AsyncWriteExt + Unpin + AsyncReadExt>(
     udp_client: &mut UdpClient,
     writable: &mut T,
@@ -203,6 +334,12 @@ impl DataBridge<ChannelSocket> for ServerTlsStream<TcpStream> {
     }
 }
 
+impl DataBridge<ChannelSocket> for MuxStream {
+    async fn bridge_to(&mut self, to: &mut ChannelSocket) -> Result<()> {
+        bridge_channel_socket_with_writable(to, self).await
+    }
+}
+
 async fn bridge_channel_socket_with_writable<T: AsyncWriteExt + Unpin + AsyncReadExt>(
     channel_socket: &mut ChannelSocket,
     writable: &mut T,
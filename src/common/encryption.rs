@@ -1,6 +1,9 @@
 use rustls::{
-    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName},
-    ClientConfig, RootCertStore,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+    server::WebPkiClientVerifier,
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
 };
 
 use rustls_native_certs::load_native_certs;
@@ -11,6 +14,7 @@ use std::{
 };
 
 use tokio_rustls::{TlsAcceptor, TlsConnector};
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
 
 use super::connection::Connection;
 use tokio::{io::Result, net::TcpStream};
@@ -20,24 +24,43 @@ pub struct ServerTlsEncryption {
 }
 
 impl ServerTlsEncryption {
-    pub async fn new(cert_path: &str, key_path: &str) -> Self {
-        let cert_reader =
-            CertificateDer::pem_file_iter(cert_path).expect("Failed to read Server cert");
+    pub async fn new(
+        cert_path: &str,
+        key_path: &str,
+        client_ca_path: Option<&str>,
+    ) -> Result<Self> {
+        let certs = read_certificates(cert_path, "server certificate")?;
+        let key = read_private_key(key_path, "server key")?;
 
-        let key = PrivateKeyDer::from_pem_file(key_path).expect("Failed to read Server key");
+        let builder = rustls::ServerConfig::builder();
 
-        let certs: Vec<CertificateDer> = cert_reader
-            .flatten()
-            .collect();
+        let builder = match client_ca_path {
+            Some(client_ca_path) => {
+                let verifier =
+                    WebPkiClientVerifier::builder(Arc::new(read_root_cert_store(client_ca_path)?))
+                        .build()
+                        .map_err(|e| {
+                            Error::new(
+                                ErrorKind::InvalidData,
+                                format!("Failed to create client certificate verifier: {e}"),
+                            )
+                        })?;
 
-        let config = rustls::ServerConfig::builder()
-            .with_no_client_auth()
-            .with_single_cert(certs, key)
-            .expect("Failed to create Server config");
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let config = builder.with_single_cert(certs, key).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Failed to use server certificate '{cert_path}': {e}"),
+            )
+        })?;
 
         let acceptor = TlsAcceptor::from(Arc::new(config));
 
-        ServerTlsEncryption { acceptor }
+        Ok(ServerTlsEncryption { acceptor })
     }
 
     pub async fn accept(&self, stream: TcpStream) -> Result<Connection> {
@@ -52,14 +75,51 @@ pub struct ClientTlsEncryption {
 }
 
 impl ClientTlsEncryption {
-    pub async fn new(ca_path: Option<String>) -> Self {
-        let client_config = ClientConfig::builder()
-            .with_root_certificates(Self::resolve_client_root_cert_store(ca_path))
-            .with_no_client_auth();
+    /// Creates encryption for connecting to the server. If `client_cert` is set as a pair of
+    /// certificate and key paths, the certificate is presented to the server.
+    pub async fn new(ca_path: Option<String>, client_cert: Option<(&str, &str)>) -> Result<Self> {
+        let builder = ClientConfig::builder()
+            .with_root_certificates(Self::resolve_client_root_cert_store(ca_path)?);
+
+        let client_config = match client_cert {
+            Some((cert_path, key_path)) => {
+                let certs = read_certificates(cert_path, "client certificate")?;
+                let key = read_private_key(key_path, "client key")?;
+
+                builder.with_client_auth_cert(certs, key).map_err(|e| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Failed to use client certificate '{cert_path}': {e}"),
+                    )
+                })?
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        Ok(ClientTlsEncryption {
+            connector: TlsConnector::from(Arc::new(client_config)),
+        })
+    }
+
+    /// Creates encryption for connecting to local services. If `insecure_skip_verify` is set,
+    /// any certificate is accepted, which is only meant for local development.
+    pub fn new_upstream(ca_path: Option<String>, insecure_skip_verify: bool) -> Result<Self> {
+        let builder = ClientConfig::builder();
+
+        let client_config = if insecure_skip_verify {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(SkipServerVerification::new()))
+                .with_no_client_auth()
+        } else {
+            builder
+                .with_root_certificates(Self::resolve_client_root_cert_store(ca_path)?)
+                .with_no_client_auth()
+        };
 
         let connector = TlsConnector::from(Arc::new(client_config));
 
-        ClientTlsEncryption { connector }
+        Ok(ClientTlsEncryption { connector })
     }
 
     pub async fn connect(&self, stream: TcpStream, domain: &str) -> Result<Connection> {
@@ -74,35 +134,203 @@ impl ClientTlsEncryption {
         Ok(Connection::from(stream))
     }
 
-    fn resolve_client_root_cert_store(ca_path: Option<String>) -> RootCertStore {
-        let mut root_store = RootCertStore::empty();
-
+    fn resolve_client_root_cert_store(ca_path: Option<String>) -> Result<RootCertStore> {
         match ca_path {
-            Some(ca_path) => {
-                let cert_reader =
-                    CertificateDer::pem_file_iter(ca_path).expect("Failed to read CA certificate");
-                let certs: Vec<CertificateDer> = cert_reader
-                    .flatten()
-                    .collect();
-
-                for cert in certs {
-                    root_store
-                        .add(cert)
-                        .expect("Failed to add CA certificate to root store");
-                }
-            }
+            Some(ca_path) => read_root_cert_store(&ca_path),
             None => {
-                let native_certs =
-                    load_native_certs().expect("Failed to load native OS certificates");
+                let mut root_store = RootCertStore::empty();
+                let native_certs = load_native_certs();
 
-                for cert in native_certs {
-                    root_store
-                        .add(cert)
-                        .expect("Failed to add Native certificate to root store");
+                if let Some(e) = native_certs.errors.first() {
+                    return Err(Error::other(format!(
+                        "Failed to load native OS certificates: {e}"
+                    )));
                 }
+
+                for cert in native_certs.certs {
+                    root_store.add(cert).map_err(|e| {
+                        Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Failed to add native certificate to root store: {e}"),
+                        )
+                    })?;
+                }
+
+                Ok(root_store)
+            }
+        }
+    }
+}
+
+fn read_certificates(path: &str, name: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let certs: Vec<CertificateDer> = CertificateDer::pem_file_iter(path)
+        .map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Failed to read {name} '{path}': {e}"),
+            )
+        })?
+        .flatten()
+        .collect();
+
+    if certs.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("No certificates found in {name} '{path}'"),
+        ));
+    }
+
+    Ok(certs)
+}
+
+fn read_private_key(path: &str, name: &str) -> Result<PrivateKeyDer<'static>> {
+    PrivateKeyDer::from_pem_file(path).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Failed to read {name} '{path}': {e}"),
+        )
+    })
+}
+
+fn read_root_cert_store(ca_path: &str) -> Result<RootCertStore> {
+    let mut root_store = RootCertStore::empty();
+
+    let cert_reader = CertificateDer::pem_file_iter(ca_path).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Failed to read CA certificate '{ca_path}': {e}"),
+        )
+    })?;
+    let certs: Vec<CertificateDer> = cert_reader
+        .flatten()
+        .collect();
+
+    if certs.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("No certificates found in CA certificate '{ca_path}'"),
+        ));
+    }
+
+    for cert in certs {
+        root_store.add(cert).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Failed to add CA certificate '{ca_path}' to root store: {e}"),
+            )
+        })?;
+    }
+
+    Ok(root_store)
+}
+
+/// Returns subject common names followed by DNS, email and URI subject alternative names of
+/// the certificate.
+pub fn get_certificate_names(cert: &CertificateDer) -> Vec<String> {
+    let Ok((_, cert)) = X509Certificate::from_der(cert.as_ref()) else {
+        return vec![];
+    };
+
+    let mut names: Vec<String> = cert
+        .subject()
+        .iter_common_name()
+        .filter_map(|name| name.as_str().ok())
+        .map(|name| name.to_owned())
+        .collect();
+
+    if let Ok(Some(alternative_names)) = cert.subject_alternative_name() {
+        for name in alternative_names.value.general_names.iter() {
+            match name {
+                GeneralName::DNSName(name)
+                | GeneralName::RFC822Name(name)
+                | GeneralName::URI(name) => names.push(name.to_string()),
+                _ => {}
             }
         }
+    }
+
+    names
+}
+
+#[derive(Debug)]
+struct SkipServerVerification {
+    provider: CryptoProvider,
+}
+
+impl SkipServerVerification {
+    fn new() -> Self {
+        Self {
+            provider: rustls::crypto::aws_lc_rs::default_provider(),
+        }
+    }
+}
+
+impl ServerCertVerifier for SkipServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_missing_certificates_return_error() {
+        assert!(
+            ServerTlsEncryption::new("/nonexistent/cert.pem", "/nonexistent/key.pem", None)
+                .await
+                .is_err()
+        );
 
-        root_store
+        assert!(
+            ClientTlsEncryption::new(
+                Some("/nonexistent/ca.pem".to_string()),
+                Some(("/nonexistent/cert.pem", "/nonexistent/key.pem")),
+            )
+            .await
+            .is_err()
+        );
     }
 }
@@ -0,0 +1,246 @@
+use std::fs;
+
+use serde_json::Value;
+
+/// Fields which can be read from a file by setting `<field>_file` instead.
+const SECRET_FIELDS: [&str; 6] = [
+    "tunnel_key",
+    "monitor_key",
+    "key",
+    "token_secret",
+    "username",
+    "password",
+];
+
+/// Expands `${VAR}` and `${VAR:-default}` in all string values using the given lookup. Default is
+/// used when the variable is not set or is empty. `$${` is kept as a literal `${`.
+pub fn expand_variables<F>(value: &mut Value, lookup: &F) -> Result<(), Vec<String>>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut errors = Vec::new();
+    expand_value(value, "", lookup, &mut errors);
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+fn expand_value<F>(value: &mut Value, path: &str, lookup: &F, errors: &mut Vec<String>)
+where
+    F: Fn(&str) -> Option<String>,
+{
+    match value {
+        Value::String(text) => match expand_string(text, lookup) {
+            Ok(expanded) => *text = expanded,
+            Err(e) => errors.push(format!("{path}: {e}")),
+        },
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                expand_value(item, &join_path(path, &index.to_string()), lookup, errors);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, item) in fields.iter_mut() {
+                expand_value(item, &join_path(path, key), lookup, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    match path.is_empty() {
+        true => key.to_owned(),
+        false => format!("{path}.{key}"),
+    }
+}
+
+pub fn expand_string<F>(text: &str, lookup: &F) -> Result<String, String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(index) = rest.find('$') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            result.push_str("${");
+            rest = escaped;
+            continue;
+        }
+
+        let Some(expression) = rest.strip_prefix("${") else {
+            result.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let Some(end) = expression.find('}') else {
+            return Err(format!("Unterminated variable in '{text}'."));
+        };
+
+        let (name, default) = match expression[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&expression[..end], None),
+        };
+
+        let is_valid_name = !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        if !is_valid_name {
+            return Err(format!("Invalid variable name '{name}'."));
+        }
+
+        match (lookup(name).filter(|value| !value.is_empty()), default) {
+            (Some(value), _) => result.push_str(&value),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) => return Err(format!("Environment variable '{name}' is not set.")),
+        }
+
+        rest = &expression[end + 1..];
+    }
+
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+/// Replaces `<field>_file` of secret fields with the contents of the file. Trailing newlines are
+/// removed from the contents.
+pub fn resolve_secret_files(value: &mut Value) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    resolve_value(value, "", &mut errors);
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+fn resolve_value(value: &mut Value, path: &str, errors: &mut Vec<String>) {
+    match value {
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                resolve_value(item, &join_path(path, &index.to_string()), errors);
+            }
+        }
+        Value::Object(fields) => {
+            for field in SECRET_FIELDS {
+                let file_field = format!("{field}_file");
+
+                let Some(file) = fields.remove(&file_field) else {
+                    continue;
+                };
+
+                let field_path = join_path(path, &file_field);
+
+                if fields.get(field).is_some_and(|value| !value.is_null()) {
+                    errors.push(format!(
+                        "{field_path}: Only one of '{field}' and '{file_field}' can be set."
+                    ));
+                    continue;
+                }
+
+                let Value::String(file) = file else {
+                    errors.push(format!("{field_path}: Path to the file must be a string."));
+                    continue;
+                };
+
+                match fs::read_to_string(&file) {
+                    Ok(secret) => {
+                        let secret = secret.trim_end_matches(['\r', '\n']).to_owned();
+                        fields.insert(field.to_owned(), Value::String(secret));
+                    }
+                    Err(e) => errors.push(format!("{field_path}: Could not read '{file}': {e}")),
+                }
+            }
+
+            for (key, item) in fields.iter_mut() {
+                resolve_value(item, &join_path(path, key), errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOST" => Some("example.com".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_string() {
+        assert_eq!(
+            expand_string("https://${HOST}:3456", &lookup).unwrap(),
+            "https://example.com:3456"
+        );
+        assert_eq!(expand_string("${PORT:-3456}", &lookup).unwrap(), "3456");
+        assert_eq!(
+            expand_string("${EMPTY:-default}", &lookup).unwrap(),
+            "default"
+        );
+        assert_eq!(
+            expand_string("$${HOST} costs $5", &lookup).unwrap(),
+            "${HOST} costs $5"
+        );
+    }
+
+    #[test]
+    fn test_expand_string_errors() {
+        assert!(expand_string("${MISSING}", &lookup).is_err());
+        assert!(expand_string("${HOST", &lookup).is_err());
+        assert!(expand_string("${1HOST}", &lookup).is_err());
+    }
+
+    #[test]
+    fn test_expand_variables() {
+        let mut value = json!({
+            "tunnel": {
+                "server_address": "${HOST}",
+                "server_port": 3456,
+                "proxies": [{ "address": "${MISSING}" }]
+            }
+        });
+
+        let errors = expand_variables(&mut value, &lookup).unwrap_err();
+
+        assert_eq!(value["tunnel"]["server_address"], "example.com");
+        assert_eq!(
+            errors,
+            vec!["tunnel.proxies.0.address: Environment variable 'MISSING' is not set."]
+        );
+    }
+
+    #[test]
+    fn test_resolve_secret_files() {
+        let file = std::env::temp_dir().join(format!("tunnelize-secret-{}", uuid::Uuid::new_v4()));
+        fs::write(&file, "secret-key\n").unwrap();
+
+        let mut value = json!({
+            "tunnel": { "tunnel_key_file": file },
+            "server": { "monitor_key": "key", "monitor_key_file": file }
+        });
+
+        let errors = resolve_secret_files(&mut value).unwrap_err();
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(value["tunnel"]["tunnel_key"], "secret-key");
+        assert!(value["tunnel"].get("tunnel_key_file").is_none());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("server.monitor_key_file"));
+    }
+}
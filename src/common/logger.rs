@@ -20,7 +20,17 @@ fn resolve_log_level(command: &Commands) -> &'static str {
                 "error"
             }
         }
+        Commands::Http { tunnel, .. }
+        | Commands::Tcp { tunnel, .. }
+        | Commands::Udp { tunnel, .. } => {
+            if tunnel.verbose {
+                VERBOSE_LOG_LEVEL
+            } else {
+                "error"
+            }
+        }
         Commands::Monitor { .. } => "error",
+        Commands::Config { .. } => "error",
     }
 }
 
@@ -0,0 +1,186 @@
+use std::{
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, Result};
+
+use super::connection::AsyncStream;
+
+#[derive(Default, Debug)]
+pub struct TransferStats {
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct TransferTotals {
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+}
+
+impl TransferStats {
+    pub fn add_received(&self, bytes: usize) {
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_sent(&self, bytes: usize) {
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn get_totals(&self) -> TransferTotals {
+        TransferTotals {
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Receives a copy of all data passing through a metered stream.
+pub trait StreamObserver: Send {
+    fn on_received(&mut self, data: &[u8]);
+    fn on_sent(&mut self, data: &[u8]);
+}
+
+/// Byte stream which counts all data read from and written to it.
+pub struct MeteredStream {
+    inner: Box<dyn AsyncStream>,
+    stats: Arc<TransferStats>,
+    observer: Option<Box<dyn StreamObserver>>,
+}
+
+impl std::fmt::Debug for MeteredStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MeteredStream")
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl MeteredStream {
+    pub fn new(inner: Box<dyn AsyncStream>, stats: Arc<TransferStats>) -> Self {
+        Self {
+            inner,
+            stats,
+            observer: None,
+        }
+    }
+
+    pub fn with_observer(mut self, observer: Box<dyn StreamObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+}
+
+impl AsyncRead for MeteredStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<()>> {
+        let filled_before = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+
+        if let Poll::Ready(Ok(())) = result {
+            let received = &buf.filled()[filled_before..];
+            self.stats.add_received(received.len());
+
+            if let Some(observer) = self.observer.as_mut() {
+                observer.on_received(received);
+            }
+        }
+
+        result
+    }
+}
+
+impl AsyncWrite for MeteredStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+
+        if let Poll::Ready(Ok(written)) = result {
+            self.stats.add_sent(written);
+
+            if let Some(observer) = self.observer.as_mut() {
+                observer.on_sent(&buf[..written]);
+            }
+        }
+
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, duplex};
+
+    #[tokio::test]
+    async fn test_counts_transferred_bytes() {
+        let (local, mut remote) = duplex(64);
+        let stats = Arc::new(TransferStats::default());
+        let mut stream = MeteredStream::new(Box::new(local), stats.clone());
+
+        stream.write_all(b"hello").await.unwrap();
+        remote.write_all(b"hi").await.unwrap();
+
+        let mut buffer = [0u8; 2];
+        stream.read_exact(&mut buffer).await.unwrap();
+
+        assert_eq!(
+            stats.get_totals(),
+            TransferTotals {
+                bytes_received: 2,
+                bytes_sent: 5,
+            }
+        );
+    }
+
+    struct RecordingObserver(Arc<std::sync::Mutex<(Vec<u8>, Vec<u8>)>>);
+
+    impl StreamObserver for RecordingObserver {
+        fn on_received(&mut self, data: &[u8]) {
+            self.0.lock().unwrap().0.extend_from_slice(data);
+        }
+
+        fn on_sent(&mut self, data: &[u8]) {
+            self.0.lock().unwrap().1.extend_from_slice(data);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observer_receives_data() {
+        let (local, mut remote) = duplex(64);
+        let recorded = Arc::new(std::sync::Mutex::new((Vec::new(), Vec::new())));
+        let mut stream = MeteredStream::new(Box::new(local), Arc::new(TransferStats::default()))
+            .with_observer(Box::new(RecordingObserver(recorded.clone())));
+
+        stream.write_all(b"response").await.unwrap();
+        remote.write_all(b"request").await.unwrap();
+
+        let mut buffer = [0u8; 7];
+        stream.read_exact(&mut buffer).await.unwrap();
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.0, b"request");
+        assert_eq!(recorded.1, b"response");
+    }
+}
@@ -1,12 +1,19 @@
+pub mod atomic_file;
 pub mod channel;
 pub mod channel_socket;
 pub mod cli;
+pub mod config_format;
 pub mod configuration;
 pub mod connection;
+pub mod control_channel;
 pub mod data_bridge;
 pub mod data_request;
 pub mod encryption;
+pub mod interpolation;
+pub mod key_pair;
 pub mod logger;
+pub mod metered_stream;
+pub mod multiplexer;
 pub mod periodic_trigger;
 pub mod protocol_socket;
 pub mod tasks;
@@ -0,0 +1,738 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    io::{Error, ErrorKind},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use log::debug;
+use tokio::{
+    io::{
+        AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, ReadBuf, Result,
+    },
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::connection::Connection;
+
+const FRAME_DATA: u8 = 0;
+const FRAME_WINDOW_UPDATE: u8 = 1;
+const FRAME_CLOSE: u8 = 2;
+const FRAME_RESET: u8 = 3;
+
+const FRAME_HEADER_SIZE: usize = 21;
+const MAX_FRAME_DATA_SIZE: usize = 16 * 1024;
+
+const STREAM_WINDOW_SIZE: usize = 256 * 1024;
+
+const CONTROL_STREAM_ID: Uuid = Uuid::nil();
+
+#[derive(Debug, PartialEq)]
+enum Frame {
+    Data { stream_id: Uuid, data: Vec<u8> },
+    WindowUpdate { stream_id: Uuid, credit: u32 },
+    Close { stream_id: Uuid },
+    Reset { stream_id: Uuid },
+}
+
+impl Frame {
+    fn encode(&self) -> Vec<u8> {
+        let (kind, stream_id, value, data): (u8, &Uuid, u32, &[u8]) = match self {
+            Frame::Data { stream_id, data } => (FRAME_DATA, stream_id, data.len() as u32, data),
+            Frame::WindowUpdate { stream_id, credit } => {
+                (FRAME_WINDOW_UPDATE, stream_id, *credit, &[])
+            }
+            Frame::Close { stream_id } => (FRAME_CLOSE, stream_id, 0, &[]),
+            Frame::Reset { stream_id } => (FRAME_RESET, stream_id, 0, &[]),
+        };
+
+        let mut buffer = Vec::with_capacity(FRAME_HEADER_SIZE + data.len());
+        buffer.push(kind);
+        buffer.extend_from_slice(stream_id.as_bytes());
+        buffer.extend_from_slice(&value.to_be_bytes());
+        buffer.extend_from_slice(data);
+
+        buffer
+    }
+
+    async fn read_from<T: AsyncReadExt + Unpin>(stream: &mut T) -> Result<Frame> {
+        let mut header = [0u8; FRAME_HEADER_SIZE];
+        stream.read_exact(&mut header).await?;
+
+        let stream_id = Uuid::from_slice(&header[1..17]).map_err(Error::other)?;
+        let value = u32::from_be_bytes([header[17], header[18], header[19], header[20]]);
+
+        match header[0] {
+            FRAME_DATA => {
+                if value as usize > MAX_FRAME_DATA_SIZE {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Data frame too large: {value} bytes"),
+                    ));
+                }
+
+                let mut data = vec![0u8; value as usize];
+                stream.read_exact(&mut data).await?;
+
+                Ok(Frame::Data { stream_id, data })
+            }
+            FRAME_WINDOW_UPDATE => Ok(Frame::WindowUpdate {
+                stream_id,
+                credit: value,
+            }),
+            FRAME_CLOSE => Ok(Frame::Close { stream_id }),
+            FRAME_RESET => Ok(Frame::Reset { stream_id }),
+            kind => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Unknown frame type: {kind}"),
+            )),
+        }
+    }
+}
+
+struct SendWindow {
+    credit: usize,
+    is_reset: bool,
+    waker: Option<Waker>,
+}
+
+impl SendWindow {
+    fn add_credit(&mut self, credit: usize) {
+        self.credit += credit;
+        self.wake();
+    }
+
+    fn reset(&mut self) {
+        self.is_reset = true;
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Data received for a stream and not read yet. Peer can only send as much as the credit
+/// granted to it, so the buffer never grows past the stream window.
+struct ReceiveWindow {
+    buffer: VecDeque<u8>,
+    credit: usize,
+    is_closed: bool,
+    waker: Option<Waker>,
+}
+
+impl ReceiveWindow {
+    /// Returns false, without buffering anything, if the data goes past the granted credit.
+    fn push(&mut self, data: &[u8]) -> bool {
+        if data.len() > self.credit {
+            return false;
+        }
+
+        self.credit -= data.len();
+
+        if !self.is_closed {
+            self.buffer.extend(data);
+            self.wake();
+        }
+
+        true
+    }
+
+    fn grant(&mut self, credit: usize) {
+        self.credit += credit;
+    }
+
+    fn close(&mut self) {
+        self.is_closed = true;
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+struct StreamEntry {
+    receive_window: Arc<Mutex<ReceiveWindow>>,
+    send_window: Arc<Mutex<SendWindow>>,
+}
+
+impl StreamEntry {
+    fn reset(&mut self) {
+        self.receive_window.lock().unwrap().close();
+        self.send_window.lock().unwrap().reset();
+    }
+}
+
+struct Shared {
+    streams: Mutex<HashMap<Uuid, StreamEntry>>,
+    frame_tx: UnboundedSender<Frame>,
+    cancel_token: CancellationToken,
+}
+
+impl Shared {
+    fn send_frame(&self, frame: Frame) -> Result<()> {
+        self.frame_tx
+            .send(frame)
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "Multiplexed connection is closed."))
+    }
+
+    fn handle_frame(&self, frame: Frame) {
+        let mut streams = self.streams.lock().unwrap();
+
+        match frame {
+            Frame::Data { stream_id, data } => {
+                let Some(stream) = streams.get(&stream_id) else {
+                    debug!("Received data for unknown stream {}", stream_id);
+                    let _ = self.send_frame(Frame::Reset { stream_id });
+                    return;
+                };
+
+                if !stream.receive_window.lock().unwrap().push(&data) {
+                    debug!("Stream {} sent more data than its window allows", stream_id);
+
+                    if let Some(mut stream) = streams.remove(&stream_id) {
+                        stream.reset();
+                    }
+
+                    let _ = self.send_frame(Frame::Reset { stream_id });
+                }
+            }
+            Frame::WindowUpdate { stream_id, credit } => {
+                if let Some(stream) = streams.get(&stream_id) {
+                    stream
+                        .send_window
+                        .lock()
+                        .unwrap()
+                        .add_credit(credit as usize);
+                }
+            }
+            Frame::Close { stream_id } => {
+                if let Some(stream) = streams.get(&stream_id) {
+                    stream.receive_window.lock().unwrap().close();
+                }
+            }
+            Frame::Reset { stream_id } => {
+                if let Some(mut stream) = streams.remove(&stream_id) {
+                    stream.reset();
+                }
+            }
+        }
+    }
+
+    fn reset_all_streams(&self) {
+        for (_, mut stream) in self.streams.lock().unwrap().drain() {
+            stream.reset();
+        }
+    }
+}
+
+/// Carries the control messages and any number of link streams over a
+/// single connection. Every stream has its own send window so a slow
+/// link cannot stall the others.
+#[derive(Clone)]
+pub struct Multiplexer {
+    shared: Arc<Shared>,
+}
+
+impl fmt::Debug for Multiplexer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multiplexer")
+            .field("is_closed", &self.is_closed())
+            .finish()
+    }
+}
+
+impl Multiplexer {
+    pub fn start(connection: Connection) -> Result<(Multiplexer, MuxStream)> {
+        Ok(Self::start_with(connection.into_stream()?))
+    }
+
+    fn start_with<T>(stream: T) -> (Multiplexer, MuxStream)
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+        let shared = Arc::new(Shared {
+            streams: Mutex::new(HashMap::new()),
+            frame_tx,
+            cancel_token: CancellationToken::new(),
+        });
+
+        let (reader, writer) = tokio::io::split(stream);
+
+        tokio::spawn(read_frames(shared.clone(), BufReader::new(reader)));
+        tokio::spawn(write_frames(
+            shared.cancel_token.clone(),
+            frame_rx,
+            BufWriter::new(writer),
+        ));
+
+        let multiplexer = Multiplexer { shared };
+        let control_stream = multiplexer
+            .open_stream(CONTROL_STREAM_ID)
+            .expect("Control stream must open on a new multiplexer.");
+
+        (multiplexer, control_stream)
+    }
+
+    pub fn open_stream(&self, stream_id: Uuid) -> Result<MuxStream> {
+        let mut streams = self.shared.streams.lock().unwrap();
+
+        // Checked under the lock so a stream cannot slip in after the
+        // remaining streams have been reset on shutdown.
+        if self.is_closed() {
+            return Err(Error::new(
+                ErrorKind::NotConnected,
+                "Multiplexed connection is closed.",
+            ));
+        }
+
+        if streams.contains_key(&stream_id) {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("Stream {stream_id} is already open."),
+            ));
+        }
+
+        let receive_window = Arc::new(Mutex::new(ReceiveWindow {
+            buffer: VecDeque::new(),
+            credit: STREAM_WINDOW_SIZE,
+            is_closed: false,
+            waker: None,
+        }));
+        let send_window = Arc::new(Mutex::new(SendWindow {
+            credit: STREAM_WINDOW_SIZE,
+            is_reset: false,
+            waker: None,
+        }));
+
+        streams.insert(
+            stream_id,
+            StreamEntry {
+                receive_window: receive_window.clone(),
+                send_window: send_window.clone(),
+            },
+        );
+
+        Ok(MuxStream {
+            id: stream_id,
+            shared: self.shared.clone(),
+            receive_window,
+            unacknowledged: 0,
+            send_window,
+            is_read_closed: false,
+            is_write_closed: false,
+        })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shared.cancel_token.is_cancelled()
+    }
+
+    pub fn shutdown(&self) {
+        self.shared.cancel_token.cancel();
+        self.shared.reset_all_streams();
+    }
+}
+
+async fn read_frames<T: AsyncReadExt + Unpin>(shared: Arc<Shared>, mut reader: T) {
+    loop {
+        tokio::select! {
+            _ = shared.cancel_token.cancelled() => {
+                break;
+            }
+            result = Frame::read_from(&mut reader) => {
+                match result {
+                    Ok(frame) => shared.handle_frame(frame),
+                    Err(e) => {
+                        debug!("Multiplexed connection ended: {:?}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    shared.cancel_token.cancel();
+    shared.reset_all_streams();
+}
+
+async fn write_frames<T: AsyncWriteExt + Unpin>(
+    cancel_token: CancellationToken,
+    mut frame_rx: UnboundedReceiver<Frame>,
+    mut writer: T,
+) {
+    loop {
+        let frame = tokio::select! {
+            _ = cancel_token.cancelled() => {
+                break;
+            }
+            frame = frame_rx.recv() => frame,
+        };
+
+        let Some(mut frame) = frame else {
+            break;
+        };
+
+        loop {
+            if let Err(e) = writer.write_all(&frame.encode()).await {
+                debug!("Failed to write multiplexed frame: {:?}", e);
+                cancel_token.cancel();
+                return;
+            }
+
+            match frame_rx.try_recv() {
+                Ok(next_frame) => frame = next_frame,
+                Err(_) => break,
+            }
+        }
+
+        if let Err(e) = writer.flush().await {
+            debug!("Failed to flush multiplexed frames: {:?}", e);
+            cancel_token.cancel();
+            return;
+        }
+    }
+
+    if let Err(e) = writer.shutdown().await {
+        debug!("Error while closing multiplexed connection: {:?}", e);
+    }
+}
+
+pub struct MuxStream {
+    id: Uuid,
+    shared: Arc<Shared>,
+    receive_window: Arc<Mutex<ReceiveWindow>>,
+    unacknowledged: usize,
+    send_window: Arc<Mutex<SendWindow>>,
+    is_read_closed: bool,
+    is_write_closed: bool,
+}
+
+impl fmt::Debug for MuxStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MuxStream").field("id", &self.id).finish()
+    }
+}
+
+impl MuxStream {
+    pub fn get_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn acknowledge(&mut self, read: usize) {
+        self.unacknowledged += read;
+
+        if self.unacknowledged < STREAM_WINDOW_SIZE / 2 {
+            return;
+        }
+
+        // Credit is granted before the update is sent, so data sent on it is never rejected.
+        self.receive_window
+            .lock()
+            .unwrap()
+            .grant(self.unacknowledged);
+
+        let _ = self.shared.send_frame(Frame::WindowUpdate {
+            stream_id: self.id,
+            credit: self.unacknowledged as u32,
+        });
+
+        self.unacknowledged = 0;
+    }
+}
+
+impl AsyncRead for MuxStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<()>> {
+        let this = self.get_mut();
+
+        let read = {
+            let mut receive_window = this.receive_window.lock().unwrap();
+
+            if receive_window.buffer.is_empty() {
+                if receive_window.is_closed {
+                    this.is_read_closed = true;
+                    return Poll::Ready(Ok(()));
+                }
+
+                receive_window.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            let read = buf.remaining().min(receive_window.buffer.len());
+
+            let (front, back) = receive_window.buffer.as_slices();
+            let from_front = read.min(front.len());
+            buf.put_slice(&front[..from_front]);
+            buf.put_slice(&back[..read - from_front]);
+            receive_window.buffer.drain(..read);
+
+            read
+        };
+
+        this.acknowledge(read);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for MuxStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        let this = self.get_mut();
+
+        if this.is_write_closed {
+            return Poll::Ready(Err(Error::new(
+                ErrorKind::BrokenPipe,
+                "Stream is closed for writing.",
+            )));
+        }
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let written = {
+            let mut send_window = this.send_window.lock().unwrap();
+
+            if send_window.is_reset {
+                return Poll::Ready(Err(Error::new(
+                    ErrorKind::ConnectionReset,
+                    "Stream was reset.",
+                )));
+            }
+
+            if send_window.credit == 0 {
+                send_window.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            let written = buf.len().min(send_window.credit).min(MAX_FRAME_DATA_SIZE);
+            send_window.credit -= written;
+
+            written
+        };
+
+        this.shared.send_frame(Frame::Data {
+            stream_id: this.id,
+            data: buf[..written].to_vec(),
+        })?;
+
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+
+        if !this.is_write_closed {
+            this.is_write_closed = true;
+            let _ = this.shared.send_frame(Frame::Close { stream_id: this.id });
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for MuxStream {
+    fn drop(&mut self) {
+        let was_open = self
+            .shared
+            .streams
+            .lock()
+            .unwrap()
+            .remove(&self.id)
+            .is_some();
+
+        if was_open && !(self.is_read_closed && self.is_write_closed) {
+            let _ = self.shared.send_frame(Frame::Reset { stream_id: self.id });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::transport::{read_message, write_message};
+
+    fn create_pair() -> ((Multiplexer, MuxStream), (Multiplexer, MuxStream)) {
+        let (left, right) = tokio::io::duplex(64 * 1024);
+
+        (
+            Multiplexer::start_with(left),
+            Multiplexer::start_with(right),
+        )
+    }
+
+    #[test]
+    fn test_frame_encode() {
+        let stream_id = Uuid::new_v4();
+        let encoded = Frame::Data {
+            stream_id,
+            data: vec![1, 2, 3],
+        }
+        .encode();
+
+        assert_eq!(encoded.len(), FRAME_HEADER_SIZE + 3);
+        assert_eq!(encoded[0], FRAME_DATA);
+        assert_eq!(&encoded[1..17], stream_id.as_bytes());
+        assert_eq!(&encoded[17..21], &3u32.to_be_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_frame_roundtrip() {
+        let frame = Frame::WindowUpdate {
+            stream_id: Uuid::new_v4(),
+            credit: 1024,
+        };
+        let encoded = frame.encode();
+
+        let decoded = Frame::read_from(&mut encoded.as_slice()).await.unwrap();
+
+        assert_eq!(decoded, frame);
+    }
+
+    #[tokio::test]
+    async fn test_frame_data_too_large() {
+        let mut encoded = Frame::Close {
+            stream_id: Uuid::new_v4(),
+        }
+        .encode();
+        encoded[0] = FRAME_DATA;
+        encoded[17..21].copy_from_slice(&(MAX_FRAME_DATA_SIZE as u32 + 1).to_be_bytes());
+
+        let result = Frame::read_from(&mut encoded.as_slice()).await;
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_control_stream_messages() {
+        let ((_left, mut left_control), (_right, mut right_control)) = create_pair();
+
+        write_message(&mut left_control, &"hello".to_string())
+            .await
+            .unwrap();
+
+        let message: String = read_message(&mut right_control).await.unwrap();
+
+        assert_eq!(message, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_stream_transfers_more_than_window() {
+        let ((left, _left_control), (right, _right_control)) = create_pair();
+        let stream_id = Uuid::new_v4();
+
+        let mut left_stream = left.open_stream(stream_id).unwrap();
+        let mut right_stream = right.open_stream(stream_id).unwrap();
+
+        let data: Vec<u8> = (0..STREAM_WINDOW_SIZE * 4).map(|i| i as u8).collect();
+        let expected = data.clone();
+
+        let writer = tokio::spawn(async move {
+            left_stream.write_all(&data).await.unwrap();
+            left_stream.shutdown().await.unwrap();
+            left_stream
+        });
+
+        let mut received = Vec::new();
+        right_stream.read_to_end(&mut received).await.unwrap();
+
+        writer.await.unwrap();
+
+        assert_eq!(received, expected);
+    }
+
+    #[tokio::test]
+    async fn test_stream_past_window_is_reset() {
+        let (mut peer, stream) = tokio::io::duplex(64 * 1024);
+        let (multiplexer, _control) = Multiplexer::start_with(stream);
+        let stream_id = Uuid::new_v4();
+
+        let mut mux_stream = multiplexer.open_stream(stream_id).unwrap();
+
+        let frame = Frame::Data {
+            stream_id,
+            data: vec![0u8; MAX_FRAME_DATA_SIZE],
+        };
+
+        for _ in 0..STREAM_WINDOW_SIZE / MAX_FRAME_DATA_SIZE + 1 {
+            peer.write_all(&frame.encode()).await.unwrap();
+        }
+
+        assert_eq!(
+            Frame::read_from(&mut peer).await.unwrap(),
+            Frame::Reset { stream_id }
+        );
+
+        let error = mux_stream.write_all(b"data").await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ConnectionReset);
+        assert!(
+            !multiplexer
+                .shared
+                .streams
+                .lock()
+                .unwrap()
+                .contains_key(&stream_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dropped_stream_resets_peer() {
+        let ((left, _left_control), (right, _right_control)) = create_pair();
+        let stream_id = Uuid::new_v4();
+
+        let left_stream = left.open_stream(stream_id).unwrap();
+        let mut right_stream = right.open_stream(stream_id).unwrap();
+
+        drop(left_stream);
+
+        let mut buffer = [0u8; 16];
+        assert_eq!(right_stream.read(&mut buffer).await.unwrap(), 0);
+
+        let error = right_stream.write_all(b"data").await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ConnectionReset);
+    }
+
+    #[tokio::test]
+    async fn test_open_stream_twice() {
+        let ((left, _left_control), _) = create_pair();
+        let stream_id = Uuid::new_v4();
+
+        let _stream = left.open_stream(stream_id).unwrap();
+
+        assert_eq!(
+            left.open_stream(stream_id).unwrap_err().kind(),
+            ErrorKind::AlreadyExists
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_streams() {
+        let ((left, mut left_control), (right, mut right_control)) = create_pair();
+
+        left.shutdown();
+
+        let mut buffer = [0u8; 16];
+        assert_eq!(left_control.read(&mut buffer).await.unwrap(), 0);
+        assert_eq!(right_control.read(&mut buffer).await.unwrap(), 0);
+        assert!(left.open_stream(Uuid::new_v4()).is_err());
+        assert!(right.is_closed());
+    }
+}
@@ -28,7 +28,9 @@ pub async fn connect_to_address<Protocol: ProtocolSocket>(
     context: Protocol::Context,
 ) -> Result<(Protocol::Socket, SocketAddr)> {
     let addr_port = format!("{address}:{port}");
-    let socket_addrs: Vec<SocketAddr> = addr_port.to_socket_addrs()?.collect();
+    // IPv6 addresses are accepted with or without brackets.
+    let host = address.trim_start_matches('[').trim_end_matches(']');
+    let socket_addrs: Vec<SocketAddr> = (host, port).to_socket_addrs()?.collect();
 
     if socket_addrs.is_empty() {
         return Err(Error::new(
@@ -15,6 +15,10 @@ pub enum ClientEncryption {
     Tls {
         #[serde(skip_serializing_if = "Option::is_none", default)]
         ca_path: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        cert_path: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        key_path: Option<String>,
     },
     None,
 }
@@ -30,10 +34,26 @@ impl From<Option<ClientEncryption>> for ClientEncryption {
 
 impl Validatable for ClientEncryption {
     fn validate(&self, result: &mut Validation) {
-        if let Self::Tls { ca_path } = self
-            && let Some(cert) = ca_path
+        if let Self::Tls {
+            ca_path,
+            cert_path,
+            key_path,
+        } = self
         {
-            result.validate_rule::<FileMustExist>("ca_path", cert);
+            if let Some(cert) = ca_path {
+                result.validate_rule::<FileMustExist>("ca_path", cert);
+            }
+
+            match (cert_path, key_path) {
+                (Some(cert_path), Some(key_path)) => {
+                    result.validate_rule::<FileMustExist>("cert_path", cert_path);
+                    result.validate_rule::<FileMustExist>("key_path", key_path);
+                }
+                (None, None) => {}
+                _ => result.add_error(
+                    "Both cert_path and key_path must be set to use a client certificate",
+                ),
+            }
         }
     }
 }
@@ -45,8 +65,13 @@ pub async fn create_tcp_client(
 ) -> Result<Connection> {
     match connect_to_address::<TcpStream>(address, port, ()).await {
         Ok((stream, _)) => match encryption {
-            ClientEncryption::Tls { ca_path } => {
-                let tls = ClientTlsEncryption::new(ca_path).await;
+            ClientEncryption::Tls {
+                ca_path,
+                cert_path,
+                key_path,
+            } => {
+                let client_cert = cert_path.as_deref().zip(key_path.as_deref());
+                let tls = ClientTlsEncryption::new(ca_path, client_cert).await?;
 
                 info!("Connected to (TLS) server at {}", address);
 
@@ -67,3 +92,131 @@ pub async fn create_tcp_client(
         }
     }
 }
+
+#[derive(Debug, PartialEq)]
+pub struct ServerAddress {
+    pub host: String,
+    pub port: u16,
+    /// Set when the address starts with `https://`.
+    pub is_tls: bool,
+}
+
+/// Splits server address in `host[:port]` form, optionally prefixed with `http://` or
+/// `https://`, into host and port. Port defaults to `default_port` if missing. IPv6 addresses
+/// with a port must be in brackets, as in `[::1]:3456`.
+pub fn parse_server_address(server_address: &str, default_port: u16) -> Result<ServerAddress> {
+    let invalid_address = |reason: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid server address '{server_address}': {reason}"),
+        )
+    };
+
+    let (address, is_tls) = match server_address.strip_prefix("https://") {
+        Some(address) => (address, true),
+        None => (
+            server_address
+                .strip_prefix("http://")
+                .unwrap_or(server_address),
+            false,
+        ),
+    };
+
+    let (host, port) = match address.strip_prefix('[') {
+        Some(address) => {
+            let Some((host, rest)) = address.split_once(']') else {
+                return Err(invalid_address("missing closing bracket"));
+            };
+
+            match rest {
+                "" => (host, None),
+                _ => match rest.strip_prefix(':') {
+                    Some(port) => (host, Some(port)),
+                    None => return Err(invalid_address("unexpected text after bracket")),
+                },
+            }
+        }
+        None if address.matches(':').count() > 1 => (address, None),
+        None => match address.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (address, None),
+        },
+    };
+
+    if host.is_empty() {
+        return Err(invalid_address("missing host"));
+    }
+
+    let port = match port {
+        Some(port) => match port.parse::<u16>() {
+            Ok(port) if port > 0 => port,
+            _ => return Err(invalid_address(&format!("invalid port '{port}'"))),
+        },
+        None => default_port,
+    };
+
+    Ok(ServerAddress {
+        host: host.to_owned(),
+        port,
+        is_tls,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_address(host: &str, port: u16, is_tls: bool) -> ServerAddress {
+        ServerAddress {
+            host: host.to_string(),
+            port,
+            is_tls,
+        }
+    }
+
+    #[test]
+    fn test_parse_server_address() {
+        assert_eq!(
+            parse_server_address("example.com", 3456).unwrap(),
+            create_address("example.com", 3456, false)
+        );
+        assert_eq!(
+            parse_server_address("example.com:4000", 3456).unwrap(),
+            create_address("example.com", 4000, false)
+        );
+        assert_eq!(
+            parse_server_address("http://example.com:4000", 3456).unwrap(),
+            create_address("example.com", 4000, false)
+        );
+        assert_eq!(
+            parse_server_address("https://example.com:4000", 3456).unwrap(),
+            create_address("example.com", 4000, true)
+        );
+    }
+
+    #[test]
+    fn test_parse_server_address_with_ipv6() {
+        assert_eq!(
+            parse_server_address("[::1]:4000", 3456).unwrap(),
+            create_address("::1", 4000, false)
+        );
+        assert_eq!(
+            parse_server_address("[::1]", 3456).unwrap(),
+            create_address("::1", 3456, false)
+        );
+        assert_eq!(
+            parse_server_address("::1", 3456).unwrap(),
+            create_address("::1", 3456, false)
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_server_address() {
+        assert!(parse_server_address("http://example.com:invalid", 3456).is_err());
+        assert!(parse_server_address("example.com:70000", 3456).is_err());
+        assert!(parse_server_address("example.com:", 3456).is_err());
+        assert!(parse_server_address("tcp://example.com", 3456).is_err());
+        assert!(parse_server_address("[::1:4000", 3456).is_err());
+        assert!(parse_server_address(":4000", 3456).is_err());
+    }
+}
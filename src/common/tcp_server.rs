@@ -24,7 +24,8 @@ impl TcpServer {
                 ServerEncryption::Tls {
                     cert_path: cert,
                     key_path: key,
-                } => Some(ServerTlsEncryption::new(&cert, &key).await),
+                    client_ca_path,
+                } => Some(ServerTlsEncryption::new(&cert, &key, client_ca_path.as_deref()).await?),
             },
             listener: TcpListener::bind(format!("{address}:{port}")).await?,
         })
@@ -23,7 +23,7 @@ pub fn get_random_secret(len: usize) -> String {
 }
 
 pub fn is_constant_time_equals(a: &str, b: &str) -> bool {
-    return a.as_bytes().ct_eq(b.as_bytes()).into();
+    a.as_bytes().ct_eq(b.as_bytes()).into()
 }
 
 #[cfg(test)]
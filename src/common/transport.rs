@@ -65,7 +65,7 @@ fn serialize_message<T>(message: &T) -> Result<Bytes, MessageError>
 where
     T: ?Sized + serde::Serialize,
 {
-    let encoded: Vec<u8> = rmp_serde::to_vec(message)?;
+    let encoded: Vec<u8> = rmp_serde::to_vec_named(message)?;
     let mut bytes = BytesMut::with_capacity(encoded.len());
     bytes.put_slice(&encoded);
     Ok(bytes.freeze())
@@ -120,6 +120,10 @@ pub async fn read_message<T: AsyncReadExt + Unpin, M>(stream: &mut T) -> Result<
 where
     M: DeserializeOwned,
 {
+    deserialize_message(read_frame(stream).await?)
+}
+
+pub async fn read_frame<T: AsyncReadExt + Unpin>(stream: &mut T) -> Result<Bytes, MessageError> {
     let start_of_length = read_exact(stream, 1).await?;
 
     let rest_of_length_bytes = read_exact_with_timeout(stream, 3).await?;
@@ -135,9 +139,7 @@ where
         return Err(MessageError::InvalidLength(length));
     }
 
-    let message_bytes = read_exact_with_timeout(stream, length as usize).await?;
-
-    deserialize_message(message_bytes)
+    read_exact_with_timeout(stream, length as usize).await
 }
 
 pub async fn write_message<T: tokio::io::AsyncWriteExt + Unpin, M>(
@@ -147,7 +149,13 @@ pub async fn write_message<T: tokio::io::AsyncWriteExt + Unpin, M>(
 where
     M: ?Sized + serde::Serialize,
 {
-    let message_bytes = serialize_message(message)?;
+    write_frame(stream, &serialize_message(message)?).await
+}
+
+pub async fn write_frame<T: tokio::io::AsyncWriteExt + Unpin>(
+    stream: &mut T,
+    message_bytes: &[u8],
+) -> Result<(), MessageError> {
     let length: u32 = message_bytes.len() as u32;
 
     if length > MAX_MESSAGE_LENGTH {
@@ -158,7 +166,7 @@ where
     length_bytes.put_u32(length);
 
     stream.write_all(&length_bytes).await?;
-    stream.write_all(&message_bytes).await?;
+    stream.write_all(message_bytes).await?;
 
     Ok(())
 }
@@ -196,6 +204,25 @@ mod tests {
         assert_eq!(message, deserialized);
     }
 
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct OptionalMessage {
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        first: Option<u16>,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        second: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_deserialize_message_with_skipped_fields() {
+        let message = OptionalMessage {
+            first: None,
+            second: Some("value".to_string()),
+        };
+        let serialized = serialize_message(&message).unwrap();
+        let deserialized: OptionalMessage = deserialize_message(serialized).unwrap();
+        assert_eq!(message, deserialized);
+    }
+
     #[tokio::test]
     async fn test_read_message() {
         let message = TestMessage {
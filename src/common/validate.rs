@@ -14,6 +14,7 @@ pub trait RuleFor<Value> {
 pub struct Validation {
     breadcrumbs: Vec<String>,
     errors: Vec<String>,
+    warnings: Vec<String>,
 }
 
 impl Validation {
@@ -28,6 +29,7 @@ impl Validation {
     pub fn new() -> Self {
         Self {
             errors: vec![],
+            warnings: vec![],
             breadcrumbs: vec![],
         }
     }
@@ -73,13 +75,24 @@ impl Validation {
     }
 
     pub fn add_field_error(&mut self, field: &str, error: &str) {
-        self.errors.push(format!(
+        let error = self.format_field_message(field, error);
+        self.errors.push(error);
+    }
+
+    /// Adds a warning for the field. Warnings are reported but do not make the item invalid.
+    pub fn add_field_warning(&mut self, field: &str, warning: &str) {
+        let warning = self.format_field_message(field, warning);
+        self.warnings.push(warning);
+    }
+
+    fn format_field_message(&self, field: &str, message: &str) -> String {
+        format!(
             "{}{}{}: {}",
             self.breadcrumbs.join("."),
             if !self.breadcrumbs.is_empty() { "." } else { "" },
             field,
-            error.to_owned()
-        ));
+            message
+        )
     }
 
     pub fn is_valid(&self) -> bool {
@@ -89,6 +102,10 @@ impl Validation {
     pub fn errors(&self) -> &Vec<String> {
         &self.errors
     }
+
+    pub fn warnings(&self) -> &Vec<String> {
+        &self.warnings
+    }
 }
 
 #[cfg(test)]
@@ -185,4 +202,15 @@ mod tests {
         assert_eq!(validation.errors().len(), 1);
         assert_eq!(validation.errors()[0], "child: TestValidatable is invalid");
     }
+
+    #[test]
+    fn test_warnings_do_not_invalidate() {
+        let mut validation = Validation::new();
+        validation.push_breadcrumb("parent");
+        validation.add_field_warning("test_field", "Test warning");
+        validation.pop_breadcrumb();
+
+        assert!(validation.is_valid());
+        assert_eq!(validation.warnings(), &vec!["parent.test_field: Test warning"]);
+    }
 }
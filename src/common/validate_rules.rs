@@ -132,6 +132,14 @@ impl RuleFor<u16> for MustBeGreaterThanZero {
     }
 }
 
+impl RuleFor<u32> for MustBeGreaterThanZero {
+    fn validate(field: &str, value: &u32, result: &mut Validation) {
+        if *value == 0 {
+            result.add_field_error(field, "Value must be greater than zero.");
+        }
+    }
+}
+
 impl RuleFor<u64> for MustBeGreaterThanZero {
     fn validate(field: &str, value: &u64, result: &mut Validation) {
         if *value == 0 {
@@ -159,6 +167,42 @@ impl Rule for MustNotBeEmptyString {
     }
 }
 
+/// Checks an inclusive `(from, to)` port range. Errors are reported on `<field>_from` and
+/// `<field>_to`, so `reserve_ports` checks `reserve_ports_from` and `reserve_ports_to`.
+pub struct PortRangeMustBeValid;
+
+impl Rule for PortRangeMustBeValid {
+    type Value = (u16, u16);
+    fn validate(field: &str, value: &(u16, u16), result: &mut Validation) {
+        let (from, to) = *value;
+        let from_field = format!("{field}_from");
+
+        if to == 0 {
+            result.add_field_error(&format!("{field}_to"), "Port range cannot be empty.");
+            return;
+        }
+
+        if from > to {
+            result.add_field_error(
+                &from_field,
+                &format!("{from_field} must be less than {field}_to, range is reversed."),
+            );
+            return;
+        }
+
+        result.validate_rule::<PortMustBeValid>(&from_field, &from);
+
+        if from < 1024 {
+            result.add_field_warning(
+                &from_field,
+                &format!(
+                    "Range {from}-{to} includes privileged ports below 1024, which need elevated permissions to bind."
+                ),
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -368,6 +412,11 @@ mod tests {
         assert!(!validation.is_valid());
         assert_field_error(&validation, "value", "Value must be greater than zero.");
 
+        validation = Validation::new();
+        MustBeGreaterThanZero::validate("value", &0u32, &mut validation);
+        assert!(!validation.is_valid());
+        assert_field_error(&validation, "value", "Value must be greater than zero.");
+
         validation = Validation::new();
         MustBeGreaterThanZero::validate("value", &0u64, &mut validation);
         assert!(!validation.is_valid());
@@ -408,4 +457,38 @@ mod tests {
         MustNotBeEmptyString::validate("value", &"non-empty".to_string(), &mut validation);
         assert!(validation.is_valid());
     }
+
+    #[test]
+    fn test_port_range_must_be_valid() {
+        let mut validation = Validation::new();
+        PortRangeMustBeValid::validate("ports", &(4000, 4000), &mut validation);
+        assert!(validation.is_valid());
+        assert!(validation.warnings().is_empty());
+
+        validation = Validation::new();
+        PortRangeMustBeValid::validate("ports", &(5000, 4000), &mut validation);
+        assert_field_error(
+            &validation,
+            "ports_from",
+            "ports_from must be less than ports_to, range is reversed.",
+        );
+
+        validation = Validation::new();
+        PortRangeMustBeValid::validate("ports", &(0, 0), &mut validation);
+        assert_field_error(&validation, "ports_to", "Port range cannot be empty.");
+
+        validation = Validation::new();
+        PortRangeMustBeValid::validate("ports", &(0, 100), &mut validation);
+        assert_field_error(&validation, "ports_from", "Port cannot be zero.");
+    }
+
+    #[test]
+    fn test_port_range_privileged_ports_warning() {
+        let mut validation = Validation::new();
+        PortRangeMustBeValid::validate("ports", &(1000, 2000), &mut validation);
+
+        assert!(validation.is_valid());
+        assert_eq!(validation.warnings().len(), 1);
+        assert!(validation.warnings()[0].starts_with("ports_from: Range 1000-2000"));
+    }
 }
@@ -0,0 +1,260 @@
+use std::io::{Error, ErrorKind, Result};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    common::{
+        config_format::ConfigFormat,
+        validate::{Validatable, Validation},
+    },
+    configuration::{TunnelizeConfiguration, read_configuration, resolve_configuration_path},
+    server::configuration::ServerConfiguration,
+    tunnel::configuration::TunnelConfiguration,
+};
+
+const REDACTED_FIELDS: [&str; 6] = [
+    "tunnel_key",
+    "monitor_key",
+    "key",
+    "token_secret",
+    "password",
+    "token",
+];
+
+/// Configuration as it is used at runtime, with the selected profiles resolved.
+struct CheckedConfiguration {
+    server: Option<ServerConfiguration>,
+    tunnel: Option<TunnelConfiguration>,
+    profiles: Vec<(String, TunnelConfiguration)>,
+}
+
+impl Validatable for CheckedConfiguration {
+    fn validate(&self, result: &mut Validation) {
+        if self.server.is_none() && self.tunnel.is_none() && self.profiles.is_empty() {
+            result.add_error("Configuration must have a server, tunnel or profiles section.");
+        }
+
+        if let Some(server) = &self.server {
+            result.validate_child("server", server);
+        }
+
+        if let Some(tunnel) = &self.tunnel {
+            result.validate_child("tunnel", tunnel);
+        }
+
+        for (name, tunnel) in self.profiles.iter() {
+            result.validate_child(&format!("profiles.{name}"), tunnel);
+        }
+    }
+}
+
+impl CheckedConfiguration {
+    /// Resolves the given profile into the tunnel section. Without a profile, all profiles are
+    /// resolved so that each of them is checked.
+    fn resolve(
+        config: TunnelizeConfiguration,
+        profile: Option<&str>,
+    ) -> std::result::Result<Self, String> {
+        if let Some(profile) = profile {
+            let mut config = config;
+            config.select_tunnel_profile(Some(profile))?;
+
+            return Ok(Self {
+                server: config.server,
+                tunnel: config.tunnel,
+                profiles: Vec::new(),
+            });
+        }
+
+        let mut profiles = Vec::new();
+
+        for name in config.profiles.iter().flat_map(|profiles| profiles.keys()) {
+            let mut selected = config.clone();
+            selected.select_tunnel_profile(Some(name))?;
+            profiles.extend(selected.tunnel.map(|tunnel| (name.clone(), tunnel)));
+        }
+
+        Ok(Self {
+            server: config.server,
+            tunnel: config.tunnel,
+            profiles,
+        })
+    }
+
+    fn get_effective_value(&self) -> Result<Value> {
+        let mut profiles = Map::new();
+
+        for (name, tunnel) in self.profiles.iter() {
+            profiles.insert(name.clone(), serde_json::to_value(tunnel.with_defaults())?);
+        }
+
+        let mut value = serde_json::to_value(TunnelizeConfiguration {
+            server: self.server.as_ref().map(|server| server.with_defaults()),
+            tunnel: self.tunnel.as_ref().map(|tunnel| tunnel.with_defaults()),
+            tunnel_defaults: None,
+            profiles: None,
+        })?;
+
+        if let Value::Object(fields) = &mut value
+            && !profiles.is_empty()
+        {
+            fields.insert("profiles".to_owned(), Value::Object(profiles));
+        }
+
+        clean_value(&mut value);
+
+        Ok(value)
+    }
+}
+
+/// Removes unset values, which cannot be written in all formats, and hides secrets.
+fn clean_value(value: &mut Value) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(clean_value),
+        Value::Object(fields) => {
+            fields.retain(|_, item| !item.is_null());
+
+            for (key, item) in fields.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) && item.is_string() {
+                    *item = Value::String("<redacted>".to_owned());
+                    continue;
+                }
+
+                clean_value(item);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn check(
+    config_file: Option<String>,
+    profile: Option<String>,
+    format: Option<ConfigFormat>,
+) -> Result<()> {
+    let config_path = resolve_configuration_path(config_file.clone())?;
+    let format = format.unwrap_or_else(|| ConfigFormat::from_path(&config_path));
+
+    let config =
+        CheckedConfiguration::resolve(read_configuration(config_file)?, profile.as_deref())
+            .map_err(|e| {
+                eprintln!("{e}");
+                Error::new(ErrorKind::InvalidInput, e)
+            })?;
+
+    let validation = Validation::validate(&config);
+
+    if !validation.is_valid() {
+        eprintln!("Configuration is invalid. Please fix following errors:");
+        for error in validation.errors() {
+            eprintln!("- {error}");
+        }
+
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Configuration is invalid.",
+        ));
+    }
+
+    let effective = format
+        .serialize(&config.get_effective_value()?)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    println!("Configuration {} is valid.", config_path.display());
+
+    if !validation.warnings().is_empty() {
+        println!();
+        println!("Warnings:");
+        for warning in validation.warnings() {
+            println!("- {warning}");
+        }
+    }
+    println!();
+    println!("Effective configuration:");
+    println!("{}", effective.trim_end());
+
+    if let Some(server) = &config.server {
+        println!();
+        println!("Server listeners:");
+
+        for listener in server.get_listeners() {
+            println!("- {listener}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn create_configuration(value: Value) -> TunnelizeConfiguration {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_effective_configuration_has_defaults() {
+        let config = create_configuration(json!({
+            "server": {
+                "tunnel_key": "secret",
+                "endpoints": {
+                    "http": { "type": "http", "port": 8080, "hostname_template": "{name}.localhost" }
+                }
+            },
+            "tunnel": {
+                "server_address": "localhost",
+                "proxies": [{
+                    "endpoint_name": "http",
+                    "address": "localhost",
+                    "port": 3000,
+                    "endpoint_config": { "type": "http" },
+                    "health_check": { "type": "tcp" }
+                }]
+            }
+        }));
+
+        let value = CheckedConfiguration::resolve(config, None)
+            .unwrap()
+            .get_effective_value()
+            .unwrap();
+
+        assert_eq!(value["server"]["server_port"], 3456);
+        assert_eq!(value["server"]["tunnel_key"], "<redacted>");
+        assert_eq!(value["server"]["endpoints"]["http"]["address"], "0.0.0.0");
+        assert_eq!(value["tunnel"]["reconnect"]["max_delay_seconds"], 60);
+        assert_eq!(value["tunnel"]["proxies"][0]["health_check"]["port"], 3000);
+        assert!(value["tunnel"].get("dashboard").is_none());
+    }
+
+    #[test]
+    fn test_all_profiles_are_checked() {
+        let config = create_configuration(json!({
+            "tunnel_defaults": { "server_address": "localhost", "proxies": [] },
+            "profiles": {
+                "valid": {
+                    "proxies": [{
+                        "endpoint_name": "tcp",
+                        "address": "localhost",
+                        "port": 22,
+                        "endpoint_config": { "type": "tcp" }
+                    }]
+                },
+                "invalid": {}
+            }
+        }));
+
+        let checked = CheckedConfiguration::resolve(config.clone(), None).unwrap();
+        let validation = Validation::validate(&checked);
+
+        assert_eq!(
+            validation.errors(),
+            &vec!["profiles.invalid.proxies: At least one proxy is required."]
+        );
+
+        let checked = CheckedConfiguration::resolve(config, Some("valid")).unwrap();
+        assert!(Validation::validate(&checked).is_valid());
+        assert!(checked.tunnel.is_some());
+    }
+}
@@ -1,18 +1,21 @@
 use std::{
-    fs::File,
-    io::{BufReader, BufWriter, ErrorKind},
-    path::PathBuf,
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
 };
 
 use log::info;
 use serde::{Deserialize, Serialize};
 
-use serde_json::Value;
+use serde_json::{Map, Value};
 use tokio::io::Result;
 
 use crate::{
     common::{
-        cli::Commands,
+        cli::{Commands, TunnelRunArgs},
+        config_format::ConfigFormat,
+        interpolation::{expand_variables, resolve_secret_files},
         validate::{Validatable, Validation},
     },
     server::configuration::ServerConfiguration,
@@ -26,6 +29,66 @@ pub struct TunnelizeConfiguration {
 
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub tunnel: Option<TunnelConfiguration>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tunnel_defaults: Option<Map<String, Value>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub profiles: Option<BTreeMap<String, Map<String, Value>>>,
+}
+
+impl TunnelizeConfiguration {
+    /// Resolves the tunnel configuration from the named profile merged over `tunnel_defaults`.
+    /// Without a profile name, `tunnel` section is used if present, otherwise the only profile.
+    pub fn select_tunnel_profile(
+        &mut self,
+        profile: Option<&str>,
+    ) -> std::result::Result<(), String> {
+        let profiles = self.profiles.clone().unwrap_or_default();
+
+        let name = match profile {
+            Some(name) => name.to_owned(),
+            None if self.tunnel.is_some() || profiles.is_empty() => return Ok(()),
+            None if profiles.len() == 1 => profiles.keys().next().cloned().unwrap_or_default(),
+            None => {
+                return Err(format!(
+                    "Configuration has multiple profiles ({}), choose one with --profile.",
+                    profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+                ));
+            }
+        };
+
+        let Some(overrides) = profiles.get(&name) else {
+            return Err(format!("Profile '{name}' not found in configuration."));
+        };
+
+        let mut tunnel = Value::Object(self.tunnel_defaults.clone().unwrap_or_default());
+        merge_values(&mut tunnel, Value::Object(overrides.clone()));
+
+        self.tunnel = Some(
+            serde_json::from_value(tunnel)
+                .map_err(|e| format!("Profile '{name}' is invalid: {e}"))?,
+        );
+
+        Ok(())
+    }
+}
+
+/// Merges objects recursively, all other values from `overrides` replace values in `base`.
+fn merge_values(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
 }
 
 impl Validatable for TunnelizeConfiguration {
@@ -40,15 +103,35 @@ impl Validatable for TunnelizeConfiguration {
     }
 }
 
+const CONFIGURATION_FILES: [&str; 4] = [
+    "tunnelize.json",
+    "tunnelize.toml",
+    "tunnelize.yaml",
+    "tunnelize.yml",
+];
+
+/// Returns the first default configuration file which exists, or `tunnelize.json` if none do.
 pub fn get_configuration_path() -> std::result::Result<std::path::PathBuf, std::io::Error> {
-    let path = PathBuf::from("tunnelize.json");
+    let path = CONFIGURATION_FILES
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(CONFIGURATION_FILES[0]));
+
     Ok(path)
 }
 
-pub fn write_configuration(configuration: TunnelizeConfiguration) -> Result<()> {
-    let config_path = get_configuration_path()?;
+pub fn write_configuration(
+    configuration: TunnelizeConfiguration,
+    format: ConfigFormat,
+) -> Result<()> {
+    let config_path = PathBuf::from(format!("tunnelize.{}", format.get_extension()));
+
+    let contents = format
+        .serialize(&configuration)
+        .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
 
-    serde_json::to_writer_pretty(BufWriter::new(File::create(&config_path)?), &configuration)?;
+    fs::write(&config_path, contents)?;
 
     println!(
         "Initialized tunnel configuration at {}",
@@ -58,13 +141,35 @@ pub fn write_configuration(configuration: TunnelizeConfiguration) -> Result<()>
     Ok(())
 }
 
+pub fn resolve_configuration_path(config_file: Option<String>) -> Result<PathBuf> {
+    config_file
+        .map(|f| Ok(PathBuf::from(f)))
+        .unwrap_or_else(get_configuration_path)
+}
+
 pub fn load_configuration<T>(config_file: Option<String>) -> Result<T>
 where
     T: TryFrom<TunnelizeConfiguration, Error = &'static str>,
 {
-    let config_path = config_file
-        .map(|f| Ok::<PathBuf, std::io::Error>(PathBuf::from(f)))
-        .unwrap_or_else(get_configuration_path)?;
+    into_valid_configuration(read_configuration(config_file)?)
+}
+
+pub fn load_tunnel_configuration(
+    config_file: Option<String>,
+    profile: Option<&str>,
+) -> Result<TunnelConfiguration> {
+    let mut config = read_configuration(config_file)?;
+
+    if let Err(e) = config.select_tunnel_profile(profile) {
+        eprintln!("{e}");
+        return Err(std::io::Error::new(ErrorKind::InvalidInput, e));
+    }
+
+    into_valid_configuration(config)
+}
+
+pub fn read_configuration(config_file: Option<String>) -> Result<TunnelizeConfiguration> {
+    let config_path = resolve_configuration_path(config_file)?;
 
     if !config_path.exists() {
         println!(
@@ -81,12 +186,48 @@ where
         "Loading configuration from {}",
         config_path.to_str().unwrap_or("<unknown>")
     );
-    let reader = BufReader::new(File::open(&config_path)?);
+    parse_configuration(&fs::read_to_string(&config_path)?, &config_path).map_err(|errors| {
+        eprintln!("Configuration could not be loaded. Please fix following errors:");
+        for error in errors.iter() {
+            eprintln!("- {}", error.replace('\n', "\n  "));
+        }
 
-    let config: TunnelizeConfiguration = serde_json::from_reader(reader)?;
+        std::io::Error::new(ErrorKind::InvalidData, "Configuration could not be loaded.")
+    })
+}
 
+/// Parses configuration in the format of the file, expanding variables and secret files before
+/// the values are read into the configuration.
+fn parse_configuration(
+    text: &str,
+    config_path: &Path,
+) -> std::result::Result<TunnelizeConfiguration, Vec<String>> {
+    let format = ConfigFormat::from_path(config_path);
+    let mut value: Value = format.parse(text).map_err(|e| vec![e])?;
+
+    expand_variables(&mut value, &|name| std::env::var(name).ok())?;
+    resolve_secret_files(&mut value)?;
+
+    serde_json::from_value(value).map_err(|e| {
+        // Values do not keep their position in the file, so the original text is parsed again
+        // to get the error with its line. If the text is valid, an expanded value caused it.
+        match format.parse::<TunnelizeConfiguration>(text) {
+            Err(located) => vec![located],
+            Ok(_) => vec![e.to_string()],
+        }
+    })
+}
+
+fn into_valid_configuration<T>(config: TunnelizeConfiguration) -> Result<T>
+where
+    T: TryFrom<TunnelizeConfiguration, Error = &'static str>,
+{
     let validation_result = Validation::validate(&config);
 
+    for warning in validation_result.warnings() {
+        eprintln!("Warning: {warning}");
+    }
+
     if !validation_result.is_valid() {
         eprintln!("Configuration is invalid. Please fix following errors:");
         for error in validation_result.errors() {
@@ -105,22 +246,24 @@ where
 pub fn get_default_command() -> Commands {
     let tunnel = Commands::Tunnel {
         config: None,
+        profile: None,
         #[cfg(debug_assertions)]
         verbose: true,
         #[cfg(not(debug_assertions))]
         verbose: false,
+        run: TunnelRunArgs::default(),
+        command: None,
     };
 
     let Ok(config_path) = get_configuration_path() else {
         return tunnel;
     };
 
-    let Ok(file) = File::open(config_path) else {
+    let Ok(text) = fs::read_to_string(&config_path) else {
         return tunnel;
     };
 
-    let reader = BufReader::new(file);
-    let Ok(json) = serde_json::from_reader::<_, Value>(reader) else {
+    let Ok(json) = ConfigFormat::from_path(&config_path).parse::<Value>(&text) else {
         return tunnel;
     };
 
@@ -131,7 +274,7 @@ pub fn get_default_command() -> Commands {
         for (key, value) in obj.iter() {
             if key == "server" && !value.is_null() {
                 server_key_set = true;
-            } else if key == "tunnel" && !value.is_null() {
+            } else if (key == "tunnel" || key == "profiles") && !value.is_null() {
                 tunnel_key_set = true;
             }
         }
@@ -143,3 +286,174 @@ pub fn get_default_command() -> Commands {
 
     tunnel
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn create_configuration(value: Value) -> TunnelizeConfiguration {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn create_profiles_configuration() -> TunnelizeConfiguration {
+        create_configuration(json!({
+            "tunnel_defaults": {
+                "name": "my-tunnel",
+                "server_address": "localhost",
+                "reconnect": { "initial_delay_seconds": 2, "max_delay_seconds": 30 },
+                "proxies": []
+            },
+            "profiles": {
+                "staging": {
+                    "server_address": "staging.example.com",
+                    "reconnect": { "max_delay_seconds": 10 },
+                    "proxies": [{
+                        "endpoint_name": "http",
+                        "address": "localhost",
+                        "port": 8080,
+                        "endpoint_config": { "type": "http" }
+                    }]
+                },
+                "production": {
+                    "server_address": "example.com"
+                }
+            }
+        }))
+    }
+
+    #[test]
+    fn test_select_tunnel_profile_merges_defaults() {
+        let mut config = create_profiles_configuration();
+
+        config.select_tunnel_profile(Some("staging")).unwrap();
+
+        let tunnel = config.tunnel.unwrap();
+        assert_eq!(tunnel.name.as_deref(), Some("my-tunnel"));
+        assert_eq!(tunnel.server_address, "staging.example.com");
+        assert_eq!(tunnel.proxies.len(), 1);
+
+        let reconnect = tunnel.reconnect.unwrap();
+        assert_eq!(reconnect.get_initial_delay_seconds(), 2);
+        assert_eq!(reconnect.get_max_delay_seconds(), 10);
+    }
+
+    #[test]
+    fn test_select_tunnel_profile_errors() {
+        let mut config = create_profiles_configuration();
+
+        assert!(config.select_tunnel_profile(None).is_err());
+        assert!(config.select_tunnel_profile(Some("missing")).is_err());
+        assert!(config.tunnel.is_none());
+    }
+
+    #[test]
+    fn test_select_only_tunnel_profile() {
+        let mut config = create_configuration(json!({
+            "profiles": {
+                "only": { "server_address": "example.com", "proxies": [] }
+            }
+        }));
+
+        config.select_tunnel_profile(None).unwrap();
+
+        assert_eq!(config.tunnel.unwrap().server_address, "example.com");
+    }
+
+    #[test]
+    fn test_tunnel_section_used_without_profile() {
+        let mut config = create_configuration(json!({
+            "tunnel": { "server_address": "tunnel.example.com", "proxies": [] },
+            "profiles": {
+                "staging": { "server_address": "staging.example.com", "proxies": [] },
+                "production": { "server_address": "example.com", "proxies": [] }
+            }
+        }));
+
+        config.select_tunnel_profile(None).unwrap();
+        assert_eq!(
+            config.tunnel.as_ref().unwrap().server_address,
+            "tunnel.example.com"
+        );
+
+        config.select_tunnel_profile(Some("production")).unwrap();
+        assert_eq!(config.tunnel.unwrap().server_address, "example.com");
+    }
+
+    #[test]
+    fn test_parse_configuration_formats() {
+        let json = r#"{
+            // Tunnel used for local development.
+            "tunnel": {
+                "server_address": "example.com",
+                "proxies": [{
+                    "endpoint_name": "http",
+                    "address": "localhost",
+                    "port": 8080,
+                    "endpoint_config": { "type": "http" } /* random name */
+                }]
+            }
+        }"#;
+
+        let toml = r#"
+            # Tunnel used for local development.
+            [tunnel]
+            server_address = "example.com"
+
+            [[tunnel.proxies]]
+            endpoint_name = "http"
+            address = "localhost"
+            port = 8080
+            endpoint_config = { type = "http" }
+        "#;
+
+        let yaml = r#"
+# Tunnel used for local development.
+tunnel:
+  server_address: example.com
+  proxies:
+    - endpoint_name: http
+      address: localhost
+      port: 8080
+      endpoint_config:
+        type: http
+"#;
+
+        for (text, file) in [
+            (json, "tunnelize.json"),
+            (toml, "tunnelize.toml"),
+            (yaml, "tunnelize.yml"),
+        ] {
+            let config = parse_configuration(text, Path::new(file)).unwrap();
+            assert!(Validation::validate(&config).is_valid());
+
+            let tunnel = config.tunnel.unwrap();
+            assert_eq!(tunnel.server_address, "example.com");
+            assert_eq!(tunnel.proxies[0].port, 8080);
+        }
+    }
+
+    #[test]
+    fn test_parse_configuration_error_has_line() {
+        let toml =
+            "[tunnel]\nserver_address = \"localhost\"\nproxies = []\nserver_port = \"abc\"\n";
+        let errors = parse_configuration(toml, Path::new("tunnelize.toml")).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("line 4"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn test_written_configuration_can_be_read() {
+        let config = create_profiles_configuration();
+
+        for format in [ConfigFormat::Json, ConfigFormat::Toml, ConfigFormat::Yaml] {
+            let text = format.serialize(&config).unwrap();
+            let path = format!("tunnelize.{}", format.get_extension());
+            let mut parsed = parse_configuration(&text, Path::new(&path)).unwrap();
+
+            parsed.select_tunnel_profile(Some("staging")).unwrap();
+            assert_eq!(parsed.tunnel.unwrap().server_address, "staging.example.com");
+        }
+    }
+}
@@ -5,7 +5,9 @@ use log::error;
 use crate::{
     common::{
         cli::InitCommands,
-        tcp_client::{create_tcp_client, ClientEncryption},
+        config_format::ConfigFormat,
+        key_pair::PrivateKey,
+        tcp_client::{create_tcp_client, parse_server_address, ClientEncryption, ServerAddress},
         text::get_random_secret,
     },
     configuration::{write_configuration, TunnelizeConfiguration},
@@ -24,19 +26,26 @@ use crate::{
     tunnel::configuration::{ProxyConfiguration, TunnelConfiguration, TunnelProxy},
 };
 
-pub async fn init_for(command: InitCommands) -> Result<(), std::io::Error> {
+const PRIVATE_KEY_FILE: &str = "tunnelize_ed25519.pem";
+
+pub async fn init_for(command: InitCommands, format: ConfigFormat) -> Result<(), std::io::Error> {
     match command {
         InitCommands::All => {
             let monitor_key = get_random_secret(32);
             let monitor_password = get_random_secret(32);
 
-            write_configuration(TunnelizeConfiguration {
-                server: Some(get_default_server_configuration(
-                    monitor_key.clone(),
-                    monitor_password.clone(),
-                )),
-                tunnel: Some(get_default_tunnel_configuration(Some(monitor_key.clone()))),
-            })?;
+            write_configuration(
+                TunnelizeConfiguration {
+                    server: Some(get_default_server_configuration(
+                        monitor_key.clone(),
+                        monitor_password.clone(),
+                    )),
+                    tunnel: Some(get_default_tunnel_configuration(Some(monitor_key.clone()))),
+                    tunnel_defaults: None,
+                    profiles: None,
+                },
+                format,
+            )?;
 
             print_generated_monitor_credentials(&monitor_key, &monitor_password);
         }
@@ -47,6 +56,7 @@ pub async fn init_for(command: InitCommands) -> Result<(), std::io::Error> {
             write_configuration(
                 get_default_server_configuration(monitor_key.clone(), monitor_password.clone())
                     .into(),
+                format,
             )?;
 
             print_generated_monitor_credentials(&monitor_key, &monitor_password);
@@ -56,32 +66,40 @@ pub async fn init_for(command: InitCommands) -> Result<(), std::io::Error> {
             ca: ca_path,
             tls,
             key,
+            generate_key,
         } => {
-            let Some(mut server_address) = server else {
-                write_configuration(get_default_tunnel_configuration(None).into())?;
+            let private_key_path = match generate_key {
+                true => Some(generate_private_key()?),
+                false => None,
+            };
+
+            let Some(server_address) = server else {
+                write_configuration(
+                    TunnelConfiguration {
+                        private_key_path,
+                        ..get_default_tunnel_configuration(None)
+                    }
+                    .into(),
+                    format,
+                )?;
 
                 return Ok(());
             };
 
             println!("Connecting to: {server_address}");
 
-            if server_address.starts_with("http://") || server_address.starts_with("https://") {
-                server_address = server_address
-                    .replace("http://", "")
-                    .replace("https://", "");
-            }
-
-            let (address, port) = match server_address.find(':') {
-                Some(index) => {
-                    let (address, port) = server_address.split_at(index);
-
-                    (address.to_owned(), port[1..].parse::<u16>().unwrap_or(3456))
-                }
-                None => (server_address, 3456),
-            };
+            let ServerAddress {
+                host: address,
+                port,
+                is_tls,
+            } = parse_server_address(&server_address, 3456)?;
 
             let encryption: Option<ClientEncryption> =
-                tls.then_some(ClientEncryption::Tls { ca_path });
+                (tls || is_tls).then_some(ClientEncryption::Tls {
+                    ca_path,
+                    cert_path: None,
+                    key_path: None,
+                });
 
             let mut connection =
                 match create_tcp_client(&address, port, encryption.clone().into()).await {
@@ -119,6 +137,14 @@ pub async fn init_for(command: InitCommands) -> Result<(), std::io::Error> {
                 encryption: Some(encryption.into()),
                 tunnel_key: key,
                 monitor_key: None,
+                private_key_path,
+                reconnect: None,
+                multiplex_links: None,
+                watch_config: None,
+                control_socket: None,
+                dashboard: None,
+                inspector: None,
+                status_file: None,
                 proxies: Vec::new(),
             };
 
@@ -138,7 +164,13 @@ pub async fn init_for(command: InitCommands) -> Result<(), std::io::Error> {
                                 desired_name: http
                                     .allow_custom_hostnames
                                     .then(|| "custom-name".to_owned()),
+                                pool: None,
                             },
+                            tls: None,
+                            health_check: None,
+                            targets: None,
+                            target_selection: None,
+                            target_cooldown_seconds: None,
                         });
                     }
                     PublicEndpointConfiguration::Tcp(tcp) => {
@@ -153,7 +185,13 @@ pub async fn init_for(command: InitCommands) -> Result<(), std::io::Error> {
                                 desired_port: tcp
                                     .allow_desired_port
                                     .then_some(tcp.reserve_ports_from),
+                                pool: None,
                             },
+                            tls: None,
+                            health_check: None,
+                            targets: None,
+                            target_selection: None,
+                            target_cooldown_seconds: None,
                         });
                     }
                     PublicEndpointConfiguration::Udp(udp) => {
@@ -169,7 +207,13 @@ pub async fn init_for(command: InitCommands) -> Result<(), std::io::Error> {
                                     .allow_desired_port
                                     .then_some(udp.reserve_ports_from),
                                 bind_address: None,
+                                pool: None,
                             },
+                            tls: None,
+                            health_check: None,
+                            targets: None,
+                            target_selection: None,
+                            target_cooldown_seconds: None,
                         });
                     }
                     PublicEndpointConfiguration::Monitoring(monitor) => {
@@ -182,7 +226,7 @@ pub async fn init_for(command: InitCommands) -> Result<(), std::io::Error> {
                 port = port.wrapping_add(1);
             }
 
-            write_configuration(tunnel_config.into())?;
+            write_configuration(tunnel_config.into(), format)?;
 
             connection.shutdown().await;
         }
@@ -200,6 +244,28 @@ fn print_generated_monitor_credentials(monitor_key: &str, monitor_password: &str
     println!();
 }
 
+/// Generates a tunnel key pair and prints the public key, which has to be added to the
+/// authorized keys file on the server.
+fn generate_private_key() -> Result<String, std::io::Error> {
+    let path = std::env::current_dir()?.join(PRIVATE_KEY_FILE);
+    let path = path.display().to_string();
+
+    let private_key = PrivateKey::generate()?;
+
+    if let Err(e) = private_key.write(&path) {
+        error!("Could not write private key to '{}': {}", path, e);
+        return Err(e);
+    }
+
+    println!("Private key written to: {path}");
+    println!("Add the following line to the authorized keys file on the server:");
+    println!();
+    println!("{} my-tunnel", private_key.get_public_key());
+    println!();
+
+    Ok(path)
+}
+
 fn get_default_tunnel_configuration(monitor_key: Option<String>) -> TunnelConfiguration {
     let mut configuration = TunnelConfiguration {
         name: Some("my-tunnel".to_owned()),
@@ -209,6 +275,14 @@ fn get_default_tunnel_configuration(monitor_key: Option<String>) -> TunnelConfig
         encryption: None,
         tunnel_key: None,
         monitor_key,
+        private_key_path: None,
+        reconnect: None,
+        multiplex_links: None,
+        watch_config: None,
+        control_socket: None,
+        dashboard: None,
+        inspector: None,
+        status_file: None,
         proxies: Vec::new(),
     };
 
@@ -218,14 +292,28 @@ fn get_default_tunnel_configuration(monitor_key: Option<String>) -> TunnelConfig
         port: 8080,
         endpoint_config: ProxyConfiguration::Http {
             desired_name: Some("myname".to_owned()),
+            pool: None,
         },
+        tls: None,
+        health_check: None,
+        targets: None,
+        target_selection: None,
+        target_cooldown_seconds: None,
     });
 
     configuration.proxies.push(TunnelProxy {
         address: "localhost".to_owned(),
         endpoint_name: "tcp".to_owned(),
         port: 8081,
-        endpoint_config: ProxyConfiguration::Tcp { desired_port: None },
+        endpoint_config: ProxyConfiguration::Tcp {
+            desired_port: None,
+            pool: None,
+        },
+        tls: None,
+        health_check: None,
+        targets: None,
+        target_selection: None,
+        target_cooldown_seconds: None,
     });
 
     configuration.proxies.push(TunnelProxy {
@@ -235,7 +323,13 @@ fn get_default_tunnel_configuration(monitor_key: Option<String>) -> TunnelConfig
         endpoint_config: ProxyConfiguration::Udp {
             desired_port: None,
             bind_address: None,
+            pool: None,
         },
+        tls: None,
+        health_check: None,
+        targets: None,
+        target_selection: None,
+        target_cooldown_seconds: None,
     });
 
     configuration
@@ -251,11 +345,19 @@ fn get_default_server_configuration(
         monitor_key: Some(monitor_key),
         max_tunnel_input_wait: None,
         tunnel_key: None,
+        tunnel_credentials: None,
+        client_certificates: None,
+        authorized_keys_file: None,
+        auth_hook: None,
+        token_secret: None,
+        token_revocation_file: None,
         endpoints: HashMap::new(),
         max_tunnels: None,
         max_clients: None,
         max_proxies_per_tunnel: None,
         max_input_read_length: None,
+        tunnel_resume_grace_seconds: None,
+        allow_multiplexed_links: None,
         encryption: None,
     };
 
@@ -270,6 +372,8 @@ fn get_default_server_configuration(
             full_url_template: None,
             allow_custom_hostnames: None,
             require_authorization: None,
+            allow_pools: None,
+            pool_strategy: None,
         }),
     );
 
@@ -296,6 +400,8 @@ fn get_default_server_configuration(
             encryption: None,
             full_hostname_template: Some("localhost:{port}".to_owned()),
             address: None,
+            allow_pools: None,
+            pool_strategy: None,
         }),
     );
 
@@ -308,6 +414,8 @@ fn get_default_server_configuration(
             inactivity_timeout: None,
             full_hostname_template: Some("localhost:{port}".to_owned()),
             address: None,
+            allow_pools: None,
+            pool_strategy: None,
         }),
     );
 
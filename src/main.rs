@@ -1,5 +1,5 @@
 use common::{
-    cli::{parse_command, Commands, InitCommands},
+    cli::{parse_command, Commands, ConfigCommands, InitCommands, ProxyType},
     logger::initialize_logger,
 };
 use configuration::get_default_command;
@@ -7,6 +7,7 @@ use init::init_for;
 use log::info;
 
 mod common;
+mod config_check;
 pub mod configuration;
 mod init;
 mod server;
@@ -22,7 +23,7 @@ async fn main() -> Result<(), std::io::Error> {
     initialize_logger(&command);
 
     if let Err(e) = run_command(command).await {
-        info!("Command failed. Reason: {}", e.to_string());
+        info!("Command failed. Reason: {}", e);
         std::process::exit(1);
     }
 
@@ -31,19 +32,61 @@ async fn main() -> Result<(), std::io::Error> {
 
 async fn run_command(command: Commands) -> Result<(), std::io::Error> {
     match command {
-        Commands::Init { command } => {
-            init_for(command.unwrap_or(InitCommands::All)).await?;
+        Commands::Init { command, format } => {
+            init_for(command.unwrap_or(InitCommands::All), format).await?;
         }
         Commands::Server { config } => {
             info!("Starting server...");
 
             server::start(config).await?;
         }
-        Commands::Tunnel { config, .. } => {
-            tunnel::start(config).await?;
+        Commands::Tunnel {
+            config,
+            profile,
+            command: Some(command),
+            ..
+        } => {
+            tunnel::process_tunnel_command(command, config, profile).await?;
         }
-        Commands::Monitor { command, config } => {
-            tunnel::process_monitor_command(command, config).await?;
+        Commands::Tunnel {
+            config,
+            profile,
+            run,
+            ..
+        } => {
+            tunnel::start(config, profile, run).await?;
+        }
+        Commands::Http { tunnel, name } => {
+            tunnel::start_quick(ProxyType::Http, tunnel, name, None).await?;
+        }
+        Commands::Tcp {
+            tunnel,
+            desired_port,
+        } => {
+            tunnel::start_quick(ProxyType::Tcp, tunnel, None, desired_port).await?;
+        }
+        Commands::Udp {
+            tunnel,
+            desired_port,
+        } => {
+            tunnel::start_quick(ProxyType::Udp, tunnel, None, desired_port).await?;
+        }
+        Commands::Monitor {
+            command,
+            config,
+            profile,
+        } => {
+            tunnel::process_monitor_command(command, config, profile).await?;
+        }
+        Commands::Config {
+            command:
+                ConfigCommands::Check {
+                    config,
+                    profile,
+                    format,
+                },
+        } => {
+            config_check::check(config, profile, format)?;
         }
     }
 
@@ -0,0 +1,390 @@
+use std::{process::Stdio, sync::Arc, time::Duration};
+
+use aws_lc_rs::digest::{SHA256, digest};
+use axum::http::Uri;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, Error, ErrorKind, Result},
+    net::TcpStream,
+    process::Command,
+    time::timeout,
+};
+
+use crate::{
+    common::{
+        connection::Connection,
+        encryption::ClientTlsEncryption,
+        validate::{Validatable, Validation},
+        validate_rules::{MustBeGreaterThanZero, MustNotBeEmptyString},
+    },
+    tunnel::configuration::ProxyConfiguration,
+};
+
+use super::{services::Services, tunnel_policy::TunnelPolicy};
+
+const MAX_RESPONSE_LENGTH: u64 = 64 * 1024;
+
+/// External service which decides access when none of the keys configured on the server match.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuthHookConfig {
+    #[serde(flatten)]
+    pub backend: AuthHookBackend,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timeout_seconds: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cache_ttl_seconds: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum AuthHookBackend {
+    /// Runs the executable with the request written to its standard input.
+    Exec {
+        command: String,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        args: Option<Vec<String>>,
+    },
+    /// Sends the request as a JSON `POST` to the URL.
+    Http { url: String },
+}
+
+impl AuthHookConfig {
+    pub fn get_timeout_seconds(&self) -> u64 {
+        self.timeout_seconds.unwrap_or(5)
+    }
+
+    pub fn get_cache_ttl_seconds(&self) -> u64 {
+        self.cache_ttl_seconds.unwrap_or(60)
+    }
+}
+
+impl Validatable for AuthHookConfig {
+    fn validate(&self, result: &mut Validation) {
+        match &self.backend {
+            AuthHookBackend::Exec { command, .. } => {
+                result.validate_rule::<MustNotBeEmptyString>("command", command);
+            }
+            AuthHookBackend::Http { url } => {
+                if let Err(e) = parse_url(url) {
+                    result.add_field_error("url", &format!("{e}."));
+                }
+            }
+        }
+
+        if let Some(timeout_seconds) = &self.timeout_seconds {
+            result.validate_rule_for::<u64, MustBeGreaterThanZero>(
+                "timeout_seconds",
+                timeout_seconds,
+            );
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthHookKind {
+    Tunnel,
+    Monitor,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AuthHookProxy {
+    pub endpoint_name: String,
+    #[serde(flatten)]
+    pub proxy: ProxyConfiguration,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AuthHookRequest {
+    pub kind: AuthHookKind,
+    pub key: Option<String>,
+    pub client_address: Option<String>,
+    pub certificate_names: Vec<String>,
+    pub proxies: Vec<AuthHookProxy>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuthHookResponse {
+    pub allow: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub identity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub policy: Option<TunnelPolicy>,
+}
+
+impl AuthHookResponse {
+    fn deny() -> Self {
+        Self {
+            allow: false,
+            identity: None,
+            policy: None,
+        }
+    }
+}
+
+/// Asks the hook whether the request is allowed. Answers are cached for `cache_ttl_seconds`,
+/// failed calls are not cached and deny access.
+pub async fn authorize(services: &Arc<Services>, request: &AuthHookRequest) -> AuthHookResponse {
+    let Some(hook) = services.get_config().auth_hook.clone() else {
+        return AuthHookResponse::deny();
+    };
+
+    let cache_key = match serde_json::to_vec(request) {
+        Ok(json) => STANDARD.encode(digest(&SHA256, &json)),
+        Err(e) => {
+            error!("Failed to encode auth hook request: {}", e);
+            return AuthHookResponse::deny();
+        }
+    };
+
+    if let Some(response) = services.get_auth_cache_manager().await.get(&cache_key) {
+        debug!("Using cached auth hook response.");
+        return response;
+    }
+
+    let wait = Duration::from_secs(hook.get_timeout_seconds());
+
+    let response = match timeout(wait, call_hook(&hook.backend, request)).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => {
+            error!("Auth hook failed: {}", e);
+            return AuthHookResponse::deny();
+        }
+        Err(_) => {
+            error!("Auth hook did not respond in {} seconds.", wait.as_secs());
+            return AuthHookResponse::deny();
+        }
+    };
+
+    if let Some(policy) = &response.policy
+        && let Some(error) = Validation::validate(policy).errors().first()
+    {
+        error!("Auth hook returned an invalid policy: {}", error);
+        return AuthHookResponse::deny();
+    }
+
+    services.get_auth_cache_manager().await.insert(
+        cache_key,
+        response.clone(),
+        Duration::from_secs(hook.get_cache_ttl_seconds()),
+    );
+
+    response
+}
+
+async fn call_hook(
+    backend: &AuthHookBackend,
+    request: &AuthHookRequest,
+) -> Result<AuthHookResponse> {
+    let body = serde_json::to_vec(request)?;
+
+    match backend {
+        AuthHookBackend::Exec { command, args } => call_exec(command, args, &body).await,
+        AuthHookBackend::Http { url } => call_http(url, &body).await,
+    }
+}
+
+async fn call_exec(
+    command: &str,
+    args: &Option<Vec<String>>,
+    body: &[u8],
+) -> Result<AuthHookResponse> {
+    let mut child = Command::new(command)
+        .args(args.iter().flatten())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| Error::other(format!("Failed to run '{command}': {e}")))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(body).await?;
+    }
+
+    let output = child.wait_with_output().await?;
+
+    if !output.status.success() {
+        return Err(Error::other(format!(
+            "'{command}' exited with {}",
+            output.status
+        )));
+    }
+
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+async fn call_http(url: &str, body: &[u8]) -> Result<AuthHookResponse> {
+    let (uri, is_tls) = parse_url(url).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    let host = uri.host().unwrap_or_default();
+    let port = uri.port_u16().unwrap_or(if is_tls { 443 } else { 80 });
+
+    let stream = TcpStream::connect((host, port)).await?;
+
+    let connection = match is_tls {
+        true => {
+            ClientTlsEncryption::new_upstream(None, false)?
+                .connect(stream, host)
+                .await?
+        }
+        false => Connection::from(stream),
+    };
+
+    let mut stream = connection.into_stream()?;
+
+    let head = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        uri.path_and_query()
+            .map(|path| path.as_str())
+            .unwrap_or("/"),
+        uri.authority()
+            .map(|authority| authority.as_str())
+            .unwrap_or(host),
+        body.len()
+    );
+
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+
+    let mut response = Vec::new();
+    stream
+        .take(MAX_RESPONSE_LENGTH)
+        .read_to_end(&mut response)
+        .await?;
+
+    parse_http_response(&response)
+}
+
+fn parse_url(url: &str) -> std::result::Result<(Uri, bool), String> {
+    let uri: Uri = url.parse().map_err(|e| format!("URL is not valid: {e}"))?;
+
+    let is_tls = match uri.scheme_str() {
+        Some("http") => false,
+        Some("https") => true,
+        _ => return Err("URL must start with http:// or https://".to_owned()),
+    };
+
+    if uri.host().is_none_or(str::is_empty) {
+        return Err("URL must contain a host".to_owned());
+    }
+
+    Ok((uri, is_tls))
+}
+
+/// Status 200 must contain the answer in the body. Statuses 401 and 403 deny access without
+/// a body, all other statuses are treated as errors.
+fn parse_http_response(response: &[u8]) -> Result<AuthHookResponse> {
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut parsed = httparse::Response::new(&mut headers);
+
+    let head_length = match parsed.parse(response) {
+        Ok(httparse::Status::Complete(length)) => length,
+        Ok(httparse::Status::Partial) => {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "Incomplete HTTP response",
+            ));
+        }
+        Err(e) => return Err(Error::new(ErrorKind::InvalidData, e)),
+    };
+
+    let status = parsed.code.unwrap_or_default();
+
+    if status == 401 || status == 403 {
+        return Ok(AuthHookResponse::deny());
+    }
+
+    if status != 200 {
+        return Err(Error::other(format!("Hook responded with status {status}")));
+    }
+
+    let is_chunked = parsed.headers.iter().any(|header| {
+        header.name.eq_ignore_ascii_case("Transfer-Encoding")
+            && String::from_utf8_lossy(header.value)
+                .to_lowercase()
+                .contains("chunked")
+    });
+
+    let body = &response[head_length..];
+
+    if is_chunked {
+        let body = decode_chunked(body)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid chunked body"))?;
+        return Ok(serde_json::from_slice(&body)?);
+    }
+
+    Ok(serde_json::from_slice(body)?)
+}
+
+fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+
+    loop {
+        let line_end = body.windows(2).position(|window| window == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_end]).ok()?;
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+
+        body = &body[line_end + 2..];
+
+        if size == 0 {
+            return Some(decoded);
+        }
+
+        decoded.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_http_response() {
+        let response = parse_http_response(
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"allow\": true, \"identity\": \"alice\"}",
+        )
+        .unwrap();
+        assert!(response.allow);
+        assert_eq!(response.identity, Some("alice".to_string()));
+
+        let response = parse_http_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\n{\"all\r\na;ext=1\r\now\": true}\r\n0\r\n\r\n",
+        )
+        .unwrap();
+        assert!(response.allow);
+
+        let response = parse_http_response(b"HTTP/1.1 403 Forbidden\r\n\r\n").unwrap();
+        assert_eq!(response, AuthHookResponse::deny());
+
+        assert!(parse_http_response(b"HTTP/1.1 500 Internal Server Error\r\n\r\n").is_err());
+        assert!(parse_http_response(b"HTTP/1.1 200 OK\r\n\r\nnot json").is_err());
+    }
+
+    #[test]
+    fn test_validate_auth_hook_config() {
+        let config: AuthHookConfig = toml::from_str(
+            "type = \"http\"\nurl = \"https://auth.example.com/tunnels\"\ncache_ttl_seconds = 30",
+        )
+        .unwrap();
+        assert!(Validation::validate(&config).is_valid());
+        assert_eq!(config.get_cache_ttl_seconds(), 30);
+        assert_eq!(config.get_timeout_seconds(), 5);
+
+        let config: AuthHookConfig =
+            serde_json::from_str(r#"{"type": "http", "url": "ftp://example.com"}"#).unwrap();
+        assert_eq!(
+            Validation::validate(&config).errors(),
+            &vec!["url: URL must start with http:// or https://.".to_string()]
+        );
+
+        let config: AuthHookConfig =
+            serde_json::from_str(r#"{"type": "exec", "command": "", "timeout_seconds": 0}"#)
+                .unwrap();
+        assert_eq!(Validation::validate(&config).errors().len(), 2);
+    }
+}
@@ -0,0 +1,137 @@
+use std::fs;
+
+use crate::common::{key_pair::PublicKey, validate::Validation};
+
+use super::tunnel_policy::TunnelPolicy;
+
+/// Public key a tunnel can authenticate with, read from a line of the authorized keys file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthorizedKey {
+    pub name: String,
+    pub public_key: PublicKey,
+    pub policy: Option<TunnelPolicy>,
+}
+
+impl AuthorizedKey {
+    pub fn get_policy(&self) -> TunnelPolicy {
+        self.policy.clone().unwrap_or_default()
+    }
+
+    /// Parses a line in `ssh-ed25519 <key> <name> [policy]` form, where the policy is written
+    /// as JSON.
+    fn parse(line: &str) -> Result<Self, String> {
+        let (key_type, rest) = split_field(line);
+        let (key, rest) = split_field(rest);
+        let (name, policy) = split_field(rest);
+
+        let public_key = PublicKey::parse(&format!("{key_type} {key}"))?;
+
+        if name.is_empty() {
+            return Err("Name is missing after the public key".to_owned());
+        }
+
+        let policy = match policy.trim() {
+            "" => None,
+            policy => {
+                let policy: TunnelPolicy = serde_json::from_str(policy)
+                    .map_err(|e| format!("Policy is not valid: {e}"))?;
+
+                let validation = Validation::validate(&policy);
+
+                if let Some(error) = validation.errors().first() {
+                    return Err(format!("Policy is not valid: {error}"));
+                }
+
+                Some(policy)
+            }
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            public_key,
+            policy,
+        })
+    }
+}
+
+fn split_field(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    text.split_once(char::is_whitespace).unwrap_or((text, ""))
+}
+
+/// Parses the authorized keys file. Empty lines and lines starting with `#` are skipped.
+pub fn parse_authorized_keys(text: &str) -> Result<Vec<AuthorizedKey>, String> {
+    let mut keys: Vec<AuthorizedKey> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let key = AuthorizedKey::parse(line).map_err(|e| format!("Line {}: {}", index + 1, e))?;
+
+        if keys.iter().any(|other| other.name == key.name) {
+            return Err(format!(
+                "Line {}: Name '{}' is used more than once",
+                index + 1,
+                key.name
+            ));
+        }
+
+        keys.push(key);
+    }
+
+    Ok(keys)
+}
+
+pub fn read_authorized_keys(path: &str) -> Result<Vec<AuthorizedKey>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read authorized keys file '{path}': {e}"))?;
+
+    parse_authorized_keys(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHKRiCjq8CwrZykF33gQqON5qY6tIHBWMXAbDadwXatY";
+
+    #[test]
+    fn test_parse_authorized_keys() {
+        let text = format!(
+            "# Tunnels of the team\n\n{KEY}  laptop\n{KEY} ci {{\"allowed_endpoints\": [\"tcp\"]}}\n"
+        );
+
+        let keys = parse_authorized_keys(&text).unwrap();
+
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].name, "laptop");
+        assert_eq!(keys[0].policy, None);
+        assert_eq!(keys[1].name, "ci");
+        assert_eq!(
+            keys[1].get_policy().allowed_endpoints,
+            Some(vec!["tcp".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_authorized_keys() {
+        assert_eq!(
+            parse_authorized_keys(KEY).unwrap_err(),
+            "Line 1: Name is missing after the public key"
+        );
+        assert_eq!(
+            parse_authorized_keys(&format!("{KEY} a\n{KEY} a")).unwrap_err(),
+            "Line 2: Name 'a' is used more than once"
+        );
+        assert_eq!(
+            parse_authorized_keys(&format!("{KEY} a {{\"max_tunnels\": 0}}")).unwrap_err(),
+            "Line 1: Policy is not valid: max_tunnels: Value must be greater than zero."
+        );
+        assert!(parse_authorized_keys("ssh-rsa AAAA a").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    fmt::Display,
     io::{Error, ErrorKind},
     sync::Arc,
 };
@@ -19,6 +20,12 @@ use crate::{
     tunnel::configuration::ProxyConfiguration,
 };
 
+use super::{
+    auth_hook::AuthHookConfig,
+    authorized_keys::read_authorized_keys,
+    tunnel_policy::{ClientCertificate, TunnelCredential, TunnelPolicy},
+};
+
 use super::endpoints::{
     http::configuration::{HttpEndpointConfig, HttpPublicEndpointConfig},
     monitor::configuration::{MonitorEndpointConfig, PublicMonitorEndpointConfig},
@@ -49,6 +56,7 @@ impl EndpointServerEncryption {
             } => Ok(ServerEncryption::Tls {
                 cert_path: cert_path.clone(),
                 key_path: key_path.clone(),
+                client_ca_path: None,
             }),
             EndpointServerEncryption::Tls {
                 cert_path: None,
@@ -58,6 +66,7 @@ impl EndpointServerEncryption {
                     Some(ServerEncryption::Tls {
                         ref cert_path,
                         ref key_path,
+                        ..
                     }) => (cert_path, key_path),
                     _ => {
                         return Err(Error::new(
@@ -70,6 +79,7 @@ impl EndpointServerEncryption {
                 Ok(ServerEncryption::Tls {
                     cert_path: cert_path.clone(),
                     key_path: key_path.clone(),
+                    client_ca_path: None,
                 })
             }
             _ => Err(Error::new(
@@ -97,6 +107,24 @@ pub struct ServerConfiguration {
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub monitor_key: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tunnel_credentials: Option<Vec<TunnelCredential>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub client_certificates: Option<Vec<ClientCertificate>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub authorized_keys_file: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub auth_hook: Option<AuthHookConfig>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub token_secret: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub token_revocation_file: Option<String>,
+
     pub endpoints: HashMap<String, EndpointConfiguration>,
 
     #[serde(skip_serializing_if = "Option::is_none", default)]
@@ -113,6 +141,12 @@ pub struct ServerConfiguration {
 
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub max_input_read_length: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tunnel_resume_grace_seconds: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub allow_multiplexed_links: Option<bool>,
 }
 
 impl From<ServerConfiguration> for TunnelizeConfiguration {
@@ -120,6 +154,8 @@ impl From<ServerConfiguration> for TunnelizeConfiguration {
         TunnelizeConfiguration {
             server: Some(val),
             tunnel: None,
+            tunnel_defaults: None,
+            profiles: None,
         }
     }
 }
@@ -170,6 +206,244 @@ impl ServerConfiguration {
     pub fn get_encryption(&self) -> ServerEncryption {
         self.encryption.clone().unwrap_or(ServerEncryption::None)
     }
+
+    pub fn get_tunnel_resume_grace_seconds(&self) -> u64 {
+        self.tunnel_resume_grace_seconds.unwrap_or(60)
+    }
+
+    pub fn get_allow_multiplexed_links(&self) -> bool {
+        self.allow_multiplexed_links.unwrap_or(true)
+    }
+
+    pub fn get_tunnel_credentials(&self) -> &[TunnelCredential] {
+        self.tunnel_credentials.as_deref().unwrap_or_default()
+    }
+
+    pub fn get_client_certificates(&self) -> &[ClientCertificate] {
+        self.client_certificates.as_deref().unwrap_or_default()
+    }
+
+    fn validate_credential(
+        &self,
+        index: usize,
+        credential: &TunnelCredential,
+        result: &mut Validation,
+    ) {
+        let field = format!("tunnel_credentials.{index}");
+        result.validate_child(&field, credential);
+
+        let previous = &self.get_tunnel_credentials()[..index];
+
+        if previous.iter().any(|other| other.name == credential.name) {
+            result.add_field_error(
+                &format!("{field}.name"),
+                &format!(
+                    "Credential '{}' is defined more than once.",
+                    credential.name
+                ),
+            );
+        }
+
+        if previous.iter().any(|other| other.key == credential.key)
+            || self.tunnel_key.as_ref() == Some(&credential.key)
+            || self.monitor_key.as_ref() == Some(&credential.key)
+        {
+            result.add_field_error(
+                &format!("{field}.key"),
+                "Key must be different from other tunnel and monitor keys.",
+            );
+        }
+
+        self.validate_policy_endpoints(&field, credential.policy.as_ref(), result);
+    }
+
+    fn validate_client_certificate(
+        &self,
+        index: usize,
+        certificate: &ClientCertificate,
+        result: &mut Validation,
+    ) {
+        let field = format!("client_certificates.{index}");
+        result.validate_child(&field, certificate);
+
+        self.validate_policy_endpoints(&field, certificate.policy.as_ref(), result);
+    }
+
+    fn validate_policy_endpoints(
+        &self,
+        field: &str,
+        policy: Option<&TunnelPolicy>,
+        result: &mut Validation,
+    ) {
+        let allowed_endpoints = policy.and_then(|policy| policy.allowed_endpoints.as_ref());
+
+        for (endpoint_index, name) in allowed_endpoints.into_iter().flatten().enumerate() {
+            if !self.endpoints.contains_key(name) {
+                result.add_field_error(
+                    &format!("{field}.policy.allowed_endpoints.{endpoint_index}"),
+                    &format!("Endpoint '{name}' does not exist."),
+                );
+            }
+        }
+    }
+
+    /// Returns the main listener and listeners of all endpoints, sorted by endpoint name. TCP and
+    /// UDP endpoints bind ports from their range when tunnels connect, so the whole range is used.
+    pub fn get_listeners(&self) -> Vec<ServerListener> {
+        let mut listeners = vec![ServerListener {
+            name: "server".to_owned(),
+            endpoint_type: None,
+            protocol: "tcp",
+            address: self.get_server_address(),
+            port_from: self.get_server_port(),
+            port_to: self.get_server_port(),
+            tls: self.get_encryption() != ServerEncryption::None,
+        }];
+
+        let mut endpoints: Vec<_> = self.endpoints.iter().collect();
+        endpoints.sort_by_key(|(name, _)| *name);
+
+        for (name, endpoint) in endpoints {
+            let (protocol, address, port_from, port_to, encryption) = match endpoint {
+                EndpointConfiguration::Http(http) => (
+                    "tcp",
+                    http.get_address(),
+                    http.port,
+                    http.port,
+                    http.get_encryption(),
+                ),
+                EndpointConfiguration::Monitoring(monitor) => (
+                    "tcp",
+                    monitor.get_address(),
+                    monitor.port,
+                    monitor.port,
+                    monitor.get_encryption(),
+                ),
+                EndpointConfiguration::Tcp(tcp) => (
+                    "tcp",
+                    tcp.get_address(),
+                    tcp.reserve_ports_from,
+                    tcp.reserve_ports_to,
+                    tcp.get_encryption(),
+                ),
+                EndpointConfiguration::Udp(udp) => (
+                    "udp",
+                    udp.get_address(),
+                    udp.reserve_ports_from,
+                    udp.reserve_ports_to,
+                    EndpointServerEncryption::None,
+                ),
+            };
+
+            listeners.push(ServerListener {
+                name: name.clone(),
+                endpoint_type: Some(endpoint.get_type_string()),
+                protocol,
+                address,
+                port_from,
+                port_to,
+                tls: encryption != EndpointServerEncryption::None,
+            });
+        }
+
+        listeners
+    }
+
+    /// Returns configuration with all optional values which have a default set to that default.
+    pub fn with_defaults(&self) -> Self {
+        Self {
+            server_port: Some(self.get_server_port()),
+            server_address: Some(self.get_server_address()),
+            max_tunnel_input_wait: Some(self.get_max_tunnel_input_wait()),
+            tunnel_key: self.tunnel_key.clone(),
+            monitor_key: self.monitor_key.clone(),
+            tunnel_credentials: self.tunnel_credentials.clone(),
+            client_certificates: self.client_certificates.clone(),
+            authorized_keys_file: self.authorized_keys_file.clone(),
+            auth_hook: self.auth_hook.clone(),
+            token_secret: self.token_secret.clone(),
+            token_revocation_file: self.token_revocation_file.clone(),
+            endpoints: self
+                .endpoints
+                .iter()
+                .map(|(name, endpoint)| (name.clone(), endpoint.with_defaults()))
+                .collect(),
+            encryption: Some(self.get_encryption()),
+            max_tunnels: Some(self.get_max_tunnels()),
+            max_clients: Some(self.get_max_clients()),
+            max_proxies_per_tunnel: Some(self.get_max_proxies_per_tunnel()),
+            max_input_read_length: Some(self.get_max_input_read_length()),
+            tunnel_resume_grace_seconds: Some(self.get_tunnel_resume_grace_seconds()),
+            allow_multiplexed_links: Some(self.get_allow_multiplexed_links()),
+        }
+    }
+}
+
+/// Address and ports the server binds for its main listener or an endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerListener {
+    pub name: String,
+    pub endpoint_type: Option<&'static str>,
+    pub protocol: &'static str,
+    pub address: String,
+    pub port_from: u16,
+    pub port_to: u16,
+    pub tls: bool,
+}
+
+impl ServerListener {
+    fn get_field(&self) -> String {
+        match self.endpoint_type {
+            None => "server_port".to_owned(),
+            Some("tcp" | "udp") => format!("endpoints.{}.config.reserve_ports_from", self.name),
+            Some(_) => format!("endpoints.{}.config.port", self.name),
+        }
+    }
+
+    pub fn get_ports(&self) -> String {
+        match self.port_from == self.port_to {
+            true => self.port_from.to_string(),
+            false => format!("{}-{}", self.port_from, self.port_to),
+        }
+    }
+
+    /// Listeners conflict when they use the same protocol on overlapping ports and addresses.
+    /// Unspecified address such as 0.0.0.0 overlaps with every other address.
+    pub fn conflicts_with(&self, other: &ServerListener) -> bool {
+        let is_unspecified = |address: &str| {
+            address
+                .trim_matches(['[', ']'])
+                .parse::<std::net::IpAddr>()
+                .is_ok_and(|ip| ip.is_unspecified())
+        };
+
+        self.port_from <= self.port_to
+            && other.port_from <= other.port_to
+            && self.protocol == other.protocol
+            && (self.address == other.address
+                || is_unspecified(&self.address)
+                || is_unspecified(&other.address))
+            && self.port_from <= other.port_to
+            && other.port_from <= self.port_to
+    }
+}
+
+impl Display for ServerListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.endpoint_type {
+            Some(endpoint_type) => write!(f, "{} ({} endpoint)", self.name, endpoint_type)?,
+            None => write!(f, "{}", self.name)?,
+        }
+
+        write!(
+            f,
+            ": {} {}:{}, TLS {}",
+            self.protocol,
+            self.address,
+            self.get_ports(),
+            if self.tls { "on" } else { "off" }
+        )
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -220,6 +494,15 @@ impl EndpointConfiguration {
             Self::Monitoring(_) => "monitoring",
         }
     }
+
+    pub fn with_defaults(&self) -> Self {
+        match self {
+            Self::Http(config) => Self::Http(config.with_defaults()),
+            Self::Tcp(config) => Self::Tcp(config.with_defaults()),
+            Self::Udp(config) => Self::Udp(config.with_defaults()),
+            Self::Monitoring(config) => Self::Monitoring(config.with_defaults()),
+        }
+    }
 }
 
 impl Validatable for ServerConfiguration {
@@ -243,6 +526,55 @@ impl Validatable for ServerConfiguration {
             result.validate_rule::<MustNotBeEmptyString>("monitor_key", key);
         }
 
+        if let Some(secret) = &self.token_secret
+            && secret.len() < 32
+        {
+            result.add_field_error(
+                "token_secret",
+                "Secret must be at least 32 characters long.",
+            );
+        }
+
+        if let Some(file) = &self.token_revocation_file {
+            result.validate_rule::<MustNotBeEmptyString>("token_revocation_file", file);
+        }
+
+        for (index, credential) in self.get_tunnel_credentials().iter().enumerate() {
+            self.validate_credential(index, credential, result);
+        }
+
+        if !self.get_client_certificates().is_empty()
+            && !self.get_encryption().requires_client_certificate()
+        {
+            result.add_field_error(
+                "client_certificates",
+                "Server encryption must have client_ca_path set to use client certificates.",
+            );
+        }
+
+        for (index, certificate) in self.get_client_certificates().iter().enumerate() {
+            self.validate_client_certificate(index, certificate, result);
+        }
+
+        if let Some(file) = &self.authorized_keys_file {
+            match read_authorized_keys(file) {
+                Ok(keys) => {
+                    for key in keys.iter() {
+                        self.validate_policy_endpoints(
+                            &format!("authorized_keys_file.{}", key.name),
+                            key.policy.as_ref(),
+                            result,
+                        );
+                    }
+                }
+                Err(error) => result.add_field_error("authorized_keys_file", &format!("{error}.")),
+            }
+        }
+
+        if let Some(auth_hook) = &self.auth_hook {
+            result.validate_child("auth_hook", auth_hook);
+        }
+
         for (name, endpoint) in &self.endpoints {
             result.validate_child(&format!("endpoints.{name}"), endpoint);
         }
@@ -258,6 +590,25 @@ impl Validatable for ServerConfiguration {
             "max_proxies_per_tunnel",
             &self.get_max_proxies_per_tunnel(),
         );
+
+        let listeners = self.get_listeners();
+
+        for (index, listener) in listeners.iter().enumerate() {
+            for other in listeners[..index].iter() {
+                if listener.conflicts_with(other) {
+                    result.add_field_error(
+                        &listener.get_field(),
+                        &format!(
+                            "Conflicts with {} on {} {}:{}.",
+                            other.get_field(),
+                            other.protocol,
+                            other.address,
+                            other.get_ports()
+                        ),
+                    );
+                }
+            }
+        }
     }
 }
 
@@ -304,12 +655,20 @@ mod tests {
             max_tunnel_input_wait: Some(60),
             tunnel_key: Some("tunnel_key".to_string()),
             monitor_key: Some("monitor_key".to_string()),
+            tunnel_credentials: None,
+            client_certificates: None,
+            authorized_keys_file: None,
+            auth_hook: None,
+            token_secret: None,
+            token_revocation_file: None,
             endpoints: HashMap::new(),
             encryption: Some(ServerEncryption::None),
             max_tunnels: Some(200),
             max_clients: Some(200),
             max_proxies_per_tunnel: Some(20),
             max_input_read_length: None,
+            tunnel_resume_grace_seconds: None,
+            allow_multiplexed_links: None,
         }
     }
 
@@ -397,6 +756,30 @@ mod tests {
         assert_eq!(config.get_encryption(), ServerEncryption::None);
     }
 
+    #[test]
+    fn test_get_tunnel_resume_grace_seconds() {
+        let config = default_server_config();
+        assert_eq!(config.get_tunnel_resume_grace_seconds(), 60);
+
+        let config = ServerConfiguration {
+            tunnel_resume_grace_seconds: Some(0),
+            ..default_server_config()
+        };
+        assert_eq!(config.get_tunnel_resume_grace_seconds(), 0);
+    }
+
+    #[test]
+    fn test_get_allow_multiplexed_links() {
+        let config = default_server_config();
+        assert!(config.get_allow_multiplexed_links());
+
+        let config = ServerConfiguration {
+            allow_multiplexed_links: Some(false),
+            ..default_server_config()
+        };
+        assert!(!config.get_allow_multiplexed_links());
+    }
+
     #[test]
     fn test_endpoint_server_encryption_to_encryption() {
         let server_config = Arc::new(default_server_config());
@@ -416,6 +799,7 @@ mod tests {
             ServerEncryption::Tls {
                 cert_path: "cert.pem".to_string(),
                 key_path: "key.pem".to_string(),
+                client_ca_path: None,
             }
         );
 
@@ -427,6 +811,7 @@ mod tests {
             encryption: Some(ServerEncryption::Tls {
                 cert_path: "default_cert.pem".to_string(),
                 key_path: "default_key.pem".to_string(),
+                client_ca_path: Some("client_ca.pem".to_string()),
             }),
             ..default_server_config()
         });
@@ -435,7 +820,139 @@ mod tests {
             ServerEncryption::Tls {
                 cert_path: "default_cert.pem".to_string(),
                 key_path: "default_key.pem".to_string(),
+                client_ca_path: None,
+            }
+        );
+    }
+
+    fn create_endpoints_config(endpoints: serde_json::Value) -> ServerConfiguration {
+        ServerConfiguration {
+            endpoints: serde_json::from_value(endpoints).unwrap(),
+            ..default_server_config()
+        }
+    }
+
+    #[test]
+    fn test_get_listeners() {
+        let config = create_endpoints_config(serde_json::json!({
+            "udp": { "type": "udp", "reserve_ports_from": 5000, "reserve_ports_to": 5010 },
+            "https": {
+                "type": "http",
+                "port": 443,
+                "hostname_template": "{name}.localhost",
+                "encryption": { "type": "tls" }
             }
+        }));
+
+        let listeners: Vec<String> = config
+            .get_listeners()
+            .iter()
+            .map(|listener| listener.to_string())
+            .collect();
+
+        assert_eq!(
+            listeners,
+            vec![
+                "server: tcp 127.0.0.1:8080, TLS off",
+                "https (http endpoint): tcp 0.0.0.0:443, TLS on",
+                "udp (udp endpoint): udp 0.0.0.0:5000-5010, TLS off",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conflicting_listeners() {
+        let config = create_endpoints_config(serde_json::json!({
+            "http": { "type": "http", "port": 4005, "hostname_template": "{name}.localhost" },
+            "monitor": {
+                "type": "monitoring",
+                "port": 8080,
+                "authentication": { "type": "bearer", "token": "token" }
+            },
+            "tcp": { "type": "tcp", "reserve_ports_from": 4000, "reserve_ports_to": 4010 },
+            "udp": { "type": "udp", "reserve_ports_from": 4000, "reserve_ports_to": 4010 }
+        }));
+
+        let validation = Validation::validate(&config);
+
+        assert_eq!(
+            validation.errors(),
+            &vec![
+                "endpoints.monitor.config.port: Conflicts with server_port on tcp 127.0.0.1:8080.",
+                "endpoints.tcp.config.reserve_ports_from: Conflicts with endpoints.http.config.port on tcp 0.0.0.0:4005.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_listeners_on_different_addresses_do_not_conflict() {
+        let config = create_endpoints_config(serde_json::json!({
+            "first": {
+                "type": "tcp",
+                "address": "127.0.0.1",
+                "reserve_ports_from": 4000,
+                "reserve_ports_to": 4010
+            },
+            "second": {
+                "type": "tcp",
+                "address": "127.0.0.2",
+                "reserve_ports_from": 4005,
+                "reserve_ports_to": 4020
+            },
+            "reversed": { "type": "udp", "reserve_ports_from": 6000, "reserve_ports_to": 5000 }
+        }));
+
+        let validation = Validation::validate(&config);
+
+        assert_eq!(
+            validation.errors(),
+            &vec![
+                "endpoints.reversed.config.reserve_ports_from: reserve_ports_from must be less than reserve_ports_to, range is reversed."
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_tunnel_credentials() {
+        let config = ServerConfiguration {
+            tunnel_credentials: serde_json::from_value(serde_json::json!([
+                { "name": "ci", "key": "ci-key", "policy": { "allowed_endpoints": ["tcp"] } },
+                { "name": "ci", "key": "tunnel_key" }
+            ]))
+            .unwrap(),
+            ..create_endpoints_config(serde_json::json!({
+                "http": { "type": "http", "port": 8081, "hostname_template": "{name}.localhost" }
+            }))
+        };
+
+        assert_eq!(
+            Validation::validate(&config).errors(),
+            &vec![
+                "tunnel_credentials.0.policy.allowed_endpoints.0: Endpoint 'tcp' does not exist.",
+                "tunnel_credentials.1.name: Credential 'ci' is defined more than once.",
+                "tunnel_credentials.1.key: Key must be different from other tunnel and monitor keys.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_client_certificates() {
+        let config = ServerConfiguration {
+            client_certificates: serde_json::from_value(serde_json::json!([
+                { "name": "*.ci.example.com", "policy": { "allowed_endpoints": ["tcp"] } },
+                { "name": "" }
+            ]))
+            .unwrap(),
+            ..default_server_config()
+        };
+
+        assert_eq!(
+            Validation::validate(&config).errors(),
+            &vec![
+                "client_certificates: Server encryption must have client_ca_path set to use client certificates.",
+                "client_certificates.0.policy.allowed_endpoints.0: Endpoint 'tcp' does not exist.",
+                "client_certificates.1.name: Value cannot be empty.",
+            ]
         );
     }
 }
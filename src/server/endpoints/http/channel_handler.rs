@@ -7,12 +7,16 @@ use uuid::Uuid;
 
 use crate::{
     common::channel::{OkResponse, Request},
-    server::endpoints::{
-        http::HttpEndpointInfo,
-        messages::{
-            EndpointChannelRequest, RegisterTunnelResponse, RemoveTunnelRequest,
-            ResolvedEndpointInfo,
+    server::{
+        endpoints::{
+            http::HttpEndpointInfo,
+            messages::{
+                EndpointChannelRequest, RegisterProxyResponse, RegisterTunnelResponse,
+                RemoveProxyResponse, RemoveTunnelRequest, ResolvedEndpointInfo,
+            },
         },
+        incoming_requests::ProxySession,
+        tunnel_policy::TunnelAccess,
     },
     tunnel::configuration::ProxyConfiguration,
 };
@@ -26,62 +30,238 @@ pub async fn handle(
         EndpointChannelRequest::RegisterTunnelRequest(tunnel_request) => {
             let mut proxy_info = HashMap::<Uuid, ResolvedEndpointInfo>::new();
 
+            let proxy_ids: Vec<Uuid> = tunnel_request
+                .proxy_sessions
+                .iter()
+                .map(|session| session.proxy_id)
+                .collect();
+
+            let tunnel_id = &tunnel_request.tunnel_id;
+
+            tunnel_host.remove_missing_proxies(tunnel_id, &proxy_ids);
+
+            // Hostnames the tunnel held before, for example while waiting to be resumed, stay
+            // assigned to it if the request is rejected.
+            let mut added_proxy_ids: Vec<Uuid> = Vec::new();
+
             for proxy_session in tunnel_request.proxy_sessions.iter() {
-                let ProxyConfiguration::Http { desired_name } = &proxy_session.config else {
-                    debug!("Proxy session configuration passed is not for Http endpoint");
-                    continue;
-                };
-                if let Some(desired_name) = desired_name {
-                    if !config.get_allow_custom_hostnames() {
-                        request.respond(RegisterTunnelResponse::Rejected {
-                            reason: "Custom hostnames are not allowed for this endpoint".to_owned(),
-                        });
-                        return Ok(());
-                    }
+                let is_held = tunnel_host
+                    .find_hostname(tunnel_id, &proxy_session.proxy_id)
+                    .is_some();
 
-                    if desired_name.is_empty() {
-                        request.respond(RegisterTunnelResponse::Rejected {
-                            reason: "Desired hostname cannot be empty".to_owned(),
-                        });
-                        return Ok(());
+                match register_proxy(
+                    config,
+                    tunnel_host,
+                    tunnel_id,
+                    proxy_session,
+                    &tunnel_request.access,
+                ) {
+                    Ok(endpoint_info) => {
+                        if !is_held {
+                            added_proxy_ids.push(proxy_session.proxy_id);
+                        }
+
+                        proxy_info.insert(proxy_session.proxy_id, endpoint_info);
                     }
+                    Err(reason) => {
+                        added_proxy_ids.push(proxy_session.proxy_id);
+
+                        for proxy_id in added_proxy_ids.iter() {
+                            tunnel_host.remove_proxy(tunnel_id, proxy_id);
+                        }
 
-                    if desired_name.len() > 20 {
-                        request.respond(RegisterTunnelResponse::Rejected {
-                            reason: "Desired hostname cannot be longer than 20 characters"
-                                .to_owned(),
-                        });
+                        request.respond(RegisterTunnelResponse::Rejected { reason });
                         return Ok(());
                     }
                 }
-
-                let hostname = tunnel_host.register_host(
-                    desired_name,
-                    &tunnel_request.tunnel_id,
-                    &proxy_session.proxy_id,
-                );
-
-                info!(
-                    "Tunnel ID '{}' connected to http endpoint with hostname '{}'",
-                    tunnel_request.tunnel_id, hostname
-                );
-
-                proxy_info.insert(
-                    proxy_session.proxy_id,
-                    ResolvedEndpointInfo::Http(HttpEndpointInfo {
-                        assigned_url: config.get_full_url(&hostname),
-                    }),
-                );
             }
 
             request.respond(RegisterTunnelResponse::Accepted { proxy_info });
         }
+        EndpointChannelRequest::RegisterProxyRequest(proxy_request) => {
+            match register_proxy(
+                config,
+                tunnel_host,
+                &proxy_request.tunnel_id,
+                &proxy_request.proxy_session,
+                &proxy_request.access,
+            ) {
+                Ok(endpoint_info) => {
+                    request.respond(RegisterProxyResponse::Accepted { endpoint_info })
+                }
+                Err(reason) => request.respond(RegisterProxyResponse::Rejected { reason }),
+            }
+        }
         EndpointChannelRequest::RemoveTunnelRequest(RemoveTunnelRequest { tunnel_id }) => {
             info!("Removing tunnel ID '{}' from http endpoint.", tunnel_id);
             tunnel_host.remove_tunnel_by_id(tunnel_id);
             request.respond(OkResponse);
         }
+        EndpointChannelRequest::RemoveProxyRequest(remove_request) => {
+            info!(
+                "Removing proxy ID '{}' of tunnel ID '{}' from http endpoint.",
+                remove_request.proxy_id, remove_request.tunnel_id
+            );
+            let removed =
+                tunnel_host.remove_proxy(&remove_request.tunnel_id, &remove_request.proxy_id);
+            request.respond(RemoveProxyResponse { removed });
+        }
     }
 
     Ok(())
 }
+
+fn validate_name(label: &str, name: &str) -> std::result::Result<(), String> {
+    if name.is_empty() {
+        return Err(format!("{label} cannot be empty"));
+    }
+
+    if name.len() > 20 {
+        return Err(format!("{label} cannot be longer than 20 characters"));
+    }
+
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!(
+            "{label} can only contain alphanumeric characters and hyphens"
+        ));
+    }
+
+    Ok(())
+}
+
+fn register_proxy(
+    config: &HttpEndpointConfig,
+    tunnel_host: &mut TunnelHost,
+    tunnel_id: &Uuid,
+    proxy_session: &ProxySession,
+    access: &TunnelAccess,
+) -> std::result::Result<ResolvedEndpointInfo, String> {
+    let policy = &access.policy;
+
+    let ProxyConfiguration::Http { desired_name, pool } = &proxy_session.config else {
+        debug!("Proxy session configuration passed is not for Http endpoint");
+        return Err("Invalid configuration for HTTP endpoint.".to_owned());
+    };
+
+    if let Some(desired_name) = desired_name {
+        if !config.get_allow_custom_hostnames() {
+            return Err("Custom hostnames are not allowed for this endpoint".to_owned());
+        }
+
+        validate_name("Desired hostname", desired_name)?;
+    }
+
+    if let Some(pool) = pool {
+        if !config.get_allow_pools() {
+            return Err("Tunnel pools are not allowed for this endpoint".to_owned());
+        }
+
+        validate_name("Pool name", pool)?;
+    }
+
+    if policy.has_hostname_restriction() {
+        let Some(name) = pool.as_ref().or(desired_name.as_ref()) else {
+            return Err("Hostname allowed for this tunnel key must be requested".to_owned());
+        };
+
+        if !policy.is_hostname_allowed(name) {
+            return Err(format!("Hostname '{name}' is not allowed for this tunnel key"));
+        }
+
+        // Taken names would get a random suffix, which the policy does not allow.
+        if pool.is_none()
+            && !tunnel_host.is_name_available(name, tunnel_id, &proxy_session.proxy_id)
+        {
+            return Err(format!("Hostname '{name}' is already taken"));
+        }
+    }
+
+    let hostname = tunnel_host.register_host(
+        desired_name,
+        pool.as_deref(),
+        tunnel_id,
+        &proxy_session.proxy_id,
+        access,
+    )?;
+
+    info!(
+        "Tunnel ID '{}' connected to http endpoint with hostname '{}'",
+        tunnel_id, hostname
+    );
+
+    Ok(ResolvedEndpointInfo::Http(HttpEndpointInfo {
+        assigned_url: config.get_full_url(&hostname),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (TunnelHost, HttpEndpointConfig) {
+        let config = HttpEndpointConfig {
+            hostname_template: "{name}.localhost".to_string(),
+            allow_custom_hostnames: None,
+            port: 8080,
+            encryption: None,
+            address: None,
+            max_client_input_wait_secs: None,
+            full_url_template: None,
+            require_authorization: None,
+            allow_pools: Some(true),
+            pool_strategy: None,
+        };
+        let tunnel_host = TunnelHost::new(&config);
+        (tunnel_host, config)
+    }
+
+    fn register_pool(
+        config: &HttpEndpointConfig,
+        tunnel_host: &mut TunnelHost,
+        pool: &str,
+    ) -> std::result::Result<ResolvedEndpointInfo, String> {
+        register_proxy(
+            config,
+            tunnel_host,
+            &Uuid::new_v4(),
+            &ProxySession {
+                proxy_id: Uuid::new_v4(),
+                config: ProxyConfiguration::Http {
+                    desired_name: None,
+                    pool: Some(pool.to_string()),
+                },
+            },
+            &TunnelAccess::default(),
+        )
+    }
+
+    #[test]
+    fn test_register_proxy_validates_pool() {
+        let (mut tunnel_host, config) = setup();
+
+        assert_eq!(
+            register_pool(&config, &mut tunnel_host, "").unwrap_err(),
+            "Pool name cannot be empty"
+        );
+        assert_eq!(
+            register_pool(&config, &mut tunnel_host, &"a".repeat(21)).unwrap_err(),
+            "Pool name cannot be longer than 20 characters"
+        );
+        assert_eq!(
+            register_pool(&config, &mut tunnel_host, "api.example.com").unwrap_err(),
+            "Pool name can only contain alphanumeric characters and hyphens"
+        );
+        assert!(
+            tunnel_host
+                .get_candidates("api.example.com.localhost")
+                .is_empty()
+        );
+
+        let Ok(ResolvedEndpointInfo::Http(info)) =
+            register_pool(&config, &mut tunnel_host, "api-pool")
+        else {
+            panic!("Valid pool name should be registered");
+        };
+        assert!(info.assigned_url.contains("api-pool.localhost"));
+    }
+}
@@ -10,7 +10,7 @@ use crate::{
             MustNotBeEmptyString, PortMustBeValid,
         },
     },
-    server::configuration::EndpointServerEncryption,
+    server::{configuration::EndpointServerEncryption, endpoints::pool::PoolStrategy},
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -36,6 +36,12 @@ pub struct HttpEndpointConfig {
 
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub require_authorization: Option<AuthorizeUser>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub allow_pools: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pool_strategy: Option<PoolStrategy>,
 }
 
 impl HttpEndpointConfig {
@@ -89,6 +95,26 @@ impl HttpEndpointConfig {
     pub fn get_allow_custom_hostnames(&self) -> bool {
         self.allow_custom_hostnames.unwrap_or(true)
     }
+
+    pub fn get_allow_pools(&self) -> bool {
+        self.allow_pools.unwrap_or(false)
+    }
+
+    pub fn get_pool_strategy(&self) -> PoolStrategy {
+        self.pool_strategy.unwrap_or_default()
+    }
+
+    pub fn with_defaults(&self) -> Self {
+        Self {
+            encryption: Some(self.get_encryption()),
+            address: Some(self.get_address()),
+            max_client_input_wait_secs: Some(self.get_max_client_input_wait_secs()),
+            allow_custom_hostnames: Some(self.get_allow_custom_hostnames()),
+            allow_pools: Some(self.get_allow_pools()),
+            pool_strategy: Some(self.get_pool_strategy()),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -107,6 +133,9 @@ pub struct HttpPublicEndpointConfig {
     pub hostname_template: String,
     pub full_url_template: Option<String>,
     pub require_authorization: Option<AuthorizeUser>,
+
+    #[serde(default)]
+    pub allow_pools: bool,
 }
 
 impl From<&HttpEndpointConfig> for HttpPublicEndpointConfig {
@@ -119,6 +148,7 @@ impl From<&HttpEndpointConfig> for HttpPublicEndpointConfig {
             hostname_template: config.hostname_template.clone(),
             full_url_template: config.full_url_template.clone(),
             require_authorization: config.require_authorization.clone(),
+            allow_pools: config.get_allow_pools(),
         }
     }
 }
@@ -147,10 +177,20 @@ impl Display for HttpPublicEndpointConfig {
         )?;
         writeln!(f, "Template: {}", self.hostname_template)?;
 
-        if let Some(_) = &self.require_authorization {
+        if self.require_authorization.is_some() {
             writeln!(f, "Requires clients to authorize")?;
         }
 
+        writeln!(
+            f,
+            "Tunnel pools: {}",
+            if self.allow_pools {
+                "Allowed"
+            } else {
+                "Not Allowed"
+            }
+        )?;
+
         Ok(())
     }
 }
@@ -205,6 +245,8 @@ mod tests {
             full_url_template: None,
             allow_custom_hostnames: None,
             require_authorization: None,
+            allow_pools: None,
+            pool_strategy: None,
         }
     }
 
@@ -4,7 +4,7 @@ use log::error;
 use tokio::{io::Result, sync::RwLock};
 use uuid::Uuid;
 
-use crate::{common::connection::Connection, server::{services::{Client, Services}, session::messages::{ClientLinkRequest, ClientLinkResponse}}};
+use crate::{common::connection::Connection, server::{endpoints::pool::{LinkError, link_client, resolve_candidates}, services::{Client, Services}}};
 
 use super::{configuration::HttpEndpointConfig, protocol::{HttpRequestReader, HttpResponseBuilder}, tunnel_host::TunnelHost};
 
@@ -41,7 +41,9 @@ pub async fn handle(
         }
     };
 
-    let Some(session) = tunnel_host.read().await.get_session(&hostname) else {
+    let candidates = tunnel_host.read().await.get_candidates(&hostname);
+
+    if candidates.is_empty() {
         stream
             .close_with_data(
                 &HttpResponseBuilder::as_error(
@@ -53,7 +55,23 @@ pub async fn handle(
         return Err(Error::other(
             "No tunnel is assigned for the requested hostname",
         ));
-    };
+    }
+
+    let candidates = resolve_candidates(services, config.get_pool_strategy(), candidates).await;
+
+    if candidates.is_empty() {
+        stream
+            .close_with_data(
+                &HttpResponseBuilder::as_unavailable(
+                    "Service behind the tunnel is currently unavailable",
+                )
+                .build_bytes(),
+            )
+            .await;
+        return Err(Error::other(
+            "Service behind the tunnel is unavailable",
+        ));
+    }
 
     let client_id = Uuid::new_v4();
 
@@ -77,44 +95,29 @@ pub async fn handle(
         return Err(error);
     }
 
-    match services
-        .get_tunnel_manager()
-        .await
-        .send_session_request(
-            &session.tunnel_id,
-            ClientLinkRequest {
-                client_id,
-                proxy_id: session.proxy_id,
-            },
-        )
-        .await
-    {
-        Ok(result) => {
-            if let ClientLinkResponse::Rejected { reason } = result {
-                error!(
-                    "Client ID '{}' rejected by tunnel ID '{}': {}",
-                    client_id, session.tunnel_id, reason
-                );
-
-                services
-                    .get_client_manager()
-                    .await
-                    .cancel_client(
-                        &client_id,
-                        &Some(HttpResponseBuilder::as_error(&reason).build_bytes()),
-                    )
-                    .await;
-
-                return Err(Error::other(reason));
-            }
-
+    match link_client(services, client_id, &candidates).await {
+        Ok(tunnel) => {
             println!(
                 "Client ID '{}' linked to tunnel ID '{}'",
-                client_id, session.tunnel_id
+                client_id, tunnel.tunnel_id
             );
         }
-        Err(e) => {
-            error!("Failed to link client to tunnel: {}", e);
+        Err(LinkError::Rejected(reason)) => {
+            error!("Client ID '{}' rejected by tunnel: {}", client_id, reason);
+
+            services
+                .get_client_manager()
+                .await
+                .cancel_client(
+                    &client_id,
+                    &Some(HttpResponseBuilder::as_error(&reason).build_bytes()),
+                )
+                .await;
+
+            return Err(Error::other(reason));
+        }
+        Err(LinkError::Failed) => {
+            error!("Failed to link client to tunnel");
 
             services
                 .get_client_manager()
@@ -140,18 +143,18 @@ async fn validate_authorization(
     config: &HttpEndpointConfig,
     request: &HttpRequestReader,
 ) -> bool {
-    if let Some(user) = &config.require_authorization {
-        if !request.is_authorization_matching(&user.username, &user.password) {
-            stream
-                .close_with_data(&HttpResponseBuilder::as_unauthorized(
-                    &user.realm, 
-                    "Access to the requested endpoint is not authorized. Please provide valid credentials.",
-                ).build_bytes())
-                .await;
+    if let Some(user) = &config.require_authorization
+        && !request.is_authorization_matching(&user.username, &user.password)
+    {
+        stream
+            .close_with_data(&HttpResponseBuilder::as_unauthorized(
+                &user.realm, 
+                "Access to the requested endpoint is not authorized. Please provide valid credentials.",
+            ).build_bytes())
+            .await;
 
-            
-            return false;
-        }
+        
+        return false;
     }
 
     true
@@ -103,6 +103,7 @@ pub enum HttpStatusCode {
     MovedPermanently,
     BadRequest,
     BadGateway,
+    ServiceUnavailable,
 }
 
 impl HttpStatusCode {
@@ -111,6 +112,7 @@ impl HttpStatusCode {
             HttpStatusCode::Unauthorized => "401 Unauthorized",
             HttpStatusCode::BadRequest => "400 Bad Request",
             HttpStatusCode::BadGateway => "502 Bad Gateway",
+            HttpStatusCode::ServiceUnavailable => "503 Service Unavailable",
             HttpStatusCode::MovedPermanently => "301 Moved Permanently",
         }
     }
@@ -168,6 +170,10 @@ impl HttpResponseBuilder {
         Self::new(HttpStatusCode::BadGateway, message)
     }
 
+    pub fn as_unavailable(message: &str) -> Self {
+        Self::new(HttpStatusCode::ServiceUnavailable, message)
+    }
+
     pub fn as_bad_request(message: &str) -> Self {
         let mut instance = Self::new(HttpStatusCode::BadRequest, message);
 
@@ -285,6 +291,17 @@ mod tests {
         assert_eq!(response.body, "Error occurred");
     }
 
+    #[test]
+    fn test_http_response_builder_as_unavailable() {
+        let response = HttpResponseBuilder::as_unavailable("Service unavailable");
+        assert_eq!(response.status_code, HttpStatusCode::ServiceUnavailable);
+        assert!(
+            response
+                .build()
+                .contains("HTTP/1.1 503 Service Unavailable")
+        );
+    }
+
     #[test]
     fn test_http_response_builder_as_bad_request() {
         let response = HttpResponseBuilder::as_bad_request("Bad Request");
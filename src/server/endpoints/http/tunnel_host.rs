@@ -2,20 +2,21 @@ use std::collections::HashMap;
 
 use uuid::Uuid;
 
-use crate::common::text::get_random_letters;
+use crate::{
+    common::text::get_random_letters,
+    server::{
+        endpoints::pool::{PoolMember, PoolStrategy, TunnelPool},
+        tunnel_policy::TunnelAccess,
+    },
+};
 
 use super::HttpEndpointConfig;
 
 pub struct TunnelHost {
     hostname_template: String,
     allow_custom_hostnames: bool,
-    host_tunnel_map: HashMap<String, HostTunnelSession>,
-}
-
-#[derive(Clone)]
-pub struct HostTunnelSession {
-    pub tunnel_id: Uuid,
-    pub proxy_id: Uuid,
+    pool_strategy: PoolStrategy,
+    host_tunnel_map: HashMap<String, TunnelPool>,
 }
 
 impl TunnelHost {
@@ -24,6 +25,7 @@ impl TunnelHost {
             host_tunnel_map: HashMap::new(),
             allow_custom_hostnames: config.get_allow_custom_hostnames(),
             hostname_template: config.hostname_template.clone(),
+            pool_strategy: config.get_pool_strategy(),
         }
     }
 
@@ -61,32 +63,106 @@ impl TunnelHost {
         hostname
     }
 
+    /// Checks if the name can be registered as is, without a suffix being added to it.
+    pub fn is_name_available(&self, name: &str, tunnel_id: &Uuid, proxy_id: &Uuid) -> bool {
+        let hostname = self.hostname_template.replace("{name}", name);
+
+        self.find_hostname(tunnel_id, proxy_id)
+            .is_none_or(|registered| registered == hostname)
+            && self
+                .host_tunnel_map
+                .get(&hostname)
+                .is_none_or(|tunnel_pool| tunnel_pool.has_member(tunnel_id, proxy_id))
+    }
+
     pub fn register_host(
         &mut self,
         desired_hostname: &Option<String>,
+        pool: Option<&str>,
         tunnel_id: &Uuid,
         proxy_id: &Uuid,
-    ) -> String {
-        let hostname = self.generate_unique_hostname(desired_hostname);
-
-        self.host_tunnel_map.insert(
-            hostname.clone(),
-            HostTunnelSession {
-                tunnel_id: *tunnel_id,
-                proxy_id: *proxy_id,
-            },
-        );
+        access: &TunnelAccess,
+    ) -> Result<String, String> {
+        if let Some(hostname) = self.find_hostname(tunnel_id, proxy_id) {
+            return Ok(hostname);
+        }
 
-        hostname
+        let Some(pool) = pool else {
+            let hostname = self.generate_unique_hostname(desired_hostname);
+
+            self.host_tunnel_map.insert(
+                hostname.clone(),
+                TunnelPool::new(None, access.clone(), *tunnel_id, *proxy_id),
+            );
+
+            return Ok(hostname);
+        };
+
+        let hostname = self.hostname_template.replace("{name}", pool);
+
+        match self.host_tunnel_map.get_mut(&hostname) {
+            Some(tunnel_pool) if tunnel_pool.is_named(pool) => {
+                if !tunnel_pool.can_join(access) {
+                    return Err(format!("Pool '{pool}' belongs to another tunnel key"));
+                }
+
+                tunnel_pool.add_member(*tunnel_id, *proxy_id);
+            }
+            Some(_) => {
+                return Err(format!("Hostname for pool '{pool}' is already taken"));
+            }
+            None => {
+                self.host_tunnel_map.insert(
+                    hostname.clone(),
+                    TunnelPool::new(Some(pool.to_owned()), access.clone(), *tunnel_id, *proxy_id),
+                );
+            }
+        }
+
+        Ok(hostname)
+    }
+
+    fn retain_members(&mut self, keep: impl Fn(&PoolMember) -> bool) {
+        for tunnel_pool in self.host_tunnel_map.values_mut() {
+            tunnel_pool.retain_members(&keep);
+        }
+
+        self.host_tunnel_map
+            .retain(|_, tunnel_pool| !tunnel_pool.is_empty());
+    }
+
+    fn get_member_count(&self) -> usize {
+        self.host_tunnel_map.values().map(|p| p.len()).sum()
     }
 
     pub fn remove_tunnel_by_id(&mut self, tunnel_id: &Uuid) {
+        self.retain_members(|m| &m.tunnel_id != tunnel_id);
+    }
+
+    pub fn remove_missing_proxies(&mut self, tunnel_id: &Uuid, proxy_ids: &[Uuid]) {
+        self.retain_members(|m| &m.tunnel_id != tunnel_id || proxy_ids.contains(&m.proxy_id));
+    }
+
+    pub fn remove_proxy(&mut self, tunnel_id: &Uuid, proxy_id: &Uuid) -> bool {
+        let count = self.get_member_count();
+
+        self.retain_members(|m| &m.tunnel_id != tunnel_id || &m.proxy_id != proxy_id);
+
+        self.get_member_count() != count
+    }
+
+    pub fn find_hostname(&self, tunnel_id: &Uuid, proxy_id: &Uuid) -> Option<String> {
         self.host_tunnel_map
-            .retain(|_, v| &v.tunnel_id != tunnel_id);
+            .iter()
+            .find(|(_, v)| v.has_member(tunnel_id, proxy_id))
+            .map(|(hostname, _)| hostname.clone())
     }
 
-    pub fn get_session(&self, hostname: &str) -> Option<HostTunnelSession> {
-        self.host_tunnel_map.get(hostname).cloned()
+    pub fn get_candidates(&self, hostname: &str) -> Vec<PoolMember> {
+        self.host_tunnel_map
+            .get(hostname)
+            .map(|tunnel_pool| tunnel_pool.get_candidates(self.pool_strategy))
+            .unwrap_or_default()
     }
 }
 
@@ -105,6 +181,8 @@ mod tests {
             max_client_input_wait_secs: None,
             full_url_template: None,
             require_authorization: None,
+            allow_pools: None,
+            pool_strategy: None,
         };
         let tunnel_host = TunnelHost::new(&config);
         (tunnel_host, config)
@@ -128,9 +206,19 @@ mod tests {
         let proxy_id = Uuid::new_v4();
         let desired_hostname = Some("customhost".to_string());
 
-        let hostname = tunnel_host.register_host(&desired_hostname, &tunnel_id, &proxy_id);
+        let hostname = tunnel_host
+            .register_host(
+                &desired_hostname,
+                None,
+                &tunnel_id,
+                &proxy_id,
+                &TunnelAccess::default(),
+            )
+            .unwrap();
         assert!(tunnel_host.host_tunnel_map.contains_key(&hostname));
-        let session = tunnel_host.get_session(&hostname).unwrap();
+        let candidates = tunnel_host.get_candidates(&hostname);
+        assert_eq!(candidates.len(), 1);
+        let session = &candidates[0];
         assert_eq!(session.tunnel_id, tunnel_id);
         assert_eq!(session.proxy_id, proxy_id);
     }
@@ -142,7 +230,15 @@ mod tests {
         let proxy_id = Uuid::new_v4();
         let desired_hostname = Some("customhost".to_string());
 
-        let hostname = tunnel_host.register_host(&desired_hostname, &tunnel_id, &proxy_id);
+        let hostname = tunnel_host
+            .register_host(
+                &desired_hostname,
+                None,
+                &tunnel_id,
+                &proxy_id,
+                &TunnelAccess::default(),
+            )
+            .unwrap();
         assert!(tunnel_host.host_tunnel_map.contains_key(&hostname));
 
         tunnel_host.remove_tunnel_by_id(&tunnel_id);
@@ -150,18 +246,223 @@ mod tests {
     }
 
     #[test]
-    fn test_get_session() {
+    fn test_get_candidates() {
         let (mut tunnel_host, _) = setup();
         let tunnel_id = Uuid::new_v4();
         let proxy_id = Uuid::new_v4();
         let desired_hostname = Some("customhost".to_string());
 
-        let hostname = tunnel_host.register_host(&desired_hostname, &tunnel_id, &proxy_id);
-        let session = tunnel_host.get_session(&hostname).unwrap();
+        let hostname = tunnel_host
+            .register_host(
+                &desired_hostname,
+                None,
+                &tunnel_id,
+                &proxy_id,
+                &TunnelAccess::default(),
+            )
+            .unwrap();
+        let candidates = tunnel_host.get_candidates(&hostname);
+        assert_eq!(candidates.len(), 1);
+        let session = &candidates[0];
         assert_eq!(session.tunnel_id, tunnel_id);
         assert_eq!(session.proxy_id, proxy_id);
 
-        let non_existent_session = tunnel_host.get_session("nonexistent");
-        assert!(non_existent_session.is_none());
+        assert!(tunnel_host.get_candidates("nonexistent").is_empty());
+    }
+
+    #[test]
+    fn test_register_host_again_returns_same_hostname() {
+        let (mut tunnel_host, _) = setup();
+        let tunnel_id = Uuid::new_v4();
+        let proxy_id = Uuid::new_v4();
+
+        let hostname = tunnel_host
+            .register_host(&None, None, &tunnel_id, &proxy_id, &TunnelAccess::default())
+            .unwrap();
+        let same_hostname = tunnel_host
+            .register_host(&None, None, &tunnel_id, &proxy_id, &TunnelAccess::default())
+            .unwrap();
+
+        assert_eq!(hostname, same_hostname);
+        assert_eq!(tunnel_host.host_tunnel_map.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_missing_proxies() {
+        let (mut tunnel_host, _) = setup();
+        let tunnel_id = Uuid::new_v4();
+        let kept_proxy_id = Uuid::new_v4();
+        let removed_proxy_id = Uuid::new_v4();
+
+        let kept = tunnel_host
+            .register_host(
+                &None,
+                None,
+                &tunnel_id,
+                &kept_proxy_id,
+                &TunnelAccess::default(),
+            )
+            .unwrap();
+        let removed = tunnel_host
+            .register_host(
+                &None,
+                None,
+                &tunnel_id,
+                &removed_proxy_id,
+                &TunnelAccess::default(),
+            )
+            .unwrap();
+
+        tunnel_host.remove_missing_proxies(&tunnel_id, &[kept_proxy_id]);
+
+        assert!(!tunnel_host.get_candidates(&kept).is_empty());
+        assert!(tunnel_host.get_candidates(&removed).is_empty());
+    }
+
+    #[test]
+    fn test_remove_proxy() {
+        let (mut tunnel_host, _) = setup();
+        let tunnel_id = Uuid::new_v4();
+        let kept_proxy_id = Uuid::new_v4();
+        let removed_proxy_id = Uuid::new_v4();
+
+        let kept = tunnel_host
+            .register_host(
+                &None,
+                None,
+                &tunnel_id,
+                &kept_proxy_id,
+                &TunnelAccess::default(),
+            )
+            .unwrap();
+        let removed = tunnel_host
+            .register_host(
+                &None,
+                None,
+                &tunnel_id,
+                &removed_proxy_id,
+                &TunnelAccess::default(),
+            )
+            .unwrap();
+
+        assert!(tunnel_host.remove_proxy(&tunnel_id, &removed_proxy_id));
+        assert!(!tunnel_host.remove_proxy(&tunnel_id, &removed_proxy_id));
+
+        assert!(!tunnel_host.get_candidates(&kept).is_empty());
+        assert!(tunnel_host.get_candidates(&removed).is_empty());
+    }
+
+    #[test]
+    fn test_register_host_in_pool() {
+        let (mut tunnel_host, _) = setup();
+        let first_tunnel_id = Uuid::new_v4();
+        let second_tunnel_id = Uuid::new_v4();
+
+        let hostname = tunnel_host
+            .register_host(
+                &None,
+                Some("app"),
+                &first_tunnel_id,
+                &Uuid::new_v4(),
+                &TunnelAccess::default(),
+            )
+            .unwrap();
+        let same_hostname = tunnel_host
+            .register_host(
+                &None,
+                Some("app"),
+                &second_tunnel_id,
+                &Uuid::new_v4(),
+                &TunnelAccess::default(),
+            )
+            .unwrap();
+
+        assert_eq!(hostname, "app");
+        assert_eq!(hostname, same_hostname);
+        assert_eq!(tunnel_host.get_candidates(&hostname).len(), 2);
+
+        tunnel_host.remove_tunnel_by_id(&first_tunnel_id);
+        assert_eq!(tunnel_host.get_candidates(&hostname).len(), 1);
+
+        tunnel_host.remove_tunnel_by_id(&second_tunnel_id);
+        assert!(tunnel_host.host_tunnel_map.is_empty());
+    }
+
+    #[test]
+    fn test_register_host_in_pool_of_other_owner() {
+        let (mut tunnel_host, _) = setup();
+        let owner = TunnelAccess::for_identity("alice", Default::default());
+
+        tunnel_host
+            .register_host(&None, Some("app"), &Uuid::new_v4(), &Uuid::new_v4(), &owner)
+            .unwrap();
+
+        assert!(
+            tunnel_host
+                .register_host(
+                    &None,
+                    Some("app"),
+                    &Uuid::new_v4(),
+                    &Uuid::new_v4(),
+                    &TunnelAccess::for_identity("mallory", Default::default()),
+                )
+                .is_err()
+        );
+        assert!(
+            tunnel_host
+                .register_host(&None, Some("app"), &Uuid::new_v4(), &Uuid::new_v4(), &owner)
+                .is_ok()
+        );
+        assert_eq!(tunnel_host.get_candidates("app").len(), 2);
+    }
+
+    #[test]
+    fn test_register_pool_with_taken_hostname() {
+        let (mut tunnel_host, _) = setup();
+
+        tunnel_host
+            .register_host(
+                &Some("app".to_string()),
+                None,
+                &Uuid::new_v4(),
+                &Uuid::new_v4(),
+                &TunnelAccess::default(),
+            )
+            .unwrap();
+
+        assert!(
+            tunnel_host
+                .register_host(
+                    &None,
+                    Some("app"),
+                    &Uuid::new_v4(),
+                    &Uuid::new_v4(),
+                    &TunnelAccess::default()
+                )
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_is_name_available() {
+        let (mut tunnel_host, _) = setup();
+        let tunnel_id = Uuid::new_v4();
+        let proxy_id = Uuid::new_v4();
+
+        assert!(tunnel_host.is_name_available("app", &tunnel_id, &proxy_id));
+
+        tunnel_host
+            .register_host(
+                &Some("app".to_string()),
+                None,
+                &tunnel_id,
+                &proxy_id,
+                &TunnelAccess::default(),
+            )
+            .unwrap();
+
+        assert!(tunnel_host.is_name_available("app", &tunnel_id, &proxy_id));
+        assert!(!tunnel_host.is_name_available("other", &tunnel_id, &proxy_id));
+        assert!(!tunnel_host.is_name_available("app", &Uuid::new_v4(), &Uuid::new_v4()));
     }
 }
@@ -4,20 +4,32 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    common::channel::OkResponse, create_channel_enum, server::incoming_requests::ProxySession,
+    common::channel::OkResponse,
+    create_channel_enum,
+    server::{incoming_requests::ProxySession, tunnel_policy::TunnelAccess},
 };
 
 use super::{http::HttpEndpointInfo, tcp::TcpEndpointInfo, udp::UdpEndpointInfo};
 
 create_channel_enum!(EndpointChannelRequest -> EndpointChannelResponse, {
     RegisterTunnelRequest -> RegisterTunnelResponse,
-    RemoveTunnelRequest -> OkResponse
+    RegisterProxyRequest -> RegisterProxyResponse,
+    RemoveTunnelRequest -> OkResponse,
+    RemoveProxyRequest -> RemoveProxyResponse
 });
 
 #[derive(Clone, Debug)]
 pub struct RegisterTunnelRequest {
     pub tunnel_id: Uuid,
     pub proxy_sessions: Vec<ProxySession>,
+    pub access: TunnelAccess,
+}
+
+#[derive(Clone, Debug)]
+pub struct RegisterProxyRequest {
+    pub tunnel_id: Uuid,
+    pub proxy_session: ProxySession,
+    pub access: TunnelAccess,
 }
 
 #[derive(Clone, Debug)]
@@ -30,6 +42,12 @@ pub enum RegisterTunnelResponse {
     },
 }
 
+#[derive(Clone, Debug)]
+pub enum RegisterProxyResponse {
+    Accepted { endpoint_info: ResolvedEndpointInfo },
+    Rejected { reason: String },
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum ResolvedEndpointInfo {
@@ -38,7 +56,29 @@ pub enum ResolvedEndpointInfo {
     Udp(UdpEndpointInfo),
 }
 
+impl ResolvedEndpointInfo {
+    /// Returns the URL or hostname clients use to reach the proxy.
+    pub fn get_assigned_address(&self) -> String {
+        match self {
+            ResolvedEndpointInfo::Http(info) => info.assigned_url.clone(),
+            ResolvedEndpointInfo::Tcp(info) => info.assigned_hostname.clone(),
+            ResolvedEndpointInfo::Udp(info) => info.assigned_hostname.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct RemoveTunnelRequest {
     pub tunnel_id: Uuid,
 }
+
+#[derive(Clone, Debug)]
+pub struct RemoveProxyRequest {
+    pub tunnel_id: Uuid,
+    pub proxy_id: Uuid,
+}
+
+#[derive(Clone, Debug)]
+pub struct RemoveProxyResponse {
+    pub removed: bool,
+}
@@ -8,6 +8,7 @@ use tokio::io::Result;
 pub mod http;
 pub mod messages;
 pub mod monitor;
+pub mod pool;
 pub mod tcp;
 pub mod udp;
 
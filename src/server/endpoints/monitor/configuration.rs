@@ -42,6 +42,15 @@ impl MonitorEndpointConfig {
             .clone()
             .unwrap_or(MonitorOrigin::Any)
     }
+
+    pub fn with_defaults(&self) -> Self {
+        Self {
+            encryption: Some(self.get_encryption()),
+            address: Some(self.get_address()),
+            allow_cors_origins: Some(self.get_allow_cors_origins()),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -28,6 +28,7 @@ mod middleware;
 mod response;
 mod state;
 mod system_routes;
+mod token_routes;
 mod tunnel_routes;
 
 pub async fn start(
@@ -44,6 +45,7 @@ pub async fn start(
         .nest("/tunnels", tunnel_routes::get_router())
         .nest("/links", link_routes::get_router())
         .nest("/system", system_routes::get_router())
+        .nest("/tokens", token_routes::get_router())
         .layer(from_fn_with_state(
             state.clone(),
             middleware::handle_authorization,
@@ -138,6 +140,7 @@ async fn start_server(
         Ok(ServerEncryption::Tls {
             ref cert_path,
             ref key_path,
+            ..
         }) => start_https_server(address, cert_path, key_path, app).await,
         Err(e) => {
             error!("Failed to start '{}' server: {}", name, e);
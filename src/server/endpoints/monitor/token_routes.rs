@@ -0,0 +1,57 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, post},
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::server::{monitoring, tunnel_policy::TunnelPolicy};
+
+use super::{
+    response::{into_json, into_message},
+    state::AppState,
+};
+
+#[derive(Deserialize)]
+struct CreateTokenRequest {
+    identity: String,
+    expires_in_hours: Option<u64>,
+    policy: Option<TunnelPolicy>,
+}
+
+async fn create_token(
+    State(state): State<AppState>,
+    Json(request): Json<CreateTokenRequest>,
+) -> impl IntoResponse {
+    match monitoring::create_token(
+        &state.services,
+        request.identity,
+        request.expires_in_hours.unwrap_or(24),
+        request.policy,
+    )
+    .await
+    {
+        Ok(token) => into_json(StatusCode::CREATED, token),
+        Err(error) => into_message(StatusCode::BAD_REQUEST, &error),
+    }
+}
+
+async fn revoke_token(
+    Path(token_id): Path<Uuid>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if let Err(error) = monitoring::revoke_token(&state.services, &token_id).await {
+        return into_message(StatusCode::BAD_REQUEST, &error);
+    }
+
+    into_message(StatusCode::OK, "Token revoked")
+}
+
+pub fn get_router() -> Router<AppState> {
+    Router::new()
+        .route("/", post(create_token))
+        .route("/{id}", delete(revoke_token))
+}
@@ -0,0 +1,272 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::server::{
+    services::Services,
+    session::messages::{ClientLinkRequest, ClientLinkResponse},
+    tunnel_policy::TunnelAccess,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PoolStrategy {
+    #[default]
+    RoundRobin,
+    LeastActiveLinks,
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolMember {
+    pub tunnel_id: Uuid,
+    pub proxy_id: Uuid,
+}
+
+/// Tunnels sharing one hostname or port. A tunnel which did not request a pool is the only member of its own.
+#[derive(Debug)]
+pub struct TunnelPool {
+    name: Option<String>,
+    owner: TunnelAccess,
+    members: Vec<PoolMember>,
+    next_index: AtomicUsize,
+}
+
+impl TunnelPool {
+    pub fn new(name: Option<String>, owner: TunnelAccess, tunnel_id: Uuid, proxy_id: Uuid) -> Self {
+        Self {
+            name,
+            owner,
+            members: vec![PoolMember {
+                tunnel_id,
+                proxy_id,
+            }],
+            next_index: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.as_deref() == Some(name)
+    }
+
+    /// Tunnels can join a pool created with the same credential or identity. Unrestricted access
+    /// can join any pool.
+    pub fn can_join(&self, access: &TunnelAccess) -> bool {
+        access.is_unrestricted() || self.owner.has_same_owner(access)
+    }
+
+    pub fn add_member(&mut self, tunnel_id: Uuid, proxy_id: Uuid) {
+        if !self.has_member(&tunnel_id, &proxy_id) {
+            self.members.push(PoolMember {
+                tunnel_id,
+                proxy_id,
+            });
+        }
+    }
+
+    pub fn has_member(&self, tunnel_id: &Uuid, proxy_id: &Uuid) -> bool {
+        self.members
+            .iter()
+            .any(|m| &m.tunnel_id == tunnel_id && &m.proxy_id == proxy_id)
+    }
+
+    pub fn retain_members(&mut self, keep: impl Fn(&PoolMember) -> bool) {
+        self.members.retain(keep);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Returns all members in the order they should be tried for the next client.
+    pub fn get_candidates(&self, strategy: PoolStrategy) -> Vec<PoolMember> {
+        let mut candidates = self.members.clone();
+
+        if candidates.len() < 2 {
+            return candidates;
+        }
+
+        let start = match strategy {
+            PoolStrategy::Random => rand::random_range(0..candidates.len()),
+            _ => self.next_index.fetch_add(1, Ordering::Relaxed) % candidates.len(),
+        };
+
+        candidates.rotate_left(start);
+        candidates
+    }
+}
+
+/// Orders candidates by the strategy and drops tunnels which reported their service as unhealthy.
+pub async fn resolve_candidates(
+    services: &Arc<Services>,
+    strategy: PoolStrategy,
+    mut candidates: Vec<PoolMember>,
+) -> Vec<PoolMember> {
+    {
+        let tunnel_manager = services.get_tunnel_manager().await;
+        candidates.retain(|m| {
+            !tunnel_manager
+                .get_proxy_health(&m.tunnel_id, &m.proxy_id)
+                .is_unhealthy()
+        });
+    }
+
+    if strategy == PoolStrategy::LeastActiveLinks && candidates.len() > 1 {
+        let link_manager = services.get_link_manager().await;
+        candidates.sort_by_key(|m| link_manager.get_active_link_count(&m.tunnel_id, &m.proxy_id));
+    }
+
+    candidates
+}
+
+pub enum LinkError {
+    Rejected(String),
+    Failed,
+}
+
+/// Asks candidates in order to link the client, until one accepts it.
+pub async fn link_client(
+    services: &Arc<Services>,
+    client_id: Uuid,
+    candidates: &[PoolMember],
+) -> Result<PoolMember, LinkError> {
+    let mut error = LinkError::Failed;
+
+    for candidate in candidates {
+        let response = services
+            .get_tunnel_manager()
+            .await
+            .send_session_request(
+                &candidate.tunnel_id,
+                ClientLinkRequest {
+                    client_id,
+                    proxy_id: candidate.proxy_id,
+                },
+            )
+            .await;
+
+        match response {
+            Ok(ClientLinkResponse::Accepted) => return Ok(*candidate),
+            Ok(ClientLinkResponse::Rejected { reason }) => {
+                debug!(
+                    "Client ID '{}' rejected by tunnel ID '{}': {}",
+                    client_id, candidate.tunnel_id, reason
+                );
+                error = LinkError::Rejected(reason);
+            }
+            Err(e) => {
+                debug!(
+                    "Failed to link client ID '{}' to tunnel ID '{}': {}",
+                    client_id, candidate.tunnel_id, e
+                );
+            }
+        }
+    }
+
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_pool(size: usize) -> TunnelPool {
+        let mut pool = TunnelPool::new(
+            Some("app".to_string()),
+            TunnelAccess::default(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+
+        for _ in 1..size {
+            pool.add_member(Uuid::new_v4(), Uuid::new_v4());
+        }
+
+        pool
+    }
+
+    #[test]
+    fn test_add_member_once() {
+        let tunnel_id = Uuid::new_v4();
+        let proxy_id = Uuid::new_v4();
+        let mut pool = TunnelPool::new(None, TunnelAccess::default(), tunnel_id, proxy_id);
+
+        pool.add_member(tunnel_id, proxy_id);
+        assert_eq!(pool.len(), 1);
+        assert!(pool.has_member(&tunnel_id, &proxy_id));
+    }
+
+    #[test]
+    fn test_can_join_only_with_same_owner() {
+        let owner = TunnelAccess::for_identity("alice", Default::default());
+        let pool = TunnelPool::new(
+            Some("app".to_string()),
+            owner.clone(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+
+        assert!(pool.can_join(&owner));
+        assert!(pool.can_join(&TunnelAccess::unrestricted()));
+        assert!(!pool.can_join(&TunnelAccess::default()));
+        assert!(!pool.can_join(&TunnelAccess::for_identity("mallory", Default::default())));
+    }
+
+    #[test]
+    fn test_round_robin_rotates_candidates() {
+        let pool = create_pool(3);
+
+        let first = pool.get_candidates(PoolStrategy::RoundRobin);
+        let second = pool.get_candidates(PoolStrategy::RoundRobin);
+        let third = pool.get_candidates(PoolStrategy::RoundRobin);
+        let fourth = pool.get_candidates(PoolStrategy::RoundRobin);
+
+        assert_eq!(first.len(), 3);
+        assert_eq!(second[0], first[1]);
+        assert_eq!(third[0], first[2]);
+        assert_eq!(fourth, first);
+    }
+
+    #[test]
+    fn test_random_returns_all_members() {
+        let pool = create_pool(4);
+
+        for _ in 0..10 {
+            let candidates = pool.get_candidates(PoolStrategy::Random);
+            assert_eq!(candidates.len(), 4);
+            assert!(
+                candidates
+                    .iter()
+                    .all(|m| pool.has_member(&m.tunnel_id, &m.proxy_id))
+            );
+        }
+    }
+
+    #[test]
+    fn test_retain_members() {
+        let tunnel_id = Uuid::new_v4();
+        let mut pool = TunnelPool::new(
+            Some("app".to_string()),
+            TunnelAccess::default(),
+            tunnel_id,
+            Uuid::new_v4(),
+        );
+        pool.add_member(Uuid::new_v4(), Uuid::new_v4());
+
+        pool.retain_members(|m| m.tunnel_id != tunnel_id);
+        assert_eq!(pool.len(), 1);
+        assert!(pool.is_named("app"));
+
+        pool.retain_members(|_| false);
+        assert!(pool.is_empty());
+    }
+}
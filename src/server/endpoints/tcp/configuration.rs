@@ -6,10 +6,10 @@ use crate::{
     common::{
         validate::{Validatable, Validation},
         validate_rules::{
-            HostAddressMustBeValid, PortHostnameTemplatemustBeValid, PortMustBeValid,
+            HostAddressMustBeValid, PortHostnameTemplatemustBeValid, PortRangeMustBeValid,
         },
     },
-    server::configuration::EndpointServerEncryption,
+    server::{configuration::EndpointServerEncryption, endpoints::pool::PoolStrategy},
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -28,6 +28,12 @@ pub struct TcpEndpointConfig {
 
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub full_hostname_template: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub allow_pools: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pool_strategy: Option<PoolStrategy>,
 }
 
 impl TcpEndpointConfig {
@@ -58,6 +64,25 @@ impl TcpEndpointConfig {
             .clone()
             .unwrap_or(EndpointServerEncryption::None)
     }
+
+    pub fn get_allow_pools(&self) -> bool {
+        self.allow_pools.unwrap_or(false)
+    }
+
+    pub fn get_pool_strategy(&self) -> PoolStrategy {
+        self.pool_strategy.unwrap_or_default()
+    }
+
+    pub fn with_defaults(&self) -> Self {
+        Self {
+            address: Some(self.get_address()),
+            allow_desired_port: Some(self.get_allow_desired_port()),
+            encryption: Some(self.get_encryption()),
+            allow_pools: Some(self.get_allow_pools()),
+            pool_strategy: Some(self.get_pool_strategy()),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -66,6 +91,9 @@ pub struct TcpPublicEndpointConfig {
     pub allow_desired_port: bool,
     pub reserve_ports_from: u16,
     pub reserve_ports_to: u16,
+
+    #[serde(default)]
+    pub allow_pools: bool,
 }
 
 impl From<&TcpEndpointConfig> for TcpPublicEndpointConfig {
@@ -75,6 +103,7 @@ impl From<&TcpEndpointConfig> for TcpPublicEndpointConfig {
             allow_desired_port: config.get_allow_desired_port(),
             reserve_ports_from: config.reserve_ports_from,
             reserve_ports_to: config.reserve_ports_to,
+            allow_pools: config.get_allow_pools(),
         }
     }
 }
@@ -92,6 +121,11 @@ impl Display for TcpPublicEndpointConfig {
             "Port range: {} - {}",
             self.reserve_ports_from, self.reserve_ports_to
         )?;
+        writeln!(
+            f,
+            "Tunnel pools: {}",
+            if self.allow_pools { "Allowed" } else { "Not Allowed" }
+        )?;
 
         Ok(())
     }
@@ -112,15 +146,10 @@ impl Validatable for TcpEndpointConfig {
             );
         }
 
-        if self.reserve_ports_from > self.reserve_ports_to {
-            result.validate_rule::<PortMustBeValid>("reserve_ports_from", &self.reserve_ports_from);
-            result.validate_rule::<PortMustBeValid>("reserve_ports_to", &self.reserve_ports_to);
-
-            result.add_field_error(
-                "reserve_ports_from",
-                "reserve_ports_from must be less than reserve_ports_to.",
-            );
-        }
+        result.validate_rule::<PortRangeMustBeValid>(
+            "reserve_ports",
+            &(self.reserve_ports_from, self.reserve_ports_to),
+        );
     }
 }
 
@@ -142,6 +171,8 @@ mod tests {
             reserve_ports_to: 9000,
             encryption,
             full_hostname_template: full_hostname_template.map(|s| s.to_string()),
+            allow_pools: None,
+            pool_strategy: None,
         }
     }
 
@@ -7,9 +7,9 @@ use uuid::Uuid;
 
 use crate::common::connection::Connection;
 use crate::common::tcp_server::TcpServer;
+use crate::server::endpoints::pool::{LinkError, link_client, resolve_candidates};
 use crate::server::services::Client;
 use crate::server::services::Services as MainServices;
-use crate::server::session::messages::{ClientLinkRequest, ClientLinkResponse};
 
 use super::tcp_services::TcpServices;
 
@@ -54,20 +54,35 @@ pub async fn start(port: u16, services: Arc<TcpServices>) -> Result<()> {
 }
 
 pub async fn start_client(port: u16, mut connection: Connection, services: &Arc<TcpServices>) {
-    let tunnel_host = services.get_tunnel_host().await;
+    let candidates = services.get_tunnel_host().await.get_candidates(port);
 
-    let Some(tunnel) = tunnel_host.get_tunnel(port) else {
+    if candidates.is_empty() {
         error!("No tunnel found for port {}", port);
         connection.shutdown().await;
         return;
-    };
+    }
+
+    debug!("Found {} tunnel(s) for port {}", candidates.len(), port);
 
-    debug!("Found tunnel for port {}: {}", port, tunnel.tunnel_id);
+    let main_services = services.get_main_services();
+    let candidates = resolve_candidates(
+        &main_services,
+        services.get_config().get_pool_strategy(),
+        candidates,
+    )
+    .await;
+
+    if candidates.is_empty() {
+        info!(
+            "Service behind the tunnel on port {} is unavailable, refusing client.",
+            port
+        );
+        connection.shutdown().await;
+        return;
+    }
 
     let client_id = Uuid::new_v4();
     let client = Client::new(client_id, services.get_endpoint_name(), connection, None);
-
-    let main_services = services.get_main_services();
     if let Err((error, link)) = main_services
         .get_client_manager()
         .await
@@ -82,34 +97,21 @@ pub async fn start_client(port: u16, mut connection: Connection, services: &Arc<
         return;
     }
 
-    let Ok(response) = main_services
-        .get_tunnel_manager()
-        .await
-        .send_session_request(
-            &tunnel.tunnel_id,
-            ClientLinkRequest {
-                client_id,
-                proxy_id: tunnel.proxy_id,
-            },
-        )
-        .await
-    else {
-        error!("Error sending client link request");
-        discard_client(client_id, &main_services).await;
-        return;
-    };
-
-    match response {
-        ClientLinkResponse::Accepted => {
+    match link_client(&main_services, client_id, &candidates).await {
+        Ok(tunnel) => {
             info!(
                 "Client connected to tunnel {} on port {}",
                 tunnel.tunnel_id, port
             );
         }
-        ClientLinkResponse::Rejected { reason } => {
+        Err(LinkError::Rejected(reason)) => {
             error!("Client rejected by tunnel: {}", reason);
             discard_client(client_id, &main_services).await;
         }
+        Err(LinkError::Failed) => {
+            error!("Error sending client link request");
+            discard_client(client_id, &main_services).await;
+        }
     }
 }
 
@@ -2,18 +2,19 @@ use std::collections::HashMap;
 
 use uuid::Uuid;
 
+use crate::server::{
+    endpoints::pool::{PoolMember, PoolStrategy, TunnelPool},
+    tunnel_policy::{TunnelAccess, TunnelPolicy},
+};
+
 use super::configuration::TcpEndpointConfig;
 
 pub struct TunnelHost {
     max_port: u16,
     min_port: u16,
     allow_desired_port: bool,
-    host_tunnel_map: HashMap<u16, Tunnel>,
-}
-
-pub struct Tunnel {
-    pub tunnel_id: Uuid,
-    pub proxy_id: Uuid,
+    pool_strategy: PoolStrategy,
+    host_tunnel_map: HashMap<u16, TunnelPool>,
 }
 
 impl TunnelHost {
@@ -23,6 +24,7 @@ impl TunnelHost {
             max_port: config.reserve_ports_to,
             min_port: config.reserve_ports_from,
             allow_desired_port: config.get_allow_desired_port(),
+            pool_strategy: config.get_pool_strategy(),
         }
     }
 
@@ -30,25 +32,30 @@ impl TunnelHost {
         self.host_tunnel_map.len() < (self.max_port - self.min_port + 1) as usize
     }
 
-    pub fn get_first_available_port(&self) -> Option<u16> {
-        (self.min_port..=self.max_port).find(|&port| !self.host_tunnel_map.contains_key(&port))
+    pub fn get_first_available_port(&self, policy: &TunnelPolicy) -> Option<u16> {
+        (self.min_port..=self.max_port).find(|&port| {
+            !self.host_tunnel_map.contains_key(&port) && policy.is_port_allowed(port)
+        })
     }
 
-    pub fn resolve_port(&self, port: Option<u16>) -> Option<u16> {
+    pub fn resolve_port(&self, port: Option<u16>, policy: &TunnelPolicy) -> Option<u16> {
         let port = match port {
             Some(port) => {
                 if self.allow_desired_port {
                     port
                 } else {
-                    self.get_first_available_port()?
+                    self.get_first_available_port(policy)?
                 }
             }
-            None => self.get_first_available_port()?,
+            None => self.get_first_available_port(policy)?,
         };
 
-        if self.host_tunnel_map.contains_key(&port) || port < self.min_port || port > self.max_port
+        if self.host_tunnel_map.contains_key(&port)
+            || port < self.min_port
+            || port > self.max_port
+            || !policy.is_port_allowed(port)
         {
-            return self.get_first_available_port();
+            return self.get_first_available_port(policy);
         }
 
         Some(port)
@@ -57,31 +64,98 @@ impl TunnelHost {
     pub fn add_tunnel(
         &mut self,
         desired_port: Option<u16>,
+        pool: Option<&str>,
         tunnel_id: Uuid,
         proxy_id: Uuid,
+        access: &TunnelAccess,
     ) -> Result<u16, String> {
-        let Some(port) = self.resolve_port(desired_port) else {
+        if let Some(port) = self.find_tunnel_port(&tunnel_id, &proxy_id) {
+            return Ok(port);
+        }
+
+        if let Some(name) = pool
+            && let Some(port) = self.find_pool_port(name)
+        {
+            if !access.policy.is_port_allowed(port) {
+                return Err(format!("Pool port {port} is not allowed for this tunnel key"));
+            }
+
+            if let Some(tunnel_pool) = self.host_tunnel_map.get_mut(&port) {
+                if !tunnel_pool.can_join(access) {
+                    return Err(format!("Pool '{name}' belongs to another tunnel key"));
+                }
+
+                tunnel_pool.add_member(tunnel_id, proxy_id);
+            }
+
+            return Ok(port);
+        }
+
+        let Some(port) = self.resolve_port(desired_port, &access.policy) else {
             return Err("No available ports".to_string());
         };
 
         self.host_tunnel_map.insert(
             port,
-            Tunnel {
+            TunnelPool::new(
+                pool.map(|name| name.to_string()),
+                access.clone(),
                 tunnel_id,
                 proxy_id,
-            },
+            ),
         );
 
         Ok(port)
     }
 
+    fn retain_members(&mut self, keep: impl Fn(&PoolMember) -> bool) {
+        for tunnel_pool in self.host_tunnel_map.values_mut() {
+            tunnel_pool.retain_members(&keep);
+        }
+
+        self.host_tunnel_map
+            .retain(|_, tunnel_pool| !tunnel_pool.is_empty());
+    }
+
+    fn get_member_count(&self) -> usize {
+        self.host_tunnel_map.values().map(|p| p.len()).sum()
+    }
+
     pub fn remove_tunnel(&mut self, tunnel_id: &Uuid) {
+        self.retain_members(|m| &m.tunnel_id != tunnel_id);
+    }
+
+    pub fn remove_missing_proxies(&mut self, tunnel_id: &Uuid, proxy_ids: &[Uuid]) {
+        self.retain_members(|m| &m.tunnel_id != tunnel_id || proxy_ids.contains(&m.proxy_id));
+    }
+
+    pub fn remove_proxy(&mut self, tunnel_id: &Uuid, proxy_id: &Uuid) -> bool {
+        let count = self.get_member_count();
+
+        self.retain_members(|m| &m.tunnel_id != tunnel_id || &m.proxy_id != proxy_id);
+
+        self.get_member_count() != count
+    }
+
+    pub fn find_tunnel_port(&self, tunnel_id: &Uuid, proxy_id: &Uuid) -> Option<u16> {
         self.host_tunnel_map
-            .retain(|_, v| &v.tunnel_id != tunnel_id);
+            .iter()
+            .find(|(_, v)| v.has_member(tunnel_id, proxy_id))
+            .map(|(port, _)| *port)
     }
 
-    pub fn get_tunnel(&self, port: u16) -> Option<&Tunnel> {
-        self.host_tunnel_map.get(&port)
+    pub fn find_pool_port(&self, pool: &str) -> Option<u16> {
+        self.host_tunnel_map
+            .iter()
+            .find(|(_, v)| v.is_named(pool))
+            .map(|(port, _)| *port)
+    }
+
+    pub fn get_candidates(&self, port: u16) -> Vec<PoolMember> {
+        self.host_tunnel_map
+            .get(&port)
+            .map(|tunnel_pool| tunnel_pool.get_candidates(self.pool_strategy))
+            .unwrap_or_default()
     }
 }
 
@@ -90,6 +164,22 @@ mod tests {
     use super::*;
     use uuid::Uuid;
 
+    const UNRESTRICTED: TunnelPolicy = TunnelPolicy {
+        allowed_endpoints: None,
+        max_proxies: None,
+        max_tunnels: None,
+        allowed_hostnames: None,
+        allowed_ports: None,
+    };
+
+    const UNRESTRICTED_ACCESS: TunnelAccess = TunnelAccess {
+        credential: None,
+        identity: None,
+        token_id: None,
+        policy: UNRESTRICTED,
+        unrestricted: true,
+    };
+
     fn setup_tunnel_host(min_port: u16, max_port: u16, allow_desired_port: bool) -> TunnelHost {
         let config = TcpEndpointConfig {
             reserve_ports_from: min_port,
@@ -98,6 +188,8 @@ mod tests {
             allow_desired_port: Some(allow_desired_port),
             encryption: None,
             full_hostname_template: None,
+            allow_pools: None,
+            pool_strategy: None,
         };
         TunnelHost::new(&config)
     }
@@ -111,21 +203,21 @@ mod tests {
     #[test]
     fn test_get_first_available_port() {
         let mut tunnel_host = setup_tunnel_host(1000, 1005, true);
-        assert_eq!(tunnel_host.get_first_available_port(), Some(1000));
+        assert_eq!(tunnel_host.get_first_available_port(&UNRESTRICTED), Some(1000));
         tunnel_host
-            .add_tunnel(Some(1000), Uuid::new_v4(), Uuid::new_v4())
+            .add_tunnel(Some(1000), None, Uuid::new_v4(), Uuid::new_v4(), &UNRESTRICTED_ACCESS)
             .unwrap();
-        assert_eq!(tunnel_host.get_first_available_port(), Some(1001));
+        assert_eq!(tunnel_host.get_first_available_port(&UNRESTRICTED), Some(1001));
     }
 
     #[test]
     fn test_resolve_port() {
         let mut tunnel_host = setup_tunnel_host(1000, 1005, true);
-        assert_eq!(tunnel_host.resolve_port(Some(1000)), Some(1000));
+        assert_eq!(tunnel_host.resolve_port(Some(1000), &UNRESTRICTED), Some(1000));
         tunnel_host
-            .add_tunnel(Some(1000), Uuid::new_v4(), Uuid::new_v4())
+            .add_tunnel(Some(1000), None, Uuid::new_v4(), Uuid::new_v4(), &UNRESTRICTED_ACCESS)
             .unwrap();
-        assert_eq!(tunnel_host.resolve_port(Some(1000)), Some(1001));
+        assert_eq!(tunnel_host.resolve_port(Some(1000), &UNRESTRICTED), Some(1001));
     }
 
     #[test]
@@ -134,10 +226,10 @@ mod tests {
         let tunnel_id = Uuid::new_v4();
         let proxy_id = Uuid::new_v4();
         let port = tunnel_host
-            .add_tunnel(Some(1000), tunnel_id, proxy_id)
+            .add_tunnel(Some(1000), None, tunnel_id, proxy_id, &UNRESTRICTED_ACCESS)
             .unwrap();
         assert_eq!(port, 1000);
-        assert!(tunnel_host.get_tunnel(1000).is_some());
+        assert!(!tunnel_host.get_candidates(1000).is_empty());
     }
 
     #[test]
@@ -146,22 +238,164 @@ mod tests {
         let tunnel_id = Uuid::new_v4();
         let proxy_id = Uuid::new_v4();
         tunnel_host
-            .add_tunnel(Some(1000), tunnel_id, proxy_id)
+            .add_tunnel(Some(1000), None, tunnel_id, proxy_id, &UNRESTRICTED_ACCESS)
             .unwrap();
         tunnel_host.remove_tunnel(&tunnel_id);
-        assert!(tunnel_host.get_tunnel(1000).is_none());
+        assert!(tunnel_host.get_candidates(1000).is_empty());
     }
 
     #[test]
-    fn test_get_tunnel() {
+    fn test_get_candidates() {
         let mut tunnel_host = setup_tunnel_host(1000, 1005, true);
         let tunnel_id = Uuid::new_v4();
         let proxy_id = Uuid::new_v4();
         tunnel_host
-            .add_tunnel(Some(1000), tunnel_id, proxy_id)
+            .add_tunnel(Some(1000), None, tunnel_id, proxy_id, &UNRESTRICTED_ACCESS)
             .unwrap();
-        let tunnel = tunnel_host.get_tunnel(1000).unwrap();
+        let candidates = tunnel_host.get_candidates(1000);
+        assert_eq!(candidates.len(), 1);
+        let tunnel = &candidates[0];
         assert_eq!(tunnel.tunnel_id, tunnel_id);
         assert_eq!(tunnel.proxy_id, proxy_id);
     }
+
+    #[test]
+    fn test_add_tunnel_again_returns_same_port() {
+        let mut tunnel_host = setup_tunnel_host(1000, 1005, true);
+        let tunnel_id = Uuid::new_v4();
+        let proxy_id = Uuid::new_v4();
+        let port = tunnel_host
+            .add_tunnel(None, None, tunnel_id, proxy_id, &UNRESTRICTED_ACCESS)
+            .unwrap();
+        let same_port = tunnel_host
+            .add_tunnel(None, None, tunnel_id, proxy_id, &UNRESTRICTED_ACCESS)
+            .unwrap();
+        assert_eq!(port, same_port);
+        assert_eq!(tunnel_host.find_tunnel_port(&tunnel_id, &proxy_id), Some(port));
+    }
+
+    #[test]
+    fn test_remove_missing_proxies() {
+        let mut tunnel_host = setup_tunnel_host(1000, 1005, true);
+        let tunnel_id = Uuid::new_v4();
+        let kept_proxy_id = Uuid::new_v4();
+        let removed_proxy_id = Uuid::new_v4();
+        let kept_port = tunnel_host
+            .add_tunnel(None, None, tunnel_id, kept_proxy_id, &UNRESTRICTED_ACCESS)
+            .unwrap();
+        let removed_port = tunnel_host
+            .add_tunnel(None, None, tunnel_id, removed_proxy_id, &UNRESTRICTED_ACCESS)
+            .unwrap();
+        tunnel_host.remove_missing_proxies(&tunnel_id, &[kept_proxy_id]);
+        assert!(!tunnel_host.get_candidates(kept_port).is_empty());
+        assert!(tunnel_host.get_candidates(removed_port).is_empty());
+    }
+
+    #[test]
+    fn test_remove_proxy() {
+        let mut tunnel_host = setup_tunnel_host(1000, 1005, true);
+        let tunnel_id = Uuid::new_v4();
+        let kept_proxy_id = Uuid::new_v4();
+        let removed_proxy_id = Uuid::new_v4();
+        let kept_port = tunnel_host
+            .add_tunnel(None, None, tunnel_id, kept_proxy_id, &UNRESTRICTED_ACCESS)
+            .unwrap();
+        let removed_port = tunnel_host
+            .add_tunnel(None, None, tunnel_id, removed_proxy_id, &UNRESTRICTED_ACCESS)
+            .unwrap();
+        assert!(tunnel_host.remove_proxy(&tunnel_id, &removed_proxy_id));
+        assert!(!tunnel_host.remove_proxy(&tunnel_id, &removed_proxy_id));
+        assert!(!tunnel_host.get_candidates(kept_port).is_empty());
+        assert!(tunnel_host.get_candidates(removed_port).is_empty());
+    }
+
+    #[test]
+    fn test_add_tunnel_to_pool() {
+        let mut tunnel_host = setup_tunnel_host(1000, 1005, true);
+        let first_tunnel_id = Uuid::new_v4();
+        let second_tunnel_id = Uuid::new_v4();
+        let port = tunnel_host
+            .add_tunnel(None, Some("app"), first_tunnel_id, Uuid::new_v4(), &UNRESTRICTED_ACCESS)
+            .unwrap();
+        let same_port = tunnel_host
+            .add_tunnel(
+                Some(1003),
+                Some("app"),
+                second_tunnel_id,
+                Uuid::new_v4(),
+                &UNRESTRICTED_ACCESS,
+            )
+            .unwrap();
+        let other_port = tunnel_host
+            .add_tunnel(None, Some("other"), Uuid::new_v4(), Uuid::new_v4(), &UNRESTRICTED_ACCESS)
+            .unwrap();
+
+        assert_eq!(port, same_port);
+        assert_ne!(port, other_port);
+        assert_eq!(tunnel_host.get_candidates(port).len(), 2);
+
+        tunnel_host.remove_tunnel(&first_tunnel_id);
+        assert_eq!(tunnel_host.get_candidates(port).len(), 1);
+
+        tunnel_host.remove_tunnel(&second_tunnel_id);
+        assert!(tunnel_host.get_candidates(port).is_empty());
+        assert_eq!(tunnel_host.find_pool_port("app"), None);
+    }
+
+    #[test]
+    fn test_add_tunnel_to_pool_of_other_owner() {
+        let mut tunnel_host = setup_tunnel_host(1000, 1005, true);
+        let owner = TunnelAccess::for_identity("alice", UNRESTRICTED);
+        let other = TunnelAccess::for_identity("mallory", UNRESTRICTED);
+
+        let port = tunnel_host
+            .add_tunnel(None, Some("app"), Uuid::new_v4(), Uuid::new_v4(), &owner)
+            .unwrap();
+
+        assert!(tunnel_host
+            .add_tunnel(None, Some("app"), Uuid::new_v4(), Uuid::new_v4(), &other)
+            .is_err());
+        assert_eq!(
+            tunnel_host.add_tunnel(None, Some("app"), Uuid::new_v4(), Uuid::new_v4(), &owner),
+            Ok(port)
+        );
+        assert_eq!(tunnel_host.get_candidates(port).len(), 2);
+    }
+
+    #[test]
+    fn test_add_tunnel_with_port_policy() {
+        let mut tunnel_host = setup_tunnel_host(1000, 1005, true);
+        let access = TunnelAccess {
+            policy: TunnelPolicy {
+                allowed_ports: Some(vec![crate::server::tunnel_policy::PortRange {
+                    from: 1003,
+                    to: 1004,
+                }]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let pool_port = tunnel_host
+            .add_tunnel(None, Some("app"), Uuid::new_v4(), Uuid::new_v4(), &UNRESTRICTED_ACCESS)
+            .unwrap();
+        assert_eq!(pool_port, 1000);
+
+        let port = tunnel_host
+            .add_tunnel(Some(1001), None, Uuid::new_v4(), Uuid::new_v4(), &access)
+            .unwrap();
+        assert_eq!(port, 1003);
+
+        let port = tunnel_host
+            .add_tunnel(None, None, Uuid::new_v4(), Uuid::new_v4(), &access)
+            .unwrap();
+        assert_eq!(port, 1004);
+
+        assert!(tunnel_host
+            .add_tunnel(None, None, Uuid::new_v4(), Uuid::new_v4(), &access)
+            .is_err());
+        assert!(tunnel_host
+            .add_tunnel(None, Some("app"), Uuid::new_v4(), Uuid::new_v4(), &access)
+            .is_err());
+    }
 }
@@ -2,13 +2,21 @@ use std::{collections::HashMap, sync::Arc};
 
 use crate::{
     common::channel::{OkResponse, Request},
-    server::endpoints::messages::{
-        EndpointChannelRequest, RegisterTunnelResponse, ResolvedEndpointInfo,
+    server::{
+        endpoints::messages::{
+            EndpointChannelRequest, RegisterProxyResponse, RegisterTunnelResponse,
+            RemoveProxyResponse, ResolvedEndpointInfo,
+        },
+        incoming_requests::ProxySession,
+        tunnel_policy::TunnelAccess,
     },
     tunnel::configuration::ProxyConfiguration,
 };
 
-use super::{udp_services::UdpServices, UdpEndpointInfo};
+use super::{
+    configuration::UdpEndpointConfig, udp_services::UdpServices, tunnel_host::TunnelHost,
+    UdpEndpointInfo,
+};
 use log::{debug, info};
 use tokio::io::Result;
 use uuid::Uuid;
@@ -21,57 +29,72 @@ pub async fn handle(
         EndpointChannelRequest::RegisterTunnelRequest(register_request) => {
             let tunnel_id = register_request.tunnel_id;
             let mut proxy_info = HashMap::<Uuid, ResolvedEndpointInfo>::new();
-            let config = services.get_config();
             let mut tunnel_host = services.get_tunnel_host().await;
+            let config = services.get_config();
+
+            let proxy_ids: Vec<Uuid> = register_request
+                .proxy_sessions
+                .iter()
+                .map(|session| session.proxy_id)
+                .collect();
+
+            tunnel_host.remove_missing_proxies(&tunnel_id, &proxy_ids);
+
+            let mut added_proxy_ids: Vec<Uuid> = Vec::new();
 
             for session in register_request.proxy_sessions.iter() {
-                let ProxyConfiguration::Udp { desired_port, .. } = session.config else {
-                    debug!("Proxy session configuration passed is not for Udp endpoint");
-                    reject_tunnel(
-                        &mut request,
-                        &tunnel_id,
-                        services,
-                        "Invalid configuration for UDP endpoint.",
-                    )
-                    .await;
-                    return Ok(());
-                };
-
-                if !tunnel_host.has_available_ports() {
-                    reject_tunnel(
-                        &mut request,
-                        &tunnel_id,
-                        services,
-                        "No available ports to be assigned.",
-                    )
-                    .await;
-                    return Ok(());
-                }
+                let is_held = tunnel_host
+                    .find_tunnel_port(&tunnel_id, &session.proxy_id)
+                    .is_some();
+
+                match register_proxy(
+                    &config,
+                    &mut tunnel_host,
+                    tunnel_id,
+                    session,
+                    &register_request.access,
+                ) {
+                    Ok(endpoint_info) => {
+                        if !is_held {
+                            added_proxy_ids.push(session.proxy_id);
+                        }
 
-                let Ok(port) =
-                    tunnel_host.add_tunnel(desired_port, tunnel_id, session.proxy_id)
-                else {
-                    reject_tunnel(
-                        &mut request,
-                        &tunnel_id,
-                        services,
-                        "Failed to assign port.",
-                    )
-                    .await;
-
-                    return Ok(());
-                };
-
-                proxy_info.insert(
-                    session.proxy_id,
-                    ResolvedEndpointInfo::Udp(UdpEndpointInfo {
-                        assigned_hostname: config.get_assigned_hostname(port),
-                    }),
-                );
+                        proxy_info.insert(session.proxy_id, endpoint_info);
+                    }
+                    Err(reason) => {
+                        added_proxy_ids.push(session.proxy_id);
+                        reject_tunnel(
+                            &mut request,
+                            &tunnel_id,
+                            &mut tunnel_host,
+                            &added_proxy_ids,
+                            &reason,
+                        )
+                        .await;
+                        return Ok(());
+                    }
+                }
             }
 
             request.respond(RegisterTunnelResponse::Accepted { proxy_info });
         }
+        EndpointChannelRequest::RegisterProxyRequest(proxy_request) => {
+            let config = services.get_config();
+            let result = register_proxy(
+                &config,
+                &mut *services.get_tunnel_host().await,
+                proxy_request.tunnel_id,
+                &proxy_request.proxy_session,
+                &proxy_request.access,
+            );
+
+            match result {
+                Ok(endpoint_info) => {
+                    request.respond(RegisterProxyResponse::Accepted { endpoint_info })
+                }
+                Err(reason) => request.respond(RegisterProxyResponse::Rejected { reason }),
+            }
+        }
         EndpointChannelRequest::RemoveTunnelRequest(remove_request) => {
             info!(
                 "Removing tunnel ID '{}' from udp endpoint.",
@@ -83,18 +106,71 @@ pub async fn handle(
                 .remove_tunnel(&remove_request.tunnel_id);
             request.respond(OkResponse);
         }
+        EndpointChannelRequest::RemoveProxyRequest(remove_request) => {
+            info!(
+                "Removing proxy ID '{}' of tunnel ID '{}' from udp endpoint.",
+                remove_request.proxy_id, remove_request.tunnel_id
+            );
+            let removed = services
+                .get_tunnel_host()
+                .await
+                .remove_proxy(&remove_request.tunnel_id, &remove_request.proxy_id);
+            request.respond(RemoveProxyResponse { removed });
+        }
     }
 
     Ok(())
 }
 
+fn register_proxy(
+    config: &UdpEndpointConfig,
+    tunnel_host: &mut TunnelHost,
+    tunnel_id: Uuid,
+    session: &ProxySession,
+    access: &TunnelAccess,
+) -> std::result::Result<ResolvedEndpointInfo, String> {
+    let ProxyConfiguration::Udp { desired_port, .. } = session.config else {
+        debug!("Proxy session configuration passed is not for Udp endpoint");
+        return Err("Invalid configuration for UDP endpoint.".to_owned());
+    };
+
+    let pool = session.config.get_pool().map(|name| name.as_str());
+
+    if pool.is_some() && !config.get_allow_pools() {
+        return Err("Tunnel pools are not allowed for this endpoint.".to_owned());
+    }
+
+    if tunnel_host
+        .find_tunnel_port(&tunnel_id, &session.proxy_id)
+        .is_none()
+        && pool.and_then(|name| tunnel_host.find_pool_port(name)).is_none()
+        && !tunnel_host.has_available_ports()
+    {
+        return Err("No available ports to be assigned.".to_owned());
+    }
+
+    let port = tunnel_host
+        .add_tunnel(desired_port, pool, tunnel_id, session.proxy_id, access)
+        .map_err(|e| format!("Failed to assign port: {e}"))?;
+
+    Ok(ResolvedEndpointInfo::Udp(UdpEndpointInfo {
+        assigned_hostname: config.get_assigned_hostname(port),
+    }))
+}
+
+/// Removes only the proxies added by the rejected request. Ports the tunnel held before, for
+/// example while waiting to be resumed, stay assigned to it.
 async fn reject_tunnel(
     request: &mut Request<EndpointChannelRequest>,
     tunnel_id: &Uuid,
-    services: &Arc<UdpServices>,
+    tunnel_host: &mut TunnelHost,
+    added_proxy_ids: &[Uuid],
     reason: &str,
 ) {
-    services.get_tunnel_host().await.remove_tunnel(tunnel_id);
+    for proxy_id in added_proxy_ids {
+        tunnel_host.remove_proxy(tunnel_id, proxy_id);
+    }
+
     request.respond(RegisterTunnelResponse::Rejected {
         reason: reason.to_string(),
     });
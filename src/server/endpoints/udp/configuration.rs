@@ -2,12 +2,15 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{
-    validate::{Validatable, Validation},
-    validate_rules::{
-        HostAddressMustBeValid, MustBeGreaterThanZero, PortHostnameTemplatemustBeValid,
-        PortMustBeValid,
+use crate::{
+    common::{
+        validate::{Validatable, Validation},
+        validate_rules::{
+            HostAddressMustBeValid, MustBeGreaterThanZero, PortHostnameTemplatemustBeValid,
+            PortRangeMustBeValid,
+        },
     },
+    server::endpoints::pool::PoolStrategy,
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -26,6 +29,12 @@ pub struct UdpEndpointConfig {
 
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub full_hostname_template: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub allow_pools: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pool_strategy: Option<PoolStrategy>,
 }
 
 impl UdpEndpointConfig {
@@ -52,6 +61,25 @@ impl UdpEndpointConfig {
     pub fn get_inactivity_timeout(&self) -> u64 {
         self.inactivity_timeout.unwrap_or(300)
     }
+
+    pub fn get_allow_pools(&self) -> bool {
+        self.allow_pools.unwrap_or(false)
+    }
+
+    pub fn get_pool_strategy(&self) -> PoolStrategy {
+        self.pool_strategy.unwrap_or_default()
+    }
+
+    pub fn with_defaults(&self) -> Self {
+        Self {
+            address: Some(self.get_address()),
+            allow_desired_port: Some(self.get_allow_desired_port()),
+            inactivity_timeout: Some(self.get_inactivity_timeout()),
+            allow_pools: Some(self.get_allow_pools()),
+            pool_strategy: Some(self.get_pool_strategy()),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -60,6 +88,9 @@ pub struct UdpPublicEndpointConfig {
     pub allow_desired_port: bool,
     pub reserve_ports_from: u16,
     pub reserve_ports_to: u16,
+
+    #[serde(default)]
+    pub allow_pools: bool,
 }
 
 impl From<&UdpEndpointConfig> for UdpPublicEndpointConfig {
@@ -69,6 +100,7 @@ impl From<&UdpEndpointConfig> for UdpPublicEndpointConfig {
             allow_desired_port: config.get_allow_desired_port(),
             reserve_ports_from: config.reserve_ports_from,
             reserve_ports_to: config.reserve_ports_to,
+            allow_pools: config.get_allow_pools(),
         }
     }
 }
@@ -86,6 +118,11 @@ impl Display for UdpPublicEndpointConfig {
             "Port range: {} - {}",
             self.reserve_ports_from, self.reserve_ports_to
         )?;
+        writeln!(
+            f,
+            "Tunnel pools: {}",
+            if self.allow_pools { "Allowed" } else { "Not Allowed" }
+        )?;
 
         Ok(())
     }
@@ -109,15 +146,10 @@ impl Validatable for UdpEndpointConfig {
             &self.get_inactivity_timeout(),
         );
 
-        if self.reserve_ports_from > self.reserve_ports_to {
-            result.validate_rule::<PortMustBeValid>("reserve_ports_from", &self.reserve_ports_from);
-            result.validate_rule::<PortMustBeValid>("reserve_ports_to", &self.reserve_ports_to);
-
-            result.add_field_error(
-                "reserve_ports_from",
-                "reserve_ports_from must be less than reserve_ports_to.",
-            );
-        }
+        result.validate_rule::<PortRangeMustBeValid>(
+            "reserve_ports",
+            &(self.reserve_ports_from, self.reserve_ports_to),
+        );
     }
 }
 
@@ -133,6 +165,8 @@ mod tests {
             reserve_ports_from: 1000,
             reserve_ports_to: 2000,
             full_hostname_template: Some("host:{port}".to_string()),
+            allow_pools: None,
+            pool_strategy: None,
         }
     }
 
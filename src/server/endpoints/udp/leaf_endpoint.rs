@@ -8,8 +8,8 @@ use uuid::Uuid;
 use crate::common::periodic_trigger::PeriodicTrigger;
 use crate::common::udp_server::{ReceivedClient, UdpServer};
 
+use crate::server::endpoints::pool::{LinkError, link_client, resolve_candidates};
 use crate::server::services::Client as MainClient;
-use crate::server::session::messages::{ClientLinkRequest, ClientLinkResponse};
 
 use super::udp_services::UdpServices;
 
@@ -66,14 +66,37 @@ pub async fn start(port: u16, services: Arc<UdpServices>) -> Result<()> {
     Ok(())
 }
 
-async fn start_new_client(services: &Arc<UdpServices>, received_client: ReceivedClient, port: u16) {
-    let Some(tunnel) = services.get_tunnel_host().await.get_tunnel(port) else {
+async fn start_new_client(
+    services: &Arc<UdpServices>,
+    mut received_client: ReceivedClient,
+    port: u16,
+) {
+    let candidates = services.get_tunnel_host().await.get_candidates(port);
+
+    if candidates.is_empty() {
         error!(
             "No tunnel found for port {}. Stopping UDP connection.",
             port
         );
         return;
-    };
+    }
+
+    let main_services = services.get_main_services();
+    let candidates = resolve_candidates(
+        &main_services,
+        services.get_config().get_pool_strategy(),
+        candidates,
+    )
+    .await;
+
+    if candidates.is_empty() {
+        info!(
+            "Service behind the tunnel on port {} is unavailable, refusing client.",
+            port
+        );
+        received_client.connection.shutdown().await;
+        return;
+    }
 
     let client = MainClient::new(
         received_client.id,
@@ -82,8 +105,6 @@ async fn start_new_client(services: &Arc<UdpServices>, received_client: Received
         Some(received_client.data),
     );
 
-    let main_services = services.get_main_services();
-
     if let Err((error, link)) = main_services
         .get_client_manager()
         .await
@@ -98,34 +119,21 @@ async fn start_new_client(services: &Arc<UdpServices>, received_client: Received
         return;
     }
 
-    let Ok(response) = main_services
-        .get_tunnel_manager()
-        .await
-        .send_session_request(
-            &tunnel.tunnel_id,
-            ClientLinkRequest {
-                client_id: received_client.id,
-                proxy_id: tunnel.proxy_id,
-            },
-        )
-        .await
-    else {
-        error!("Error sending client link request");
-        discard_client(received_client.id, services).await;
-        return;
-    };
-
-    match response {
-        ClientLinkResponse::Accepted => {
+    match link_client(&main_services, received_client.id, &candidates).await {
+        Ok(tunnel) => {
             info!(
                 "Client connected to tunnel {} on port {}",
                 tunnel.tunnel_id, port
             );
         }
-        ClientLinkResponse::Rejected { reason } => {
+        Err(LinkError::Rejected(reason)) => {
             error!("Client rejected by tunnel: {}", reason);
             discard_client(received_client.id, services).await;
         }
+        Err(LinkError::Failed) => {
+            error!("Error sending client link request");
+            discard_client(received_client.id, services).await;
+        }
     }
 }
 
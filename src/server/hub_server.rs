@@ -23,7 +23,7 @@ pub async fn start(services: Arc<Services>, cancel_token: CancellationToken) ->
     {
         Ok(listener) => listener,
         Err(e) => {
-            error!("Failed to bind client listener: {}", e);
+            error!("Failed to start client listener: {}", e);
             return Ok(());
         }
     };
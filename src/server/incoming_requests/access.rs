@@ -1,29 +1,209 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use crate::{common::text::is_constant_time_equals, server::services::Services};
+use chrono::Utc;
+use log::{debug, error};
 
-pub fn has_tunnel_access(services: &Arc<Services>, key: Option<&String>) -> bool {
-    let config = services.get_config();
-    if let Some(endpoint_key) = config.tunnel_key.as_ref() {
-        if let Some(request_key) = key {
-            return is_constant_time_equals(endpoint_key, request_key);
+use crate::{
+    common::{connection::Connection, key_pair::PublicKey, text::is_constant_time_equals},
+    server::{
+        auth_hook::{self, AuthHookKind, AuthHookProxy, AuthHookRequest},
+        authorized_keys::{AuthorizedKey, read_authorized_keys},
+        configuration::ServerConfiguration,
+        services::Services,
+        tunnel_policy::{TunnelAccess, TunnelPolicy},
+        tunnel_token::TunnelToken,
+    },
+};
+
+/// Identity of tunnels the auth hook allowed without returning an identity.
+const AUTH_HOOK_IDENTITY: &str = "auth-hook";
+
+/// Details of the connecting client. Address and requested proxies are only used by the auth
+/// hook.
+#[derive(Clone, Debug, Default)]
+pub struct ClientDetails {
+    pub address: Option<SocketAddr>,
+    pub certificate_names: Vec<String>,
+    pub proxies: Vec<AuthHookProxy>,
+}
+
+impl ClientDetails {
+    pub fn new(address: SocketAddr, connection: &Connection) -> Self {
+        Self {
+            address: Some(address),
+            certificate_names: connection.get_peer_certificate_names(),
+            proxies: vec![],
         }
-        return false;
     }
 
-    true
+    fn to_hook_request(&self, kind: AuthHookKind, key: Option<&String>) -> AuthHookRequest {
+        AuthHookRequest {
+            kind,
+            key: key.cloned(),
+            client_address: self.address.map(|address| address.ip().to_string()),
+            certificate_names: self.certificate_names.clone(),
+            proxies: self.proxies.clone(),
+        }
+    }
 }
 
-pub fn has_monitoring_access(services: &Arc<Services>, key: Option<&String>) -> bool {
+pub async fn has_tunnel_access(
+    services: &Arc<Services>,
+    key: Option<&String>,
+    client: &ClientDetails,
+) -> bool {
+    get_tunnel_access(services, key, None, client)
+        .await
+        .is_some()
+}
+
+/// Resolves access for a connecting tunnel. Admin key is checked against the monitor key and
+/// gives unrestricted access, same as the server tunnel key. Client certificates, named
+/// credentials and tokens give access limited by their policy. If none of them match, the auth
+/// hook decides. Server without any keys or hook allows everyone.
+pub async fn get_tunnel_access(
+    services: &Arc<Services>,
+    key: Option<&String>,
+    admin_key: Option<&String>,
+    client: &ClientDetails,
+) -> Option<TunnelAccess> {
     let config = services.get_config();
-    if let Some(endpoint_key) = config.monitor_key.as_ref() {
-        if let Some(request_key) = key {
-            return is_constant_time_equals(endpoint_key, request_key);
+
+    let is_key_equal = |expected: Option<&String>, actual: Option<&String>| match (expected, actual)
+    {
+        (Some(expected), Some(actual)) => is_constant_time_equals(expected, actual),
+        _ => false,
+    };
+
+    if is_key_equal(config.monitor_key.as_ref(), admin_key) {
+        return Some(TunnelAccess::unrestricted());
+    }
+
+    if let Some(access) = get_certificate_access(&config, &client.certificate_names) {
+        return Some(access);
+    }
+
+    let credentials = config.get_tunnel_credentials();
+
+    if config.tunnel_key.is_none()
+        && credentials.is_empty()
+        && config.token_secret.is_none()
+        && config.authorized_keys_file.is_none()
+        && config.auth_hook.is_none()
+    {
+        return Some(TunnelAccess::unrestricted());
+    }
+
+    if is_key_equal(config.tunnel_key.as_ref(), key) {
+        return Some(TunnelAccess::unrestricted());
+    }
+
+    if let (Some(secret), Some(key)) = (&config.token_secret, key)
+        && TunnelToken::is_token(key)
+    {
+        return get_token_access(services, key, secret).await;
+    }
+
+    if let Some(credential) = credentials
+        .iter()
+        .find(|credential| is_key_equal(Some(&credential.key), key))
+    {
+        return Some(TunnelAccess::for_credential(credential));
+    }
+
+    config.auth_hook.as_ref()?;
+
+    let response =
+        auth_hook::authorize(services, &client.to_hook_request(AuthHookKind::Tunnel, key)).await;
+
+    if !response.allow {
+        return None;
+    }
+
+    Some(TunnelAccess::for_identity(
+        response.identity.as_deref().unwrap_or(AUTH_HOOK_IDENTITY),
+        response.policy.unwrap_or_default(),
+    ))
+}
+
+/// Certificate names are only set for clients verified against the server `client_ca_path`.
+/// Without `client_certificates` configured, every verified certificate gives access without
+/// restrictions.
+fn get_certificate_access(
+    config: &ServerConfiguration,
+    certificate_names: &[String],
+) -> Option<TunnelAccess> {
+    let name = certificate_names.first()?;
+    let certificates = config.get_client_certificates();
+
+    if certificates.is_empty() {
+        return Some(TunnelAccess::for_identity(name, TunnelPolicy::default()));
+    }
+
+    certificates.iter().find_map(|certificate| {
+        certificate
+            .find_matching_name(certificate_names)
+            .map(|name| TunnelAccess::for_identity(name, certificate.get_policy()))
+    })
+}
+
+/// Finds the key in the authorized keys file. File is read on every call, so that keys can be
+/// added and removed without restarting the server.
+pub fn find_authorized_key(
+    config: &ServerConfiguration,
+    public_key: &PublicKey,
+) -> Option<AuthorizedKey> {
+    let path = config.authorized_keys_file.as_ref()?;
+
+    match read_authorized_keys(path) {
+        Ok(keys) => keys.into_iter().find(|key| &key.public_key == public_key),
+        Err(e) => {
+            error!("{}", e);
+            None
+        }
+    }
+}
+
+async fn get_token_access(
+    services: &Arc<Services>,
+    key: &str,
+    secret: &str,
+) -> Option<TunnelAccess> {
+    let token = match TunnelToken::decode(key, secret, Utc::now().timestamp()) {
+        Ok(token) => token,
+        Err(e) => {
+            debug!("Tunnel token rejected: {}", e);
+            return None;
         }
-        return false;
+    };
+
+    if services.get_token_manager().await.is_revoked(&token.id) {
+        debug!("Tunnel token '{}' has been revoked.", token.id);
+        return None;
+    }
+
+    Some(TunnelAccess::for_token(&token))
+}
+
+pub async fn has_monitoring_access(
+    services: &Arc<Services>,
+    key: Option<&String>,
+    client: &ClientDetails,
+) -> bool {
+    let config = services.get_config();
+    if let Some(endpoint_key) = config.monitor_key.as_ref()
+        && let Some(request_key) = key
+        && is_constant_time_equals(endpoint_key, request_key)
+    {
+        return true;
     }
 
-    true
+    if config.auth_hook.is_some() {
+        let request = client.to_hook_request(AuthHookKind::Monitor, key);
+        return auth_hook::authorize(services, &request).await.allow;
+    }
+
+    config.monitor_key.is_none()
 }
 
 #[cfg(test)]
@@ -32,15 +212,25 @@ mod tests {
     use tokio_util::sync::CancellationToken;
 
     use super::*;
-    use crate::server::configuration::ServerConfiguration;
+    use crate::server::auth_hook::{AuthHookBackend, AuthHookConfig};
     use crate::server::services::Services;
+    use crate::server::tunnel_policy::{ClientCertificate, TunnelCredential};
     use std::collections::HashMap;
     use std::sync::Arc;
 
-    fn create_services(tunnel_key: Option<String>, monitor_key: Option<String>) -> Arc<Services> {
-        let config = ServerConfiguration {
+    fn create_config(
+        tunnel_key: Option<String>,
+        monitor_key: Option<String>,
+    ) -> ServerConfiguration {
+        ServerConfiguration {
             tunnel_key,
             monitor_key,
+            tunnel_credentials: None,
+            client_certificates: None,
+            authorized_keys_file: None,
+            auth_hook: None,
+            token_secret: None,
+            token_revocation_file: None,
             server_port: None,
             server_address: None,
             max_tunnel_input_wait: None,
@@ -50,45 +240,282 @@ mod tests {
             max_clients: None,
             max_proxies_per_tunnel: None,
             max_input_read_length: None,
-        };
+            tunnel_resume_grace_seconds: None,
+            allow_multiplexed_links: None,
+        }
+    }
 
-        Arc::new(Services::new(config, CancellationToken::new()))
+    fn create_services(tunnel_key: Option<String>, monitor_key: Option<String>) -> Arc<Services> {
+        create_services_for(create_config(tunnel_key, monitor_key))
     }
 
-    #[test]
-    fn test_has_tunnel_access() {
+    fn create_services_for(config: ServerConfiguration) -> Arc<Services> {
+        Arc::new(Services::new(config, CancellationToken::new()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_has_tunnel_access() {
         let services = create_services(Some("test".to_string()), None);
 
-        assert!(has_tunnel_access(&services, Some(&"test".to_string())));
-        assert!(!has_tunnel_access(&services, Some(&"test2".to_string())));
-        assert!(!has_tunnel_access(&services, None));
+        assert!(
+            has_tunnel_access(
+                &services,
+                Some(&"test".to_string()),
+                &ClientDetails::default()
+            )
+            .await
+        );
+        assert!(
+            !has_tunnel_access(
+                &services,
+                Some(&"test2".to_string()),
+                &ClientDetails::default()
+            )
+            .await
+        );
+        assert!(!has_tunnel_access(&services, None, &ClientDetails::default()).await);
     }
 
-    #[test]
-    fn test_has_tunnel_access_no_key() {
+    #[tokio::test]
+    async fn test_has_tunnel_access_no_key() {
         let services = create_services(None, None);
 
-        assert!(has_tunnel_access(&services, Some(&"test".to_string())));
-        assert!(has_tunnel_access(&services, None));
+        assert!(
+            has_tunnel_access(
+                &services,
+                Some(&"test".to_string()),
+                &ClientDetails::default()
+            )
+            .await
+        );
+        assert!(has_tunnel_access(&services, None, &ClientDetails::default()).await);
     }
 
-    #[test]
-    fn test_has_monitoring_access() {
-        let services = create_services(None, Some("test".to_string()));
+    #[tokio::test]
+    async fn test_get_tunnel_access_with_credentials() {
+        let policy = TunnelPolicy {
+            max_proxies: Some(1),
+            ..Default::default()
+        };
+
+        let services = create_services_for(ServerConfiguration {
+            tunnel_credentials: Some(vec![TunnelCredential {
+                name: "ci".to_string(),
+                key: "ci-key".to_string(),
+                policy: Some(policy.clone()),
+            }]),
+            ..create_config(None, Some("admin".to_string()))
+        });
+
+        let access = get_tunnel_access(
+            &services,
+            Some(&"ci-key".to_string()),
+            None,
+            &ClientDetails::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(access.credential, Some("ci".to_string()));
+        assert_eq!(access.policy, policy);
 
-        assert!(has_monitoring_access(&services, Some(&"test".to_string())));
-        assert!(!has_monitoring_access(
+        let admin_access = get_tunnel_access(
             &services,
-            Some(&"test2".to_string())
-        ));
-        assert!(!has_monitoring_access(&services, None));
+            Some(&"ci-key".to_string()),
+            Some(&"admin".to_string()),
+            &ClientDetails::default(),
+        )
+        .await;
+        assert_eq!(admin_access, Some(TunnelAccess::unrestricted()));
+
+        assert!(
+            get_tunnel_access(
+                &services,
+                Some(&"wrong".to_string()),
+                None,
+                &ClientDetails::default()
+            )
+            .await
+            .is_none()
+        );
+        assert!(!has_tunnel_access(&services, None, &ClientDetails::default()).await);
+    }
+
+    #[tokio::test]
+    async fn test_get_tunnel_access_with_token() {
+        let secret = "0123456789abcdef0123456789abcdef";
+        let services = create_services_for(ServerConfiguration {
+            token_secret: Some(secret.to_string()),
+            ..create_config(Some("test".to_string()), None)
+        });
+
+        let token = TunnelToken::new("alice".to_string(), Utc::now().timestamp() + 60, None);
+        let key = token.encode(secret).unwrap();
+
+        let access = get_tunnel_access(&services, Some(&key), None, &ClientDetails::default())
+            .await
+            .unwrap();
+        assert_eq!(access.identity, Some("alice".to_string()));
+        assert_eq!(access.token_id, Some(token.id));
+
+        let expired = TunnelToken::new("alice".to_string(), Utc::now().timestamp() - 1, None)
+            .encode(secret)
+            .unwrap();
+        assert!(!has_tunnel_access(&services, Some(&expired), &ClientDetails::default()).await);
+
+        services
+            .get_token_manager()
+            .await
+            .revoke(token.id)
+            .await
+            .unwrap();
+        assert!(!has_tunnel_access(&services, Some(&key), &ClientDetails::default()).await);
+    }
+
+    #[tokio::test]
+    async fn test_get_tunnel_access_with_client_certificate() {
+        let client = ClientDetails {
+            certificate_names: vec!["runner.ci.example.com".to_string()],
+            ..Default::default()
+        };
+
+        let services = create_services(Some("test".to_string()), None);
+        let access = get_tunnel_access(&services, None, None, &client)
+            .await
+            .unwrap();
+        assert_eq!(access.identity, Some("runner.ci.example.com".to_string()));
+        assert_eq!(access.policy, TunnelPolicy::default());
+
+        let policy = TunnelPolicy {
+            max_tunnels: Some(1),
+            ..Default::default()
+        };
+
+        let services = create_services_for(ServerConfiguration {
+            client_certificates: Some(vec![ClientCertificate {
+                name: "*.ci.example.com".to_string(),
+                policy: Some(policy.clone()),
+            }]),
+            ..create_config(Some("test".to_string()), None)
+        });
+
+        let access = get_tunnel_access(&services, None, None, &client)
+            .await
+            .unwrap();
+        assert_eq!(access.identity, Some("runner.ci.example.com".to_string()));
+        assert_eq!(access.policy, policy);
+
+        let other = ClientDetails {
+            certificate_names: vec!["laptop.example.com".to_string()],
+            ..Default::default()
+        };
+        assert!(!has_tunnel_access(&services, None, &other).await);
+        assert!(has_tunnel_access(&services, Some(&"test".to_string()), &other).await);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_get_tunnel_access_with_auth_hook() {
+        let calls_file =
+            std::env::temp_dir().join(format!("tunnelize-hook-{}", uuid::Uuid::new_v4()));
+        let script = format!(
+            r#"request=$(cat); echo call >> "{}"; case "$request" in *'"key":"alice-key"'*) echo '{{"allow": true, "identity": "alice", "policy": {{"max_proxies": 1}}}}';; *'"key":"anonymous-key"'*) echo '{{"allow": true}}';; *) echo '{{"allow": false}}';; esac"#,
+            calls_file.display()
+        );
+
+        let services = create_services_for(ServerConfiguration {
+            auth_hook: Some(AuthHookConfig {
+                backend: AuthHookBackend::Exec {
+                    command: "sh".to_string(),
+                    args: Some(vec!["-c".to_string(), script]),
+                },
+                timeout_seconds: None,
+                cache_ttl_seconds: None,
+            }),
+            ..create_config(Some("test".to_string()), None)
+        });
+
+        let client = ClientDetails::default();
+        let key = "alice-key".to_string();
+
+        let access = get_tunnel_access(&services, Some(&key), None, &client)
+            .await
+            .unwrap();
+        assert_eq!(access.identity, Some("alice".to_string()));
+        assert_eq!(access.policy.max_proxies, Some(1));
+
+        let access =
+            get_tunnel_access(&services, Some(&"anonymous-key".to_string()), None, &client)
+                .await
+                .unwrap();
+        assert_eq!(access.identity.as_deref(), Some(AUTH_HOOK_IDENTITY));
+        assert!(!access.is_unrestricted());
+
+        assert!(has_tunnel_access(&services, Some(&key), &client).await);
+        assert!(!has_tunnel_access(&services, Some(&"bob-key".to_string()), &client).await);
+        assert!(has_tunnel_access(&services, Some(&"test".to_string()), &client).await);
+
+        let calls = std::fs::read_to_string(&calls_file).unwrap();
+        std::fs::remove_file(&calls_file).unwrap();
+        assert_eq!(calls.lines().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_only_server_keys_give_unrestricted_access() {
+        let secret = "0123456789abcdef0123456789abcdef";
+        let services = create_services_for(ServerConfiguration {
+            tunnel_credentials: Some(vec![TunnelCredential {
+                name: "ci".to_string(),
+                key: "ci-key".to_string(),
+                policy: None,
+            }]),
+            token_secret: Some(secret.to_string()),
+            ..create_config(Some("test".to_string()), Some("admin".to_string()))
+        });
+        let client = ClientDetails::default();
+
+        let token = TunnelToken::new("alice".to_string(), Utc::now().timestamp() + 60, None)
+            .encode(secret)
+            .unwrap();
+
+        for key in ["ci-key".to_string(), token] {
+            let access = get_tunnel_access(&services, Some(&key), None, &client)
+                .await
+                .unwrap();
+            assert!(!access.is_unrestricted());
+        }
+
+        let access = get_tunnel_access(&services, Some(&"test".to_string()), None, &client)
+            .await
+            .unwrap();
+        assert!(access.is_unrestricted());
+
+        let access = get_tunnel_access(
+            &services,
+            Some(&"ci-key".to_string()),
+            Some(&"admin".to_string()),
+            &client,
+        )
+        .await
+        .unwrap();
+        assert!(access.is_unrestricted());
+    }
+
+    #[tokio::test]
+    async fn test_has_monitoring_access() {
+        let services = create_services(None, Some("test".to_string()));
+        let client = ClientDetails::default();
+
+        assert!(has_monitoring_access(&services, Some(&"test".to_string()), &client).await);
+        assert!(!has_monitoring_access(&services, Some(&"test2".to_string()), &client).await);
+        assert!(!has_monitoring_access(&services, None, &client).await);
     }
 
-    #[test]
-    fn test_has_monitoring_access_no_key() {
+    #[tokio::test]
+    async fn test_has_monitoring_access_no_key() {
         let services = create_services(None, None);
+        let client = ClientDetails::default();
 
-        assert!(has_monitoring_access(&services, Some(&"test".to_string())));
-        assert!(has_monitoring_access(&services, None));
+        assert!(has_monitoring_access(&services, Some(&"test".to_string()), &client).await);
+        assert!(has_monitoring_access(&services, None, &client).await);
     }
 }
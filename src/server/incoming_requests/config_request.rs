@@ -7,7 +7,7 @@ use crate::{
     server::{configuration::PublicEndpointConfiguration, services::Services},
 };
 
-use super::access::has_tunnel_access;
+use super::access::{ClientDetails, has_tunnel_access};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProcessConfigRequest {
@@ -50,7 +50,9 @@ pub async fn process(
                 return;
             }
 
-            if !has_tunnel_access(&services, request.tunnel_key.as_ref()) {
+            let client = ClientDetails::new(address, &response_stream);
+
+            if !has_tunnel_access(&services, request.tunnel_key.as_ref(), &client).await {
                 services.get_bfp_manager().await.log_ip_attempt(&ip_address);
                 response_stream
                     .respond_message(&ProcessConfigResponse::AccessDenied)
@@ -1,26 +1,42 @@
-use std::{collections::HashMap, io::Error, net::SocketAddr, sync::Arc};
+use std::{collections::HashMap, io::Error, net::SocketAddr, sync::Arc, time::Duration};
 
 use log::{debug, info};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    common::connection::Connection,
+    common::{
+        connection::Connection,
+        control_channel::{ControlChannel, IncomingRequest},
+        key_pair::{PublicKey, create_challenge_nonce},
+        multiplexer::Multiplexer,
+    },
     server::{
+        auth_hook::AuthHookProxy,
+        authorized_keys::AuthorizedKey,
         configuration::ServerConfiguration,
         endpoints::messages::{
             RegisterTunnelRequest, RegisterTunnelResponse, RemoveTunnelRequest,
             ResolvedEndpointInfo,
         },
         services::events::ServiceEvent,
-        session::{self, tunnel::TunnelProxyInfo},
+        session::{
+            self,
+            tunnel::{TunnelProxyInfo, TunnelSession, TunnelSessionEnd},
+        },
+        tunnel_policy::{TunnelAccess, TunnelPolicy},
     },
     tunnel::configuration::ProxyConfiguration,
 };
 
-use super::{super::services::Services, access::has_tunnel_access};
+use super::{
+    super::services::Services,
+    ServerRequestMessage,
+    access::{ClientDetails, find_authorized_key, get_tunnel_access},
+    proxy_health::ProxyHealth,
+};
 
-use tokio::io::Result;
+use tokio::{io::Result, sync::mpsc::UnboundedReceiver, time::timeout};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct InitTunelRequest {
@@ -28,6 +44,18 @@ pub struct InitTunelRequest {
     pub tunnel_key: Option<String>,
     pub admin_key: Option<String>,
     pub proxies: Vec<InputProxy>,
+    #[serde(default)]
+    pub resume_token: Option<String>,
+    #[serde(default)]
+    pub multiplex: bool,
+    #[serde(default)]
+    pub public_key: Option<String>,
+}
+
+/// Signature of the nonce the server sent in `InitTunnelResponse::Challenge`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SolveChallengeRequest {
+    pub signature: Vec<u8>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -37,6 +65,8 @@ pub struct InputProxy {
     pub forward_address: String,
     pub forward_port: u16,
     pub proxy: ProxyConfiguration,
+    #[serde(default)]
+    pub health: ProxyHealth,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -44,6 +74,13 @@ pub enum InitTunnelResponse {
     Accepted {
         tunnel_id: Uuid,
         endpoint_info: HashMap<Uuid, ResolvedEndpointInfo>,
+        #[serde(default)]
+        resume_token: Option<String>,
+        #[serde(default)]
+        multiplex: bool,
+    },
+    Challenge {
+        nonce: Vec<u8>,
     },
     Rejected {
         reason: String,
@@ -70,7 +107,14 @@ pub async fn process(
 
     let config = services.get_config();
 
-    if services.get_tunnel_manager().await.get_count() >= config.get_max_tunnels() {
+    let resumable_tunnel_id = match &request.resume_token {
+        Some(token) => services.get_resume_manager().await.find_tunnel(token),
+        None => None,
+    };
+
+    if resumable_tunnel_id.is_none()
+        && services.get_tunnel_manager().await.get_count() >= config.get_max_tunnels()
+    {
         response_stream
             .respond_message(&InitTunnelResponse::Rejected {
                 reason: "Too many tunnels connected".to_string(),
@@ -79,31 +123,92 @@ pub async fn process(
         return;
     }
 
-    if let Err(e) = validate_server_access(&services, &request, &mut response_stream).await {
-        services.get_bfp_manager().await.log_ip_attempt(&ip_address);
-        debug!("Error validating server access: {:?}", e);
-        return;
-    }
+    let access = match validate_server_access(&services, &request, &mut response_stream, address)
+        .await
+    {
+        Ok(access) => access,
+        Err(e) => {
+            services.get_bfp_manager().await.log_ip_attempt(&ip_address);
+            debug!("Error validating server access: {:?}", e);
+            return;
+        }
+    };
 
     services
         .get_bfp_manager()
         .await
         .clear_ip_attempts(&ip_address);
 
-    if let Err(e) = validate_requested_proxies(&request, &config, &mut response_stream).await {
+    if let Some(tunnel_id) = &resumable_tunnel_id
+        && !services
+            .get_resume_manager()
+            .await
+            .is_owner(tunnel_id, &access)
+    {
+        services.get_bfp_manager().await.log_ip_attempt(&ip_address);
+        response_stream
+            .respond_message(&InitTunnelResponse::Rejected {
+                reason: "Resume token does not belong to this tunnel key".to_string(),
+            })
+            .await;
+        return;
+    }
+
+    if let Some(max_tunnels) = access.policy.max_tunnels
+        && resumable_tunnel_id.is_none()
+        && services.get_tunnel_manager().await.get_access_count(&access) >= max_tunnels
+    {
+        response_stream
+            .respond_message(&InitTunnelResponse::Rejected {
+                reason: "Too many tunnels connected with this tunnel key".to_string(),
+            })
+            .await;
+        return;
+    }
+
+    if let Err(e) =
+        validate_requested_proxies(&request, &config, &access, &mut response_stream).await
+    {
         debug!("Error validating requested proxies: {:?}", e);
         return;
     }
 
-    start_tunnel_session(services, request, response_stream).await;
+    start_tunnel_session(services, request, access, response_stream, resumable_tunnel_id).await;
 }
 
 async fn validate_server_access(
     services: &Arc<Services>,
     request: &InitTunelRequest,
     response_stream: &mut Connection,
-) -> Result<()> {
-    if !has_tunnel_access(services, request.tunnel_key.as_ref()) {
+    address: SocketAddr,
+) -> Result<TunnelAccess> {
+    if let Some(public_key) = &request.public_key
+        && let Ok(public_key) = PublicKey::parse(public_key)
+        && let Some(key) = find_authorized_key(&services.get_config(), &public_key)
+    {
+        return verify_key_challenge(services, key, response_stream).await;
+    }
+
+    let client = ClientDetails {
+        proxies: request
+            .proxies
+            .iter()
+            .map(|proxy| AuthHookProxy {
+                endpoint_name: proxy.endpoint_name.clone(),
+                proxy: proxy.proxy.clone(),
+            })
+            .collect(),
+        ..ClientDetails::new(address, response_stream)
+    };
+
+    let Some(access) = get_tunnel_access(
+        services,
+        request.tunnel_key.as_ref(),
+        request.admin_key.as_ref(),
+        &client,
+    )
+    .await
+    else {
         response_stream
             .respond_message(&InitTunnelResponse::Rejected {
                 reason: "Tunnel key is wrong or not valid".to_string(),
@@ -111,23 +216,62 @@ async fn validate_server_access(
             .await;
 
         return Err(Error::other("Tunnel key is wrong or not valid"));
+    };
+
+    Ok(access)
+}
+
+async fn verify_key_challenge(
+    services: &Arc<Services>,
+    key: AuthorizedKey,
+    response_stream: &mut Connection,
+) -> Result<TunnelAccess> {
+    let nonce = create_challenge_nonce()?;
+
+    response_stream
+        .respond_message(&InitTunnelResponse::Challenge {
+            nonce: nonce.to_vec(),
+        })
+        .await;
+
+    let wait = Duration::from_secs(services.get_config().get_max_tunnel_input_wait() as u64);
+
+    let signature = match timeout(wait, response_stream.read_message::<ServerRequestMessage>()).await
+    {
+        Ok(Ok(ServerRequestMessage::SolveChallengeRequest(request))) => request.signature,
+        _ => Vec::new(),
+    };
+
+    if !key.public_key.verify_challenge(&nonce, &signature) {
+        response_stream
+            .respond_message(&InitTunnelResponse::Rejected {
+                reason: "Public key challenge failed".to_string(),
+            })
+            .await;
+
+        return Err(Error::other(format!(
+            "Public key challenge failed for key '{}'",
+            key.name
+        )));
     }
 
-    Ok(())
+    Ok(TunnelAccess::for_identity(&key.name, key.get_policy()))
 }
 
 async fn validate_requested_proxies(
     request: &InitTunelRequest,
     config: &ServerConfiguration,
+    access: &TunnelAccess,
     response_stream: &mut Connection,
 ) -> Result<()> {
-    if request.proxies.len() > config.get_max_proxies_per_tunnel() {
+    let max_proxies = access
+        .policy
+        .get_max_proxies(config.get_max_proxies_per_tunnel());
+
+    if request.proxies.len() > max_proxies {
         response_stream
             .respond_message(&InitTunnelResponse::Rejected {
-                reason: format!(
-                    "Too many proxies requested. Max allowed: {}",
-                    config.get_max_proxies_per_tunnel()
-                ),
+                reason: format!("Too many proxies requested. Max allowed: {max_proxies}"),
             })
             .await;
         return Err(Error::other("Too many proxies requested.".to_owned()));
@@ -136,20 +280,8 @@ async fn validate_requested_proxies(
     let mut errors: Vec<String> = vec![];
 
     for proxy in request.proxies.iter() {
-        if let Some(endpoint) = config.endpoints.get(&proxy.endpoint_name) {
-            if !endpoint.matches_proxy_type(&proxy.proxy) {
-                errors.push(format!(
-                    "Requested endpoint '{}' is of type '{}', but requested proxy is of type '{}'",
-                    proxy.endpoint_name,
-                    endpoint.get_type_string(),
-                    proxy.proxy.get_type_string()
-                ));
-            }
-        } else {
-            errors.push(format!(
-                "Requested non-existing endpoint: {}",
-                proxy.endpoint_name
-            ));
+        if let Err(error) = validate_proxy(proxy, config, &access.policy) {
+            errors.push(error);
         }
     }
 
@@ -165,6 +297,30 @@ async fn validate_requested_proxies(
     Ok(())
 }
 
+pub(super) fn validate_proxy(
+    proxy: &InputProxy,
+    config: &ServerConfiguration,
+    policy: &TunnelPolicy,
+) -> std::result::Result<(), String> {
+    let Some(endpoint) = config.endpoints.get(&proxy.endpoint_name) else {
+        return Err(format!(
+            "Requested non-existing endpoint: {}",
+            proxy.endpoint_name
+        ));
+    };
+
+    if !endpoint.matches_proxy_type(&proxy.proxy) {
+        return Err(format!(
+            "Requested endpoint '{}' is of type '{}', but requested proxy is of type '{}'",
+            proxy.endpoint_name,
+            endpoint.get_type_string(),
+            proxy.proxy.get_type_string()
+        ));
+    }
+
+    policy.check_proxy(&proxy.endpoint_name, &proxy.proxy)
+}
+
 #[derive(Debug, Clone)]
 pub struct ProxySession {
     pub proxy_id: Uuid,
@@ -174,6 +330,8 @@ pub struct ProxySession {
 async fn resolve_endpoint_info(
     tunnel_id: Uuid,
     request: &InitTunelRequest,
+    access: &TunnelAccess,
+    is_resume: bool,
     services: &Arc<Services>,
 ) -> Result<(Vec<TunnelProxyInfo>, HashMap<Uuid, ResolvedEndpointInfo>)> {
     let mut service_proxies = HashMap::<String, Vec<ProxySession>>::new();
@@ -197,6 +355,8 @@ async fn resolve_endpoint_info(
     }
 
     let mut proxy_data = HashMap::<Uuid, ResolvedEndpointInfo>::new();
+    // A resumed tunnel keeps its registrations until the grace period ends, so a failed resume
+    // does not give away its ports and hostnames.
     let mut registered_endpoints: Vec<String> = Vec::new();
 
     for (service_name, proxies) in service_proxies.iter() {
@@ -208,6 +368,7 @@ async fn resolve_endpoint_info(
                 RegisterTunnelRequest {
                     tunnel_id,
                     proxy_sessions: proxies.clone(),
+                    access: access.clone(),
                 },
             )
             .await
@@ -216,7 +377,9 @@ async fn resolve_endpoint_info(
                 "Error while sending RegisterProxyRequest to endpoint '{}'",
                 service_name
             );
-            rollback_endpoint_registrations(services, tunnel_id, &registered_endpoints).await;
+            if !is_resume {
+                rollback_endpoint_registrations(services, tunnel_id, &registered_endpoints).await;
+            }
             return Err(Error::other(format!(
                 "Error while sending RegisterProxyRequest to endpoint '{service_name}'"
             )));
@@ -233,7 +396,10 @@ async fn resolve_endpoint_info(
                     "Endpoint '{}' rejected tunnel registration: {}",
                     service_name, reason
                 );
-                rollback_endpoint_registrations(services, tunnel_id, &registered_endpoints).await;
+                if !is_resume {
+                    rollback_endpoint_registrations(services, tunnel_id, &registered_endpoints)
+                        .await;
+                }
                 return Err(Error::other(format!(
                     "Endpoint '{service_name}' rejected tunnel registration: {reason}"
                 )));
@@ -250,10 +416,12 @@ async fn resolve_endpoint_info(
             };
 
             tunnel_proxy_info.push(TunnelProxyInfo {
+                proxy_id,
                 details: endpoint_info.clone(),
                 endpoint: service_name.clone(),
                 forward_address: input_proxy.forward_address.clone(),
                 forward_port: input_proxy.forward_port,
+                health: input_proxy.health.clone(),
             });
 
             proxy_data.insert(proxy_id, endpoint_info);
@@ -283,14 +451,46 @@ async fn rollback_endpoint_registrations(
     }
 }
 
+async fn remove_unused_endpoint_registrations(
+    services: &Arc<Services>,
+    tunnel_id: Uuid,
+    request: &InitTunelRequest,
+) {
+    let unused_endpoints: Vec<String> = services
+        .get_endpoint_manager()
+        .await
+        .get_endpoint_names()
+        .into_iter()
+        .filter(|name| !request.proxies.iter().any(|p| &p.endpoint_name == name))
+        .collect();
+
+    rollback_endpoint_registrations(services, tunnel_id, &unused_endpoints).await;
+}
+
 async fn start_tunnel_session(
     services: Arc<Services>,
     request: InitTunelRequest,
+    access: TunnelAccess,
     mut response_stream: Connection,
+    resumable_tunnel_id: Option<Uuid>,
 ) {
-    let tunnel_id = Uuid::new_v4();
+    let tunnel_id = match resumable_tunnel_id {
+        Some(tunnel_id) => {
+            info!("Resuming tunnel session: {}", tunnel_id);
+            remove_unused_endpoint_registrations(&services, tunnel_id, &request).await;
+            tunnel_id
+        }
+        None => Uuid::new_v4(),
+    };
 
-    let (proxies, endpoint_info) = match resolve_endpoint_info(tunnel_id, &request, &services).await
+    let (proxies, endpoint_info) = match resolve_endpoint_info(
+        tunnel_id,
+        &request,
+        &access,
+        resumable_tunnel_id.is_some(),
+        &services,
+    )
+    .await
     {
         Ok(data) => data,
         Err(e) => {
@@ -303,36 +503,177 @@ async fn start_tunnel_session(
         }
     };
 
+    let (resume_token, generation) = services
+        .get_resume_manager()
+        .await
+        .activate(&tunnel_id, &access);
+
+    if resumable_tunnel_id.is_some() {
+        // Connection of the previous session might still be open if the tunnel
+        // noticed the disconnect before the server did.
+        let _ = services.get_tunnel_manager().await.cancel_session(&tunnel_id);
+    }
+
     let (tunnel_session, channel_rx) =
-        session::tunnel::create(tunnel_id, request.name.clone(), proxies);
+        session::tunnel::create(tunnel_id, request.name.clone(), access, proxies);
 
     let tunnel_id = tunnel_session.get_id();
 
-    info!("Tunnel connected. Assigned ID: {}", tunnel_id);
+    match tunnel_session.get_access().identity.as_ref() {
+        Some(identity) => info!(
+            "Tunnel connected. Assigned ID: {}, identity: {}",
+            tunnel_id, identity
+        ),
+        None => info!("Tunnel connected. Assigned ID: {}", tunnel_id),
+    }
+
+    let config = services.get_config();
+    let multiplex = request.multiplex && config.get_allow_multiplexed_links();
 
     response_stream
         .respond_message(&InitTunnelResponse::Accepted {
             tunnel_id,
             endpoint_info,
+            resume_token: (config.get_tunnel_resume_grace_seconds() > 0).then_some(resume_token),
+            multiplex,
         })
         .await;
 
     services
         .push_event(ServiceEvent::TunnelConnected {
-            tunnel_session: tunnel_session.clone(),
+            tunnel_session: Box::new(tunnel_session.clone()),
         })
         .await;
 
+    let (control, requests, multiplexer) = match start_control_channel(response_stream, multiplex)
+    {
+        Ok(channels) => channels,
+        Err(e) => {
+            info!("Failed to start tunnel {} session: {}", tunnel_id, e);
+            end_tunnel_session(
+                services,
+                tunnel_session,
+                generation,
+                TunnelSessionEnd::ConnectionLost,
+            )
+            .await;
+            return;
+        }
+    };
+
     info!("Tunnel session started: {}", tunnel_id);
-    session::tunnel::start(
+    let session_end = session::tunnel::start(
         services.clone(),
-        tunnel_session,
-        response_stream,
+        tunnel_session.clone(),
+        control,
+        requests,
+        multiplexer,
         channel_rx,
     )
     .await;
 
     info!("Tunnel session ended: {}", tunnel_id);
+    end_tunnel_session(services, tunnel_session, generation, session_end).await;
+}
+
+fn start_control_channel(
+    stream: Connection,
+    multiplex: bool,
+) -> Result<(
+    ControlChannel,
+    UnboundedReceiver<IncomingRequest>,
+    Option<Multiplexer>,
+)> {
+    if !multiplex {
+        let (control, requests) = ControlChannel::start(stream)?;
+        return Ok((control, requests, None));
+    }
+
+    let (multiplexer, control_stream) = Multiplexer::start(stream)?;
+    let (control, requests) = ControlChannel::start(control_stream.into())?;
+
+    Ok((control, requests, Some(multiplexer)))
+}
+
+async fn end_tunnel_session(
+    services: Arc<Services>,
+    tunnel_session: TunnelSession,
+    generation: u64,
+    session_end: TunnelSessionEnd,
+) {
+    let tunnel_id = tunnel_session.get_id();
+    let grace_seconds = services.get_config().get_tunnel_resume_grace_seconds();
+
+    if let TunnelSessionEnd::ConnectionLost = session_end
+        && grace_seconds > 0
+    {
+        if !services
+            .get_resume_manager()
+            .await
+            .suspend(&tunnel_id, generation)
+        {
+            debug!("Tunnel {} has already been resumed.", tunnel_id);
+            return;
+        }
+
+        info!(
+            "Tunnel {} connection lost, keeping its assignments for {} seconds.",
+            tunnel_id, grace_seconds
+        );
+
+        tokio::spawn(expire_suspended_tunnel(
+            services,
+            tunnel_session,
+            generation,
+            grace_seconds,
+        ));
+        return;
+    }
+
+    if !services
+        .get_resume_manager()
+        .await
+        .release(&tunnel_id, generation)
+    {
+        debug!("Tunnel {} has already been resumed.", tunnel_id);
+        return;
+    }
+
+    tunnel_session.cancel();
+
+    services
+        .push_event(ServiceEvent::TunnelDisconnected { tunnel_id })
+        .await;
+}
+
+async fn expire_suspended_tunnel(
+    services: Arc<Services>,
+    tunnel_session: TunnelSession,
+    generation: u64,
+    grace_seconds: u64,
+) {
+    let tunnel_id = tunnel_session.get_id();
+    let cancel_token = services.get_cancel_token();
+
+    tokio::select! {
+        _ = cancel_token.cancelled() => {
+            return;
+        }
+        _ = tokio::time::sleep(Duration::from_secs(grace_seconds)) => {}
+    }
+
+    if !services
+        .get_resume_manager()
+        .await
+        .expire(&tunnel_id, generation)
+    {
+        return;
+    }
+
+    info!("Tunnel {} was not resumed in time, removing it.", tunnel_id);
+
+    tunnel_session.cancel();
+
     services
         .push_event(ServiceEvent::TunnelDisconnected { tunnel_id })
         .await;
@@ -10,21 +10,36 @@ mod heartbeat_request;
 mod init_link;
 mod init_tunnel;
 mod monitoring_request;
+mod proxy_health;
+mod tunnel_proxy;
 
 pub use config_request::{
     ConfigRequest, ProcessConfigRequest, ProcessConfigResponse, PublicEndpointConfig,
 };
 pub use heartbeat_request::{process_heartbeat_request, HeartbeatRequest, HeartbeatResponse};
 pub use init_link::{InitLinkRequest, InitLinkResponse};
-pub use init_tunnel::{InitTunelRequest, InitTunnelResponse, InputProxy, ProxySession};
+pub use init_tunnel::{
+    InitTunelRequest, InitTunnelResponse, InputProxy, ProxySession, SolveChallengeRequest,
+};
 pub use monitoring_request::{ProcessMonitoringRequest, ProcessMonitoringResponse};
+pub use proxy_health::{
+    process_update_proxy_health, ProxyHealth, UpdateProxyHealthRequest, UpdateProxyHealthResponse,
+};
+pub use tunnel_proxy::{
+    process_add_tunnel_proxy, process_remove_tunnel_proxy, AddTunnelProxyRequest,
+    AddTunnelProxyResponse, RemoveTunnelProxyRequest, RemoveTunnelProxyResponse,
+};
 
 create_data_enum!(ServerRequestMessage, {
     InitTunelRequest -> InitTunnelResponse,
+    SolveChallengeRequest -> InitTunnelResponse,
     InitLinkRequest -> InitLinkResponse,
     ProcessMonitoringRequest -> ProcessMonitoringResponse,
     ProcessConfigRequest -> ProcessConfigResponse,
-    HeartbeatRequest -> HeartbeatResponse
+    HeartbeatRequest -> HeartbeatResponse,
+    AddTunnelProxyRequest -> AddTunnelProxyResponse,
+    RemoveTunnelProxyRequest -> RemoveTunnelProxyResponse,
+    UpdateProxyHealthRequest -> UpdateProxyHealthResponse
 });
 
 pub async fn handle(
@@ -47,7 +62,39 @@ pub async fn handle(
             config_request::process(services, request, stream, address).await
         }
         ServerRequestMessage::HeartbeatRequest(request) => {
-            heartbeat_request::process_heartbeat_request(&services, request, &mut stream).await
+            stream
+                .respond_message(
+                    &heartbeat_request::process_heartbeat_request(&services, request).await,
+                )
+                .await
+        }
+        ServerRequestMessage::SolveChallengeRequest(_) => {
+            stream
+                .respond_message(&InitTunnelResponse::Rejected {
+                    reason: "Challenge can only be solved while connecting a tunnel".to_string(),
+                })
+                .await
+        }
+        ServerRequestMessage::AddTunnelProxyRequest(_) => {
+            stream
+                .respond_message(&AddTunnelProxyResponse::Rejected {
+                    reason: "Proxies can only be added from a connected tunnel".to_string(),
+                })
+                .await
+        }
+        ServerRequestMessage::RemoveTunnelProxyRequest(_) => {
+            stream
+                .respond_message(&RemoveTunnelProxyResponse::Rejected {
+                    reason: "Proxies can only be removed from a connected tunnel".to_string(),
+                })
+                .await
+        }
+        ServerRequestMessage::UpdateProxyHealthRequest(_) => {
+            stream
+                .respond_message(&UpdateProxyHealthResponse::Rejected {
+                    reason: "Proxy health can only be reported from a connected tunnel".to_string(),
+                })
+                .await
         }
     }
 }
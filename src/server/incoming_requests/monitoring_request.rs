@@ -5,12 +5,12 @@ use serde::{Deserialize, Serialize};
 use crate::{
     common::{cli::MonitorCommands, connection::Connection},
     server::{
-        monitoring::{self, Records, SystemInfo},
+        monitoring::{self, IssuedToken, Records, SystemInfo},
         services::{ClientInfo, EndpointInfo, LinkInfo, Services, TunnelInfo},
     },
 };
 
-use super::access::{has_monitoring_access, has_tunnel_access};
+use super::access::{ClientDetails, get_tunnel_access, has_monitoring_access};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProcessMonitoringRequest {
@@ -32,6 +32,8 @@ pub enum ProcessMonitoringResponse {
     ListLinks(Records<LinkInfo>),
     GetLink(LinkInfo),
     LinkDisconnected,
+    TokenCreated(IssuedToken),
+    TokenRevoked,
     Rejected { reason: String },
 }
 
@@ -54,7 +56,17 @@ pub async fn process(
         return;
     }
 
-    if !has_tunnel_access(&services, request.tunnel_key.as_ref()) {
+    let client = ClientDetails::new(address, &response_stream);
+
+    let tunnel_access = get_tunnel_access(
+        &services,
+        request.tunnel_key.as_ref(),
+        request.monitor_key.as_ref(),
+        &client,
+    )
+    .await;
+
+    let Some(tunnel_access) = tunnel_access else {
         services.get_bfp_manager().await.log_ip_attempt(&ip_address);
         response_stream
             .respond_message(&ProcessMonitoringResponse::Rejected {
@@ -62,11 +74,24 @@ pub async fn process(
             })
             .await;
 
+        response_stream.shutdown().await;
+        return;
+    };
+
+    if !tunnel_access.is_unrestricted() {
+        response_stream
+            .respond_message(&ProcessMonitoringResponse::Rejected {
+                reason:
+                    "Access denied. Monitor commands require the server tunnel key or monitor key."
+                        .to_string(),
+            })
+            .await;
+
         response_stream.shutdown().await;
         return;
     }
 
-    if !has_monitoring_access(&services, request.monitor_key.as_ref()) {
+    if !has_monitoring_access(&services, request.monitor_key.as_ref(), &client).await {
         services.get_bfp_manager().await.log_ip_attempt(&ip_address);
         response_stream
             .respond_message(&ProcessMonitoringResponse::Rejected {
@@ -185,5 +210,46 @@ pub async fn process(
                 .respond_message(&ProcessMonitoringResponse::LinkDisconnected)
                 .await;
         }
+        MonitorCommands::CreateToken {
+            identity,
+            expires_in_hours,
+            policy,
+        } => {
+            let policy = match policy
+                .map(|policy| serde_json::from_str(&policy))
+                .transpose()
+            {
+                Ok(policy) => policy,
+                Err(e) => {
+                    response_stream
+                        .respond_message(&ProcessMonitoringResponse::Rejected {
+                            reason: format!("Invalid policy: {e}"),
+                        })
+                        .await;
+                    return;
+                }
+            };
+
+            let response =
+                match monitoring::create_token(&services, identity, expires_in_hours, policy).await
+                {
+                    Ok(token) => ProcessMonitoringResponse::TokenCreated(token),
+                    Err(reason) => ProcessMonitoringResponse::Rejected { reason },
+                };
+
+            response_stream.respond_message(&response).await;
+        }
+        MonitorCommands::RevokeToken { id } => {
+            if let Err(error) = monitoring::revoke_token(&services, &id).await {
+                response_stream
+                    .respond_message(&ProcessMonitoringResponse::Rejected { reason: error })
+                    .await;
+                return;
+            };
+
+            response_stream
+                .respond_message(&ProcessMonitoringResponse::TokenRevoked)
+                .await;
+        }
     }
 }
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::super::services::Services;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum ProxyHealth {
+    #[default]
+    Unknown,
+    Healthy,
+    Unhealthy {
+        reason: String,
+    },
+}
+
+impl ProxyHealth {
+    pub fn is_unhealthy(&self) -> bool {
+        matches!(self, ProxyHealth::Unhealthy { .. })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UpdateProxyHealthRequest {
+    pub proxy_id: Uuid,
+    pub health: ProxyHealth,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum UpdateProxyHealthResponse {
+    Updated,
+    Rejected { reason: String },
+}
+
+pub async fn process_update_proxy_health(
+    services: &Arc<Services>,
+    tunnel_id: Uuid,
+    request: UpdateProxyHealthRequest,
+) -> UpdateProxyHealthResponse {
+    let is_updated = services.get_tunnel_manager().await.set_proxy_health(
+        &tunnel_id,
+        &request.proxy_id,
+        request.health.clone(),
+    );
+
+    if !is_updated {
+        return UpdateProxyHealthResponse::Rejected {
+            reason: format!("Proxy '{}' is not registered", request.proxy_id),
+        };
+    }
+
+    info!(
+        "Proxy '{}' of tunnel {} health changed: {:?}",
+        request.proxy_id, tunnel_id, request.health
+    );
+
+    UpdateProxyHealthResponse::Updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_unhealthy() {
+        assert!(!ProxyHealth::Unknown.is_unhealthy());
+        assert!(!ProxyHealth::Healthy.is_unhealthy());
+        assert!(
+            ProxyHealth::Unhealthy {
+                reason: "Connection refused".to_string()
+            }
+            .is_unhealthy()
+        );
+    }
+
+    #[test]
+    fn test_serialize_health() {
+        assert_eq!(
+            serde_json::to_string(&ProxyHealth::Unhealthy {
+                reason: "Connection refused".to_string()
+            })
+            .unwrap(),
+            r#"{"status":"unhealthy","reason":"Connection refused"}"#
+        );
+
+        let health: ProxyHealth =
+            rmp_serde::from_slice(&rmp_serde::to_vec(&ProxyHealth::Healthy).unwrap()).unwrap();
+        assert_eq!(health, ProxyHealth::Healthy);
+    }
+}
@@ -0,0 +1,215 @@
+use std::sync::Arc;
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::server::{
+    endpoints::messages::{
+        RegisterProxyRequest, RegisterProxyResponse, RemoveProxyRequest, RemoveProxyResponse,
+        ResolvedEndpointInfo,
+    },
+    session::tunnel::TunnelProxyInfo,
+};
+
+use super::{
+    super::services::Services,
+    init_tunnel::{InputProxy, ProxySession, validate_proxy},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AddTunnelProxyRequest {
+    pub proxy: InputProxy,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum AddTunnelProxyResponse {
+    Accepted { endpoint_info: ResolvedEndpointInfo },
+    Rejected { reason: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RemoveTunnelProxyRequest {
+    pub proxy_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum RemoveTunnelProxyResponse {
+    Removed,
+    Rejected { reason: String },
+}
+
+pub async fn process_add_tunnel_proxy(
+    services: &Arc<Services>,
+    tunnel_id: Uuid,
+    request: AddTunnelProxyRequest,
+) -> AddTunnelProxyResponse {
+    let proxy = request.proxy;
+    let config = services.get_config();
+
+    let Some(access) = services
+        .get_tunnel_manager()
+        .await
+        .get_tunnel_access(&tunnel_id)
+    else {
+        return AddTunnelProxyResponse::Rejected {
+            reason: "Tunnel session not found".to_string(),
+        };
+    };
+
+    if let Err(reason) = validate_proxy(&proxy, &config, &access.policy) {
+        return AddTunnelProxyResponse::Rejected { reason };
+    }
+
+    let max_proxies = access
+        .policy
+        .get_max_proxies(config.get_max_proxies_per_tunnel());
+
+    // Slot stays reserved until the proxy is added to the tunnel or the registration fails.
+    if let Err(reason) =
+        services
+            .get_tunnel_manager()
+            .await
+            .reserve_proxy(&tunnel_id, proxy.proxy_id, max_proxies)
+    {
+        return AddTunnelProxyResponse::Rejected { reason };
+    }
+
+    let response = services
+        .get_endpoint_manager()
+        .await
+        .send_request(
+            &proxy.endpoint_name,
+            RegisterProxyRequest {
+                tunnel_id,
+                proxy_session: ProxySession {
+                    proxy_id: proxy.proxy_id,
+                    config: proxy.proxy.clone(),
+                },
+                access,
+            },
+        )
+        .await;
+
+    let endpoint_info = match response {
+        Ok(RegisterProxyResponse::Accepted { endpoint_info }) => endpoint_info,
+        Ok(RegisterProxyResponse::Rejected { reason }) => {
+            services
+                .get_tunnel_manager()
+                .await
+                .release_proxy(&tunnel_id, &proxy.proxy_id);
+
+            debug!(
+                "Endpoint '{}' rejected proxy registration: {}",
+                proxy.endpoint_name, reason
+            );
+            return AddTunnelProxyResponse::Rejected {
+                reason: format!(
+                    "Endpoint '{}' rejected proxy registration: {}",
+                    proxy.endpoint_name, reason
+                ),
+            };
+        }
+        Err(e) => {
+            services
+                .get_tunnel_manager()
+                .await
+                .release_proxy(&tunnel_id, &proxy.proxy_id);
+
+            debug!(
+                "Error while sending RegisterProxyRequest to endpoint '{}': {}",
+                proxy.endpoint_name, e
+            );
+            return AddTunnelProxyResponse::Rejected {
+                reason: format!(
+                    "Error while registering proxy on endpoint '{}'",
+                    proxy.endpoint_name
+                ),
+            };
+        }
+    };
+
+    let is_added = services.get_tunnel_manager().await.add_proxy(
+        &tunnel_id,
+        TunnelProxyInfo {
+            proxy_id: proxy.proxy_id,
+            endpoint: proxy.endpoint_name.clone(),
+            forward_address: proxy.forward_address.clone(),
+            forward_port: proxy.forward_port,
+            details: endpoint_info.clone(),
+            health: proxy.health.clone(),
+        },
+    );
+
+    if !is_added {
+        // Tunnel disconnected while the endpoint was registering the proxy.
+        remove_endpoint_proxy(services, tunnel_id, &proxy.endpoint_name, proxy.proxy_id).await;
+        return AddTunnelProxyResponse::Rejected {
+            reason: "Tunnel session not found".to_string(),
+        };
+    }
+
+    info!(
+        "Proxy '{}' added to tunnel {} on endpoint '{}'",
+        proxy.proxy_id, tunnel_id, proxy.endpoint_name
+    );
+
+    AddTunnelProxyResponse::Accepted { endpoint_info }
+}
+
+pub async fn process_remove_tunnel_proxy(
+    services: &Arc<Services>,
+    tunnel_id: Uuid,
+    request: RemoveTunnelProxyRequest,
+) -> RemoveTunnelProxyResponse {
+    let removed_proxy = services
+        .get_tunnel_manager()
+        .await
+        .remove_proxy(&tunnel_id, &request.proxy_id);
+
+    let Some(proxy) = removed_proxy else {
+        return RemoveTunnelProxyResponse::Rejected {
+            reason: format!("Proxy '{}' is not registered", request.proxy_id),
+        };
+    };
+
+    remove_endpoint_proxy(services, tunnel_id, &proxy.endpoint, request.proxy_id).await;
+
+    info!(
+        "Proxy '{}' removed from tunnel {} on endpoint '{}'",
+        request.proxy_id, tunnel_id, proxy.endpoint
+    );
+
+    RemoveTunnelProxyResponse::Removed
+}
+
+async fn remove_endpoint_proxy(
+    services: &Arc<Services>,
+    tunnel_id: Uuid,
+    endpoint_name: &str,
+    proxy_id: Uuid,
+) {
+    let response = services
+        .get_endpoint_manager()
+        .await
+        .send_request(
+            endpoint_name,
+            RemoveProxyRequest {
+                tunnel_id,
+                proxy_id,
+            },
+        )
+        .await;
+
+    match response {
+        Ok(RemoveProxyResponse { removed: true }) => {}
+        Ok(RemoveProxyResponse { removed: false }) => debug!(
+            "Proxy '{}' was not registered on endpoint '{}'",
+            proxy_id, endpoint_name
+        ),
+        Err(e) => debug!(
+            "Error while sending RemoveProxyRequest to endpoint '{}': {}",
+            endpoint_name, e
+        ),
+    }
+}
@@ -10,6 +10,8 @@ use tokio_util::sync::CancellationToken;
 use crate::common::tasks::start_cancel_listener;
 use crate::configuration::load_configuration;
 
+pub mod auth_hook;
+pub mod authorized_keys;
 pub mod configuration;
 pub mod endpoints;
 mod hub_server;
@@ -17,12 +19,14 @@ pub mod incoming_requests;
 mod monitoring;
 mod services;
 mod session;
+pub mod tunnel_policy;
+pub mod tunnel_token;
 
 pub async fn start(configuration_file: Option<String>) -> Result<()> {
     let configuration: ServerConfiguration = load_configuration(configuration_file)?;
 
     let cancel_token = CancellationToken::new();
-    let services = Arc::new(Services::new(configuration, cancel_token.clone()));
+    let services = Arc::new(Services::new(configuration, cancel_token.clone())?);
 
     let server_future = {
         let services = services.clone();
@@ -1,9 +1,17 @@
 use std::{fmt::Debug, sync::Arc};
 
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sysinfo::System;
+use uuid::Uuid;
 
-use super::services::{ClientInfo, EndpointInfo, LinkInfo, Services, TunnelInfo};
+use crate::common::validate::Validation;
+
+use super::{
+    services::{ClientInfo, EndpointInfo, LinkInfo, Services, TunnelInfo},
+    tunnel_policy::TunnelPolicy,
+    tunnel_token::TunnelToken,
+};
 
 fn get_swap_percentage(sys: &System) -> f64 {
     let total = sys.total_swap();
@@ -12,7 +20,7 @@ fn get_swap_percentage(sys: &System) -> f64 {
         return 0f64;
     }
 
-    return sys.free_swap() as f64 / total as f64 * 100f64;
+    sys.free_swap() as f64 / total as f64 * 100f64
 }
 
 fn get_available_memory_percentage(sys: &System) -> f64 {
@@ -22,7 +30,7 @@ fn get_available_memory_percentage(sys: &System) -> f64 {
         return 0f64;
     }
 
-    return sys.available_memory() as f64 / total as f64 * 100f64;
+    sys.available_memory() as f64 / total as f64 * 100f64
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -132,3 +140,86 @@ pub async fn get_link_info(services: &Arc<Services>, id: &uuid::Uuid) -> Option<
 pub async fn disconnect_link(services: &Arc<Services>, id: &uuid::Uuid) -> Result<(), String> {
     services.get_link_manager().await.cancel_session(id)
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IssuedToken {
+    pub id: Uuid,
+    pub identity: String,
+    pub expires_at: i64,
+    pub token: String,
+}
+
+pub async fn create_token(
+    services: &Arc<Services>,
+    identity: String,
+    expires_in_hours: u64,
+    policy: Option<TunnelPolicy>,
+) -> Result<IssuedToken, String> {
+    let config = services.get_config();
+
+    let Some(secret) = config.token_secret.as_ref() else {
+        return Err("Tokens are not enabled, token_secret is not set on the server.".to_owned());
+    };
+
+    if identity.trim().is_empty() {
+        return Err("Identity cannot be empty.".to_owned());
+    }
+
+    if expires_in_hours == 0 {
+        return Err("Token must expire in at least one hour.".to_owned());
+    }
+
+    if let Some(policy) = &policy {
+        let validation = Validation::validate(policy);
+
+        if !validation.is_valid() {
+            return Err(format!(
+                "Invalid policy: {}",
+                validation.errors().join(", ")
+            ));
+        }
+
+        for name in policy.allowed_endpoints.iter().flatten() {
+            if !config.endpoints.contains_key(name) {
+                return Err(format!("Invalid policy: endpoint '{name}' does not exist."));
+            }
+        }
+    }
+
+    let expires_in_seconds = i64::try_from(expires_in_hours.saturating_mul(3600))
+        .map_err(|_| "Token expiry is too long.".to_owned())?;
+
+    let token = TunnelToken::new(
+        identity,
+        Utc::now().timestamp().saturating_add(expires_in_seconds),
+        policy,
+    );
+
+    Ok(IssuedToken {
+        token: token.encode(secret)?,
+        id: token.id,
+        identity: token.identity,
+        expires_at: token.expires_at,
+    })
+}
+
+/// Revokes the token and disconnects tunnels which are connected with it.
+pub async fn revoke_token(services: &Arc<Services>, id: &Uuid) -> Result<(), String> {
+    if services.get_config().token_secret.is_none() {
+        return Err("Tokens are not enabled, token_secret is not set on the server.".to_owned());
+    }
+
+    services
+        .get_token_manager()
+        .await
+        .revoke(*id)
+        .await
+        .map_err(|e| format!("Failed to save token revocation: {e}"))?;
+
+    services
+        .get_tunnel_manager()
+        .await
+        .cancel_token_sessions(id);
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::server::auth_hook::AuthHookResponse;
+
+/// Keeps auth hook answers until their TTL runs out, so the hook is not called on every
+/// connection. Keys are hashes of the hook requests.
+pub struct AuthCacheManager {
+    entries: HashMap<String, (Instant, AuthHookResponse)>,
+}
+
+impl AuthCacheManager {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<AuthHookResponse> {
+        self.entries
+            .get(key)
+            .filter(|(expires_at, _)| *expires_at > Instant::now())
+            .map(|(_, response)| response.clone())
+    }
+
+    pub fn insert(&mut self, key: String, response: AuthHookResponse, ttl: Duration) {
+        let now = Instant::now();
+        self.entries.retain(|_, (expires_at, _)| *expires_at > now);
+
+        if ttl.is_zero() {
+            return;
+        }
+
+        self.entries.insert(key, (now + ttl, response));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_response(allow: bool) -> AuthHookResponse {
+        AuthHookResponse {
+            allow,
+            identity: None,
+            policy: None,
+        }
+    }
+
+    #[test]
+    fn test_cached_response_expires() {
+        let mut manager = AuthCacheManager::new();
+
+        manager.insert(
+            "a".to_string(),
+            create_response(true),
+            Duration::from_secs(60),
+        );
+        manager.insert("b".to_string(), create_response(false), Duration::ZERO);
+        manager.insert(
+            "c".to_string(),
+            create_response(true),
+            Duration::from_millis(1),
+        );
+
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(manager.get("a"), Some(create_response(true)));
+        assert_eq!(manager.get("b"), None);
+        assert_eq!(manager.get("c"), None);
+    }
+}
@@ -109,6 +109,10 @@ impl EndpointManager {
         self.endpoints.len()
     }
 
+    pub fn get_endpoint_names(&self) -> Vec<String> {
+        self.endpoints.keys().cloned().collect()
+    }
+
     pub fn list_endpoints(&self) -> Vec<EndpointInfo> {
         self.endpoints.values().map(|e| e.into()).collect()
     }
@@ -161,6 +165,8 @@ mod tests {
             full_url_template: None,
             allow_custom_hostnames: None,
             require_authorization: None,
+            allow_pools: None,
+            pool_strategy: None,
         })
     }
 
@@ -180,6 +186,14 @@ mod tests {
         assert_eq!(manager.get_count(), 1);
     }
 
+    #[test]
+    fn test_get_endpoint_names() {
+        let mut manager = create_test_endpoint_manager();
+        let config = create_test_endpoint_config();
+        manager.add_endpoint("test_service", &config);
+        assert_eq!(manager.get_endpoint_names(), vec!["test_service".to_string()]);
+    }
+
     #[test]
     fn test_list_endpoints() {
         let mut manager = create_test_endpoint_manager();
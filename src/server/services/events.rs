@@ -4,7 +4,7 @@ use crate::server::session::tunnel::TunnelSession;
 
 #[derive(Clone, Debug)]
 pub enum ServiceEvent {
-    TunnelConnected { tunnel_session: TunnelSession },
+    TunnelConnected { tunnel_session: Box<TunnelSession> },
     TunnelDisconnected { tunnel_id: Uuid },
     LinkDisconnected { client_id: Uuid, session_id: Uuid },
     LinkRejected { client_id: Uuid, session_id: Uuid },
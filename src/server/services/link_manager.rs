@@ -10,6 +10,7 @@ use super::{HandleServiceEvent, client_manager::ClientInfo, events::ServiceEvent
 pub struct LinkSession {
     id: Uuid,
     tunnel_id: Uuid,
+    proxy_id: Uuid,
     client: ClientInfo,
     cancellation_token: CancellationToken,
 }
@@ -45,6 +46,7 @@ impl LinkManager {
     pub fn create_link_session(
         &mut self,
         tunnel_id: Uuid,
+        proxy_id: Uuid,
         client: ClientInfo,
         cancellation_token: CancellationToken,
     ) -> Uuid {
@@ -54,6 +56,7 @@ impl LinkManager {
             LinkSession {
                 id,
                 tunnel_id,
+                proxy_id,
                 client,
                 cancellation_token,
             },
@@ -88,6 +91,13 @@ impl LinkManager {
         self.link_sessions.len()
     }
 
+    pub fn get_active_link_count(&self, tunnel_id: &Uuid, proxy_id: &Uuid) -> usize {
+        self.link_sessions
+            .values()
+            .filter(|session| &session.tunnel_id == tunnel_id && &session.proxy_id == proxy_id)
+            .count()
+    }
+
     pub fn list_all_sessions(&self) -> Vec<LinkInfo> {
         self.link_sessions
             .values()
@@ -117,10 +127,10 @@ impl HandleServiceEvent for LinkManager {
             }
             ServiceEvent::TunnelDisconnected { tunnel_id } => {
                 for session in self.link_sessions.values() {
-                    if &session.tunnel_id == tunnel_id {
-                        if let Err(e) = self.cancel_session(&session.id) {
-                            info!("Error while cancelling link session: {:?}", e);
-                        }
+                    if &session.tunnel_id == tunnel_id
+                        && let Err(e) = self.cancel_session(&session.id)
+                    {
+                        info!("Error while cancelling link session: {:?}", e);
                     }
                 }
             }
@@ -128,10 +138,10 @@ impl HandleServiceEvent for LinkManager {
                 client_id,
                 session_id,
             } => {
-                if let Some(session) = self.link_sessions.get(session_id) {
-                    if &session.client.id == client_id {
-                        self.remove_session(session_id);
-                    }
+                if let Some(session) = self.link_sessions.get(session_id)
+                    && &session.client.id == client_id
+                {
+                    self.remove_session(session_id);
                 }
             }
             _ => {}
@@ -162,8 +172,12 @@ mod tests {
         let tunnel_id = Uuid::new_v4();
         let cancellation_token = CancellationToken::new();
 
-        let session_id =
-            manager.create_link_session(tunnel_id, client.clone(), cancellation_token.clone());
+        let session_id = manager.create_link_session(
+            tunnel_id,
+            Uuid::new_v4(),
+            client.clone(),
+            cancellation_token.clone(),
+        );
 
         assert!(manager.link_sessions.contains_key(&session_id));
         let session = manager.link_sessions.get(&session_id).unwrap();
@@ -178,8 +192,12 @@ mod tests {
         let tunnel_id = Uuid::new_v4();
         let cancellation_token = CancellationToken::new();
 
-        let session_id =
-            manager.create_link_session(tunnel_id, client.clone(), cancellation_token.clone());
+        let session_id = manager.create_link_session(
+            tunnel_id,
+            Uuid::new_v4(),
+            client.clone(),
+            cancellation_token.clone(),
+        );
 
         let result = manager.resolve_tunnel_session_client(&session_id, &tunnel_id);
         assert!(result.is_some());
@@ -194,7 +212,8 @@ mod tests {
         let tunnel_id = Uuid::new_v4();
         let cancellation_token = CancellationToken::new();
 
-        let session_id = manager.create_link_session(tunnel_id, client, cancellation_token);
+        let session_id =
+            manager.create_link_session(tunnel_id, Uuid::new_v4(), client, cancellation_token);
         manager.remove_session(&session_id);
 
         assert!(!manager.link_sessions.contains_key(&session_id));
@@ -209,10 +228,39 @@ mod tests {
         let tunnel_id = Uuid::new_v4();
         let cancellation_token = CancellationToken::new();
 
-        manager.create_link_session(tunnel_id, client, cancellation_token);
+        manager.create_link_session(tunnel_id, Uuid::new_v4(), client, cancellation_token);
         assert_eq!(manager.get_count(), 1);
     }
 
+    #[test]
+    fn test_get_active_link_count() {
+        let mut manager = create_test_link_manager();
+        let tunnel_id = Uuid::new_v4();
+        let proxy_id = Uuid::new_v4();
+
+        manager.create_link_session(
+            tunnel_id,
+            proxy_id,
+            create_test_client_info(),
+            CancellationToken::new(),
+        );
+        manager.create_link_session(
+            tunnel_id,
+            proxy_id,
+            create_test_client_info(),
+            CancellationToken::new(),
+        );
+        manager.create_link_session(
+            tunnel_id,
+            Uuid::new_v4(),
+            create_test_client_info(),
+            CancellationToken::new(),
+        );
+
+        assert_eq!(manager.get_active_link_count(&tunnel_id, &proxy_id), 2);
+        assert_eq!(manager.get_active_link_count(&Uuid::new_v4(), &proxy_id), 0);
+    }
+
     #[test]
     fn test_list_all_sessions() {
         let mut manager = create_test_link_manager();
@@ -220,7 +268,12 @@ mod tests {
         let tunnel_id = Uuid::new_v4();
         let cancellation_token = CancellationToken::new();
 
-        let session_id = manager.create_link_session(tunnel_id, client.clone(), cancellation_token);
+        let session_id = manager.create_link_session(
+            tunnel_id,
+            Uuid::new_v4(),
+            client.clone(),
+            cancellation_token,
+        );
         let sessions = manager.list_all_sessions();
 
         assert_eq!(sessions.len(), 1);
@@ -236,7 +289,12 @@ mod tests {
         let tunnel_id = Uuid::new_v4();
         let cancellation_token = CancellationToken::new();
 
-        let session_id = manager.create_link_session(tunnel_id, client.clone(), cancellation_token);
+        let session_id = manager.create_link_session(
+            tunnel_id,
+            Uuid::new_v4(),
+            client.clone(),
+            cancellation_token,
+        );
         let session_info = manager.get_session_info(&session_id);
 
         assert!(session_info.is_some());
@@ -253,7 +311,12 @@ mod tests {
         let tunnel_id = Uuid::new_v4();
         let cancellation_token = CancellationToken::new();
 
-        let session_id = manager.create_link_session(tunnel_id, client, cancellation_token.clone());
+        let session_id = manager.create_link_session(
+            tunnel_id,
+            Uuid::new_v4(),
+            client,
+            cancellation_token.clone(),
+        );
         assert!(manager.cancel_session(&session_id).is_ok());
         assert!(cancellation_token.is_cancelled());
     }
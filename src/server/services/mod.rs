@@ -1,25 +1,31 @@
-use std::sync::Arc;
+use std::{io::Result, path::PathBuf, sync::Arc};
 
+use auth_cache_manager::AuthCacheManager;
 use bfp_manager::BfpManager;
 use chrono::Utc;
 use client_manager::ClientManager;
 use endpoint_manager::EndpointManager;
 use events::ServiceEvent;
 use link_manager::LinkManager;
+use resume_manager::ResumeManager;
+use token_manager::TokenManager;
 use tokio::sync::{Mutex, MutexGuard};
 use tokio_util::sync::CancellationToken;
 use tunnel_manager::TunnelManager;
 
 use super::configuration::ServerConfiguration;
 
+mod auth_cache_manager;
 mod bfp_manager;
 mod client_manager;
 mod endpoint_manager;
 pub mod events;
 mod link_manager;
+mod resume_manager;
+mod token_manager;
 mod tunnel_manager;
 
-pub use client_manager::{Client, ClientInfo};
+pub use client_manager::{Client, ClientInfo, ClientLink};
 pub use endpoint_manager::EndpointInfo;
 pub use link_manager::LinkInfo;
 pub use tunnel_manager::TunnelInfo;
@@ -34,23 +40,31 @@ pub struct Services {
     endpoint_manager: Mutex<EndpointManager>,
     link_manager: Mutex<LinkManager>,
     bfp_manager: Mutex<BfpManager>,
+    resume_manager: Mutex<ResumeManager>,
+    token_manager: Mutex<TokenManager>,
+    auth_cache_manager: Mutex<AuthCacheManager>,
     config: Arc<ServerConfiguration>,
     cancel_token: CancellationToken,
     start_time: i64,
 }
 
 impl Services {
-    pub fn new(config: ServerConfiguration, cancel_token: CancellationToken) -> Self {
-        Self {
+    pub fn new(config: ServerConfiguration, cancel_token: CancellationToken) -> Result<Self> {
+        Ok(Self {
             client_manager: Mutex::new(ClientManager::new(config.get_max_clients())),
             tunnel_manager: Mutex::new(TunnelManager::new()),
             endpoint_manager: Mutex::new(EndpointManager::new()),
             link_manager: Mutex::new(LinkManager::new()),
             bfp_manager: Mutex::new(BfpManager::new()),
+            resume_manager: Mutex::new(ResumeManager::new()),
+            token_manager: Mutex::new(TokenManager::new(
+                config.token_revocation_file.as_ref().map(PathBuf::from),
+            )?),
+            auth_cache_manager: Mutex::new(AuthCacheManager::new()),
             config: Arc::new(config),
             start_time: Utc::now().timestamp(),
             cancel_token,
-        }
+        })
     }
 
     pub async fn get_client_manager(&self) -> MutexGuard<'_, ClientManager> {
@@ -73,6 +87,18 @@ impl Services {
         self.bfp_manager.lock().await
     }
 
+    pub async fn get_resume_manager(&self) -> MutexGuard<'_, ResumeManager> {
+        self.resume_manager.lock().await
+    }
+
+    pub async fn get_token_manager(&self) -> MutexGuard<'_, TokenManager> {
+        self.token_manager.lock().await
+    }
+
+    pub async fn get_auth_cache_manager(&self) -> MutexGuard<'_, AuthCacheManager> {
+        self.auth_cache_manager.lock().await
+    }
+
     pub async fn push_event(&self, event: ServiceEvent) {
         self.get_tunnel_manager().await.handle_event(&event).await;
         self.get_endpoint_manager().await.handle_event(&event).await;
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::{
+    common::text::{get_random_secret, is_constant_time_equals},
+    server::tunnel_policy::TunnelAccess,
+};
+
+struct ResumableTunnel {
+    token: String,
+    generation: u64,
+    suspended: bool,
+    owner: TunnelAccess,
+}
+
+pub struct ResumeManager {
+    tunnels: HashMap<Uuid, ResumableTunnel>,
+}
+
+impl ResumeManager {
+    pub fn new() -> Self {
+        Self {
+            tunnels: HashMap::new(),
+        }
+    }
+
+    pub fn find_tunnel(&self, token: &str) -> Option<Uuid> {
+        self.tunnels
+            .iter()
+            .find(|(_, tunnel)| is_constant_time_equals(&tunnel.token, token))
+            .map(|(id, _)| *id)
+    }
+
+    /// Tunnel can only be resumed by the same owner it was created by, so a leaked resume token
+    /// alone is not enough to take over its assignments.
+    pub fn is_owner(&self, tunnel_id: &Uuid, access: &TunnelAccess) -> bool {
+        self.tunnels
+            .get(tunnel_id)
+            .is_some_and(|tunnel| tunnel.owner.has_same_owner(access))
+    }
+
+    /// Activates a new session of the tunnel. Every session gets a new token, so tokens of
+    /// previous sessions cannot be used anymore.
+    pub fn activate(&mut self, tunnel_id: &Uuid, owner: &TunnelAccess) -> (String, u64) {
+        let tunnel = self
+            .tunnels
+            .entry(*tunnel_id)
+            .or_insert_with(|| ResumableTunnel {
+                token: String::new(),
+                generation: 0,
+                suspended: false,
+                owner: owner.clone(),
+            });
+
+        tunnel.token = get_random_secret(64);
+        tunnel.generation += 1;
+        tunnel.suspended = false;
+
+        (tunnel.token.clone(), tunnel.generation)
+    }
+
+    pub fn is_current(&self, tunnel_id: &Uuid, generation: u64) -> bool {
+        self.tunnels
+            .get(tunnel_id)
+            .is_some_and(|tunnel| tunnel.generation == generation)
+    }
+
+    pub fn suspend(&mut self, tunnel_id: &Uuid, generation: u64) -> bool {
+        match self.tunnels.get_mut(tunnel_id) {
+            Some(tunnel) if tunnel.generation == generation => {
+                tunnel.suspended = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn expire(&mut self, tunnel_id: &Uuid, generation: u64) -> bool {
+        let is_expired = self
+            .tunnels
+            .get(tunnel_id)
+            .is_some_and(|tunnel| tunnel.generation == generation && tunnel.suspended);
+
+        if is_expired {
+            self.tunnels.remove(tunnel_id);
+        }
+
+        is_expired
+    }
+
+    pub fn release(&mut self, tunnel_id: &Uuid, generation: u64) -> bool {
+        if !self.is_current(tunnel_id, generation) {
+            return false;
+        }
+
+        self.tunnels.remove(tunnel_id);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_owner(identity: &str) -> TunnelAccess {
+        TunnelAccess::for_identity(identity, Default::default())
+    }
+
+    #[test]
+    fn test_activate_rotates_token() {
+        let mut manager = ResumeManager::new();
+        let tunnel_id = Uuid::new_v4();
+
+        let (token, generation) = manager.activate(&tunnel_id, &create_owner("alice"));
+        assert_eq!(manager.find_tunnel(&token), Some(tunnel_id));
+
+        let (resumed_token, resumed_generation) =
+            manager.activate(&tunnel_id, &create_owner("alice"));
+
+        assert_ne!(token, resumed_token);
+        assert_eq!(resumed_generation, generation + 1);
+        assert!(manager.find_tunnel(&token).is_none());
+        assert_eq!(manager.find_tunnel(&resumed_token), Some(tunnel_id));
+    }
+
+    #[test]
+    fn test_is_owner() {
+        let mut manager = ResumeManager::new();
+        let tunnel_id = Uuid::new_v4();
+        manager.activate(&tunnel_id, &create_owner("alice"));
+
+        assert!(manager.is_owner(&tunnel_id, &create_owner("alice")));
+        assert!(!manager.is_owner(&tunnel_id, &create_owner("bob")));
+        assert!(!manager.is_owner(&tunnel_id, &TunnelAccess::default()));
+        assert!(!manager.is_owner(&Uuid::new_v4(), &create_owner("alice")));
+    }
+
+    #[test]
+    fn test_find_tunnel_invalid_token() {
+        let mut manager = ResumeManager::new();
+        manager.activate(&Uuid::new_v4(), &TunnelAccess::default());
+
+        assert!(manager.find_tunnel("invalid").is_none());
+    }
+
+    #[test]
+    fn test_suspend_and_expire() {
+        let mut manager = ResumeManager::new();
+        let tunnel_id = Uuid::new_v4();
+        let (token, generation) = manager.activate(&tunnel_id, &TunnelAccess::default());
+
+        assert!(!manager.expire(&tunnel_id, generation));
+        assert!(manager.suspend(&tunnel_id, generation));
+        assert!(manager.expire(&tunnel_id, generation));
+        assert!(manager.find_tunnel(&token).is_none());
+    }
+
+    #[test]
+    fn test_resumed_tunnel_does_not_expire() {
+        let mut manager = ResumeManager::new();
+        let tunnel_id = Uuid::new_v4();
+        let (_, generation) = manager.activate(&tunnel_id, &TunnelAccess::default());
+
+        manager.suspend(&tunnel_id, generation);
+        let (_, new_generation) = manager.activate(&tunnel_id, &TunnelAccess::default());
+
+        assert!(!manager.expire(&tunnel_id, generation));
+        assert!(!manager.expire(&tunnel_id, new_generation));
+        assert!(!manager.suspend(&tunnel_id, generation));
+        assert!(!manager.release(&tunnel_id, generation));
+        assert!(manager.is_current(&tunnel_id, new_generation));
+    }
+
+    #[test]
+    fn test_release() {
+        let mut manager = ResumeManager::new();
+        let tunnel_id = Uuid::new_v4();
+        let (token, generation) = manager.activate(&tunnel_id, &TunnelAccess::default());
+
+        assert!(manager.release(&tunnel_id, generation));
+        assert!(manager.find_tunnel(&token).is_none());
+    }
+}
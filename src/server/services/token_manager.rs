@@ -0,0 +1,125 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{Error, Result},
+    path::PathBuf,
+};
+
+use uuid::Uuid;
+
+use crate::common::atomic_file::write_atomic;
+
+/// Keeps IDs of revoked tunnel tokens. If a revocation file is set, revoked IDs are loaded from
+/// it on start and written to it on every change, so that revocations survive restarts.
+pub struct TokenManager {
+    revoked: HashSet<Uuid>,
+    revocation_file: Option<PathBuf>,
+}
+
+impl TokenManager {
+    /// Fails if the revocation file exists but cannot be read, since starting without it would
+    /// make revoked tokens valid again.
+    pub fn new(revocation_file: Option<PathBuf>) -> Result<Self> {
+        let revoked = match &revocation_file {
+            Some(path) if path.exists() => load_revocations(path).map_err(|e| {
+                Error::new(
+                    e.kind(),
+                    format!(
+                        "Failed to read token revocation file '{}': {}",
+                        path.display(),
+                        e
+                    ),
+                )
+            })?,
+            _ => HashSet::new(),
+        };
+
+        Ok(Self {
+            revoked,
+            revocation_file,
+        })
+    }
+
+    pub fn is_revoked(&self, id: &Uuid) -> bool {
+        self.revoked.contains(id)
+    }
+
+    /// Revokes the token. Returns false if it was already revoked. The token stays valid if the
+    /// revocation file cannot be written.
+    pub async fn revoke(&mut self, id: Uuid) -> Result<bool> {
+        if self.revoked.contains(&id) {
+            return Ok(false);
+        }
+
+        if let Some(path) = &self.revocation_file {
+            let mut revoked: Vec<&Uuid> = self.revoked.iter().chain([&id]).collect();
+            revoked.sort();
+
+            write_atomic(path, &serde_json::to_vec_pretty(&revoked)?).await?;
+        }
+
+        self.revoked.insert(id);
+
+        Ok(true)
+    }
+}
+
+fn load_revocations(path: &PathBuf) -> Result<HashSet<Uuid>> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_revoke() {
+        let mut manager = TokenManager::new(None).unwrap();
+        let id = Uuid::new_v4();
+
+        assert!(!manager.is_revoked(&id));
+        assert!(manager.revoke(id).await.unwrap());
+        assert!(!manager.revoke(id).await.unwrap());
+        assert!(manager.is_revoked(&id));
+    }
+
+    #[tokio::test]
+    async fn test_revocations_are_persisted() {
+        let path = std::env::temp_dir().join(format!("tunnelize-revoked-{}.json", Uuid::new_v4()));
+        let id = Uuid::new_v4();
+
+        TokenManager::new(Some(path.clone()))
+            .unwrap()
+            .revoke(id)
+            .await
+            .unwrap();
+        let manager = TokenManager::new(Some(path.clone())).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(manager.is_revoked(&id));
+        assert!(!manager.is_revoked(&Uuid::new_v4()));
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_does_not_revoke() {
+        let path = std::env::temp_dir()
+            .join(format!("tunnelize-missing-{}", Uuid::new_v4()))
+            .join("revoked.json");
+        let mut manager = TokenManager::new(Some(path)).unwrap();
+        let id = Uuid::new_v4();
+
+        assert!(manager.revoke(id).await.is_err());
+        assert!(!manager.is_revoked(&id));
+    }
+
+    #[test]
+    fn test_unreadable_revocation_file_fails() {
+        let path = std::env::temp_dir().join(format!("tunnelize-revoked-{}.json", Uuid::new_v4()));
+        fs::write(&path, "not json").unwrap();
+
+        let result = TokenManager::new(Some(path.clone()));
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+}
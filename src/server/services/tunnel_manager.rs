@@ -6,9 +6,13 @@ use uuid::Uuid;
 
 use crate::{
     common::channel::{DataResponse, RequestSender},
-    server::session::{
-        messages::{TunnelChannelRequest, TunnelChannelResponse},
-        tunnel::{TunnelProxyInfo, TunnelSession},
+    server::{
+        incoming_requests::ProxyHealth,
+        session::{
+            messages::{TunnelChannelRequest, TunnelChannelResponse},
+            tunnel::{TunnelProxyInfo, TunnelSession},
+        },
+        tunnel_policy::TunnelAccess,
     },
 };
 
@@ -22,6 +26,10 @@ pub struct TunnelManager {
 pub struct TunnelInfo {
     pub id: Uuid,
     pub name: Option<String>,
+    #[serde(default)]
+    pub credential: Option<String>,
+    #[serde(default)]
+    pub identity: Option<String>,
     pub proxies: Vec<TunnelProxyInfo>,
     pub last_heartbeat_timestamp: i64,
 }
@@ -68,6 +76,33 @@ impl TunnelManager {
         self.tunnels.len()
     }
 
+    pub fn get_access_count(&self, access: &TunnelAccess) -> usize {
+        self.tunnels
+            .values()
+            .filter(|tunnel| tunnel.get_access().has_same_owner(access))
+            .count()
+    }
+
+    /// Cancels all tunnels which connected with the token.
+    pub fn cancel_token_sessions(&self, token_id: &Uuid) -> usize {
+        let mut count = 0;
+
+        for tunnel in self.tunnels.values() {
+            if tunnel.get_access().token_id.as_ref() == Some(token_id) {
+                tunnel.cancel();
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    pub fn get_tunnel_access(&self, id: &Uuid) -> Option<TunnelAccess> {
+        self.tunnels
+            .get(id)
+            .map(|tunnel| tunnel.get_access().clone())
+    }
+
     pub fn register_tunnel_session(&mut self, tunnel: &TunnelSession) {
         self.tunnels.insert(tunnel.get_id(), tunnel.clone());
     }
@@ -90,6 +125,52 @@ impl TunnelManager {
         }
     }
 
+    pub fn reserve_proxy(
+        &mut self,
+        id: &Uuid,
+        proxy_id: Uuid,
+        max_proxies: usize,
+    ) -> Result<(), String> {
+        let Some(tunnel) = self.tunnels.get_mut(id) else {
+            return Err("Tunnel session not found".to_string());
+        };
+
+        tunnel.reserve_proxy(proxy_id, max_proxies)
+    }
+
+    pub fn release_proxy(&mut self, id: &Uuid, proxy_id: &Uuid) {
+        if let Some(tunnel) = self.tunnels.get_mut(id) {
+            tunnel.release_proxy(proxy_id);
+        }
+    }
+
+    pub fn add_proxy(&mut self, id: &Uuid, proxy: TunnelProxyInfo) -> bool {
+        let Some(tunnel) = self.tunnels.get_mut(id) else {
+            return false;
+        };
+
+        tunnel.add_proxy(proxy);
+        true
+    }
+
+    pub fn remove_proxy(&mut self, id: &Uuid, proxy_id: &Uuid) -> Option<TunnelProxyInfo> {
+        self.tunnels.get_mut(id)?.remove_proxy(proxy_id)
+    }
+
+    pub fn set_proxy_health(&mut self, id: &Uuid, proxy_id: &Uuid, health: ProxyHealth) -> bool {
+        self.tunnels
+            .get_mut(id)
+            .is_some_and(|tunnel| tunnel.set_proxy_health(proxy_id, health))
+    }
+
+    pub fn get_proxy_health(&self, id: &Uuid, proxy_id: &Uuid) -> ProxyHealth {
+        self.tunnels
+            .get(id)
+            .and_then(|tunnel| tunnel.get_proxy_health(proxy_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
     pub fn is_tunnel_stale(&self, id: &Uuid) -> bool {
         if let Some(tunnel) = self.tunnels.get(id) {
             return tunnel.is_stale();
@@ -120,11 +201,12 @@ impl HandleServiceEvent for TunnelManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::server::endpoints::{http::HttpEndpointInfo, messages::ResolvedEndpointInfo};
     use crate::server::session::tunnel::{create, TunnelSession};
     use uuid::Uuid;
 
     fn create_tunnel_session(id: Uuid) -> TunnelSession {
-        let (session, _) = create(id, None, vec![]);
+        let (session, _) = create(id, None, TunnelAccess::default(), vec![]);
         session
     }
 
@@ -145,6 +227,32 @@ mod tests {
         assert!(manager.get_tunnel_info(&id).is_some());
     }
 
+    #[test]
+    fn test_get_access_count() {
+        let mut manager = TunnelManager::new();
+        let access = TunnelAccess {
+            credential: Some("ci".to_string()),
+            ..Default::default()
+        };
+        let other_access = TunnelAccess {
+            identity: Some("ci".to_string()),
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            let (session, _) = create(Uuid::new_v4(), None, access.clone(), vec![]);
+            manager.register_tunnel_session(&session);
+        }
+        manager.register_tunnel_session(&create_tunnel_session(Uuid::new_v4()));
+
+        assert_eq!(manager.get_access_count(&access), 2);
+        assert_eq!(manager.get_access_count(&other_access), 0);
+        assert_eq!(
+            manager.list_all_tunnels().iter().filter(|t| t.credential.is_some()).count(),
+            2
+        );
+    }
+
     #[test]
     fn test_remove_tunnel_session() {
         let mut manager = TunnelManager::new();
@@ -201,4 +309,80 @@ mod tests {
         let info = manager.get_tunnel_info(&id);
         assert!(info.is_some());
     }
+
+    fn create_proxy_info(proxy_id: Uuid) -> TunnelProxyInfo {
+        TunnelProxyInfo {
+            proxy_id,
+            endpoint: "http".to_string(),
+            forward_address: "localhost".to_string(),
+            forward_port: 8080,
+            details: ResolvedEndpointInfo::Http(HttpEndpointInfo {
+                assigned_url: "http://test.localhost".to_string(),
+            }),
+            health: ProxyHealth::Unknown,
+        }
+    }
+
+    #[test]
+    fn test_add_and_remove_proxy() {
+        let mut manager = TunnelManager::new();
+        let id = Uuid::new_v4();
+        let proxy_id = Uuid::new_v4();
+        let session = create_tunnel_session(id);
+
+        assert!(!manager.add_proxy(&id, create_proxy_info(proxy_id)));
+
+        manager.register_tunnel_session(&session);
+        assert!(manager.add_proxy(&id, create_proxy_info(proxy_id)));
+        assert_eq!(manager.get_tunnel_info(&id).unwrap().proxies.len(), 1);
+
+        let removed = manager.remove_proxy(&id, &proxy_id).unwrap();
+        assert_eq!(removed.proxy_id, proxy_id);
+        assert!(manager.remove_proxy(&id, &proxy_id).is_none());
+        assert!(manager.get_tunnel_info(&id).unwrap().proxies.is_empty());
+    }
+
+    #[test]
+    fn test_reserve_proxy() {
+        let mut manager = TunnelManager::new();
+        let id = Uuid::new_v4();
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+
+        assert!(manager.reserve_proxy(&id, first, 2).is_err());
+
+        manager.register_tunnel_session(&create_tunnel_session(id));
+        assert!(manager.reserve_proxy(&id, first, 2).is_ok());
+        assert!(manager.reserve_proxy(&id, first, 2).is_err());
+        assert!(manager.reserve_proxy(&id, second, 2).is_ok());
+        assert!(manager.reserve_proxy(&id, Uuid::new_v4(), 2).is_err());
+
+        manager.release_proxy(&id, &second);
+        assert!(manager.add_proxy(&id, create_proxy_info(first)));
+        assert!(manager.reserve_proxy(&id, first, 2).is_err());
+        assert!(manager.reserve_proxy(&id, second, 2).is_ok());
+        assert!(manager.reserve_proxy(&id, Uuid::new_v4(), 2).is_err());
+    }
+
+    #[test]
+    fn test_proxy_health() {
+        let mut manager = TunnelManager::new();
+        let id = Uuid::new_v4();
+        let proxy_id = Uuid::new_v4();
+        let unhealthy = ProxyHealth::Unhealthy {
+            reason: "Connection refused".to_string(),
+        };
+
+        manager.register_tunnel_session(&create_tunnel_session(id));
+        assert!(!manager.set_proxy_health(&id, &proxy_id, unhealthy.clone()));
+        assert_eq!(manager.get_proxy_health(&id, &proxy_id), ProxyHealth::Unknown);
+
+        manager.add_proxy(&id, create_proxy_info(proxy_id));
+        assert!(manager.set_proxy_health(&id, &proxy_id, unhealthy.clone()));
+        assert_eq!(manager.get_proxy_health(&id, &proxy_id), unhealthy);
+        assert_eq!(
+            manager.get_tunnel_info(&id).unwrap().proxies[0].health,
+            unhealthy
+        );
+    }
 }
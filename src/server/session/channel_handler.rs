@@ -1,12 +1,14 @@
 use std::sync::Arc;
 
 use log::{debug, info};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use crate::{
     common::{
         channel::{Request, Responder},
-        connection::Connection,
+        control_channel::ControlChannel,
+        multiplexer::{Multiplexer, MuxStream},
     },
     server::{
         services::{Services, events::ServiceEvent},
@@ -16,14 +18,16 @@ use crate::{
 };
 
 use super::{
+    link,
     messages::{ClientLinkRequest, TunnelChannelRequest},
     tunnel::TunnelSession,
 };
 
 pub async fn handle(
-    services: &Arc<Services>,
-    tunnel_session: &TunnelSession,
-    stream: &mut Connection,
+    services: Arc<Services>,
+    tunnel_session: TunnelSession,
+    control: ControlChannel,
+    multiplexer: Option<Multiplexer>,
     mut request: Request<TunnelChannelRequest>,
 ) {
     let responder = request.take_responder();
@@ -43,16 +47,39 @@ pub async fn handle(
                 info
             };
 
+            let cancel_token = tunnel_session.get_child_cancel_token();
+
             let link_session_id = {
                 services.get_link_manager().await.create_link_session(
                     tunnel_session.get_id(),
+                    request_data.proxy_id,
                     info,
-                    tunnel_session.get_child_cancel_token(),
+                    cancel_token.clone(),
                 )
             };
 
-            let response: InitLinkResponse = match stream
-                .request_message(InitLinkRequest {
+            // The stream has to exist before the tunnel is asked for the link
+            // so no data sent by the tunnel right after accepting is lost.
+            let link_stream = match multiplexer.map(|m| m.open_stream(link_session_id)) {
+                Some(Ok(link_stream)) => Some(link_stream),
+                Some(Err(e)) => {
+                    debug!("Failed to open multiplexed link stream: {:?}", e);
+                    reject_request(
+                        &services,
+                        responder,
+                        request_data,
+                        link_session_id,
+                        "Failed to initialize link session".to_string(),
+                    )
+                    .await;
+
+                    return;
+                }
+                None => None,
+            };
+
+            let response: InitLinkResponse = match control
+                .request(InitLinkRequest {
                     tunnel_id: tunnel_session.get_id(),
                     proxy_id: request_data.proxy_id,
                     session_id: link_session_id,
@@ -63,7 +90,7 @@ pub async fn handle(
                 Err(e) => {
                     debug!("Failed to send init link response: {:?}", e);
                     reject_request(
-                        services,
+                        &services,
                         responder,
                         request_data,
                         link_session_id,
@@ -77,10 +104,20 @@ pub async fn handle(
 
             match response {
                 InitLinkResponse::Accepted => {
+                    if let Some(link_stream) = link_stream {
+                        tokio::spawn(start_multiplexed_link(
+                            services.clone(),
+                            request_data.client_id,
+                            link_session_id,
+                            link_stream,
+                            cancel_token,
+                        ));
+                    }
+
                     responder.respond(ClientLinkResponse::Accepted);
                 }
                 InitLinkResponse::Rejected { reason } => {
-                    reject_request(services, responder, request_data, link_session_id, reason)
+                    reject_request(&services, responder, request_data, link_session_id, reason)
                         .await;
                 }
             }
@@ -88,6 +125,23 @@ pub async fn handle(
     }
 }
 
+async fn start_multiplexed_link(
+    services: Arc<Services>,
+    client_id: Uuid,
+    session_id: Uuid,
+    link_stream: MuxStream,
+    cancel_token: CancellationToken,
+) {
+    link::start_multiplexed(&services, client_id, link_stream, cancel_token).await;
+
+    services
+        .push_event(ServiceEvent::LinkDisconnected {
+            client_id,
+            session_id,
+        })
+        .await;
+}
+
 async fn reject_request(
     services: &Arc<Services>,
     responder: impl Responder<TunnelChannelRequest>,
@@ -5,8 +5,11 @@ use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use crate::{
-    common::{connection::Connection, data_bridge::DataBridge},
-    server::{incoming_requests::InitLinkResponse, services::Services},
+    common::{connection::Connection, data_bridge::DataBridge, multiplexer::MuxStream},
+    server::{
+        incoming_requests::InitLinkResponse,
+        services::{ClientLink, Services},
+    },
 };
 
 pub async fn start(
@@ -15,7 +18,7 @@ pub async fn start(
     mut response_stream: Connection,
     cancel_token: CancellationToken,
 ) {
-    let Some(mut client_link) = services
+    let Some(client_link) = services
         .get_client_manager()
         .await
         .take_client_link(&client_id)
@@ -32,11 +35,38 @@ pub async fn start(
         .respond_message(&InitLinkResponse::Accepted)
         .await;
 
-    if let Some(data) = client_link.initial_tunnel_data {
-        if let Err(e) = response_stream.write_all(&data).await {
-            debug!("Error writing initial tunnel data: {:?}", e);
-            return;
-        }
+    bridge_link(client_id, client_link, response_stream, cancel_token).await;
+}
+
+pub async fn start_multiplexed(
+    services: &Arc<Services>,
+    client_id: Uuid,
+    link_stream: MuxStream,
+    cancel_token: CancellationToken,
+) {
+    let Some(client_link) = services
+        .get_client_manager()
+        .await
+        .take_client_link(&client_id)
+    else {
+        debug!("Client {} not found for multiplexed link", client_id);
+        return;
+    };
+
+    bridge_link(client_id, client_link, link_stream.into(), cancel_token).await;
+}
+
+async fn bridge_link(
+    client_id: Uuid,
+    mut client_link: ClientLink,
+    mut response_stream: Connection,
+    cancel_token: CancellationToken,
+) {
+    if let Some(data) = client_link.initial_tunnel_data
+        && let Err(e) = response_stream.write_all(&data).await
+    {
+        debug!("Error writing initial tunnel data: {:?}", e);
+        return;
     }
 
     tokio::select! {
@@ -4,20 +4,22 @@ use super::messages::TunnelChannelRequest;
 use chrono::Utc;
 use log::{debug, info};
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedReceiver;
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use crate::{
     common::{
         channel::{create_channel, RequestReceiver, RequestSender},
-        connection::Connection,
-        transport::MessageError,
+        control_channel::{ControlChannel, IncomingRequest},
+        multiplexer::Multiplexer,
     },
     server::{
         endpoints::messages::ResolvedEndpointInfo,
-        incoming_requests::{self, ServerRequestMessage},
+        incoming_requests::{self, ProxyHealth, ServerRequestMessage},
         services::TunnelInfo,
         session::channel_handler,
+        tunnel_policy::TunnelAccess,
     },
 };
 
@@ -27,7 +29,10 @@ use super::super::services::Services;
 pub struct TunnelSession {
     id: Uuid,
     name: Option<String>,
+    access: TunnelAccess,
     proxies: Vec<TunnelProxyInfo>,
+    /// Proxies being registered with their endpoint, counted against `max_proxies`.
+    reserved_proxy_ids: Vec<Uuid>,
     channel_tx: RequestSender<TunnelChannelRequest>,
     cancel_token: CancellationToken,
     last_heartbeat_timestamp: i64,
@@ -37,13 +42,16 @@ impl TunnelSession {
     pub fn new(
         id: Uuid,
         name: Option<String>,
+        access: TunnelAccess,
         proxies: Vec<TunnelProxyInfo>,
         channel_tx: RequestSender<TunnelChannelRequest>,
     ) -> Self {
         Self {
             id,
             name,
+            access,
             proxies,
+            reserved_proxy_ids: Vec::new(),
             channel_tx,
             cancel_token: CancellationToken::new(),
             last_heartbeat_timestamp: Utc::now().timestamp(),
@@ -70,6 +78,10 @@ impl TunnelSession {
         self.id
     }
 
+    pub fn get_access(&self) -> &TunnelAccess {
+        &self.access
+    }
+
     pub fn update_heartbeat_timestamp(&mut self) {
         self.last_heartbeat_timestamp = Utc::now().timestamp();
     }
@@ -78,14 +90,72 @@ impl TunnelSession {
         let current_time = Utc::now().timestamp();
         current_time - self.last_heartbeat_timestamp > 300
     }
+
+    /// Reserves a slot for the proxy while it is being registered, so that concurrent requests
+    /// cannot go over `max_proxies` or register the same proxy twice.
+    pub fn reserve_proxy(&mut self, proxy_id: Uuid, max_proxies: usize) -> Result<(), String> {
+        if self.proxies.iter().any(|p| p.proxy_id == proxy_id)
+            || self.reserved_proxy_ids.contains(&proxy_id)
+        {
+            return Err(format!("Proxy '{proxy_id}' is already registered"));
+        }
+
+        if self.proxies.len() + self.reserved_proxy_ids.len() >= max_proxies {
+            return Err(format!(
+                "Too many proxies requested. Max allowed: {max_proxies}"
+            ));
+        }
+
+        self.reserved_proxy_ids.push(proxy_id);
+        Ok(())
+    }
+
+    pub fn release_proxy(&mut self, proxy_id: &Uuid) {
+        self.reserved_proxy_ids.retain(|id| id != proxy_id);
+    }
+
+    pub fn add_proxy(&mut self, proxy: TunnelProxyInfo) {
+        self.release_proxy(&proxy.proxy_id);
+        self.proxies.retain(|p| p.proxy_id != proxy.proxy_id);
+        self.proxies.push(proxy);
+    }
+
+    pub fn remove_proxy(&mut self, proxy_id: &Uuid) -> Option<TunnelProxyInfo> {
+        let index = self
+            .proxies
+            .iter()
+            .position(|proxy| &proxy.proxy_id == proxy_id)?;
+
+        Some(self.proxies.remove(index))
+    }
+
+    pub fn set_proxy_health(&mut self, proxy_id: &Uuid, health: ProxyHealth) -> bool {
+        let Some(proxy) = self.proxies.iter_mut().find(|p| &p.proxy_id == proxy_id) else {
+            return false;
+        };
+
+        proxy.health = health;
+        true
+    }
+
+    pub fn get_proxy_health(&self, proxy_id: &Uuid) -> Option<&ProxyHealth> {
+        self.proxies
+            .iter()
+            .find(|p| &p.proxy_id == proxy_id)
+            .map(|p| &p.health)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TunnelProxyInfo {
+    #[serde(default)]
+    pub proxy_id: Uuid,
     pub endpoint: String,
     pub forward_address: String,
     pub forward_port: u16,
     pub details: ResolvedEndpointInfo,
+    #[serde(default)]
+    pub health: ProxyHealth,
 }
 
 impl From<&TunnelSession> for TunnelInfo {
@@ -93,6 +163,8 @@ impl From<&TunnelSession> for TunnelInfo {
         TunnelInfo {
             id: val.id,
             name: val.name.clone(),
+            credential: val.access.credential.clone(),
+            identity: val.access.identity.clone(),
             proxies: val.proxies.clone(),
             last_heartbeat_timestamp: val.last_heartbeat_timestamp,
         }
@@ -102,76 +174,107 @@ impl From<&TunnelSession> for TunnelInfo {
 pub fn create(
     id: Uuid,
     name: Option<String>,
+    access: TunnelAccess,
     proxies: Vec<TunnelProxyInfo>,
 ) -> (TunnelSession, RequestReceiver<TunnelChannelRequest>) {
     let (channel_tx, channel_rx) = create_channel::<TunnelChannelRequest>();
 
     (
-        TunnelSession::new(id, name, proxies, channel_tx),
+        TunnelSession::new(id, name, access, proxies, channel_tx),
         channel_rx,
     )
 }
 
+pub enum TunnelSessionEnd {
+    Cancelled,
+    ConnectionLost,
+}
+
 pub async fn start(
     services: Arc<Services>,
     session: TunnelSession,
-    mut stream: Connection,
+    control: ControlChannel,
+    mut requests: UnboundedReceiver<IncomingRequest>,
+    multiplexer: Option<Multiplexer>,
     mut channel_rx: RequestReceiver<TunnelChannelRequest>,
-) {
+) -> TunnelSessionEnd {
     let id = session.get_id();
 
-    loop {
+    let session_end = loop {
         tokio::select! {
             _ = session.wait_for_cancellation() => {
                 info!("Tunnel {} session has been cancelled.", id);
-                break;
+                break TunnelSessionEnd::Cancelled;
             }
             data = channel_rx.wait_for_requests() => {
 
                 let Some(message) = data else {
                     session.cancel();
-                    break;
+                    break TunnelSessionEnd::Cancelled;
                 };
 
-
-                channel_handler::handle(&services, &session, &mut stream, message).await;
+                tokio::spawn(channel_handler::handle(
+                    services.clone(),
+                    session.clone(),
+                    control.clone(),
+                    multiplexer.clone(),
+                    message,
+                ));
             },
-            result = stream.read_message::<ServerRequestMessage>() => {
-                match result {
-                    Ok(message) => {
-                        match message {
-                            ServerRequestMessage::HeartbeatRequest(request) => {
-                                incoming_requests::process_heartbeat_request(
-                                    &services,
-                                    request,
-                                    &mut stream
-                                ).await;
-                            },
-                            _ => debug!("Received unexpected message from client: {:?}", message)
-                        }
+            request = requests.recv() => {
+                let Some(request) = request else {
+                    debug!("Tunnel Connection closed.");
+                    break TunnelSessionEnd::ConnectionLost;
+                };
+
+                match request.decode::<ServerRequestMessage>() {
+                    Ok(ServerRequestMessage::HeartbeatRequest(heartbeat)) => {
+                        request.responder.respond(
+                            &incoming_requests::process_heartbeat_request(&services, heartbeat).await,
+                        );
+                    },
+                    Ok(ServerRequestMessage::AddTunnelProxyRequest(add_request)) => {
+                        let services = services.clone();
+                        tokio::spawn(async move {
+                            request.responder.respond(
+                                &incoming_requests::process_add_tunnel_proxy(&services, id, add_request).await,
+                            );
+                        });
                     },
-                    Err(MessageError::ConnectionClosed) => {
-                        debug!("Tunnel Connection closed.");
-                        session.cancel();
+                    Ok(ServerRequestMessage::RemoveTunnelProxyRequest(remove_request)) => {
+                        let services = services.clone();
+                        tokio::spawn(async move {
+                            request.responder.respond(
+                                &incoming_requests::process_remove_tunnel_proxy(&services, id, remove_request).await,
+                            );
+                        });
                     },
-                    Err(e) => {
-                        info!("Failed to read message from client: {}", e);
-                        session.cancel();
-                        break;
-                    }
+                    Ok(ServerRequestMessage::UpdateProxyHealthRequest(health_request)) => {
+                        request.responder.respond(
+                            &incoming_requests::process_update_proxy_health(&services, id, health_request).await,
+                        );
+                    },
+                    Ok(message) => debug!("Received unexpected message from client: {:?}", message),
+                    Err(e) => info!("Failed to read message from client: {}", e),
                 }
             }
             _ = tokio::time::sleep(tokio::time::Duration::from_secs(30)) => {
                 let manager = services.get_tunnel_manager().await;
 
                 if manager.is_tunnel_stale(&id) {
-                    info!("Tunnel {} connection is stale (no heartbeat updated received in over 5 minutes), cancelling session.", id);
-                    session.cancel();
+                    info!("Tunnel {} connection is stale (no heartbeat updated received in over 5 minutes), ending session.", id);
+                    break TunnelSessionEnd::ConnectionLost;
                 }
             }
         }
-    }
+    };
 
-    stream.shutdown().await;
+    control.shutdown();
     channel_rx.close();
+
+    if let Some(multiplexer) = multiplexer {
+        multiplexer.shutdown();
+    }
+
+    session_end
 }
@@ -0,0 +1,406 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    common::{
+        validate::{Validatable, Validation},
+        validate_rules::{AlphaNumericOnly, MustBeGreaterThanZero, MustNotBeEmptyString},
+    },
+    tunnel::configuration::ProxyConfiguration,
+};
+
+use super::tunnel_token::TunnelToken;
+
+/// Named tunnel key with its own access policy.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TunnelCredential {
+    pub name: String,
+
+    pub key: String,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub policy: Option<TunnelPolicy>,
+}
+
+impl TunnelCredential {
+    pub fn get_policy(&self) -> TunnelPolicy {
+        self.policy.clone().unwrap_or_default()
+    }
+}
+
+impl Validatable for TunnelCredential {
+    fn validate(&self, result: &mut Validation) {
+        result.validate_rule::<MustNotBeEmptyString>("name", &self.name);
+        result.validate_rule::<MustNotBeEmptyString>("key", &self.key);
+
+        if let Some(policy) = &self.policy {
+            result.validate_child("policy", policy);
+        }
+    }
+}
+
+/// Tunnel identity given to clients which present a certificate signed by the server
+/// `client_ca_path`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClientCertificate {
+    /// Matched against the subject common name and alternative names of the certificate. Can
+    /// use `*` to match any number of characters.
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub policy: Option<TunnelPolicy>,
+}
+
+impl ClientCertificate {
+    pub fn get_policy(&self) -> TunnelPolicy {
+        self.policy.clone().unwrap_or_default()
+    }
+
+    pub fn find_matching_name<'a>(&self, names: &'a [String]) -> Option<&'a String> {
+        names.iter().find(|name| matches_pattern(&self.name, name))
+    }
+}
+
+impl Validatable for ClientCertificate {
+    fn validate(&self, result: &mut Validation) {
+        result.validate_rule::<MustNotBeEmptyString>("name", &self.name);
+
+        if let Some(policy) = &self.policy {
+            result.validate_child("policy", policy);
+        }
+    }
+}
+
+/// Restrictions applied to tunnels connecting with a credential. Restrictions which are not set
+/// allow everything the server itself allows.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct TunnelPolicy {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub allowed_endpoints: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_proxies: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_tunnels: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub allowed_hostnames: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub allowed_ports: Option<Vec<PortRange>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PortRange {
+    pub from: u16,
+    pub to: u16,
+}
+
+impl PortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        self.from <= port && port <= self.to
+    }
+}
+
+impl TunnelPolicy {
+    pub fn get_max_proxies(&self, server_max_proxies: usize) -> usize {
+        self.max_proxies
+            .map_or(server_max_proxies, |max| max.min(server_max_proxies))
+    }
+
+    pub fn is_endpoint_allowed(&self, endpoint_name: &str) -> bool {
+        self.allowed_endpoints
+            .as_ref()
+            .is_none_or(|endpoints| endpoints.iter().any(|name| name == endpoint_name))
+    }
+
+    pub fn has_hostname_restriction(&self) -> bool {
+        self.allowed_hostnames.is_some()
+    }
+
+    /// Checks the name which replaces `{name}` in the hostname template against allowed
+    /// hostnames. Patterns can use `*` to match any number of characters.
+    pub fn is_hostname_allowed(&self, name: &str) -> bool {
+        self.allowed_hostnames.as_ref().is_none_or(|patterns| {
+            patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, name))
+        })
+    }
+
+    pub fn is_port_allowed(&self, port: u16) -> bool {
+        self.allowed_ports
+            .as_ref()
+            .is_none_or(|ranges| ranges.iter().any(|range| range.contains(port)))
+    }
+
+    /// Checks the parts of the requested proxy which can be checked before it reaches the
+    /// endpoint. Assigned hostnames and ports are checked by the endpoint itself.
+    pub fn check_proxy(
+        &self,
+        endpoint_name: &str,
+        proxy: &ProxyConfiguration,
+    ) -> Result<(), String> {
+        if !self.is_endpoint_allowed(endpoint_name) {
+            return Err(format!(
+                "Endpoint '{endpoint_name}' is not allowed for this tunnel key"
+            ));
+        }
+
+        match proxy {
+            ProxyConfiguration::Http { desired_name, pool } if self.has_hostname_restriction() => {
+                let Some(name) = pool.as_ref().or(desired_name.as_ref()) else {
+                    return Err(format!(
+                        "Proxy for endpoint '{endpoint_name}' must request an allowed hostname"
+                    ));
+                };
+
+                if !self.is_hostname_allowed(name) {
+                    return Err(format!(
+                        "Hostname '{name}' is not allowed for this tunnel key"
+                    ));
+                }
+            }
+            ProxyConfiguration::Tcp {
+                desired_port: Some(port),
+                ..
+            }
+            | ProxyConfiguration::Udp {
+                desired_port: Some(port),
+                ..
+            } if !self.is_port_allowed(*port) => {
+                return Err(format!("Port {port} is not allowed for this tunnel key"));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+
+    let Some(mut rest) = name.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        let Some(index) = rest.find(part) else {
+            return false;
+        };
+
+        rest = &rest[index + part.len()..];
+    }
+
+    rest.ends_with(last)
+}
+
+impl Validatable for TunnelPolicy {
+    fn validate(&self, result: &mut Validation) {
+        if let Some(endpoints) = &self.allowed_endpoints {
+            for (index, name) in endpoints.iter().enumerate() {
+                result.validate_rule::<MustNotBeEmptyString>(
+                    &format!("allowed_endpoints.{index}"),
+                    name,
+                );
+            }
+        }
+
+        if let Some(max_proxies) = &self.max_proxies {
+            result.validate_rule_for::<_, MustBeGreaterThanZero>("max_proxies", max_proxies);
+        }
+
+        if let Some(max_tunnels) = &self.max_tunnels {
+            result.validate_rule_for::<_, MustBeGreaterThanZero>("max_tunnels", max_tunnels);
+        }
+
+        if let Some(hostnames) = &self.allowed_hostnames {
+            for (index, pattern) in hostnames.iter().enumerate() {
+                let field = format!("allowed_hostnames.{index}");
+                result.validate_rule::<MustNotBeEmptyString>(&field, pattern);
+                result.validate_rule::<AlphaNumericOnly>(&field, &pattern.replace('*', ""));
+            }
+        }
+
+        if let Some(ranges) = &self.allowed_ports {
+            for (index, range) in ranges.iter().enumerate() {
+                result.validate_child(&format!("allowed_ports.{index}"), range);
+            }
+        }
+    }
+}
+
+impl Validatable for PortRange {
+    fn validate(&self, result: &mut Validation) {
+        if self.from == 0 {
+            result.add_field_error("from", "Port cannot be zero.");
+        }
+
+        if self.from > self.to {
+            result.add_field_error("from", "from must be less than to, range is reversed.");
+        }
+    }
+}
+
+/// Access given to a connected tunnel, based on the key it connected with.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TunnelAccess {
+    /// Name of the credential used, `None` if the tunnel connected without one.
+    pub credential: Option<String>,
+    /// Identity from the token, client certificate, public key or auth hook the tunnel
+    /// connected with.
+    pub identity: Option<String>,
+    pub token_id: Option<Uuid>,
+    pub policy: TunnelPolicy,
+    /// Set only for the server tunnel key, monitor key or a server without keys.
+    pub unrestricted: bool,
+}
+
+impl TunnelAccess {
+    pub fn unrestricted() -> Self {
+        Self {
+            unrestricted: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn for_credential(credential: &TunnelCredential) -> Self {
+        Self {
+            credential: Some(credential.name.clone()),
+            policy: credential.get_policy(),
+            ..Default::default()
+        }
+    }
+
+    pub fn for_identity(name: &str, policy: TunnelPolicy) -> Self {
+        Self {
+            identity: Some(name.to_owned()),
+            policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn for_token(token: &TunnelToken) -> Self {
+        Self {
+            identity: Some(token.identity.clone()),
+            token_id: Some(token.id),
+            policy: token.policy.clone().unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Access given by the server tunnel key, monitor key or a server without keys. Only this
+    /// access can run monitor commands, since they affect tunnels of everyone.
+    pub fn is_unrestricted(&self) -> bool {
+        self.unrestricted
+    }
+
+    /// Tunnels connected with the same credential or identity share `max_tunnels`.
+    pub fn has_same_owner(&self, other: &TunnelAccess) -> bool {
+        self.unrestricted == other.unrestricted
+            && self.credential == other.credential
+            && self.identity == other.identity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_policy(value: serde_json::Value) -> TunnelPolicy {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("app", "app"));
+        assert!(!matches_pattern("app", "app2"));
+        assert!(matches_pattern("team-*", "team-a"));
+        assert!(matches_pattern("team-*", "team-"));
+        assert!(!matches_pattern("team-*", "other"));
+        assert!(matches_pattern("*-dev", "api-dev"));
+        assert!(matches_pattern("a*b*c", "a-x-b-y-c"));
+        assert!(!matches_pattern("a*b*c", "a-c"));
+        assert!(!matches_pattern("ab*ba", "aba"));
+        assert!(matches_pattern("*", "anything"));
+    }
+
+    #[test]
+    fn test_default_policy_allows_everything() {
+        let policy = TunnelPolicy::default();
+
+        assert!(policy.is_endpoint_allowed("http"));
+        assert!(policy.is_hostname_allowed("app"));
+        assert!(policy.is_port_allowed(1));
+        assert_eq!(policy.get_max_proxies(10), 10);
+    }
+
+    #[test]
+    fn test_check_proxy() {
+        let policy = create_policy(serde_json::json!({
+            "allowed_endpoints": ["http", "tcp"],
+            "max_proxies": 20,
+            "allowed_hostnames": ["team-*"],
+            "allowed_ports": [{ "from": 4000, "to": 4010 }]
+        }));
+
+        let http = |desired_name: Option<&str>, pool: Option<&str>| ProxyConfiguration::Http {
+            desired_name: desired_name.map(|name| name.to_owned()),
+            pool: pool.map(|name| name.to_owned()),
+        };
+        let tcp = |desired_port: Option<u16>| ProxyConfiguration::Tcp {
+            desired_port,
+            pool: None,
+        };
+
+        assert_eq!(policy.get_max_proxies(10), 10);
+        assert!(
+            policy
+                .check_proxy("http", &http(Some("team-a"), None))
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_proxy("http", &http(None, Some("team-b")))
+                .is_ok()
+        );
+        assert!(policy.check_proxy("http", &http(None, None)).is_err());
+        assert!(
+            policy
+                .check_proxy("http", &http(Some("other"), None))
+                .is_err()
+        );
+        assert!(policy.check_proxy("tcp", &tcp(Some(4005))).is_ok());
+        assert!(policy.check_proxy("tcp", &tcp(None)).is_ok());
+        assert!(policy.check_proxy("tcp", &tcp(Some(5000))).is_err());
+        assert!(policy.check_proxy("udp", &tcp(None)).is_err());
+    }
+
+    #[test]
+    fn test_validate_policy() {
+        let policy = create_policy(serde_json::json!({
+            "max_tunnels": 0,
+            "allowed_hostnames": ["team-*", "a.b"],
+            "allowed_ports": [{ "from": 5000, "to": 4000 }]
+        }));
+
+        let validation = Validation::validate(&policy);
+
+        assert_eq!(
+            validation.errors(),
+            &vec![
+                "max_tunnels: Value must be greater than zero.",
+                "allowed_hostnames.1: Can only contain alphanumeric characters and hyphens.",
+                "allowed_ports.0.from: from must be less than to, range is reversed.",
+            ]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use aws_lc_rs::hmac;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::tunnel_policy::TunnelPolicy;
+
+const TOKEN_PREFIX: &str = "tzt1.";
+
+/// Token issued by the server which gives tunnel access until it expires. Token is signed with
+/// the server `token_secret`, so it is validated without the server keeping any state.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TunnelToken {
+    pub id: Uuid,
+    pub identity: String,
+    pub expires_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub policy: Option<TunnelPolicy>,
+}
+
+impl TunnelToken {
+    pub fn new(identity: String, expires_at: i64, policy: Option<TunnelPolicy>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            identity,
+            expires_at,
+            policy,
+        }
+    }
+
+    pub fn is_token(text: &str) -> bool {
+        text.starts_with(TOKEN_PREFIX)
+    }
+
+    pub fn encode(&self, secret: &str) -> Result<String, String> {
+        let payload = serde_json::to_vec(self).map_err(|e| e.to_string())?;
+        let signed = format!("{TOKEN_PREFIX}{}", URL_SAFE_NO_PAD.encode(payload));
+        let signature = hmac::sign(&get_key(secret), signed.as_bytes());
+
+        Ok(format!(
+            "{signed}.{}",
+            URL_SAFE_NO_PAD.encode(signature.as_ref())
+        ))
+    }
+
+    /// Checks the signature and expiry of the token and returns its contents.
+    pub fn decode(text: &str, secret: &str, now: i64) -> Result<Self, String> {
+        if !Self::is_token(text) {
+            return Err("Not a tunnel token".to_owned());
+        }
+
+        let Some((signed, signature)) = text.rsplit_once('.') else {
+            return Err("Token is not signed".to_owned());
+        };
+
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| "Token signature is invalid".to_owned())?;
+
+        hmac::verify(&get_key(secret), signed.as_bytes(), &signature)
+            .map_err(|_| "Token signature is invalid".to_owned())?;
+
+        let payload = URL_SAFE_NO_PAD
+            .decode(&signed[TOKEN_PREFIX.len()..])
+            .map_err(|_| "Token payload is invalid".to_owned())?;
+
+        let token: TunnelToken = serde_json::from_slice(&payload)
+            .map_err(|e| format!("Token payload is invalid: {e}"))?;
+
+        if token.expires_at <= now {
+            return Err(format!("Token '{}' has expired", token.id));
+        }
+
+        Ok(token)
+    }
+}
+
+fn get_key(secret: &str) -> hmac::Key {
+    hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn test_encode_and_decode() {
+        let token = TunnelToken::new(
+            "alice".to_owned(),
+            2000,
+            Some(TunnelPolicy {
+                max_proxies: Some(1),
+                ..Default::default()
+            }),
+        );
+
+        let text = token.encode(SECRET).unwrap();
+
+        assert!(TunnelToken::is_token(&text));
+        assert_eq!(TunnelToken::decode(&text, SECRET, 1000).unwrap(), token);
+        assert!(TunnelToken::decode(&text, SECRET, 2000).is_err());
+        assert!(TunnelToken::decode(&text, "other-secret", 1000).is_err());
+    }
+
+    #[test]
+    fn test_decode_tampered_token() {
+        let text = TunnelToken::new("alice".to_owned(), 2000, None)
+            .encode(SECRET)
+            .unwrap();
+
+        let forged = TunnelToken::new("admin".to_owned(), 2000, None);
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged).unwrap());
+        let (_, signature) = text.rsplit_once('.').unwrap();
+
+        let tampered = format!("{TOKEN_PREFIX}{forged_payload}.{signature}");
+
+        assert!(TunnelToken::decode(&tampered, SECRET, 1000).is_err());
+        assert!(TunnelToken::decode("tzt1.abc", SECRET, 1000).is_err());
+        assert!(TunnelToken::decode("plain-key", SECRET, 1000).is_err());
+    }
+}
@@ -1,60 +1,215 @@
 use std::sync::Arc;
 
-use log::{debug, error};
-use tokio::io::Result;
+use log::{debug, error, info};
+use tokio::io::{self, Result};
 use tokio_util::sync::CancellationToken;
 
-use crate::common::transport::MessageError;
+use crate::common::connection::Connection;
+use crate::common::control_channel::ControlChannel;
+use crate::common::multiplexer::Multiplexer;
 use crate::tunnel::incoming_requests;
 use crate::tunnel::incoming_requests::TunnelRequestMessage;
 use crate::tunnel::outgoing_requests;
 
+use super::configuration::ReconnectConfiguration;
 use super::services::Services;
 
 pub async fn start(services: Arc<Services>, cancel_token: CancellationToken) -> Result<()> {
+    let reconnect = services.get_config().get_reconnect();
+
+    let mut connection_stream = connect(&services).await?;
+
+    loop {
+        let result = run_session(&services, connection_stream, &cancel_token).await;
+
+        services.get_tunnel_data().await.clear_tunnel_id();
+        services.get_proxy_manager().await.clear_endpoint_info();
+        services.notify_status_changed();
+
+        let Err(e) = result else {
+            return Ok(());
+        };
+
+        services.print(format!("Connection to server lost: {}", e));
+
+        services
+            .get_tunnel_data()
+            .await
+            .record_error(format!("Connection to server lost: {e}"));
+
+        if !reconnect.is_enabled() {
+            cancel_token.cancel();
+            return Err(e);
+        }
+
+        match reconnect_with_backoff(&services, &reconnect, &cancel_token).await {
+            Some(connection) => connection_stream = connection,
+            None if cancel_token.is_cancelled() => return Ok(()),
+            None => {
+                cancel_token.cancel();
+                return Err(e);
+            }
+        }
+    }
+}
+
+async fn connect(services: &Arc<Services>) -> Result<Connection> {
     let config = services.get_config();
 
     let mut connection_stream = config.create_tcp_client().await?;
 
-    if let Err(e) =
-        outgoing_requests::authenticate_tunnel(&services, &config, &mut connection_stream).await
-    {
-        error!("Failed to authenticate: {}", e);
-        return Err(e);
+    let is_multiplexed =
+        match outgoing_requests::authenticate_tunnel(services, &config, &mut connection_stream)
+            .await
+        {
+            Ok(is_multiplexed) => is_multiplexed,
+            Err(e) => {
+                error!("Failed to authenticate: {}", e);
+                services
+                    .get_tunnel_data()
+                    .await
+                    .record_error(format!("Failed to authenticate: {e}"));
+                connection_stream.shutdown().await;
+                return Err(e);
+            }
+        };
+
+    if !is_multiplexed {
+        if config.get_multiplex_links() {
+            info!("Server does not allow multiplexed links, using a connection per link.");
+        }
+
+        return Ok(connection_stream);
     }
 
-    loop {
+    let (multiplexer, control_stream) = Multiplexer::start(connection_stream)?;
+
+    services
+        .get_tunnel_data()
+        .await
+        .set_multiplexer(multiplexer);
+
+    Ok(control_stream.into())
+}
+
+async fn reconnect_with_backoff(
+    services: &Arc<Services>,
+    reconnect: &ReconnectConfiguration,
+    cancel_token: &CancellationToken,
+) -> Option<Connection> {
+    let mut attempt = 1;
+
+    while reconnect.has_attempts_left(attempt) {
+        let delay = reconnect.get_delay(attempt);
+
+        info!("Reconnect attempt {} in {:?}", attempt, delay);
+        services.print(format!(
+            "Reconnecting in {:.1}s (attempt {})...",
+            delay.as_secs_f64(),
+            attempt
+        ));
+
         tokio::select! {
             _ = cancel_token.cancelled() => {
+                debug!("Reconnect cancelled.");
+                return None;
+            }
+            _ = tokio::time::sleep(delay) => {}
+        }
 
-                debug!("Ending tunnel...");
-                connection_stream.shutdown().await;
-                debug!("Hub server stopped.");
-
-                return Ok(());
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                debug!("Reconnect cancelled.");
+                return None;
             }
-            result = connection_stream.read_message::<TunnelRequestMessage>() => {
+            result = connect(services) => {
                 match result {
+                    Ok(connection) => {
+                        info!("Reconnected to server after {} attempt(s).", attempt);
+                        services.print("Reconnected to server.");
+                        return Some(connection);
+                    }
+                    Err(e) => {
+                        info!("Reconnect attempt {} failed: {}", attempt, e);
+                        services.print(format!("Reconnect attempt {} failed: {}", attempt, e));
+                    }
+                }
+            }
+        }
+
+        attempt += 1;
+    }
+
+    services.print(format!(
+        "Giving up on reconnecting after {} attempt(s).",
+        attempt - 1
+    ));
+
+    None
+}
+
+async fn run_session(
+    services: &Arc<Services>,
+    connection_stream: Connection,
+    cancel_token: &CancellationToken,
+) -> Result<()> {
+    let (control, mut requests) = ControlChannel::start(connection_stream)?;
+
+    services
+        .get_tunnel_data()
+        .await
+        .set_control(control.clone());
+
+    let mut heartbeats = tokio::spawn(send_heartbeats(services.clone(), control.clone()));
+
+    let result = loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                debug!("Ending tunnel...");
+                break Ok(());
+            }
+            request = requests.recv() => {
+                let Some(request) = request else {
+                    debug!("Connection closed.");
+                    break Err(io::Error::new(
+                        io::ErrorKind::ConnectionAborted,
+                        "Connection closed by server.",
+                    ));
+                };
+
+                match request.decode::<TunnelRequestMessage>() {
                     Ok(message) => {
-                        incoming_requests::handle(&services, &mut connection_stream, message).await;
-                    },
-                    Err(MessageError::ConnectionClosed) => {
-                        debug!("Connection closed.");
-                        cancel_token.cancel();
-                    },
+                        tokio::spawn(incoming_requests::handle(
+                            services.clone(),
+                            request.responder,
+                            message,
+                        ));
+                    }
                     Err(e) => {
                         error!("Failed to read message from server: {}", e);
-                        continue;
                     }
                 }
             }
-            _ = tokio::time::sleep(tokio::time::Duration::from_secs(30)) => {
-                if let Err(e) = outgoing_requests::send_heartbeat(&services, &mut connection_stream).await {
-                    error!("Failed to send heartbeat: {}", e);
-                    cancel_token.cancel();
-                    return Err(e);
-                }
+            result = &mut heartbeats => {
+                break Err(result.unwrap_or_else(io::Error::other));
             }
         }
+    };
+
+    heartbeats.abort();
+    control.shutdown();
+    debug!("Hub server stopped.");
+
+    result
+}
+
+async fn send_heartbeats(services: Arc<Services>, control: ControlChannel) -> io::Error {
+    loop {
+        tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+
+        if let Err(e) = outgoing_requests::send_heartbeat(&services, &control).await {
+            error!("Failed to send heartbeat: {}", e);
+            return e;
+        }
     }
 }
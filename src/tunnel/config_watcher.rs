@@ -0,0 +1,211 @@
+use std::{path::Path, sync::Arc, time::SystemTime};
+
+use log::{debug, error, info};
+use tokio::time::{Duration, sleep};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::configuration::{load_tunnel_configuration, resolve_configuration_path};
+
+use super::{
+    configuration::TunnelProxy,
+    outgoing_requests,
+    services::{Proxy, Services},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Watches the configuration file and applies proxy additions and removals to the running tunnel.
+/// Other changes in the configuration require a restart.
+pub async fn start(
+    services: Arc<Services>,
+    configuration_file: Option<String>,
+    profile: Option<String>,
+    cancel_token: CancellationToken,
+) {
+    let config_path = match resolve_configuration_path(configuration_file) {
+        Ok(path) => path,
+        Err(e) => {
+            error!("Failed to resolve configuration path: {}", e);
+            return;
+        }
+    };
+
+    let mut last_modified = get_modified_time(&config_path);
+
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                debug!("Configuration watcher stopped.");
+                return;
+            }
+            _ = sleep(POLL_INTERVAL) => {}
+        }
+
+        let modified = get_modified_time(&config_path);
+
+        if modified == last_modified {
+            continue;
+        }
+
+        last_modified = modified;
+
+        info!("Configuration file changed, reloading proxies.");
+
+        let config = match load_tunnel_configuration(
+            Some(config_path.to_string_lossy().into_owned()),
+            profile.as_deref(),
+        ) {
+            Ok(config) => config,
+            Err(e) => {
+                error!(
+                    "Failed to reload configuration, keeping current proxies: {}",
+                    e
+                );
+                continue;
+            }
+        };
+
+        apply_proxy_changes(&services, &config.proxies).await;
+    }
+}
+
+fn get_modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+async fn apply_proxy_changes(services: &Arc<Services>, desired: &[TunnelProxy]) {
+    // Running proxies are read again each time, since the control socket can add or remove them.
+    let (removed, added) = diff_proxies(&services.get_proxy_manager().await.get_proxies(), desired);
+
+    if removed.is_empty() && added.is_empty() {
+        debug!("No proxy changes found in configuration.");
+        return;
+    }
+
+    for proxy_id in removed {
+        if let Err(e) = outgoing_requests::remove_proxy(services, &proxy_id).await {
+            error!("Failed to remove proxy '{}': {}", proxy_id, e);
+        }
+    }
+
+    for proxy in added {
+        if let Err(e) = outgoing_requests::add_proxy(services, &proxy).await {
+            services.print(format!(
+                "Failed to add proxy {}:{} to endpoint '{}': {}",
+                proxy.address, proxy.port, proxy.endpoint_name, e
+            ));
+        }
+    }
+}
+
+/// Returns IDs of the current proxies which are no longer desired and desired proxies which do not exist yet.
+fn diff_proxies(
+    current: &[(Uuid, &Proxy)],
+    desired: &[TunnelProxy],
+) -> (Vec<Uuid>, Vec<TunnelProxy>) {
+    let mut unmatched: Vec<&(Uuid, &Proxy)> = current.iter().collect();
+    let mut added: Vec<TunnelProxy> = Vec::new();
+
+    for proxy in desired {
+        match unmatched.iter().position(|(_, p)| p.matches(proxy)) {
+            Some(index) => {
+                unmatched.remove(index);
+            }
+            None => added.push(proxy.clone()),
+        }
+    }
+
+    let removed = unmatched.into_iter().map(|(id, _)| *id).collect();
+
+    (removed, added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tunnel::{configuration::ProxyConfiguration, services::ProxyManager};
+
+    fn create_proxy(port: u16) -> TunnelProxy {
+        TunnelProxy {
+            endpoint_name: "http".to_string(),
+            address: "localhost".to_string(),
+            port,
+            endpoint_config: ProxyConfiguration::Http {
+                desired_name: None,
+                pool: None,
+            },
+            tls: None,
+            health_check: None,
+            targets: None,
+            target_selection: None,
+            target_cooldown_seconds: None,
+        }
+    }
+
+    fn create_proxy_manager(proxies: &[TunnelProxy]) -> (ProxyManager, Vec<Uuid>) {
+        let mut proxy_manager = ProxyManager::new();
+        let ids = proxies
+            .iter()
+            .map(|proxy| proxy_manager.add_proxy(proxy).unwrap())
+            .collect();
+
+        (proxy_manager, ids)
+    }
+
+    #[test]
+    fn test_diff_proxies_no_changes() {
+        let (proxy_manager, _) = create_proxy_manager(&[create_proxy(3000)]);
+
+        let (removed, added) = diff_proxies(&proxy_manager.get_proxies(), &[create_proxy(3000)]);
+
+        assert!(removed.is_empty());
+        assert!(added.is_empty());
+    }
+
+    #[test]
+    fn test_diff_proxies_added_and_removed() {
+        let (proxy_manager, ids) = create_proxy_manager(&[create_proxy(3000), create_proxy(4000)]);
+
+        let (removed, added) = diff_proxies(
+            &proxy_manager.get_proxies(),
+            &[create_proxy(3000), create_proxy(5000)],
+        );
+
+        assert_eq!(removed, vec![ids[1]]);
+        assert_eq!(added, vec![create_proxy(5000)]);
+    }
+
+    #[test]
+    fn test_diff_proxies_duplicates() {
+        let (proxy_manager, _) = create_proxy_manager(&[create_proxy(3000)]);
+
+        let (removed, added) = diff_proxies(
+            &proxy_manager.get_proxies(),
+            &[create_proxy(3000), create_proxy(3000)],
+        );
+
+        assert!(removed.is_empty());
+        assert_eq!(added, vec![create_proxy(3000)]);
+    }
+
+    #[test]
+    fn test_diff_proxies_changed_proxy() {
+        let (proxy_manager, ids) = create_proxy_manager(&[create_proxy(3000)]);
+        let changed = TunnelProxy {
+            endpoint_config: ProxyConfiguration::Http {
+                desired_name: Some("app".to_string()),
+                pool: None,
+            },
+            ..create_proxy(3000)
+        };
+
+        let (removed, added) =
+            diff_proxies(&proxy_manager.get_proxies(), std::slice::from_ref(&changed));
+
+        assert_eq!(removed, vec![ids[0]]);
+        assert_eq!(added, vec![changed]);
+    }
+}
@@ -1,3 +1,9 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -6,8 +12,8 @@ use crate::{
         tcp_client::{create_tcp_client, ClientEncryption},
         validate::{Validatable, Validation},
         validate_rules::{
-            AlphaNumericOnly, HostAddressMustBeValid, IpAddressMustBeValid, MustNotBeEmptyString,
-            PortMustBeValid,
+            AlphaNumericOnly, FileMustExist, HostAddressMustBeValid, IpAddressMustBeValid,
+            MustBeGreaterThanZero, MustNotBeEmptyString, PortMustBeValid,
         },
     },
     configuration::TunnelizeConfiguration,
@@ -35,6 +41,30 @@ pub struct TunnelConfiguration {
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub monitor_key: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub private_key_path: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub reconnect: Option<ReconnectConfiguration>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub multiplex_links: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub watch_config: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub control_socket: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub dashboard: Option<DashboardConfiguration>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub inspector: Option<InspectorConfiguration>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub status_file: Option<String>,
+
     pub proxies: Vec<TunnelProxy>,
 }
 
@@ -59,6 +89,266 @@ impl TunnelConfiguration {
     pub fn get_encryption(&self) -> ClientEncryption {
         self.encryption.clone().unwrap_or(ClientEncryption::None)
     }
+
+    pub fn get_reconnect(&self) -> ReconnectConfiguration {
+        self.reconnect.clone().unwrap_or_default()
+    }
+
+    pub fn get_multiplex_links(&self) -> bool {
+        self.multiplex_links.unwrap_or(false)
+    }
+
+    pub fn get_watch_config(&self) -> bool {
+        self.watch_config.unwrap_or(true)
+    }
+
+    pub fn get_control_socket_path(&self) -> PathBuf {
+        if let Some(path) = &self.control_socket {
+            return PathBuf::from(path);
+        }
+
+        let name: String = self
+            .name
+            .as_deref()
+            .unwrap_or("tunnel")
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+
+        get_runtime_dir().join(format!("tunnelize-{name}.sock"))
+    }
+
+    /// Returns configuration with all optional values which have a default set to that default.
+    /// Dashboard, inspector and status file are left as they are, as they are off when not set.
+    pub fn with_defaults(&self) -> Self {
+        Self {
+            server_port: Some(self.get_server_port()),
+            forward_connection_timeout_seconds: Some(self.get_forward_connection_timeout_seconds()),
+            encryption: Some(self.get_encryption()),
+            reconnect: Some(self.get_reconnect().with_defaults()),
+            multiplex_links: Some(self.get_multiplex_links()),
+            watch_config: Some(self.get_watch_config()),
+            control_socket: Some(self.get_control_socket_path().display().to_string()),
+            dashboard: self.dashboard.as_ref().map(|d| d.with_defaults()),
+            inspector: self.inspector.as_ref().map(|i| i.with_defaults()),
+            proxies: self.proxies.iter().map(|p| p.with_defaults()).collect(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Directory only the current user can access. Uses `XDG_RUNTIME_DIR` when set, otherwise a
+/// `tunnelize-{uid}` directory in the temp directory, which the control socket creates with
+/// 0700 permissions.
+pub fn get_runtime_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from)
+        && dir.is_absolute()
+    {
+        return dir;
+    }
+
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    #[cfg(unix)]
+    let user = unsafe { libc::geteuid() }.to_string();
+
+    #[cfg(not(unix))]
+    let user = std::env::var("USERNAME").unwrap_or_default();
+
+    std::env::temp_dir().join(format!("tunnelize-{user}"))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DashboardConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub address: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub port: Option<u16>,
+}
+
+impl DashboardConfiguration {
+    pub fn get_address(&self) -> String {
+        self.address
+            .clone()
+            .unwrap_or_else(|| "127.0.0.1".to_string())
+    }
+
+    pub fn get_port(&self) -> u16 {
+        self.port.unwrap_or(3457)
+    }
+
+    pub fn get_bind_address(&self) -> Option<SocketAddr> {
+        let ip = self.get_address().parse::<IpAddr>().ok()?;
+
+        Some(SocketAddr::new(ip, self.get_port()))
+    }
+
+    pub fn with_defaults(&self) -> Self {
+        Self {
+            address: Some(self.get_address()),
+            port: Some(self.get_port()),
+        }
+    }
+}
+
+impl Validatable for DashboardConfiguration {
+    fn validate(&self, result: &mut Validation) {
+        result.validate_rule::<IpAddressMustBeValid>("address", &self.get_address());
+        result.validate_rule::<PortMustBeValid>("port", &self.get_port());
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct InspectorConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_requests: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_body_size: Option<usize>,
+}
+
+impl InspectorConfiguration {
+    pub fn get_max_requests(&self) -> usize {
+        self.max_requests.unwrap_or(100)
+    }
+
+    pub fn get_max_body_size(&self) -> usize {
+        self.max_body_size.unwrap_or(64 * 1024)
+    }
+
+    pub fn with_defaults(&self) -> Self {
+        Self {
+            max_requests: Some(self.get_max_requests()),
+            max_body_size: Some(self.get_max_body_size()),
+        }
+    }
+}
+
+impl Validatable for InspectorConfiguration {
+    fn validate(&self, result: &mut Validation) {
+        result.validate_rule_for::<_, MustBeGreaterThanZero>(
+            "max_requests",
+            &self.get_max_requests(),
+        );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ReconnectConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub initial_delay_seconds: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_delay_seconds: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub backoff_multiplier: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub jitter_ratio: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_attempts: Option<u32>,
+}
+
+impl ReconnectConfiguration {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    pub fn get_initial_delay_seconds(&self) -> u64 {
+        self.initial_delay_seconds.unwrap_or(1)
+    }
+
+    pub fn get_max_delay_seconds(&self) -> u64 {
+        self.max_delay_seconds.unwrap_or(60)
+    }
+
+    pub fn get_backoff_multiplier(&self) -> f64 {
+        self.backoff_multiplier.unwrap_or(2.0)
+    }
+
+    pub fn get_jitter_ratio(&self) -> f64 {
+        self.jitter_ratio.unwrap_or(0.2)
+    }
+
+    pub fn get_max_attempts(&self) -> Option<u32> {
+        self.max_attempts
+    }
+
+    pub fn has_attempts_left(&self, attempt: u32) -> bool {
+        match self.get_max_attempts() {
+            Some(max_attempts) => attempt <= max_attempts,
+            None => true,
+        }
+    }
+
+    pub fn get_base_delay(&self, attempt: u32) -> Duration {
+        let initial = self.get_initial_delay_seconds() as f64;
+        let max = self.get_max_delay_seconds() as f64;
+        let exponent = attempt.saturating_sub(1).min(64) as i32;
+
+        let delay = initial * self.get_backoff_multiplier().powi(exponent);
+
+        Duration::from_secs_f64(delay.min(max))
+    }
+
+    pub fn get_delay(&self, attempt: u32) -> Duration {
+        let delay = self.get_base_delay(attempt).as_secs_f64();
+        let jitter = delay * self.get_jitter_ratio();
+
+        if jitter <= 0.0 {
+            return Duration::from_secs_f64(delay);
+        }
+
+        Duration::from_secs_f64((delay + rand::random_range(-jitter..=jitter)).max(0.0))
+    }
+
+    pub fn with_defaults(&self) -> Self {
+        Self {
+            enabled: Some(self.is_enabled()),
+            initial_delay_seconds: Some(self.get_initial_delay_seconds()),
+            max_delay_seconds: Some(self.get_max_delay_seconds()),
+            backoff_multiplier: Some(self.get_backoff_multiplier()),
+            jitter_ratio: Some(self.get_jitter_ratio()),
+            max_attempts: self.max_attempts,
+        }
+    }
+}
+
+impl Validatable for ReconnectConfiguration {
+    fn validate(&self, result: &mut Validation) {
+        result.validate_rule_for::<_, MustBeGreaterThanZero>(
+            "initial_delay_seconds",
+            &self.get_initial_delay_seconds(),
+        );
+
+        if self.get_max_delay_seconds() < self.get_initial_delay_seconds() {
+            result.add_field_error(
+                "max_delay_seconds",
+                "Max delay must be greater than or equal to initial delay.",
+            );
+        }
+
+        if self.get_backoff_multiplier() < 1.0 {
+            result.add_field_error(
+                "backoff_multiplier",
+                "Backoff multiplier must be at least 1.0.",
+            );
+        }
+
+        let jitter_ratio = self.get_jitter_ratio();
+        if !(0.0..=1.0).contains(&jitter_ratio) {
+            result.add_field_error("jitter_ratio", "Jitter ratio must be between 0.0 and 1.0.");
+        }
+
+        if let Some(max_attempts) = &self.max_attempts {
+            result.validate_rule_for::<_, MustBeGreaterThanZero>("max_attempts", max_attempts);
+        }
+    }
 }
 
 impl From<TunnelConfiguration> for TunnelizeConfiguration {
@@ -66,6 +356,8 @@ impl From<TunnelConfiguration> for TunnelizeConfiguration {
         TunnelizeConfiguration {
             server: None,
             tunnel: Some(val),
+            tunnel_defaults: None,
+            profiles: None,
         }
     }
 }
@@ -81,30 +373,226 @@ impl TryFrom<TunnelizeConfiguration> for TunnelConfiguration {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct TunnelProxy {
     pub endpoint_name: String,
     pub address: String,
     pub port: u16,
     pub endpoint_config: ProxyConfiguration,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tls: Option<ProxyTlsConfiguration>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub health_check: Option<HealthCheckConfiguration>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub targets: Option<Vec<ProxyTarget>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub target_selection: Option<TargetSelection>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub target_cooldown_seconds: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+impl TunnelProxy {
+    /// Returns all forward targets, starting with the address and port of the proxy.
+    pub fn get_targets(&self) -> Vec<ProxyTarget> {
+        let mut targets = vec![ProxyTarget {
+            address: self.address.clone(),
+            port: self.port,
+        }];
+
+        if let Some(additional) = &self.targets {
+            targets.extend(additional.iter().cloned());
+        }
+
+        targets
+    }
+
+    pub fn get_target_selection(&self) -> TargetSelection {
+        self.target_selection.unwrap_or_default()
+    }
+
+    pub fn get_target_cooldown(&self) -> Duration {
+        Duration::from_secs(self.target_cooldown_seconds.unwrap_or(30))
+    }
+
+    pub fn with_defaults(&self) -> Self {
+        Self {
+            tls: self.tls.as_ref().map(|tls| tls.with_defaults(&self.address)),
+            health_check: self
+                .health_check
+                .as_ref()
+                .map(|health_check| health_check.with_defaults(self.port)),
+            target_selection: Some(self.get_target_selection()),
+            target_cooldown_seconds: Some(self.get_target_cooldown().as_secs()),
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ProxyTarget {
+    pub address: String,
+    pub port: u16,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum TargetSelection {
+    #[default]
+    Failover,
+    RoundRobin,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ProxyTlsConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub server_name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ca_path: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub insecure_skip_verify: Option<bool>,
+}
+
+impl ProxyTlsConfiguration {
+    pub fn get_server_name<'a>(&'a self, address: &'a str) -> &'a str {
+        self.server_name.as_deref().unwrap_or(address)
+    }
+
+    pub fn get_insecure_skip_verify(&self) -> bool {
+        self.insecure_skip_verify.unwrap_or(false)
+    }
+
+    pub fn with_defaults(&self, address: &str) -> Self {
+        Self {
+            server_name: Some(self.get_server_name(address).to_owned()),
+            ca_path: self.ca_path.clone(),
+            insecure_skip_verify: Some(self.get_insecure_skip_verify()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HealthCheckConfiguration {
+    #[serde(rename = "type")]
+    pub check_type: HealthCheckType,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub port: Option<u16>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub path: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub interval_seconds: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timeout_seconds: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub unhealthy_threshold: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HealthCheckType {
+    Tcp,
+    Http,
+}
+
+impl HealthCheckConfiguration {
+    pub fn get_port(&self, proxy_port: u16) -> u16 {
+        self.port.unwrap_or(proxy_port)
+    }
+
+    pub fn get_path(&self) -> &str {
+        self.path.as_deref().unwrap_or("/")
+    }
+
+    pub fn get_interval(&self) -> Duration {
+        Duration::from_secs(self.interval_seconds.unwrap_or(10))
+    }
+
+    pub fn get_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_seconds.unwrap_or(5))
+    }
+
+    pub fn get_unhealthy_threshold(&self) -> u32 {
+        self.unhealthy_threshold.unwrap_or(3)
+    }
+
+    pub fn with_defaults(&self, proxy_port: u16) -> Self {
+        Self {
+            check_type: self.check_type,
+            port: Some(self.get_port(proxy_port)),
+            path: match self.check_type {
+                HealthCheckType::Http => Some(self.get_path().to_owned()),
+                HealthCheckType::Tcp => self.path.clone(),
+            },
+            interval_seconds: Some(self.get_interval().as_secs()),
+            timeout_seconds: Some(self.get_timeout().as_secs()),
+            unhealthy_threshold: Some(self.get_unhealthy_threshold()),
+        }
+    }
+}
+
+impl Validatable for HealthCheckConfiguration {
+    fn validate(&self, result: &mut Validation) {
+        if let Some(port) = &self.port {
+            result.validate_rule::<PortMustBeValid>("port", port);
+        }
+
+        if let Some(path) = &self.path
+            && !path.starts_with('/')
+        {
+            result.add_field_error("path", "Path must start with '/'.");
+        }
+
+        if let Some(interval_seconds) = &self.interval_seconds {
+            result.validate_rule_for::<_, MustBeGreaterThanZero>(
+                "interval_seconds",
+                interval_seconds,
+            );
+        }
+
+        if let Some(timeout_seconds) = &self.timeout_seconds {
+            result.validate_rule_for::<_, MustBeGreaterThanZero>(
+                "timeout_seconds",
+                timeout_seconds,
+            );
+        }
+
+        if let Some(unhealthy_threshold) = &self.unhealthy_threshold {
+            result.validate_rule_for::<_, MustBeGreaterThanZero>(
+                "unhealthy_threshold",
+                unhealthy_threshold,
+            );
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum ProxyConfiguration {
     Http {
         #[serde(skip_serializing_if = "Option::is_none", default)]
         desired_name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        pool: Option<String>,
     },
     Tcp {
         #[serde(skip_serializing_if = "Option::is_none", default)]
         desired_port: Option<u16>,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        pool: Option<String>,
     },
     Udp {
         #[serde(skip_serializing_if = "Option::is_none", default)]
         desired_port: Option<u16>,
         #[serde(skip_serializing_if = "Option::is_none", default)]
         bind_address: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        pool: Option<String>,
     },
 }
 
@@ -116,6 +604,14 @@ impl ProxyConfiguration {
             Self::Udp { .. } => "udp",
         }
     }
+
+    pub fn get_pool(&self) -> Option<&String> {
+        match self {
+            Self::Http { pool, .. } | Self::Tcp { pool, .. } | Self::Udp { pool, .. } => {
+                pool.as_ref()
+            }
+        }
+    }
 }
 
 impl Validatable for TunnelProxy {
@@ -128,19 +624,95 @@ impl Validatable for TunnelProxy {
         result.validate_rule::<PortMustBeValid>("port", &self.port);
 
         result.validate_child("endpoint_config", &self.endpoint_config);
+
+        if let Some(tls) = &self.tls {
+            if let ProxyConfiguration::Udp { .. } = self.endpoint_config {
+                result.add_field_error("tls", "TLS is not supported for UDP proxies.");
+            }
+
+            result.validate_child("tls", tls);
+        }
+
+        if let Some(health_check) = &self.health_check {
+            if let ProxyConfiguration::Udp { .. } = self.endpoint_config
+                && health_check.port.is_none()
+            {
+                result.add_field_error(
+                    "health_check",
+                    "Health check port must be set for UDP proxies.",
+                );
+            }
+
+            result.validate_child("health_check", health_check);
+        }
+
+        if let Some(targets) = &self.targets {
+            if let ProxyConfiguration::Udp { .. } = self.endpoint_config
+                && !targets.is_empty()
+            {
+                result.add_field_error(
+                    "targets",
+                    "Multiple targets are not supported for UDP proxies.",
+                );
+            }
+
+            for (index, target) in targets.iter().enumerate() {
+                result.validate_child(&format!("targets.{index}"), target);
+            }
+        }
+
+        if let Some(cooldown) = &self.target_cooldown_seconds {
+            result.validate_rule_for::<_, MustBeGreaterThanZero>(
+                "target_cooldown_seconds",
+                cooldown,
+            );
+        }
+    }
+}
+
+impl Validatable for ProxyTarget {
+    fn validate(&self, result: &mut Validation) {
+        result.validate_rule::<HostAddressMustBeValid>("address", &self.address);
+        result.validate_rule::<PortMustBeValid>("port", &self.port);
+    }
+}
+
+impl Validatable for ProxyTlsConfiguration {
+    fn validate(&self, result: &mut Validation) {
+        if let Some(server_name) = &self.server_name {
+            result.validate_rule::<HostAddressMustBeValid>("server_name", server_name);
+        }
+
+        if let Some(ca_path) = &self.ca_path {
+            result.validate_rule::<FileMustExist>("ca_path", ca_path);
+
+            if self.get_insecure_skip_verify() {
+                result.add_field_error(
+                    "ca_path",
+                    "CA path cannot be used when insecure_skip_verify is enabled.",
+                );
+            }
+        }
     }
 }
 
 impl Validatable for ProxyConfiguration {
     fn validate(&self, result: &mut Validation) {
         match self {
-            Self::Http { desired_name } => {
+            Self::Http { desired_name, pool } => {
                 if let Some(name) = desired_name {
                     result.validate_rule::<MustNotBeEmptyString>("desired_name", name);
                     result.validate_rule::<AlphaNumericOnly>("desired_name", name);
+
+                    if pool.is_some() {
+                        result.add_field_error(
+                            "pool",
+                            "Pool cannot be set together with desired_name.",
+                        );
+                    }
                 }
             }
-            Self::Tcp { desired_port } => {
+            Self::Tcp { desired_port, .. } => {
                 if let Some(port) = desired_port {
                     result.validate_rule::<PortMustBeValid>("desired_port", port);
                 }
@@ -148,6 +720,7 @@ impl Validatable for ProxyConfiguration {
             Self::Udp {
                 desired_port,
                 bind_address,
+                ..
             } => {
                 if let Some(port) = desired_port {
                     result.validate_rule::<PortMustBeValid>("desired_port", port);
@@ -158,6 +731,11 @@ impl Validatable for ProxyConfiguration {
                 }
             }
         }
+
+        if let Some(pool) = self.get_pool() {
+            result.validate_rule::<MustNotBeEmptyString>("pool", pool);
+            result.validate_rule::<AlphaNumericOnly>("pool", pool);
+        }
     }
 }
 
@@ -175,6 +753,30 @@ impl Validatable for TunnelConfiguration {
 
         result.validate_child("encryption", &self.get_encryption());
 
+        if let Some(private_key_path) = &self.private_key_path {
+            result.validate_rule::<FileMustExist>("private_key_path", private_key_path);
+        }
+
+        if let Some(reconnect) = &self.reconnect {
+            result.validate_child("reconnect", reconnect);
+        }
+
+        if let Some(control_socket) = &self.control_socket {
+            result.validate_rule::<MustNotBeEmptyString>("control_socket", control_socket);
+        }
+
+        if let Some(dashboard) = &self.dashboard {
+            result.validate_child("dashboard", dashboard);
+        }
+
+        if let Some(inspector) = &self.inspector {
+            result.validate_child("inspector", inspector);
+        }
+
+        if let Some(status_file) = &self.status_file {
+            result.validate_rule::<MustNotBeEmptyString>("status_file", status_file);
+        }
+
         if self.proxies.is_empty() {
             result.add_field_error("proxies", "At least one proxy is required.");
             return;
@@ -199,13 +801,27 @@ mod tests {
             encryption: Some(ClientEncryption::None),
             tunnel_key: Some("test_key".to_string()),
             monitor_key: Some("monitor_key".to_string()),
+            private_key_path: None,
+            reconnect: None,
+            multiplex_links: None,
+            watch_config: None,
+            control_socket: None,
+            dashboard: None,
+            inspector: None,
+            status_file: None,
             proxies: vec![TunnelProxy {
                 endpoint_name: "test_proxy".to_string(),
                 address: "127.0.0.1".to_string(),
                 port: 8081,
                 endpoint_config: ProxyConfiguration::Http {
                     desired_name: Some("test_http".to_string()),
+                    pool: None,
                 },
+                tls: None,
+                health_check: None,
+                targets: None,
+                target_selection: None,
+                target_cooldown_seconds: None,
             }],
         }
     }
@@ -242,6 +858,8 @@ mod tests {
         let config = TunnelConfiguration {
             encryption: Some(ClientEncryption::Tls {
                 ca_path: Some("path/to/cert".to_string()),
+                cert_path: None,
+                key_path: None,
             }),
             ..create_test_tunnel_configuration()
         };
@@ -249,7 +867,291 @@ mod tests {
             config.get_encryption(),
             ClientEncryption::Tls {
                 ca_path: Some("path/to/cert".to_string()),
+                cert_path: None,
+                key_path: None,
             }
         );
     }
+
+    #[test]
+    fn test_get_multiplex_links() {
+        let mut config = create_test_tunnel_configuration();
+        assert!(!config.get_multiplex_links());
+
+        config.multiplex_links = Some(true);
+        assert!(config.get_multiplex_links());
+    }
+
+    #[test]
+    fn test_get_watch_config() {
+        let mut config = create_test_tunnel_configuration();
+        assert!(config.get_watch_config());
+
+        config.watch_config = Some(false);
+        assert!(!config.get_watch_config());
+    }
+
+    #[test]
+    fn test_get_control_socket_path() {
+        let mut config = create_test_tunnel_configuration();
+        assert_eq!(
+            config.get_control_socket_path(),
+            get_runtime_dir().join("tunnelize-test_tunnel.sock")
+        );
+
+        config.name = Some("../my tunnel".to_string());
+        assert_eq!(
+            config.get_control_socket_path(),
+            get_runtime_dir().join("tunnelize-mytunnel.sock")
+        );
+
+        config.control_socket = Some("/run/tunnelize.sock".to_string());
+        assert_eq!(
+            config.get_control_socket_path(),
+            PathBuf::from("/run/tunnelize.sock")
+        );
+    }
+
+    #[test]
+    fn test_get_reconnect_defaults() {
+        let reconnect = create_test_tunnel_configuration().get_reconnect();
+
+        assert!(reconnect.is_enabled());
+        assert_eq!(reconnect.get_initial_delay_seconds(), 1);
+        assert_eq!(reconnect.get_max_delay_seconds(), 60);
+        assert_eq!(reconnect.get_max_attempts(), None);
+    }
+
+    #[test]
+    fn test_reconnect_base_delay_is_exponential_and_capped() {
+        let reconnect = ReconnectConfiguration {
+            initial_delay_seconds: Some(2),
+            max_delay_seconds: Some(10),
+            ..Default::default()
+        };
+
+        assert_eq!(reconnect.get_base_delay(1), Duration::from_secs(2));
+        assert_eq!(reconnect.get_base_delay(2), Duration::from_secs(4));
+        assert_eq!(reconnect.get_base_delay(3), Duration::from_secs(8));
+        assert_eq!(reconnect.get_base_delay(4), Duration::from_secs(10));
+        assert_eq!(reconnect.get_base_delay(100), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_reconnect_delay_stays_within_jitter() {
+        let reconnect = ReconnectConfiguration {
+            initial_delay_seconds: Some(10),
+            jitter_ratio: Some(0.5),
+            ..Default::default()
+        };
+
+        for _ in 0..100 {
+            let delay = reconnect.get_delay(1);
+            assert!(delay >= Duration::from_secs(5));
+            assert!(delay <= Duration::from_secs(15));
+        }
+    }
+
+    #[test]
+    fn test_reconnect_has_attempts_left() {
+        let reconnect = ReconnectConfiguration {
+            max_attempts: Some(3),
+            ..Default::default()
+        };
+
+        assert!(reconnect.has_attempts_left(3));
+        assert!(!reconnect.has_attempts_left(4));
+        assert!(ReconnectConfiguration::default().has_attempts_left(u32::MAX));
+    }
+
+    #[test]
+    fn test_reconnect_validation() {
+        let reconnect = ReconnectConfiguration {
+            initial_delay_seconds: Some(10),
+            max_delay_seconds: Some(5),
+            jitter_ratio: Some(1.5),
+            ..Default::default()
+        };
+
+        let mut validation = Validation::new();
+        reconnect.validate(&mut validation);
+        assert!(!validation.is_valid());
+    }
+
+    #[test]
+    fn test_proxy_tls_getters() {
+        let tls = ProxyTlsConfiguration::default();
+        assert_eq!(tls.get_server_name("localhost"), "localhost");
+        assert!(!tls.get_insecure_skip_verify());
+
+        let tls = ProxyTlsConfiguration {
+            server_name: Some("app.local".to_string()),
+            insecure_skip_verify: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(tls.get_server_name("localhost"), "app.local");
+        assert!(tls.get_insecure_skip_verify());
+    }
+
+    #[test]
+    fn test_proxy_tls_validation() {
+        let mut proxy = create_test_tunnel_configuration().proxies.remove(0);
+        proxy.endpoint_config = ProxyConfiguration::Tcp {
+            desired_port: None,
+            pool: None,
+        };
+        proxy.tls = Some(ProxyTlsConfiguration::default());
+
+        let mut validation = Validation::new();
+        proxy.validate(&mut validation);
+        assert!(validation.is_valid());
+
+        proxy.endpoint_config = ProxyConfiguration::Udp {
+            desired_port: None,
+            bind_address: None,
+            pool: None,
+        };
+
+        let mut validation = Validation::new();
+        proxy.validate(&mut validation);
+        assert!(!validation.is_valid());
+    }
+
+    #[test]
+    fn test_health_check_getters() {
+        let health_check = HealthCheckConfiguration {
+            check_type: HealthCheckType::Http,
+            port: None,
+            path: None,
+            interval_seconds: None,
+            timeout_seconds: None,
+            unhealthy_threshold: None,
+        };
+
+        assert_eq!(health_check.get_port(8080), 8080);
+        assert_eq!(health_check.get_path(), "/");
+        assert_eq!(health_check.get_interval(), Duration::from_secs(10));
+        assert_eq!(health_check.get_timeout(), Duration::from_secs(5));
+        assert_eq!(health_check.get_unhealthy_threshold(), 3);
+    }
+
+    #[test]
+    fn test_health_check_validation() {
+        let mut proxy = create_test_tunnel_configuration().proxies.remove(0);
+        proxy.endpoint_config = ProxyConfiguration::Udp {
+            desired_port: None,
+            bind_address: None,
+            pool: None,
+        };
+        proxy.health_check = Some(HealthCheckConfiguration {
+            check_type: HealthCheckType::Tcp,
+            port: None,
+            path: Some("health".to_string()),
+            interval_seconds: Some(0),
+            timeout_seconds: None,
+            unhealthy_threshold: None,
+        });
+
+        let mut validation = Validation::new();
+        proxy.validate(&mut validation);
+        assert_eq!(validation.errors().len(), 3);
+    }
+
+    #[test]
+    fn test_pool_validation() {
+        let mut proxy = create_test_tunnel_configuration().proxies[0].clone();
+        proxy.endpoint_config = ProxyConfiguration::Tcp {
+            desired_port: None,
+            pool: Some("app".to_string()),
+        };
+        assert_eq!(proxy.endpoint_config.get_pool(), Some(&"app".to_string()));
+
+        let mut validation = Validation::new();
+        proxy.validate(&mut validation);
+        assert!(validation.is_valid());
+
+        proxy.endpoint_config = ProxyConfiguration::Http {
+            desired_name: Some("app".to_string()),
+            pool: Some("my_pool".to_string()),
+        };
+
+        let mut validation = Validation::new();
+        proxy.validate(&mut validation);
+        assert_eq!(validation.errors().len(), 2);
+    }
+
+    #[test]
+    fn test_proxy_targets() {
+        let mut proxy = create_test_tunnel_configuration().proxies[0].clone();
+        proxy.endpoint_config = ProxyConfiguration::Tcp {
+            desired_port: None,
+            pool: None,
+        };
+        assert_eq!(proxy.get_targets().len(), 1);
+        assert_eq!(proxy.get_target_selection(), TargetSelection::Failover);
+        assert_eq!(proxy.get_target_cooldown(), Duration::from_secs(30));
+
+        proxy.targets = Some(vec![ProxyTarget {
+            address: "127.0.0.2".to_string(),
+            port: 8082,
+        }]);
+
+        let targets = proxy.get_targets();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].port, 8081);
+        assert_eq!(targets[1].address, "127.0.0.2");
+
+        let mut validation = Validation::new();
+        proxy.validate(&mut validation);
+        assert!(validation.is_valid());
+
+        proxy.endpoint_config = ProxyConfiguration::Udp {
+            desired_port: None,
+            bind_address: None,
+            pool: None,
+        };
+        proxy.targets = Some(vec![ProxyTarget {
+            address: "".to_string(),
+            port: 0,
+        }]);
+        proxy.target_cooldown_seconds = Some(0);
+
+        let mut validation = Validation::new();
+        proxy.validate(&mut validation);
+        assert_eq!(validation.errors().len(), 4);
+    }
+
+    #[test]
+    fn test_dashboard_configuration() {
+        let dashboard = DashboardConfiguration::default();
+        assert_eq!(
+            dashboard.get_bind_address(),
+            Some(SocketAddr::from(([127, 0, 0, 1], 3457)))
+        );
+
+        let ipv6_dashboard = DashboardConfiguration {
+            address: Some("::1".to_string()),
+            port: Some(8000),
+        };
+        assert_eq!(
+            ipv6_dashboard.get_bind_address().unwrap().to_string(),
+            "[::1]:8000"
+        );
+
+        let mut config = TunnelConfiguration {
+            dashboard: Some(dashboard),
+            ..create_test_tunnel_configuration()
+        };
+        config.proxies[0].endpoint_config = ProxyConfiguration::Tcp {
+            desired_port: None,
+            pool: None,
+        };
+        assert!(Validation::validate(&config).is_valid());
+
+        config.dashboard = Some(DashboardConfiguration {
+            address: Some("localhost".to_string()),
+            port: Some(0),
+        });
+        assert_eq!(Validation::validate(&config).errors().len(), 2);
+    }
 }
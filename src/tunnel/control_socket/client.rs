@@ -0,0 +1,47 @@
+use std::path::Path;
+
+use tokio::io::Result;
+
+use crate::common::data_request::DataRequest;
+
+#[cfg(unix)]
+pub async fn send_request<R: DataRequest + Into<R::DataEnum>>(
+    path: &Path,
+    request: R,
+) -> Result<R::DataResponse> {
+    use std::io::Error;
+
+    use tokio::net::UnixStream;
+
+    use crate::common::transport::{read_message, write_message};
+
+    let mut stream = UnixStream::connect(path).await.map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!(
+                "Could not connect to control socket '{}', is the tunnel running? {}",
+                path.display(),
+                e
+            ),
+        )
+    })?;
+
+    let message: R::DataEnum = request.into();
+
+    write_message(&mut stream, &message)
+        .await
+        .map_err(Error::other)?;
+
+    read_message(&mut stream).await.map_err(Error::other)
+}
+
+#[cfg(not(unix))]
+pub async fn send_request<R: DataRequest + Into<R::DataEnum>>(
+    _path: &Path,
+    _request: R,
+) -> Result<R::DataResponse> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Control socket is only supported on Unix systems.",
+    ))
+}
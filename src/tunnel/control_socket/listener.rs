@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::tunnel::services::Services;
+
+#[cfg(unix)]
+pub async fn start(services: Arc<Services>, cancel_token: CancellationToken) {
+    use log::{debug, error, info};
+    use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+    use tokio::net::{UnixListener, UnixStream};
+
+    let path = services.get_config().get_control_socket_path();
+
+    if let Err(e) = check_socket_path(&path) {
+        error!(
+            "Cannot use control socket '{}': {}, control socket is disabled.",
+            path.display(),
+            e
+        );
+        return;
+    }
+
+    if path.exists() {
+        if UnixStream::connect(&path).await.is_ok() {
+            error!(
+                "Control socket '{}' is used by another tunnel, control socket is disabled.",
+                path.display()
+            );
+            return;
+        }
+
+        debug!("Removing stale control socket '{}'", path.display());
+
+        if let Err(e) = std::fs::remove_file(&path) {
+            error!("Failed to remove stale control socket: {}", e);
+            return;
+        }
+    }
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(
+                "Failed to create control socket '{}': {}",
+                path.display(),
+                e
+            );
+            return;
+        }
+    };
+
+    // Default runtime directory is already accessible only by the current user, this also
+    // covers sockets placed elsewhere with `control_socket`.
+    if let Err(e) = std::fs::set_permissions(&path, Permissions::from_mode(0o600)) {
+        error!(
+            "Failed to set permissions of control socket '{}': {}",
+            path.display(),
+            e
+        );
+        let _ = std::fs::remove_file(&path);
+        return;
+    }
+
+    info!("Control socket listening at '{}'", path.display());
+
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                debug!("Control socket stopped.");
+                break;
+            }
+            result = listener.accept() => {
+                match result {
+                    Ok((stream, _)) => {
+                        tokio::spawn(handle_connection(services.clone(), stream));
+                    }
+                    Err(e) => {
+                        error!("Failed to accept control socket connection: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Err(e) = std::fs::remove_file(&path) {
+        debug!("Failed to remove control socket: {}", e);
+    }
+}
+
+/// Creates the default runtime directory if needed and makes sure that neither it nor an
+/// existing socket at the path belongs to another user.
+#[cfg(unix)]
+fn check_socket_path(path: &std::path::Path) -> std::io::Result<()> {
+    use std::{
+        fs::DirBuilder,
+        io::{Error, ErrorKind},
+        os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt},
+    };
+
+    use crate::tunnel::configuration::get_runtime_dir;
+
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    let user_id = unsafe { libc::geteuid() };
+
+    let runtime_dir = get_runtime_dir();
+
+    if path.parent() == Some(runtime_dir.as_path()) {
+        match DirBuilder::new().mode(0o700).create(&runtime_dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+
+        let metadata = std::fs::symlink_metadata(&runtime_dir)?;
+
+        if !metadata.is_dir() || metadata.uid() != user_id || metadata.mode() & 0o077 != 0 {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "directory '{}' must be owned by the current user and accessible only by them",
+                    runtime_dir.display()
+                ),
+            ));
+        }
+    }
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.file_type().is_socket() || metadata.uid() != user_id => {
+            Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "existing file is not a socket owned by the current user",
+            ))
+        }
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(unix)]
+async fn handle_connection(services: Arc<Services>, mut stream: tokio::net::UnixStream) {
+    use log::debug;
+
+    use super::{
+        ControlSocketRequest, add_proxy, get_request, get_status, list_requests, remove_proxy,
+        replay_request,
+    };
+    use crate::common::transport::{MessageError, read_message, write_message};
+
+    let request: ControlSocketRequest = match read_message(&mut stream).await {
+        Ok(request) => request,
+        Err(e) => {
+            debug!("Failed to read control socket request: {:?}", e);
+            return;
+        }
+    };
+
+    let result: Result<(), MessageError> = match request {
+        ControlSocketRequest::GetStatusRequest(_) => {
+            write_message(&mut stream, &get_status(&services).await).await
+        }
+        ControlSocketRequest::AddProxyRequest(request) => {
+            write_message(&mut stream, &add_proxy(&services, request).await).await
+        }
+        ControlSocketRequest::RemoveProxyRequest(request) => {
+            write_message(&mut stream, &remove_proxy(&services, request).await).await
+        }
+        ControlSocketRequest::ListRequestsRequest(_) => {
+            write_message(&mut stream, &list_requests(&services)).await
+        }
+        ControlSocketRequest::GetRequestRequest(request) => {
+            write_message(&mut stream, &get_request(&services, request)).await
+        }
+        ControlSocketRequest::ReplayRequestRequest(request) => {
+            write_message(&mut stream, &replay_request(&services, request).await).await
+        }
+    };
+
+    if let Err(e) = result {
+        debug!("Failed to write control socket response: {:?}", e);
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn start(_services: Arc<Services>, _cancel_token: CancellationToken) {
+    log::info!("Control socket is only supported on Unix systems.");
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_socket_path_refuses_other_files() {
+        let path = std::env::temp_dir().join(format!("tunnelize-{}.sock", uuid::Uuid::new_v4()));
+
+        assert!(check_socket_path(&path).is_ok());
+
+        std::fs::write(&path, "not a socket").unwrap();
+        assert!(check_socket_path(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
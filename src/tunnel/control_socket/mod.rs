@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    create_data_enum,
+    server::{endpoints::messages::ResolvedEndpointInfo, incoming_requests::ProxyHealth},
+    tunnel::{
+        configuration::{ProxyConfiguration, TunnelProxy},
+        inspector::{self, CapturedExchange, ExchangeSummary},
+        outgoing_requests,
+        services::{LinkStats, Proxy, Services, TunnelError},
+    },
+};
+
+mod client;
+mod listener;
+
+pub use client::send_request;
+pub use listener::start;
+
+create_data_enum!(ControlSocketRequest, {
+    GetStatusRequest -> TunnelStatus,
+    AddProxyRequest -> AddProxyResponse,
+    RemoveProxyRequest -> RemoveProxyResponse,
+    ListRequestsRequest -> ListRequestsResponse,
+    GetRequestRequest -> GetRequestResponse,
+    ReplayRequestRequest -> ReplayRequestResponse
+});
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetStatusRequest;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TunnelStatus {
+    pub name: Option<String>,
+    pub server_address: String,
+    pub server_port: u16,
+    pub tunnel_id: Option<Uuid>,
+    pub is_connected: bool,
+    pub is_multiplexed: bool,
+    pub heartbeat: HeartbeatStatus,
+    pub proxies: Vec<ProxyStatus>,
+    pub recent_errors: Vec<TunnelError>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HeartbeatStatus {
+    pub last_heartbeat_timestamp: Option<i64>,
+    pub failed_heartbeats: u16,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProxyStatus {
+    pub id: Uuid,
+    pub endpoint_name: String,
+    pub forward_address: String,
+    pub forward_port: u16,
+    pub endpoint_config: ProxyConfiguration,
+    pub endpoint_info: Option<ResolvedEndpointInfo>,
+    pub health: ProxyHealth,
+    pub stats: LinkStats,
+}
+
+impl ProxyStatus {
+    fn new(id: Uuid, proxy: &Proxy) -> Self {
+        Self {
+            id,
+            endpoint_name: proxy.endpoint_name.clone(),
+            forward_address: proxy.address.clone(),
+            forward_port: proxy.port,
+            endpoint_config: proxy.endpoint_config.clone(),
+            endpoint_info: proxy.endpoint_info.clone(),
+            health: proxy.health.clone(),
+            stats: proxy.stats.get_link_stats(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AddProxyRequest {
+    pub proxy: Box<TunnelProxy>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "result", rename_all = "kebab-case")]
+pub enum AddProxyResponse {
+    Added { proxy: ProxyStatus },
+    Failed { reason: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RemoveProxyRequest {
+    pub proxy_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "result", rename_all = "kebab-case")]
+pub enum RemoveProxyResponse {
+    Removed { proxy_id: Uuid },
+    Failed { reason: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListRequestsRequest;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "result", rename_all = "kebab-case")]
+pub enum ListRequestsResponse {
+    Listed { requests: Vec<ExchangeSummary> },
+    Failed { reason: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetRequestRequest {
+    pub request_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "result", rename_all = "kebab-case")]
+pub enum GetRequestResponse {
+    Found { request: Box<CapturedExchange> },
+    Failed { reason: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReplayRequestRequest {
+    pub request_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "result", rename_all = "kebab-case")]
+pub enum ReplayRequestResponse {
+    Replayed { request: Box<CapturedExchange> },
+    Failed { reason: String },
+}
+
+pub async fn get_status(services: &Arc<Services>) -> TunnelStatus {
+    let config = services.get_config();
+
+    let (tunnel_id, is_multiplexed, heartbeat, recent_errors) = {
+        let tunnel_data = services.get_tunnel_data().await;
+        (
+            tunnel_data.get_tunnel_id(),
+            tunnel_data.get_multiplexer().is_some(),
+            HeartbeatStatus {
+                last_heartbeat_timestamp: tunnel_data.last_heartbeat_timestamp,
+                failed_heartbeats: tunnel_data.failed_heartbeats,
+            },
+            tunnel_data.get_recent_errors(),
+        )
+    };
+
+    let proxies = services
+        .get_proxy_manager()
+        .await
+        .get_proxies()
+        .into_iter()
+        .map(|(id, proxy)| ProxyStatus::new(id, proxy))
+        .collect();
+
+    TunnelStatus {
+        name: config.name.clone(),
+        server_address: config.server_address.clone(),
+        server_port: config.get_server_port(),
+        tunnel_id,
+        is_connected: tunnel_id.is_some(),
+        is_multiplexed,
+        heartbeat,
+        proxies,
+        recent_errors,
+    }
+}
+
+async fn add_proxy(services: &Arc<Services>, request: AddProxyRequest) -> AddProxyResponse {
+    let proxy_id = match outgoing_requests::add_proxy(services, &request.proxy).await {
+        Ok((proxy_id, _)) => proxy_id,
+        Err(e) => {
+            return AddProxyResponse::Failed {
+                reason: e.to_string(),
+            };
+        }
+    };
+
+    match services.get_proxy_manager().await.get_proxy(&proxy_id) {
+        Some(proxy) => AddProxyResponse::Added {
+            proxy: ProxyStatus::new(proxy_id, proxy),
+        },
+        None => AddProxyResponse::Failed {
+            reason: "Proxy was removed while it was being added.".to_string(),
+        },
+    }
+}
+
+async fn remove_proxy(
+    services: &Arc<Services>,
+    request: RemoveProxyRequest,
+) -> RemoveProxyResponse {
+    match outgoing_requests::remove_proxy(services, &request.proxy_id).await {
+        Ok(()) => RemoveProxyResponse::Removed {
+            proxy_id: request.proxy_id,
+        },
+        Err(e) => RemoveProxyResponse::Failed {
+            reason: e.to_string(),
+        },
+    }
+}
+
+const INSPECTOR_DISABLED: &str = "Request inspector is not enabled.";
+
+fn list_requests(services: &Arc<Services>) -> ListRequestsResponse {
+    match services.get_inspector() {
+        Some(inspector) => ListRequestsResponse::Listed {
+            requests: inspector.get_summaries(),
+        },
+        None => ListRequestsResponse::Failed {
+            reason: INSPECTOR_DISABLED.to_string(),
+        },
+    }
+}
+
+fn get_request(services: &Arc<Services>, request: GetRequestRequest) -> GetRequestResponse {
+    let Some(inspector) = services.get_inspector() else {
+        return GetRequestResponse::Failed {
+            reason: INSPECTOR_DISABLED.to_string(),
+        };
+    };
+
+    match inspector.get_exchange(&request.request_id) {
+        Some(exchange) => GetRequestResponse::Found {
+            request: Box::new(exchange),
+        },
+        None => GetRequestResponse::Failed {
+            reason: "Request not found.".to_string(),
+        },
+    }
+}
+
+async fn replay_request(
+    services: &Arc<Services>,
+    request: ReplayRequestRequest,
+) -> ReplayRequestResponse {
+    match inspector::replay(services, &request.request_id).await {
+        Ok(exchange) => ReplayRequestResponse::Replayed {
+            request: Box::new(exchange),
+        },
+        Err(e) => ReplayRequestResponse::Failed {
+            reason: e.to_string(),
+        },
+    }
+}
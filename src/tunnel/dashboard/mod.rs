@@ -0,0 +1,267 @@
+use std::{io::ErrorKind, net::IpAddr, sync::Arc};
+
+use axum::{
+    Json, Router,
+    extract::{Path, Request, State},
+    http::{Method, StatusCode, header::HOST, uri::Authority},
+    middleware::{Next, from_fn_with_state},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+};
+use log::{debug, error, info};
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::{configuration::DashboardConfiguration, control_socket, inspector, services::Services};
+
+async fn show_dashboard() -> impl IntoResponse {
+    Html(include_str!("index.html"))
+}
+
+async fn get_status(State(services): State<Arc<Services>>) -> impl IntoResponse {
+    Json(control_socket::get_status(&services).await)
+}
+
+fn into_message(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "message": message }))).into_response()
+}
+
+fn into_inspector_disabled() -> Response {
+    into_message(StatusCode::NOT_FOUND, "Request inspector is not enabled.")
+}
+
+async fn list_requests(State(services): State<Arc<Services>>) -> Response {
+    match services.get_inspector() {
+        Some(inspector) => Json(inspector.get_summaries()).into_response(),
+        None => into_inspector_disabled(),
+    }
+}
+
+async fn clear_requests(State(services): State<Arc<Services>>) -> Response {
+    match services.get_inspector() {
+        Some(inspector) => {
+            inspector.clear();
+            into_message(StatusCode::OK, "Requests cleared.")
+        }
+        None => into_inspector_disabled(),
+    }
+}
+
+async fn get_request(
+    Path(request_id): Path<Uuid>,
+    State(services): State<Arc<Services>>,
+) -> Response {
+    let Some(inspector) = services.get_inspector() else {
+        return into_inspector_disabled();
+    };
+
+    match inspector.get_exchange(&request_id) {
+        Some(exchange) => Json(exchange).into_response(),
+        None => into_message(StatusCode::NOT_FOUND, "Request not found."),
+    }
+}
+
+async fn replay_request(
+    Path(request_id): Path<Uuid>,
+    State(services): State<Arc<Services>>,
+) -> Response {
+    match inspector::replay(&services, &request_id).await {
+        Ok(exchange) => Json(exchange).into_response(),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            into_message(StatusCode::NOT_FOUND, &e.to_string())
+        }
+        Err(e) => into_message(StatusCode::BAD_GATEWAY, &e.to_string()),
+    }
+}
+
+/// Header the dashboard page sends with requests changing state. Browsers cannot send it
+/// cross-origin without a CORS preflight, which the dashboard never allows.
+const DASHBOARD_REQUEST_HEADER: &str = "x-tunnelize-dashboard";
+
+fn is_allowed_host(host: &str, bind_ip: IpAddr) -> bool {
+    let Ok(authority) = host.parse::<Authority>() else {
+        return false;
+    };
+
+    let host = authority.host();
+
+    if host.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+
+    // Only IP addresses are allowed so that a domain rebound to this address is rejected.
+    match host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        Ok(ip) => ip.is_loopback() || ip == bind_ip,
+        Err(_) => false,
+    }
+}
+
+async fn check_request(State(bind_ip): State<IpAddr>, request: Request, next: Next) -> Response {
+    let host = request
+        .headers()
+        .get(HOST)
+        .and_then(|host| host.to_str().ok())
+        .or_else(|| {
+            request
+                .uri()
+                .authority()
+                .map(|authority| authority.as_str())
+        });
+
+    if !host.is_some_and(|host| is_allowed_host(host, bind_ip)) {
+        debug!("Dashboard request with host {:?} rejected.", host);
+        return into_message(StatusCode::FORBIDDEN, "Host is not allowed.");
+    }
+
+    let is_changing_state = request.method() == Method::POST || request.method() == Method::DELETE;
+
+    if is_changing_state && !request.headers().contains_key(DASHBOARD_REQUEST_HEADER) {
+        return into_message(
+            StatusCode::FORBIDDEN,
+            &format!("Header '{DASHBOARD_REQUEST_HEADER}' is required."),
+        );
+    }
+
+    next.run(request).await
+}
+
+fn get_router(services: Arc<Services>, bind_ip: IpAddr) -> Router {
+    Router::new()
+        .route("/", get(show_dashboard))
+        .route("/api/status", get(get_status))
+        .route("/api/requests", get(list_requests).delete(clear_requests))
+        .route("/api/requests/{id}", get(get_request))
+        .route("/api/requests/{id}/replay", post(replay_request))
+        .layer(from_fn_with_state(bind_ip, check_request))
+        .with_state(services)
+}
+
+pub async fn start(
+    services: Arc<Services>,
+    config: DashboardConfiguration,
+    cancel_token: CancellationToken,
+) {
+    let Some(address) = config.get_bind_address() else {
+        error!(
+            "Failed to parse dashboard address '{}'.",
+            config.get_address()
+        );
+        return;
+    };
+
+    info!("Dashboard listening at http://{}", address);
+    services.print(format!("[Dashboard] http://{}", address));
+
+    tokio::select! {
+        _ = cancel_token.cancelled() => {
+            debug!("Dashboard stopped.");
+        }
+        result = axum_server::bind(address)
+            .serve(get_router(services, address.ip()).into_make_service()) => {
+            if let Err(e) = result {
+                error!("Failed to start dashboard at '{}': {}", address, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tunnel::configuration::{ProxyConfiguration, TunnelConfiguration, TunnelProxy};
+    use serde_json::Value;
+
+    fn create_services() -> Arc<Services> {
+        let services = Services::new(TunnelConfiguration {
+            name: Some("test".to_string()),
+            server_address: "127.0.0.1".to_string(),
+            server_port: None,
+            forward_connection_timeout_seconds: None,
+            encryption: None,
+            tunnel_key: None,
+            monitor_key: None,
+            private_key_path: None,
+            reconnect: None,
+            multiplex_links: None,
+            watch_config: None,
+            control_socket: None,
+            dashboard: None,
+            inspector: None,
+            status_file: None,
+            proxies: vec![TunnelProxy {
+                endpoint_name: "http".to_string(),
+                address: "127.0.0.1".to_string(),
+                port: 8080,
+                endpoint_config: ProxyConfiguration::Http {
+                    desired_name: None,
+                    pool: None,
+                },
+                tls: None,
+                health_check: None,
+                targets: None,
+                target_selection: None,
+                target_cooldown_seconds: None,
+            }],
+        })
+        .unwrap();
+
+        Arc::new(services)
+    }
+
+    #[tokio::test]
+    async fn test_get_status() {
+        let services = create_services();
+        services
+            .get_tunnel_data()
+            .await
+            .record_error("Test error".to_string());
+
+        let response = get_status(State(services)).await.into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let status: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(status["name"], "test");
+        assert_eq!(status["is_connected"], false);
+        assert_eq!(status["proxies"][0]["stats"]["active_links"], 0);
+        assert_eq!(status["recent_errors"][0]["message"], "Test error");
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_inspector() {
+        let response = list_requests(State(create_services())).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_is_allowed_host() {
+        let loopback = IpAddr::from([127, 0, 0, 1]);
+        let bind_ip = IpAddr::from([192, 168, 1, 10]);
+
+        assert!(is_allowed_host("localhost:3457", loopback));
+        assert!(is_allowed_host("127.0.0.1:3457", loopback));
+        assert!(is_allowed_host("[::1]:3457", loopback));
+        assert!(is_allowed_host("192.168.1.10:3457", bind_ip));
+        assert!(!is_allowed_host("192.168.1.10:3457", loopback));
+        assert!(!is_allowed_host("attacker.example.com:3457", loopback));
+        assert!(!is_allowed_host("localhost.attacker.example.com", loopback));
+        assert!(!is_allowed_host("", loopback));
+    }
+
+    #[tokio::test]
+    async fn test_show_dashboard() {
+        let response = show_dashboard().await.into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use log::{debug, error, info};
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+use super::services::Services;
+
+pub struct AssignedAddress {
+    pub endpoint_name: String,
+    pub port: u16,
+    pub address: String,
+}
+
+fn get_variable_suffix(endpoint_name: &str) -> String {
+    endpoint_name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect()
+}
+
+/// Returns environment variables with addresses assigned to proxies. Each proxy is exported
+/// as `TUNNELIZE_URL_<ENDPOINT>_<PORT>` and `TUNNELIZE_URL_<ENDPOINT>` points to the proxy with
+/// the lowest local port on that endpoint.
+pub fn get_url_variables(mut addresses: Vec<AssignedAddress>) -> BTreeMap<String, String> {
+    addresses.sort_by_key(|address| address.port);
+
+    let mut variables = BTreeMap::new();
+
+    for address in addresses {
+        let suffix = get_variable_suffix(&address.endpoint_name);
+
+        variables
+            .entry(format!("TUNNELIZE_URL_{suffix}"))
+            .or_insert_with(|| address.address.clone());
+
+        variables.insert(
+            format!("TUNNELIZE_URL_{suffix}_{}", address.port),
+            address.address,
+        );
+    }
+
+    variables
+}
+
+/// Returns assigned addresses once the tunnel is connected and all proxies got their address.
+async fn get_assigned_addresses(services: &Arc<Services>) -> Option<Vec<AssignedAddress>> {
+    services.get_tunnel_data().await.get_tunnel_id()?;
+
+    services
+        .get_proxy_manager()
+        .await
+        .get_proxies()
+        .into_iter()
+        .map(|(_, proxy)| {
+            proxy.endpoint_info.as_ref().map(|info| AssignedAddress {
+                endpoint_name: proxy.endpoint_name.clone(),
+                port: proxy.port,
+                address: info.get_assigned_address(),
+            })
+        })
+        .collect()
+}
+
+fn create_command(command: &str) -> Command {
+    #[cfg(windows)]
+    let mut process = {
+        let mut process = Command::new("cmd");
+        process.arg("/C");
+        process
+    };
+
+    #[cfg(not(windows))]
+    let mut process = {
+        let mut process = Command::new("sh");
+        process.arg("-c");
+        process
+    };
+
+    process.arg(command).kill_on_drop(true);
+    process
+}
+
+/// Runs the command once the tunnel is established and stops the tunnel when the command exits.
+/// Returns exit code of the command or None if the command was not run to completion.
+pub async fn start(
+    services: Arc<Services>,
+    command: String,
+    cancel_token: CancellationToken,
+) -> Option<i32> {
+    let addresses = loop {
+        if let Some(addresses) = get_assigned_addresses(&services).await {
+            break addresses;
+        }
+
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                debug!("Tunnel stopped before command was started.");
+                return None;
+            }
+            _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+        }
+    };
+
+    let mut process = create_command(&command);
+
+    for (name, value) in get_url_variables(addresses) {
+        debug!("Exporting {}={}", name, value);
+        process.env(name, value);
+    }
+
+    info!("Running command: {}", command);
+
+    let mut child = match process.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            error!("Failed to run command '{}': {}", command, e);
+            services.print(format!("Failed to run command: {}", e));
+            cancel_token.cancel();
+            return Some(1);
+        }
+    };
+
+    tokio::select! {
+        _ = cancel_token.cancelled() => {
+            debug!("Tunnel stopped, killing command.");
+            let _ = child.kill().await;
+            None
+        }
+        status = child.wait() => {
+            cancel_token.cancel();
+
+            match status {
+                Ok(status) => {
+                    info!("Command exited with {}", status);
+                    Some(status.code().unwrap_or(1))
+                }
+                Err(e) => {
+                    error!("Failed to wait for command: {}", e);
+                    Some(1)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_address(endpoint_name: &str, port: u16, address: &str) -> AssignedAddress {
+        AssignedAddress {
+            endpoint_name: endpoint_name.to_string(),
+            port,
+            address: address.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_get_url_variables() {
+        let variables = get_url_variables(vec![
+            create_address("http-secure", 8081, "https://two.example.com"),
+            create_address("http-secure", 8080, "https://one.example.com"),
+            create_address("tcp", 22, "example.com:4000"),
+        ]);
+
+        assert_eq!(
+            variables,
+            BTreeMap::from([
+                (
+                    "TUNNELIZE_URL_HTTP_SECURE".to_string(),
+                    "https://one.example.com".to_string()
+                ),
+                (
+                    "TUNNELIZE_URL_HTTP_SECURE_8080".to_string(),
+                    "https://one.example.com".to_string()
+                ),
+                (
+                    "TUNNELIZE_URL_HTTP_SECURE_8081".to_string(),
+                    "https://two.example.com".to_string()
+                ),
+                (
+                    "TUNNELIZE_URL_TCP".to_string(),
+                    "example.com:4000".to_string()
+                ),
+                (
+                    "TUNNELIZE_URL_TCP_22".to_string(),
+                    "example.com:4000".to_string()
+                ),
+            ])
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_create_command_runs_in_shell() {
+        let status = create_command("exit 3").status().await.unwrap();
+
+        assert_eq!(status.code(), Some(3));
+    }
+}
@@ -0,0 +1,284 @@
+use std::{collections::HashMap, sync::Arc};
+
+use log::{debug, error};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    sync::mpsc::{self, UnboundedSender},
+    time::{Duration, Instant, interval, timeout},
+};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::server::incoming_requests::ProxyHealth;
+
+use super::{
+    configuration::{HealthCheckConfiguration, HealthCheckType},
+    outgoing_requests,
+    services::{ForwardTarget, Proxy, Services},
+};
+
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_STATUS_LINE_LENGTH: u64 = 1024;
+
+struct CheckState {
+    next_check: Instant,
+    is_running: bool,
+    failures: u32,
+    unhealthy_threshold: u32,
+    reported: Option<(Uuid, ProxyHealth)>,
+}
+
+struct CheckResult {
+    proxy_id: Uuid,
+    result: Result<(), String>,
+}
+
+/// Runs health checks of proxies which have them configured and reports health changes to the server.
+pub async fn start(services: Arc<Services>, cancel_token: CancellationToken) {
+    let (result_tx, mut result_rx) = mpsc::unbounded_channel::<CheckResult>();
+    let mut states: HashMap<Uuid, CheckState> = HashMap::new();
+    let mut ticker = interval(TICK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                debug!("Health checker stopped.");
+                return;
+            }
+            Some(check_result) = result_rx.recv() => {
+                apply_check_result(&services, &mut states, check_result).await;
+            }
+            _ = ticker.tick() => {
+                start_due_checks(&services, &mut states, &result_tx).await;
+            }
+        }
+    }
+}
+
+async fn start_due_checks(
+    services: &Arc<Services>,
+    states: &mut HashMap<Uuid, CheckState>,
+    result_tx: &UnboundedSender<CheckResult>,
+) {
+    let now = Instant::now();
+    let proxy_manager = services.get_proxy_manager().await;
+    let proxies = proxy_manager.get_proxies();
+
+    states.retain(|id, _| proxies.iter().any(|(proxy_id, _)| proxy_id == id));
+
+    for (proxy_id, proxy) in proxies {
+        let Some(health_check) = &proxy.health_check else {
+            continue;
+        };
+
+        let state = states.entry(proxy_id).or_insert_with(|| CheckState {
+            next_check: now,
+            is_running: false,
+            failures: 0,
+            unhealthy_threshold: health_check.get_unhealthy_threshold(),
+            reported: None,
+        });
+
+        if state.is_running || state.next_check > now {
+            continue;
+        }
+
+        state.is_running = true;
+        state.next_check = now + health_check.get_interval();
+
+        let proxy = proxy.clone();
+        let health_check = health_check.clone();
+        let result_tx = result_tx.clone();
+
+        tokio::spawn(async move {
+            let result =
+                match timeout(health_check.get_timeout(), run_check(&proxy, &health_check)).await {
+                    Ok(result) => result,
+                    Err(_) => Err("Health check timed out".to_string()),
+                };
+
+            let _ = result_tx.send(CheckResult { proxy_id, result });
+        });
+    }
+}
+
+async fn apply_check_result(
+    services: &Arc<Services>,
+    states: &mut HashMap<Uuid, CheckState>,
+    check_result: CheckResult,
+) {
+    let proxy_id = check_result.proxy_id;
+
+    let Some(state) = states.get_mut(&proxy_id) else {
+        return;
+    };
+
+    state.is_running = false;
+
+    let health = match check_result.result {
+        Ok(()) => {
+            state.failures = 0;
+            Some(ProxyHealth::Healthy)
+        }
+        Err(reason) => {
+            state.failures += 1;
+            debug!(
+                "Health check of proxy '{}' failed ({} of {}): {}",
+                proxy_id, state.failures, state.unhealthy_threshold, reason
+            );
+
+            (state.failures >= state.unhealthy_threshold)
+                .then_some(ProxyHealth::Unhealthy { reason })
+        }
+    };
+
+    if let Some(health) = health {
+        update_health(services, proxy_id, health).await;
+    }
+
+    report_health(services, proxy_id, state).await;
+}
+
+async fn update_health(services: &Arc<Services>, proxy_id: Uuid, health: ProxyHealth) {
+    let mut proxy_manager = services.get_proxy_manager().await;
+
+    if !proxy_manager.set_health(&proxy_id, health.clone()) {
+        return;
+    }
+
+    services.notify_status_changed();
+
+    let Some(proxy) = proxy_manager.get_proxy(&proxy_id) else {
+        return;
+    };
+
+    match health {
+        ProxyHealth::Unhealthy { reason } => services.print(format!(
+            "[Unhealthy|{}] {}:{} - {}",
+            proxy.endpoint_name, proxy.address, proxy.port, reason
+        )),
+        _ => services.print(format!(
+            "[Healthy|{}] {}:{}",
+            proxy.endpoint_name, proxy.address, proxy.port
+        )),
+    }
+}
+
+/// Sends current health of the proxy to the server unless it was already sent during the current tunnel session.
+async fn report_health(services: &Arc<Services>, proxy_id: Uuid, state: &mut CheckState) {
+    let health = match services.get_proxy_manager().await.get_proxy(&proxy_id) {
+        Some(proxy) if proxy.health != ProxyHealth::Unknown => proxy.health.clone(),
+        _ => return,
+    };
+
+    let (tunnel_id, control) = {
+        let tunnel_data = services.get_tunnel_data().await;
+        (tunnel_data.get_tunnel_id(), tunnel_data.get_control())
+    };
+
+    let (Some(tunnel_id), Some(control)) = (tunnel_id, control) else {
+        return;
+    };
+
+    let reported = (tunnel_id, health);
+
+    if state.reported.as_ref() == Some(&reported) {
+        return;
+    }
+
+    match outgoing_requests::report_proxy_health(&control, proxy_id, reported.1.clone()).await {
+        Ok(()) => state.reported = Some(reported),
+        Err(e) => error!("Failed to report health of proxy '{}': {}", proxy_id, e),
+    }
+}
+
+/// Proxy is healthy if any of its targets passes the check. If the check has its own port, only
+/// the first target is checked.
+async fn run_check(proxy: &Proxy, health_check: &HealthCheckConfiguration) -> Result<(), String> {
+    let targets = match health_check.port {
+        Some(_) => std::slice::from_ref(proxy.targets.get_primary()),
+        None => proxy.targets.get_targets(),
+    };
+
+    let mut result = Ok(());
+
+    for target in targets {
+        result = run_target_check(target, health_check).await;
+
+        if result.is_ok() {
+            break;
+        }
+    }
+
+    result
+}
+
+async fn run_target_check(
+    target: &ForwardTarget,
+    health_check: &HealthCheckConfiguration,
+) -> Result<(), String> {
+    let port = health_check.get_port(target.port);
+
+    let connection = target
+        .connect(port)
+        .await
+        .map_err(|e| format!("Failed to connect to {}:{}: {}", target.address, port, e))?;
+
+    if health_check.check_type == HealthCheckType::Tcp {
+        return Ok(());
+    }
+
+    let mut stream = connection.into_stream().map_err(|e| e.to_string())?;
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}:{}\r\nUser-Agent: tunnelize\r\nConnection: close\r\n\r\n",
+        health_check.get_path(),
+        target.address,
+        port
+    );
+
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to send health check request: {e}"))?;
+
+    let mut status_line = String::new();
+
+    BufReader::new(stream)
+        .take(MAX_STATUS_LINE_LENGTH)
+        .read_line(&mut status_line)
+        .await
+        .map_err(|e| format!("Failed to read health check response: {e}"))?;
+
+    match parse_status_code(&status_line) {
+        Some(status) if (200..400).contains(&status) => Ok(()),
+        Some(status) => Err(format!("Health check returned status {status}")),
+        None => Err("Health check returned an invalid response".to_string()),
+    }
+}
+
+fn parse_status_code(status_line: &str) -> Option<u16> {
+    let mut parts = status_line.split_whitespace();
+
+    if !parts.next()?.starts_with("HTTP/") {
+        return None;
+    }
+
+    parts.next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status_code() {
+        assert_eq!(parse_status_code("HTTP/1.1 200 OK\r\n"), Some(200));
+        assert_eq!(
+            parse_status_code("HTTP/1.0 503 Service Unavailable"),
+            Some(503)
+        );
+        assert_eq!(parse_status_code("SSH-2.0-OpenSSH_9.6\r\n"), None);
+        assert_eq!(parse_status_code(""), None);
+    }
+}
@@ -4,10 +4,7 @@ use log::error;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{
-    common::connection::Connection,
-    tunnel::{outgoing_requests, services::Services},
-};
+use crate::tunnel::{outgoing_requests, services::Services};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct InitLinkRequest {
@@ -25,49 +22,41 @@ pub enum InitLinkResponse {
 pub async fn process_init_link(
     services: &Arc<Services>,
     request: InitLinkRequest,
-    response_stream: &mut Connection,
-) {
+) -> InitLinkResponse {
     let Some((address, port)) = services
         .get_proxy_manager()
         .await
         .get_forward_address(&request.proxy_id)
     else {
-        response_stream
-            .respond_message(&InitLinkResponse::Rejected {
-                reason: "Requested proxy not found".to_string(),
-            })
-            .await;
-        return;
+        return InitLinkResponse::Rejected {
+            reason: "Requested proxy not found".to_string(),
+        };
     };
 
     let address_port = format!("{address}:{port}");
 
+    if let Err(e) = outgoing_requests::start_link_session(
+        services.clone(),
+        request.proxy_id,
+        request.session_id,
+    )
+    .await
     {
-        if let Err(e) = outgoing_requests::start_link_session(
-            services.clone(),
-            request.proxy_id,
-            request.session_id,
-        )
-        .await
-        {
-            error!("Failed to start link session: {}", e);
+        error!("Failed to start link session: {}", e);
 
-            let message = if let ErrorKind::ConnectionRefused = e.kind() {
-                format!(
-                    "Connection refused, could not connect to source at {address_port}"
-                )
-            } else {
-                format!("Failed to start link session: {}", e.kind())
-            };
+        let message = if let ErrorKind::ConnectionRefused = e.kind() {
+            format!("Connection refused, could not connect to source at {address_port}")
+        } else {
+            format!("Failed to start link session: {}", e.kind())
+        };
 
-            response_stream
-                .respond_message(&InitLinkResponse::Rejected { reason: message })
-                .await;
-            return;
-        }
+        services
+            .get_tunnel_data()
+            .await
+            .record_error(format!("Failed to start link session: {e}"));
 
-        response_stream
-            .respond_message(&InitLinkResponse::Accepted)
-            .await;
-    };
+        return InitLinkResponse::Rejected { reason: message };
+    }
+
+    InitLinkResponse::Accepted
 }
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use init_link_session::process_init_link;
 
-use crate::{common::connection::Connection, create_data_enum};
+use crate::{common::control_channel::ControlResponder, create_data_enum};
 
 use super::services::Services;
 
@@ -15,13 +15,13 @@ create_data_enum!(TunnelRequestMessage, {
 });
 
 pub async fn handle(
-    services: &Arc<Services>,
-    stream: &mut Connection,
+    services: Arc<Services>,
+    responder: ControlResponder,
     message: TunnelRequestMessage,
 ) {
     match message {
         TunnelRequestMessage::InitLinkRequest(request) => {
-            process_init_link(services, request, stream).await;
+            responder.respond(&process_init_link(&services, request).await);
         }
     }
 }
@@ -0,0 +1,615 @@
+use std::{collections::VecDeque, sync::Arc, time::Instant};
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::common::metered_stream::StreamObserver;
+
+use super::{CapturedBody, CapturedExchange, CapturedRequest, CapturedResponse, Inspector};
+
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+const MAX_HEADERS: usize = 100;
+
+enum ChunkState {
+    Size,
+    Data(u64),
+    DataEnd,
+    Trailers,
+}
+
+enum Framing {
+    Length(u64),
+    Chunked(ChunkState),
+    UntilClose,
+}
+
+struct BodyReader {
+    framing: Framing,
+    body: CapturedBody,
+    max_body_size: usize,
+    line: Vec<u8>,
+}
+
+impl BodyReader {
+    fn new(framing: Framing, max_body_size: usize) -> Self {
+        Self {
+            framing,
+            body: CapturedBody::default(),
+            max_body_size,
+            line: Vec::new(),
+        }
+    }
+
+    fn append(&mut self, data: &[u8]) {
+        self.body.size += data.len() as u64;
+
+        let available = self.max_body_size.saturating_sub(self.body.data.len());
+
+        if data.len() > available {
+            self.body.truncated = true;
+        }
+
+        self.body
+            .data
+            .extend_from_slice(&data[..data.len().min(available)]);
+    }
+
+    /// Reads a line into the line buffer, returns the number of bytes consumed and the line if it
+    /// is complete.
+    fn read_line(&mut self, data: &[u8]) -> (usize, Option<String>) {
+        match data.iter().position(|byte| *byte == b'\n') {
+            Some(position) => {
+                self.line.extend_from_slice(&data[..position]);
+                let line = String::from_utf8_lossy(&self.line).trim().to_string();
+                self.line.clear();
+                (position + 1, Some(line))
+            }
+            None => {
+                self.line.extend_from_slice(data);
+                (data.len(), None)
+            }
+        }
+    }
+
+    /// Returns the number of bytes consumed and whether the body is complete. Returns an error if
+    /// the body cannot be parsed.
+    fn feed(&mut self, data: &[u8]) -> Result<(usize, bool), ()> {
+        let mut consumed = 0;
+
+        loop {
+            let input = &data[consumed..];
+
+            match self.framing {
+                Framing::Length(0) => return Ok((consumed, true)),
+                Framing::Length(remaining) => {
+                    let length = input.len().min(remaining as usize);
+                    self.append(&input[..length]);
+                    self.framing = Framing::Length(remaining - length as u64);
+                    consumed += length;
+
+                    if remaining > length as u64 {
+                        return Ok((consumed, false));
+                    }
+                }
+                Framing::UntilClose => {
+                    self.append(input);
+                    return Ok((data.len(), false));
+                }
+                Framing::Chunked(ChunkState::Data(remaining)) => {
+                    let length = input.len().min(remaining as usize);
+                    self.append(&input[..length]);
+                    consumed += length;
+
+                    if remaining > length as u64 {
+                        self.framing =
+                            Framing::Chunked(ChunkState::Data(remaining - length as u64));
+                        return Ok((consumed, false));
+                    }
+
+                    self.framing = Framing::Chunked(ChunkState::DataEnd);
+                }
+                Framing::Chunked(ref state) => {
+                    if input.is_empty() {
+                        return Ok((consumed, false));
+                    }
+
+                    let is_trailer = matches!(state, ChunkState::Trailers);
+                    let is_data_end = matches!(state, ChunkState::DataEnd);
+
+                    let (line_length, line) = self.read_line(input);
+                    consumed += line_length;
+
+                    let Some(line) = line else {
+                        return Ok((consumed, false));
+                    };
+
+                    if is_data_end {
+                        self.framing = Framing::Chunked(ChunkState::Size);
+                    } else if is_trailer {
+                        if line.is_empty() {
+                            return Ok((consumed, true));
+                        }
+                    } else {
+                        let size = line.split(';').next().unwrap_or_default().trim();
+                        let size = u64::from_str_radix(size, 16).map_err(|_| ())?;
+
+                        self.framing = Framing::Chunked(match size {
+                            0 => ChunkState::Trailers,
+                            size => ChunkState::Data(size),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+type Headers = Vec<(String, String)>;
+
+fn to_headers(headers: &[httparse::Header]) -> Headers {
+    headers
+        .iter()
+        .map(|header| {
+            (
+                header.name.to_string(),
+                String::from_utf8_lossy(header.value).to_string(),
+            )
+        })
+        .collect()
+}
+
+fn find_header<'a>(headers: &'a Headers, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn get_framing(headers: &Headers) -> Option<Framing> {
+    if let Some(encoding) = find_header(headers, "Transfer-Encoding")
+        && encoding.to_lowercase().contains("chunked")
+    {
+        return Some(Framing::Chunked(ChunkState::Size));
+    }
+
+    find_header(headers, "Content-Length")
+        .and_then(|length| length.trim().parse().ok())
+        .map(Framing::Length)
+}
+
+struct PendingRequest {
+    request: CapturedRequest,
+    timestamp: i64,
+    started_at: Instant,
+}
+
+enum Direction {
+    Head,
+    Body(BodyReader),
+    Stopped,
+}
+
+/// Parses both directions of an HTTP/1.1 connection and pairs requests with their responses.
+pub struct ExchangeParser {
+    proxy_id: Uuid,
+    replay_of: Option<Uuid>,
+    max_body_size: usize,
+    request_buffer: Vec<u8>,
+    request_state: Direction,
+    current_request: Option<PendingRequest>,
+    pending: VecDeque<PendingRequest>,
+    response_buffer: Vec<u8>,
+    response_state: Direction,
+    current_response: Option<CapturedResponse>,
+}
+
+impl ExchangeParser {
+    pub fn new(proxy_id: Uuid, replay_of: Option<Uuid>, max_body_size: usize) -> Self {
+        Self {
+            proxy_id,
+            replay_of,
+            max_body_size,
+            request_buffer: Vec::new(),
+            request_state: Direction::Head,
+            current_request: None,
+            pending: VecDeque::new(),
+            response_buffer: Vec::new(),
+            response_state: Direction::Head,
+            current_response: None,
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        matches!(self.response_state, Direction::Stopped)
+    }
+
+    pub fn feed_request(&mut self, data: &[u8]) {
+        if matches!(self.request_state, Direction::Stopped) {
+            return;
+        }
+
+        self.request_buffer.extend_from_slice(data);
+
+        loop {
+            match self.request_state {
+                Direction::Stopped => {
+                    self.request_buffer.clear();
+                    return;
+                }
+                Direction::Head => {
+                    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+                    let mut request = httparse::Request::new(&mut headers);
+
+                    let length = match request.parse(&self.request_buffer) {
+                        Ok(httparse::Status::Complete(length)) => length,
+                        Ok(httparse::Status::Partial)
+                            if self.request_buffer.len() <= MAX_HEAD_SIZE =>
+                        {
+                            return;
+                        }
+                        _ => {
+                            self.request_state = Direction::Stopped;
+                            continue;
+                        }
+                    };
+
+                    let headers = to_headers(request.headers);
+                    let framing = get_framing(&headers).unwrap_or(Framing::Length(0));
+
+                    self.current_request = Some(PendingRequest {
+                        request: CapturedRequest {
+                            method: request.method.unwrap_or_default().to_string(),
+                            path: request.path.unwrap_or_default().to_string(),
+                            headers,
+                            body: CapturedBody::default(),
+                        },
+                        timestamp: Utc::now().timestamp(),
+                        started_at: Instant::now(),
+                    });
+
+                    self.request_buffer.drain(..length);
+                    self.request_state =
+                        Direction::Body(BodyReader::new(framing, self.max_body_size));
+                }
+                Direction::Body(ref mut reader) => {
+                    let Ok((consumed, is_complete)) = reader.feed(&self.request_buffer) else {
+                        self.request_state = Direction::Stopped;
+                        continue;
+                    };
+
+                    self.request_buffer.drain(..consumed);
+
+                    if !is_complete {
+                        return;
+                    }
+
+                    let body = std::mem::take(&mut reader.body);
+
+                    if let Some(mut pending) = self.current_request.take() {
+                        pending.request.body = body;
+                        self.pending.push_back(pending);
+                    }
+
+                    self.request_state = Direction::Head;
+                }
+            }
+        }
+    }
+
+    pub fn feed_response(&mut self, data: &[u8]) -> Vec<CapturedExchange> {
+        let mut exchanges = Vec::new();
+
+        if matches!(self.response_state, Direction::Stopped) {
+            return exchanges;
+        }
+
+        self.response_buffer.extend_from_slice(data);
+
+        loop {
+            match self.response_state {
+                Direction::Stopped => {
+                    self.response_buffer.clear();
+                    return exchanges;
+                }
+                Direction::Head => {
+                    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+                    let mut response = httparse::Response::new(&mut headers);
+
+                    let length = match response.parse(&self.response_buffer) {
+                        Ok(httparse::Status::Complete(length)) => length,
+                        Ok(httparse::Status::Partial)
+                            if self.response_buffer.len() <= MAX_HEAD_SIZE =>
+                        {
+                            return exchanges;
+                        }
+                        _ => {
+                            self.response_state = Direction::Stopped;
+                            continue;
+                        }
+                    };
+
+                    let status = response.code.unwrap_or_default();
+                    let captured = CapturedResponse {
+                        status,
+                        reason: response.reason.unwrap_or_default().to_string(),
+                        headers: to_headers(response.headers),
+                        body: CapturedBody::default(),
+                    };
+
+                    self.response_buffer.drain(..length);
+
+                    if (100..200).contains(&status) && status != 101 {
+                        continue;
+                    }
+
+                    let is_head_request = self
+                        .pending
+                        .front()
+                        .is_some_and(|pending| pending.request.method == "HEAD");
+
+                    let framing = if is_head_request || status == 204 || status == 304 {
+                        Framing::Length(0)
+                    } else {
+                        get_framing(&captured.headers).unwrap_or(Framing::UntilClose)
+                    };
+
+                    self.current_response = Some(captured);
+
+                    if status == 101 {
+                        exchanges.extend(self.complete_response(CapturedBody::default()));
+                        self.response_state = Direction::Stopped;
+                        self.request_state = Direction::Stopped;
+                        continue;
+                    }
+
+                    self.response_state =
+                        Direction::Body(BodyReader::new(framing, self.max_body_size));
+                }
+                Direction::Body(ref mut reader) => {
+                    let Ok((consumed, is_complete)) = reader.feed(&self.response_buffer) else {
+                        self.response_state = Direction::Stopped;
+                        continue;
+                    };
+
+                    self.response_buffer.drain(..consumed);
+
+                    if !is_complete {
+                        return exchanges;
+                    }
+
+                    let body = std::mem::take(&mut reader.body);
+                    exchanges.extend(self.complete_response(body));
+                    self.response_state = Direction::Head;
+                }
+            }
+        }
+    }
+
+    fn complete_response(&mut self, body: CapturedBody) -> Option<CapturedExchange> {
+        let mut response = self.current_response.take()?;
+        response.body = body;
+
+        let pending = self.pending.pop_front()?;
+
+        Some(CapturedExchange {
+            id: Uuid::new_v4(),
+            proxy_id: self.proxy_id,
+            timestamp: pending.timestamp,
+            duration_ms: Some(pending.started_at.elapsed().as_millis() as u64),
+            replay_of: self.replay_of,
+            request: pending.request,
+            response: Some(response),
+        })
+    }
+
+    /// Completes parsing when the connection is closed. Requests which did not get a response are
+    /// returned without it.
+    pub fn finish(&mut self) -> Vec<CapturedExchange> {
+        let mut exchanges = Vec::new();
+
+        let state = std::mem::replace(&mut self.response_state, Direction::Stopped);
+
+        if let Direction::Body(reader) = state
+            && let Framing::UntilClose = reader.framing
+        {
+            exchanges.extend(self.complete_response(reader.body));
+        }
+
+        exchanges.extend(self.pending.drain(..).map(|pending| CapturedExchange {
+            id: Uuid::new_v4(),
+            proxy_id: self.proxy_id,
+            timestamp: pending.timestamp,
+            duration_ms: None,
+            replay_of: self.replay_of,
+            request: pending.request,
+            response: None,
+        }));
+
+        exchanges
+    }
+}
+
+/// Captures HTTP exchanges passing through a link session and records them in the inspector.
+pub struct HttpCapture {
+    parser: ExchangeParser,
+    inspector: Arc<Inspector>,
+}
+
+impl HttpCapture {
+    pub fn new(inspector: Arc<Inspector>, proxy_id: Uuid) -> Self {
+        Self {
+            parser: ExchangeParser::new(proxy_id, None, inspector.get_max_body_size()),
+            inspector,
+        }
+    }
+}
+
+impl StreamObserver for HttpCapture {
+    fn on_received(&mut self, data: &[u8]) {
+        self.parser.feed_request(data);
+    }
+
+    fn on_sent(&mut self, data: &[u8]) {
+        for exchange in self.parser.feed_response(data) {
+            self.inspector.record(exchange);
+        }
+    }
+}
+
+impl Drop for HttpCapture {
+    fn drop(&mut self) {
+        for exchange in self.parser.finish() {
+            self.inspector.record(exchange);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_parser(max_body_size: usize) -> ExchangeParser {
+        ExchangeParser::new(Uuid::new_v4(), None, max_body_size)
+    }
+
+    #[test]
+    fn test_parse_exchange_with_content_length() {
+        let mut parser = create_parser(1024);
+
+        parser.feed_request(
+            b"POST /hook?a=1 HTTP/1.1\r\nHost: app.local\r\nContent-Length: 5\r\n\r\nhel",
+        );
+        parser.feed_request(b"lo");
+
+        let exchanges =
+            parser.feed_response(b"HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok");
+
+        assert_eq!(exchanges.len(), 1);
+        let exchange = &exchanges[0];
+        assert_eq!(exchange.request.method, "POST");
+        assert_eq!(exchange.request.path, "/hook?a=1");
+        assert_eq!(
+            exchange.request.headers[0],
+            ("Host".to_string(), "app.local".to_string())
+        );
+        assert_eq!(exchange.request.body.data, b"hello");
+
+        let response = exchange.response.as_ref().unwrap();
+        assert_eq!(response.status, 201);
+        assert_eq!(response.reason, "Created");
+        assert_eq!(response.body.data, b"ok");
+        assert!(exchange.duration_ms.is_some());
+    }
+
+    #[test]
+    fn test_parse_chunked_response() {
+        let mut parser = create_parser(1024);
+
+        parser.feed_request(b"GET / HTTP/1.1\r\n\r\n");
+
+        let mut exchanges = parser.feed_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5;ext=1\r\nhello\r\n",
+        );
+        assert!(exchanges.is_empty());
+
+        exchanges.extend(parser.feed_response(b"6\r\n world\r\n0\r\n\r\n"));
+
+        assert_eq!(exchanges.len(), 1);
+        let response = exchanges[0].response.as_ref().unwrap();
+        assert_eq!(response.body.data, b"hello world");
+        assert_eq!(response.body.size, 11);
+    }
+
+    #[test]
+    fn test_parse_keep_alive_requests() {
+        let mut parser = create_parser(1024);
+
+        parser.feed_request(b"GET /one HTTP/1.1\r\n\r\nHEAD /two HTTP/1.1\r\n\r\n");
+
+        let exchanges = parser.feed_response(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\n1HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n",
+        );
+
+        assert_eq!(exchanges.len(), 2);
+        assert_eq!(exchanges[0].request.path, "/one");
+        assert_eq!(exchanges[1].request.path, "/two");
+        assert!(exchanges[1].response.as_ref().unwrap().body.data.is_empty());
+    }
+
+    #[test]
+    fn test_truncates_body() {
+        let mut parser = create_parser(4);
+
+        parser.feed_request(b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\n0123456789");
+        let exchanges = parser.feed_response(b"HTTP/1.1 204 No Content\r\n\r\n");
+
+        let body = &exchanges[0].request.body;
+        assert_eq!(body.data, b"0123");
+        assert_eq!(body.size, 10);
+        assert!(body.truncated);
+    }
+
+    #[test]
+    fn test_response_until_close() {
+        let mut parser = create_parser(1024);
+
+        parser.feed_request(b"GET / HTTP/1.1\r\n\r\n");
+        assert!(
+            parser
+                .feed_response(b"HTTP/1.0 200 OK\r\n\r\nbody")
+                .is_empty()
+        );
+
+        let exchanges = parser.finish();
+        assert_eq!(exchanges.len(), 1);
+        assert_eq!(exchanges[0].response.as_ref().unwrap().body.data, b"body");
+    }
+
+    #[test]
+    fn test_skips_interim_responses() {
+        let mut parser = create_parser(1024);
+
+        parser.feed_request(b"POST / HTTP/1.1\r\nContent-Length: 0\r\n\r\n");
+        let exchanges = parser.feed_response(
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+        );
+
+        assert_eq!(exchanges.len(), 1);
+        assert_eq!(exchanges[0].response.as_ref().unwrap().status, 200);
+    }
+
+    #[test]
+    fn test_stops_after_upgrade() {
+        let mut parser = create_parser(1024);
+
+        parser.feed_request(b"GET /ws HTTP/1.1\r\nUpgrade: websocket\r\n\r\n");
+        let exchanges =
+            parser.feed_response(b"HTTP/1.1 101 Switching Protocols\r\n\r\n\x81\x05hello");
+
+        assert_eq!(exchanges.len(), 1);
+        assert!(parser.is_stopped());
+
+        parser.feed_request(b"\x81\x05hello");
+        assert!(parser.finish().is_empty());
+    }
+
+    #[test]
+    fn test_request_without_response() {
+        let mut parser = create_parser(1024);
+
+        parser.feed_request(b"GET / HTTP/1.1\r\n\r\n");
+
+        let exchanges = parser.finish();
+        assert_eq!(exchanges.len(), 1);
+        assert!(exchanges[0].response.is_none());
+        assert!(exchanges[0].duration_ms.is_none());
+    }
+
+    #[test]
+    fn test_stops_on_invalid_data() {
+        let mut parser = create_parser(1024);
+
+        parser.feed_request(b"\x16\x03\x01 not http\r\n\r\n");
+        assert!(parser.finish().is_empty());
+    }
+}
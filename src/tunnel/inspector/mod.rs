@@ -0,0 +1,335 @@
+use std::{
+    collections::VecDeque,
+    io::{Error, ErrorKind},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, Result},
+    time::timeout,
+};
+use uuid::Uuid;
+
+use super::{configuration::InspectorConfiguration, services::Services};
+
+mod http_capture;
+
+use http_capture::ExchangeParser;
+pub use http_capture::HttpCapture;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CapturedBody {
+    #[serde(with = "lossy_text")]
+    pub data: Vec<u8>,
+    pub size: u64,
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CapturedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: CapturedBody,
+}
+
+impl CapturedRequest {
+    /// Returns the request as it should be sent again when replayed. Connection is closed after
+    /// the response so that the end of the response can always be found.
+    fn to_replay_bytes(&self) -> Vec<u8> {
+        let mut request = format!("{} {} HTTP/1.1\r\n", self.method, self.path);
+
+        for (name, value) in self.headers.iter() {
+            let skipped = [
+                "connection",
+                "keep-alive",
+                "content-length",
+                "transfer-encoding",
+            ];
+
+            if !skipped.contains(&name.to_lowercase().as_str()) {
+                request.push_str(&format!("{name}: {value}\r\n"));
+            }
+        }
+
+        if !self.body.data.is_empty() {
+            request.push_str(&format!("Content-Length: {}\r\n", self.body.data.len()));
+        }
+
+        request.push_str("Connection: close\r\n\r\n");
+
+        let mut bytes = request.into_bytes();
+        bytes.extend_from_slice(&self.body.data);
+        bytes
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CapturedResponse {
+    pub status: u16,
+    pub reason: String,
+    pub headers: Vec<(String, String)>,
+    pub body: CapturedBody,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CapturedExchange {
+    pub id: Uuid,
+    pub proxy_id: Uuid,
+    pub timestamp: i64,
+    pub duration_ms: Option<u64>,
+    pub replay_of: Option<Uuid>,
+    pub request: CapturedRequest,
+    pub response: Option<CapturedResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExchangeSummary {
+    pub id: Uuid,
+    pub proxy_id: Uuid,
+    pub timestamp: i64,
+    pub duration_ms: Option<u64>,
+    pub replay_of: Option<Uuid>,
+    pub method: String,
+    pub path: String,
+    pub status: Option<u16>,
+}
+
+impl From<&CapturedExchange> for ExchangeSummary {
+    fn from(exchange: &CapturedExchange) -> Self {
+        Self {
+            id: exchange.id,
+            proxy_id: exchange.proxy_id,
+            timestamp: exchange.timestamp,
+            duration_ms: exchange.duration_ms,
+            replay_of: exchange.replay_of,
+            method: exchange.request.method.clone(),
+            path: exchange.request.path.clone(),
+            status: exchange.response.as_ref().map(|response| response.status),
+        }
+    }
+}
+
+/// Keeps history of HTTP requests and responses passing through HTTP proxies.
+pub struct Inspector {
+    max_requests: usize,
+    max_body_size: usize,
+    exchanges: Mutex<VecDeque<CapturedExchange>>,
+}
+
+impl Inspector {
+    pub fn new(config: &InspectorConfiguration) -> Self {
+        Self {
+            max_requests: config.get_max_requests(),
+            max_body_size: config.get_max_body_size(),
+            exchanges: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn get_max_body_size(&self) -> usize {
+        self.max_body_size
+    }
+
+    pub fn record(&self, exchange: CapturedExchange) {
+        let mut exchanges = self.exchanges.lock().unwrap();
+
+        if exchanges.len() >= self.max_requests {
+            exchanges.pop_front();
+        }
+
+        exchanges.push_back(exchange);
+    }
+
+    /// Returns summaries of captured exchanges, newest first.
+    pub fn get_summaries(&self) -> Vec<ExchangeSummary> {
+        self.exchanges
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .map(ExchangeSummary::from)
+            .collect()
+    }
+
+    pub fn get_exchange(&self, id: &Uuid) -> Option<CapturedExchange> {
+        self.exchanges
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|exchange| exchange.id == *id)
+            .cloned()
+    }
+
+    pub fn clear(&self) {
+        self.exchanges.lock().unwrap().clear();
+    }
+}
+
+/// Sends a captured request again to the local server of its proxy. Replayed request and its
+/// response are recorded as a new exchange.
+pub async fn replay(services: &Arc<Services>, id: &Uuid) -> Result<CapturedExchange> {
+    let Some(inspector) = services.get_inspector() else {
+        return Err(Error::other("Request inspector is not enabled."));
+    };
+
+    let Some(exchange) = inspector.get_exchange(id) else {
+        return Err(Error::new(ErrorKind::NotFound, "Request not found."));
+    };
+
+    if exchange.request.body.truncated {
+        return Err(Error::other(
+            "Request body was truncated when captured, request cannot be replayed.",
+        ));
+    }
+
+    let Some(proxy) = services
+        .get_proxy_manager()
+        .await
+        .get_proxy(&exchange.proxy_id)
+        .cloned()
+    else {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            "Proxy of the request no longer exists.",
+        ));
+    };
+
+    let wait_time = Duration::from_secs(
+        services
+            .get_config()
+            .get_forward_connection_timeout_seconds(),
+    );
+
+    let mut parser = ExchangeParser::new(
+        exchange.proxy_id,
+        Some(exchange.id),
+        inspector.get_max_body_size(),
+    );
+    let request = exchange.request.to_replay_bytes();
+    parser.feed_request(&request);
+
+    let mut stream = timeout(wait_time, proxy.create_forward_connection())
+        .await
+        .map_err(|_| Error::new(ErrorKind::TimedOut, "Connecting to local server timed out."))??
+        .into_stream()?;
+
+    stream.write_all(&request).await?;
+
+    let mut exchanges = Vec::new();
+    let mut buffer = vec![0u8; 8192];
+
+    while exchanges.is_empty() && !parser.is_stopped() {
+        let read = timeout(wait_time, stream.read(&mut buffer))
+            .await
+            .map_err(|_| Error::new(ErrorKind::TimedOut, "Waiting for response timed out."))??;
+
+        if read == 0 {
+            break;
+        }
+
+        exchanges.extend(parser.feed_response(&buffer[..read]));
+    }
+
+    exchanges.extend(parser.finish());
+
+    let _ = stream.shutdown().await;
+
+    let Some(replayed) = exchanges.into_iter().next() else {
+        return Err(Error::other("Failed to capture replayed request."));
+    };
+
+    inspector.record(replayed.clone());
+
+    Ok(replayed)
+}
+
+mod lossy_text {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&String::from_utf8_lossy(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        Ok(String::deserialize(deserializer)?.into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_exchange(path: &str) -> CapturedExchange {
+        CapturedExchange {
+            id: Uuid::new_v4(),
+            proxy_id: Uuid::new_v4(),
+            timestamp: 0,
+            duration_ms: Some(1),
+            replay_of: None,
+            request: CapturedRequest {
+                method: "POST".to_string(),
+                path: path.to_string(),
+                headers: vec![
+                    ("Host".to_string(), "app.local".to_string()),
+                    ("Transfer-Encoding".to_string(), "chunked".to_string()),
+                    ("Connection".to_string(), "keep-alive".to_string()),
+                ],
+                body: CapturedBody {
+                    data: b"hello".to_vec(),
+                    size: 5,
+                    truncated: false,
+                },
+            },
+            response: None,
+        }
+    }
+
+    #[test]
+    fn test_record_keeps_latest_exchanges() {
+        let inspector = Inspector::new(&InspectorConfiguration {
+            max_requests: Some(2),
+            max_body_size: None,
+        });
+
+        inspector.record(create_exchange("/one"));
+        inspector.record(create_exchange("/two"));
+        inspector.record(create_exchange("/three"));
+
+        let summaries = inspector.get_summaries();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].path, "/three");
+        assert_eq!(summaries[1].path, "/two");
+
+        assert!(inspector.get_exchange(&summaries[0].id).is_some());
+
+        inspector.clear();
+        assert!(inspector.get_summaries().is_empty());
+    }
+
+    #[test]
+    fn test_to_replay_bytes() {
+        let request = create_exchange("/hook").request;
+
+        assert_eq!(
+            String::from_utf8(request.to_replay_bytes()).unwrap(),
+            "POST /hook HTTP/1.1\r\nHost: app.local\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello"
+        );
+    }
+
+    #[test]
+    fn test_body_serialized_as_text() {
+        let body = CapturedBody {
+            data: b"hello".to_vec(),
+            size: 5,
+            truncated: false,
+        };
+
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(json["data"], "hello");
+
+        let decoded: CapturedBody = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, body);
+    }
+}
@@ -1,28 +1,43 @@
-use configuration::TunnelConfiguration;
+use configuration::{ProxyConfiguration, TunnelConfiguration, TunnelProxy};
 use log::debug;
 use services::Services;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
 use std::sync::Arc;
 use tokio::io::Result;
 
 use tokio_util::sync::CancellationToken;
 
-use crate::common::cli::MonitorCommands;
+use crate::common::cli::{
+    MonitorCommands, ProxyType, QuickTunnelArgs, TunnelCommands, TunnelRunArgs,
+};
 use crate::common::tasks::start_cancel_listener;
-use crate::configuration::load_configuration;
+use crate::common::validate::Validation;
+use crate::configuration::{load_configuration, load_tunnel_configuration};
 
 mod client;
+mod config_watcher;
+mod control_socket;
 pub mod configuration;
+mod dashboard;
+mod exec;
+mod health_checker;
+mod inspector;
 pub mod incoming_requests;
 pub mod outgoing_requests;
+mod quick_tunnel;
 mod services;
+mod status_reporter;
 
 pub async fn process_monitor_command(
     command: MonitorCommands,
     configuration_file: Option<String>,
+    profile: Option<String>,
 ) -> Result<()> {
-    outgoing_requests::process_monitor_request(load_configuration(configuration_file)?, command)
-        .await?;
+    outgoing_requests::process_monitor_request(
+        load_tunnel_configuration(configuration_file, profile.as_deref())?,
+        command,
+    )
+    .await?;
 
     Ok(())
 }
@@ -36,12 +51,221 @@ pub async fn process_get_tunnel_config(configuration_file: Option<String>) -> Re
     Ok(())
 }
 
-pub async fn start(configuration_file: Option<String>) -> Result<()> {
-    let configuration: TunnelConfiguration = load_configuration(configuration_file)?;
+pub async fn process_tunnel_command(
+    command: TunnelCommands,
+    configuration_file: Option<String>,
+    profile: Option<String>,
+) -> Result<()> {
+    let configuration = load_tunnel_configuration(configuration_file, profile.as_deref())?;
+    let socket_path = configuration.get_control_socket_path();
+
+    match command {
+        TunnelCommands::Status => {
+            let status =
+                control_socket::send_request(&socket_path, control_socket::GetStatusRequest)
+                    .await?;
+
+            print_response(&status)
+        }
+        TunnelCommands::AddProxy {
+            proxy_type,
+            port,
+            address,
+            endpoint,
+            desired_name,
+            desired_port,
+            pool,
+        } => {
+            let endpoint_config = match proxy_type {
+                ProxyType::Http => ProxyConfiguration::Http { desired_name, pool },
+                ProxyType::Tcp => ProxyConfiguration::Tcp { desired_port, pool },
+                ProxyType::Udp => ProxyConfiguration::Udp {
+                    desired_port,
+                    bind_address: None,
+                    pool,
+                },
+            };
+
+            let proxy = TunnelProxy {
+                endpoint_name: endpoint
+                    .unwrap_or_else(|| endpoint_config.get_type_string().to_owned()),
+                address,
+                port,
+                endpoint_config,
+                tls: None,
+                health_check: None,
+                targets: None,
+                target_selection: None,
+                target_cooldown_seconds: None,
+            };
+
+            let validation = Validation::validate(&proxy);
+
+            if !validation.is_valid() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    validation.errors().join(", "),
+                ));
+            }
+
+            let response = control_socket::send_request(
+                &socket_path,
+                control_socket::AddProxyRequest {
+                    proxy: Box::new(proxy),
+                },
+            )
+            .await?;
+
+            print_response(&response)?;
+
+            match response {
+                control_socket::AddProxyResponse::Added { .. } => Ok(()),
+                control_socket::AddProxyResponse::Failed { reason } => Err(Error::other(reason)),
+            }
+        }
+        TunnelCommands::RemoveProxy { id } => {
+            let response = control_socket::send_request(
+                &socket_path,
+                control_socket::RemoveProxyRequest { proxy_id: id },
+            )
+            .await?;
+
+            print_response(&response)?;
+
+            match response {
+                control_socket::RemoveProxyResponse::Removed { .. } => Ok(()),
+                control_socket::RemoveProxyResponse::Failed { reason } => {
+                    Err(Error::other(reason))
+                }
+            }
+        }
+        TunnelCommands::Requests => {
+            let response =
+                control_socket::send_request(&socket_path, control_socket::ListRequestsRequest)
+                    .await?;
+
+            print_response(&response)?;
 
-    let services = Arc::new(Services::new(configuration));
+            match response {
+                control_socket::ListRequestsResponse::Listed { .. } => Ok(()),
+                control_socket::ListRequestsResponse::Failed { reason } => {
+                    Err(Error::other(reason))
+                }
+            }
+        }
+        TunnelCommands::Request { id } => {
+            let response = control_socket::send_request(
+                &socket_path,
+                control_socket::GetRequestRequest { request_id: id },
+            )
+            .await?;
+
+            print_response(&response)?;
+
+            match response {
+                control_socket::GetRequestResponse::Found { .. } => Ok(()),
+                control_socket::GetRequestResponse::Failed { reason } => Err(Error::other(reason)),
+            }
+        }
+        TunnelCommands::Replay { id } => {
+            let response = control_socket::send_request(
+                &socket_path,
+                control_socket::ReplayRequestRequest { request_id: id },
+            )
+            .await?;
+
+            print_response(&response)?;
+
+            match response {
+                control_socket::ReplayRequestResponse::Replayed { .. } => Ok(()),
+                control_socket::ReplayRequestResponse::Failed { reason } => {
+                    Err(Error::other(reason))
+                }
+            }
+        }
+    }
+}
+
+fn print_response<T: serde::Serialize>(response: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(response)?);
+
+    Ok(())
+}
+
+pub async fn start(
+    configuration_file: Option<String>,
+    profile: Option<String>,
+    run_args: TunnelRunArgs,
+) -> Result<()> {
+    let configuration =
+        load_tunnel_configuration(configuration_file.clone(), profile.as_deref())?;
+
+    run(configuration, configuration_file, profile, run_args).await
+}
+
+pub async fn start_quick(
+    proxy_type: ProxyType,
+    args: QuickTunnelArgs,
+    desired_name: Option<String>,
+    desired_port: Option<u16>,
+) -> Result<()> {
+    let run_args = args.run.clone();
+    let configuration =
+        quick_tunnel::create_configuration(proxy_type, args, desired_name, desired_port)
+            .await
+            .inspect_err(|e| eprintln!("Could not start tunnel: {e}"))?;
+
+    run(configuration, None, None, run_args).await
+}
+
+async fn run(
+    configuration: TunnelConfiguration,
+    configuration_file: Option<String>,
+    profile: Option<String>,
+    run_args: TunnelRunArgs,
+) -> Result<()> {
+    let mut configuration = configuration;
+
+    if let Some(status_file) = run_args.status_file {
+        configuration.status_file = Some(status_file);
+    }
+
+    let watch_config = configuration.get_watch_config();
+    let dashboard = configuration.dashboard.clone();
+
+    let services = Arc::new(Services::new(configuration)?.with_output(run_args.output));
     let cancel_token = CancellationToken::new();
 
+    let status_reporter_future = status_reporter::is_enabled(&services).then(|| {
+        tokio::spawn(status_reporter::start(services.clone(), cancel_token.clone()))
+    });
+
+    if watch_config {
+        tokio::spawn(config_watcher::start(
+            services.clone(),
+            configuration_file,
+            profile,
+            cancel_token.clone(),
+        ));
+    }
+
+    tokio::spawn(health_checker::start(services.clone(), cancel_token.clone()));
+
+    if let Some(dashboard) = dashboard {
+        tokio::spawn(dashboard::start(
+            services.clone(),
+            dashboard,
+            cancel_token.clone(),
+        ));
+    }
+
+    let control_socket_future =
+        tokio::spawn(control_socket::start(services.clone(), cancel_token.clone()));
+
+    let exec_future = run_args.exec.map(|command| {
+        tokio::spawn(exec::start(services.clone(), command, cancel_token.clone()))
+    });
+
     let server_future = {
         let services = services.clone();
         let cancel_token = cancel_token.clone();
@@ -59,14 +283,35 @@ pub async fn start(configuration_file: Option<String>) -> Result<()> {
 
     let cancel_future = tokio::spawn(async move { start_cancel_listener(cancel_token).await });
 
-    match tokio::try_join!(server_future, cancel_future) {
+    let result = tokio::try_join!(server_future, cancel_future);
+
+    let _ = control_socket_future.await;
+
+    if let Some(status_reporter_future) = status_reporter_future {
+        let _ = status_reporter_future.await;
+        status_reporter::report(&services, true).await;
+    }
+
+    if let Some(exec_future) = exec_future
+        && let Ok(Some(exit_code)) = exec_future.await
+    {
+        services.print(format!("Command exited with code {exit_code}, tunnel stopped."));
+
+        if exit_code != 0 {
+            std::process::exit(exit_code);
+        }
+
+        return Ok(());
+    }
+
+    match result {
         Ok((result, _)) => match result {
             Ok(_) => {
-                println!("Tunnel stopped.");
+                services.print("Tunnel stopped.");
                 Ok(())
             }
             Err(_) => {
-                println!("Error occurred while running the tunnel");
+                services.print("Error occurred while running the tunnel");
                 Err(Error::other(
                     "Error occurred in tunnel run.",
                 ))
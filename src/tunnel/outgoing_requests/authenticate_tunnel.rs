@@ -4,80 +4,122 @@ use log::error;
 use tokio::io::{self, Result};
 
 use crate::common::connection::Connection;
+use crate::common::key_pair::PrivateKey;
 use crate::server::endpoints::messages::ResolvedEndpointInfo;
-use crate::server::incoming_requests::{InitTunelRequest, InitTunnelResponse, InputProxy};
+use crate::server::incoming_requests::{
+    InitTunelRequest, InitTunnelResponse, InputProxy, SolveChallengeRequest,
+};
 
 use crate::tunnel::configuration::TunnelConfiguration;
-use crate::tunnel::services::Services;
+use crate::tunnel::services::{Proxy, Services};
 
+/// Returns whether the server agreed to multiplex link sessions over this connection.
 pub async fn authenticate_tunnel(
     services: &Arc<Services>,
     config: &Arc<TunnelConfiguration>,
     server: &mut Connection,
-) -> Result<()> {
-    let input_proxies = process_input_proxies(services, config).await;
+) -> Result<bool> {
+    let input_proxies = get_input_proxies(services).await;
+    let resume_token = services.get_tunnel_data().await.get_resume_token();
 
-    let auth_response: InitTunnelResponse = server
+    let private_key = match &config.private_key_path {
+        Some(path) => Some(PrivateKey::read(path)?),
+        None => None,
+    };
+
+    let mut auth_response: InitTunnelResponse = server
         .request_message(InitTunelRequest {
             name: config.name.clone(),
             tunnel_key: config.tunnel_key.clone(),
             admin_key: config.monitor_key.clone(),
             proxies: input_proxies.clone(),
+            resume_token,
+            multiplex: config.get_multiplex_links(),
+            public_key: private_key
+                .as_ref()
+                .map(|key| key.get_public_key().to_string()),
         })
         .await?;
 
+    if let InitTunnelResponse::Challenge { nonce } = &auth_response {
+        let Some(private_key) = &private_key else {
+            return Err(io::Error::other(
+                "Server sent a challenge, but no private key is set",
+            ));
+        };
+
+        auth_response = server
+            .request_message(SolveChallengeRequest {
+                signature: private_key.sign_challenge(nonce),
+            })
+            .await?;
+    }
+
     match auth_response {
         InitTunnelResponse::Accepted {
             tunnel_id,
             endpoint_info,
+            resume_token,
+            multiplex,
         } => {
-            services.get_tunnel_data().await.set_tunnel_id(tunnel_id);
+            {
+                let mut tunnel_data = services.get_tunnel_data().await;
+                tunnel_data.set_tunnel_id(tunnel_id);
+                tunnel_data.set_resume_token(resume_token);
+            }
 
-            let proxy_manager = services.get_proxy_manager().await;
+            let mut proxy_manager = services.get_proxy_manager().await;
 
-            for (proxy_id, endpoint_info) in endpoint_info.iter() {
-                let Some(proxy) = proxy_manager.get_proxy(proxy_id) else {
+            for (proxy_id, endpoint_info) in endpoint_info.into_iter() {
+                proxy_manager.set_endpoint_info(&proxy_id, endpoint_info.clone());
+
+                let Some(proxy) = proxy_manager.get_proxy(&proxy_id) else {
                     error!("Failed to get proxy for proxy_id: {}", proxy_id);
                     return Err(io::Error::other("Failed to get proxy"));
                 };
 
-                let endpoint_url = match endpoint_info {
-                    ResolvedEndpointInfo::Http(info) => info.assigned_url.clone(),
-                    ResolvedEndpointInfo::Tcp(info) => info.assigned_hostname.clone(),
-                    ResolvedEndpointInfo::Udp(info) => info.assigned_hostname.clone(),
-                };
-
-                println!(
-                    "[Forward|{}] {}:{} -> {}",
-                    proxy.endpoint_name, proxy.address, proxy.port, endpoint_url
-                );
+                print_proxy_forward(services, proxy, &endpoint_info);
             }
+
+            drop(proxy_manager);
+            services.notify_status_changed();
+
+            Ok(multiplex)
         }
-        InitTunnelResponse::Rejected { reason } => {
-            return Err(io::Error::other(reason));
+        InitTunnelResponse::Challenge { .. } => {
+            Err(io::Error::other("Server sent more than one challenge"))
         }
+        InitTunnelResponse::Rejected { reason } => Err(io::Error::other(reason)),
     }
-
-    Ok(())
 }
 
-async fn process_input_proxies(
-    services: &Arc<Services>,
-    config: &Arc<TunnelConfiguration>,
-) -> Vec<InputProxy> {
-    let mut proxy_manager = services.get_proxy_manager().await;
-    let mut results = vec![];
-    for proxy in config.proxies.iter() {
-        let proxy_id = proxy_manager.add_proxy(proxy);
+pub(super) fn print_proxy_forward(
+    services: &Services,
+    proxy: &Proxy,
+    endpoint_info: &ResolvedEndpointInfo,
+) {
+    services.print(format!(
+        "[Forward|{}] {}:{} -> {}",
+        proxy.endpoint_name,
+        proxy.address,
+        proxy.port,
+        endpoint_info.get_assigned_address()
+    ));
+}
 
-        results.push(InputProxy {
+async fn get_input_proxies(services: &Arc<Services>) -> Vec<InputProxy> {
+    services
+        .get_proxy_manager()
+        .await
+        .get_proxies()
+        .into_iter()
+        .map(|(proxy_id, proxy)| InputProxy {
             proxy_id,
             endpoint_name: proxy.endpoint_name.clone(),
             proxy: proxy.endpoint_config.clone(),
             forward_address: proxy.address.clone(),
             forward_port: proxy.port,
-        });
-    }
-
-    results
+            health: proxy.health.clone(),
+        })
+        .collect()
 }
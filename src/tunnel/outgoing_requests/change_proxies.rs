@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use log::info;
+use tokio::io::{self, Result};
+use uuid::Uuid;
+
+use crate::server::endpoints::messages::ResolvedEndpointInfo;
+use crate::server::incoming_requests::{
+    AddTunnelProxyRequest, AddTunnelProxyResponse, InputProxy, ProxyHealth,
+    RemoveTunnelProxyRequest, RemoveTunnelProxyResponse,
+};
+use crate::tunnel::configuration::TunnelProxy;
+use crate::tunnel::services::Services;
+
+use super::authenticate_tunnel::print_proxy_forward;
+
+/// Registers the proxy with the tunnel and, if connected, with the server.
+/// While disconnected the proxy is only registered locally and is sent to the server on reconnect.
+pub async fn add_proxy(
+    services: &Arc<Services>,
+    proxy: &TunnelProxy,
+) -> Result<(Uuid, Option<ResolvedEndpointInfo>)> {
+    let proxy_id = services.get_proxy_manager().await.add_proxy(proxy)?;
+    services.notify_status_changed();
+
+    let Some(control) = services.get_tunnel_data().await.get_control() else {
+        info!("Not connected to server, proxy will be registered on reconnect.");
+        return Ok((proxy_id, None));
+    };
+
+    let response = control
+        .request(AddTunnelProxyRequest {
+            proxy: InputProxy {
+                proxy_id,
+                endpoint_name: proxy.endpoint_name.clone(),
+                forward_address: proxy.address.clone(),
+                forward_port: proxy.port,
+                proxy: proxy.endpoint_config.clone(),
+                health: ProxyHealth::Unknown,
+            },
+        })
+        .await;
+
+    match response {
+        Ok(AddTunnelProxyResponse::Accepted { endpoint_info }) => {
+            let mut proxy_manager = services.get_proxy_manager().await;
+            proxy_manager.set_endpoint_info(&proxy_id, endpoint_info.clone());
+
+            if let Some(proxy) = proxy_manager.get_proxy(&proxy_id) {
+                print_proxy_forward(services, proxy, &endpoint_info);
+            }
+
+            services.notify_status_changed();
+
+            Ok((proxy_id, Some(endpoint_info)))
+        }
+        Ok(AddTunnelProxyResponse::Rejected { reason }) => {
+            services.get_proxy_manager().await.remove_proxy(&proxy_id);
+            services.notify_status_changed();
+            Err(io::Error::other(reason))
+        }
+        Err(e) => {
+            services.get_proxy_manager().await.remove_proxy(&proxy_id);
+            services.notify_status_changed();
+            Err(e)
+        }
+    }
+}
+
+pub async fn remove_proxy(services: &Arc<Services>, proxy_id: &Uuid) -> Result<()> {
+    let Some(proxy) = services.get_proxy_manager().await.remove_proxy(proxy_id) else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Proxy '{proxy_id}' not found"),
+        ));
+    };
+
+    services.notify_status_changed();
+
+    services.print(format!(
+        "[Removed|{}] {}:{}",
+        proxy.endpoint_name, proxy.address, proxy.port
+    ));
+
+    let Some(control) = services.get_tunnel_data().await.get_control() else {
+        return Ok(());
+    };
+
+    match control
+        .request(RemoveTunnelProxyRequest {
+            proxy_id: *proxy_id,
+        })
+        .await?
+    {
+        RemoveTunnelProxyResponse::Removed => Ok(()),
+        RemoveTunnelProxyResponse::Rejected { reason } => Err(io::Error::other(reason)),
+    }
+}
@@ -1,11 +1,15 @@
 mod authenticate_tunnel;
+mod change_proxies;
 mod process_monitor_request;
+mod report_proxy_health;
 mod send_heartbeat;
 mod start_link_session;
 mod tunnel_config;
 
 pub use authenticate_tunnel::authenticate_tunnel;
+pub use change_proxies::{add_proxy, remove_proxy};
 pub use process_monitor_request::process_monitor_request;
+pub use report_proxy_health::report_proxy_health;
 pub use send_heartbeat::send_heartbeat;
 pub use start_link_session::start_link_session;
 pub use tunnel_config::get_tunnel_config;
@@ -0,0 +1,21 @@
+use tokio::io::{self, Result};
+use uuid::Uuid;
+
+use crate::common::control_channel::ControlChannel;
+use crate::server::incoming_requests::{
+    ProxyHealth, UpdateProxyHealthRequest, UpdateProxyHealthResponse,
+};
+
+pub async fn report_proxy_health(
+    control: &ControlChannel,
+    proxy_id: Uuid,
+    health: ProxyHealth,
+) -> Result<()> {
+    match control
+        .request(UpdateProxyHealthRequest { proxy_id, health })
+        .await?
+    {
+        UpdateProxyHealthResponse::Updated => Ok(()),
+        UpdateProxyHealthResponse::Rejected { reason } => Err(io::Error::other(reason)),
+    }
+}
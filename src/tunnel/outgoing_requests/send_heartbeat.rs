@@ -2,21 +2,22 @@ use std::sync::Arc;
 
 use tokio::io::{self, Result};
 
-use crate::common::connection::Connection;
+use crate::common::control_channel::ControlChannel;
 use crate::server::incoming_requests::{HeartbeatRequest, HeartbeatResponse};
 
 use crate::tunnel::services::Services;
 
-pub async fn send_heartbeat(services: &Arc<Services>, server: &mut Connection) -> Result<()> {
+pub async fn send_heartbeat(services: &Arc<Services>, control: &ControlChannel) -> Result<()> {
     let Some(tunnel_id) = services.get_tunnel_data().await.get_tunnel_id() else {
         return Err(io::Error::other("Tunnel ID is not set"));
     };
 
     let Ok(response): std::result::Result<HeartbeatResponse, std::io::Error> =
-        server.request_message(HeartbeatRequest { tunnel_id }).await
+        control.request(HeartbeatRequest { tunnel_id }).await
     else {
         let mut tunnel_data = services.get_tunnel_data().await;
         tunnel_data.record_failed_heartbeat();
+        tunnel_data.record_error("Heartbeat request to server failed.".to_string());
 
         if tunnel_data.too_many_failed_heartbeats() {
             return Err(io::Error::other(
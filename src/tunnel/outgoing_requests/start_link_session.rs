@@ -5,11 +5,11 @@ use tokio::time::timeout;
 use uuid::Uuid;
 
 use crate::{
-    common::data_bridge::DataBridge,
+    common::{connection::Connection, data_bridge::DataBridge, metered_stream::MeteredStream},
     server::incoming_requests::{
         InitLinkRequest as ServerInitLinkRequest, InitLinkResponse as ServerInitLinkResponse,
     },
-    tunnel::services::Services,
+    tunnel::{configuration::ProxyConfiguration, inspector::HttpCapture, services::Services},
 };
 
 pub async fn start_link_session(
@@ -21,12 +21,23 @@ pub async fn start_link_session(
 
     info!("Starting link session.");
 
+    // Proxy is cloned so that the manager is not locked while connecting.
+    let Some(proxy) = services
+        .get_proxy_manager()
+        .await
+        .get_proxy(&proxy_id)
+        .cloned()
+    else {
+        error!("Failed to find proxy session with id: {}", proxy_id);
+        return Err(tokio::io::Error::new(
+            ErrorKind::NotFound,
+            "Failed to find proxy session",
+        ));
+    };
+
     let mut forward_connection = match timeout(
         Duration::from_secs(config.get_forward_connection_timeout_seconds()),
-        services
-            .get_proxy_manager()
-            .await
-            .create_forward_connection(&proxy_id),
+        proxy.create_forward_connection(),
     )
     .await
     {
@@ -44,7 +55,49 @@ pub async fn start_link_session(
         }
     };
 
-    let mut server_connection = config.create_tcp_client().await?;
+    let multiplexer = services.get_tunnel_data().await.get_multiplexer();
+
+    let server_connection = match multiplexer {
+        Some(multiplexer) => multiplexer.open_stream(session_id)?.into(),
+        None => connect_link(&services, session_id).await?,
+    };
+
+    let mut server_stream = MeteredStream::new(
+        server_connection.into_stream()?,
+        proxy.stats.transfer.clone(),
+    );
+
+    if let ProxyConfiguration::Http { .. } = proxy.endpoint_config
+        && let Some(inspector) = services.get_inspector()
+    {
+        server_stream =
+            server_stream.with_observer(Box::new(HttpCapture::new(inspector, proxy_id)));
+    }
+
+    let mut server_connection = Connection::from(server_stream);
+
+    proxy.stats.start_link();
+
+    tokio::spawn(async move {
+        if let Err(e) = forward_connection.bridge_to(&mut server_connection).await {
+            error!("Relay session failed: {}", e);
+            services
+                .get_tunnel_data()
+                .await
+                .record_error(format!("Relay session failed: {e}"));
+        }
+
+        forward_connection.shutdown().await;
+        server_connection.shutdown().await;
+
+        proxy.stats.end_link();
+    });
+
+    Ok(())
+}
+
+async fn connect_link(services: &Arc<Services>, session_id: Uuid) -> tokio::io::Result<Connection> {
+    let mut server_connection = services.get_config().create_tcp_client().await?;
 
     let Some(tunnel_id) = services.get_tunnel_data().await.get_tunnel_id() else {
         error!("Tunnel ID not found.");
@@ -65,14 +118,5 @@ pub async fn start_link_session(
         return Err(tokio::io::Error::other(reason));
     }
 
-    tokio::spawn(async move {
-        if let Err(e) = forward_connection.bridge_to(&mut server_connection).await {
-            error!("Relay session failed: {}", e);
-        }
-
-        forward_connection.shutdown().await;
-        server_connection.shutdown().await;
-    });
-
-    Ok(())
+    Ok(server_connection)
 }
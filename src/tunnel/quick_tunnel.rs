@@ -0,0 +1,292 @@
+use std::io::{Error, ErrorKind};
+
+use log::info;
+use tokio::io::Result;
+
+use crate::{
+    common::{
+        cli::{ProxyType, QuickTunnelArgs},
+        tcp_client::{ClientEncryption, parse_server_address},
+        validate::Validation,
+    },
+    server::{
+        configuration::PublicEndpointConfiguration,
+        incoming_requests::{ProcessConfigResponse, PublicEndpointConfig},
+    },
+};
+
+use super::{
+    configuration::{ProxyConfiguration, TunnelConfiguration, TunnelProxy},
+    outgoing_requests,
+};
+
+fn is_matching_type(config: &PublicEndpointConfiguration, proxy_type: ProxyType) -> bool {
+    matches!(
+        (config, proxy_type),
+        (PublicEndpointConfiguration::Http(_), ProxyType::Http)
+            | (PublicEndpointConfiguration::Tcp(_), ProxyType::Tcp)
+            | (PublicEndpointConfiguration::Udp(_), ProxyType::Udp)
+    )
+}
+
+fn get_type_name(proxy_type: ProxyType) -> &'static str {
+    match proxy_type {
+        ProxyType::Http => "HTTP",
+        ProxyType::Tcp => "TCP",
+        ProxyType::Udp => "UDP",
+    }
+}
+
+/// Picks the endpoint to use from the server's public endpoints. If no name is given, first
+/// endpoint of the matching type is used.
+fn select_endpoint(
+    endpoints: &[PublicEndpointConfig],
+    proxy_type: ProxyType,
+    endpoint_name: Option<&str>,
+) -> Result<String> {
+    let type_name = get_type_name(proxy_type);
+
+    if let Some(endpoint_name) = endpoint_name {
+        return match endpoints.iter().find(|e| e.name == endpoint_name) {
+            Some(endpoint) if is_matching_type(&endpoint.config, proxy_type) => {
+                Ok(endpoint.name.clone())
+            }
+            Some(_) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Endpoint '{endpoint_name}' is not a {type_name} endpoint."),
+            )),
+            None => Err(Error::new(
+                ErrorKind::NotFound,
+                format!("Endpoint '{endpoint_name}' does not exist on the server."),
+            )),
+        };
+    }
+
+    let mut matching = endpoints
+        .iter()
+        .filter(|e| is_matching_type(&e.config, proxy_type));
+
+    let Some(endpoint) = matching.next() else {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("Server does not have a {type_name} endpoint."),
+        ));
+    };
+
+    if matching.next().is_some() {
+        info!(
+            "Server has multiple {} endpoints, using '{}'. Use --endpoint to choose another one.",
+            type_name, endpoint.name
+        );
+    }
+
+    Ok(endpoint.name.clone())
+}
+
+fn create_base_configuration(
+    proxy_type: ProxyType,
+    args: &QuickTunnelArgs,
+) -> Result<TunnelConfiguration> {
+    let server = parse_server_address(&args.server, 3456)?;
+
+    Ok(TunnelConfiguration {
+        name: Some(format!(
+            "{}-{}",
+            get_type_name(proxy_type).to_lowercase(),
+            args.port
+        )),
+        server_address: server.host,
+        server_port: Some(server.port),
+        forward_connection_timeout_seconds: None,
+        encryption: Some(match args.tls || server.is_tls {
+            true => ClientEncryption::Tls {
+                ca_path: args.ca.clone(),
+                cert_path: None,
+                key_path: None,
+            },
+            false => ClientEncryption::None,
+        }),
+        tunnel_key: args.key.clone(),
+        monitor_key: None,
+        private_key_path: None,
+        reconnect: None,
+        multiplex_links: None,
+        watch_config: Some(false),
+        control_socket: None,
+        dashboard: None,
+        inspector: None,
+        status_file: None,
+        proxies: Vec::new(),
+    })
+}
+
+/// Builds tunnel configuration with a single proxy from command line arguments, using the
+/// server's public endpoint configuration to find the endpoint to connect to.
+pub async fn create_configuration(
+    proxy_type: ProxyType,
+    args: QuickTunnelArgs,
+    desired_name: Option<String>,
+    desired_port: Option<u16>,
+) -> Result<TunnelConfiguration> {
+    let mut configuration = create_base_configuration(proxy_type, &args)?;
+
+    let endpoints = match outgoing_requests::get_tunnel_config(configuration.clone()).await? {
+        ProcessConfigResponse::GetPublicEndpointConfig(endpoints) => endpoints,
+        ProcessConfigResponse::AccessDenied => {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Access denied. Please check your tunnel key.",
+            ));
+        }
+    };
+
+    let endpoint_name = select_endpoint(&endpoints, proxy_type, args.endpoint.as_deref())?;
+
+    configuration.proxies.push(TunnelProxy {
+        endpoint_name,
+        address: args.address,
+        port: args.port,
+        endpoint_config: match proxy_type {
+            ProxyType::Http => ProxyConfiguration::Http {
+                desired_name,
+                pool: None,
+            },
+            ProxyType::Tcp => ProxyConfiguration::Tcp {
+                desired_port,
+                pool: None,
+            },
+            ProxyType::Udp => ProxyConfiguration::Udp {
+                desired_port,
+                bind_address: None,
+                pool: None,
+            },
+        },
+        tls: None,
+        health_check: None,
+        targets: None,
+        target_selection: None,
+        target_cooldown_seconds: None,
+    });
+
+    let validation = Validation::validate(&configuration);
+
+    if !validation.is_valid() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            validation.errors().join(", "),
+        ));
+    }
+
+    Ok(configuration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::cli::TunnelRunArgs;
+    use crate::server::endpoints::{
+        tcp::configuration::TcpPublicEndpointConfig, udp::configuration::UdpPublicEndpointConfig,
+    };
+
+    fn create_endpoints() -> Vec<PublicEndpointConfig> {
+        let tcp = |name: &str| PublicEndpointConfig {
+            name: name.to_string(),
+            config: PublicEndpointConfiguration::Tcp(TcpPublicEndpointConfig {
+                address: "localhost".to_string(),
+                allow_desired_port: true,
+                reserve_ports_from: 4000,
+                reserve_ports_to: 4010,
+                allow_pools: false,
+            }),
+        };
+
+        vec![
+            tcp("tcp-main"),
+            tcp("tcp-backup"),
+            PublicEndpointConfig {
+                name: "udp".to_string(),
+                config: PublicEndpointConfiguration::Udp(UdpPublicEndpointConfig {
+                    address: "localhost".to_string(),
+                    allow_desired_port: true,
+                    reserve_ports_from: 5000,
+                    reserve_ports_to: 5010,
+                    allow_pools: false,
+                }),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_select_first_endpoint_of_type() {
+        let endpoints = create_endpoints();
+
+        assert_eq!(
+            select_endpoint(&endpoints, ProxyType::Tcp, None).unwrap(),
+            "tcp-main"
+        );
+        assert_eq!(
+            select_endpoint(&endpoints, ProxyType::Udp, None).unwrap(),
+            "udp"
+        );
+        assert_eq!(
+            select_endpoint(&endpoints, ProxyType::Http, None)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn test_select_endpoint_by_name() {
+        let endpoints = create_endpoints();
+
+        assert_eq!(
+            select_endpoint(&endpoints, ProxyType::Tcp, Some("tcp-backup")).unwrap(),
+            "tcp-backup"
+        );
+        assert_eq!(
+            select_endpoint(&endpoints, ProxyType::Udp, Some("tcp-backup"))
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            select_endpoint(&endpoints, ProxyType::Tcp, Some("missing"))
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn test_create_base_configuration() {
+        let configuration = create_base_configuration(
+            ProxyType::Http,
+            &QuickTunnelArgs {
+                port: 8080,
+                address: "localhost".to_string(),
+                server: "https://example.com:4000".to_string(),
+                key: Some("key".to_string()),
+                tls: false,
+                ca: None,
+                endpoint: None,
+                verbose: false,
+                run: TunnelRunArgs::default(),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(configuration.name.as_deref(), Some("http-8080"));
+        assert_eq!(configuration.server_address, "example.com");
+        assert_eq!(configuration.get_server_port(), 4000);
+        assert_eq!(
+            configuration.get_encryption(),
+            ClientEncryption::Tls {
+                ca_path: None,
+                cert_path: None,
+                key_path: None,
+            }
+        );
+        assert!(!configuration.get_watch_config());
+    }
+}
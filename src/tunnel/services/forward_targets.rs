@@ -0,0 +1,256 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use log::warn;
+use tokio::io::{Error, Result};
+use tokio::net::TcpStream;
+
+use crate::common::connection::Connection;
+use crate::common::protocol_socket::connect_to_address;
+use crate::tunnel::configuration::{TargetSelection, TunnelProxy};
+
+use super::proxy_manager::ProxyTls;
+
+pub struct ForwardTarget {
+    pub address: String,
+    pub port: u16,
+    tls: Option<ProxyTls>,
+}
+
+impl ForwardTarget {
+    /// Connects to the target on the given port, using TLS if it is configured for the proxy.
+    pub async fn connect(&self, port: u16) -> Result<Connection> {
+        let (stream, _) = connect_to_address::<TcpStream>(&self.address, port, ()).await?;
+
+        match &self.tls {
+            Some(tls) => tls.connect(stream).await,
+            None => Ok(Connection::from(stream)),
+        }
+    }
+}
+
+/// Forward targets of a proxy. Shared between clones of the proxy so that selection and cooldowns
+/// are kept between links.
+pub struct ForwardTargets {
+    targets: Vec<ForwardTarget>,
+    selection: TargetSelection,
+    cooldown: Duration,
+    next_index: AtomicUsize,
+    cooldown_until: Mutex<Vec<Option<Instant>>>,
+}
+
+impl ForwardTargets {
+    pub fn new(proxy: &TunnelProxy, primary_tls: Option<ProxyTls>) -> Result<Self> {
+        let targets: Vec<ForwardTarget> = proxy
+            .get_targets()
+            .into_iter()
+            .enumerate()
+            .map(|(index, target)| {
+                Ok(ForwardTarget {
+                    tls: match index {
+                        0 => primary_tls.clone(),
+                        _ => proxy
+                            .tls
+                            .as_ref()
+                            .map(|tls| ProxyTls::new(tls, &target.address))
+                            .transpose()?,
+                    },
+                    address: target.address,
+                    port: target.port,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            cooldown_until: Mutex::new(vec![None; targets.len()]),
+            targets,
+            selection: proxy.get_target_selection(),
+            cooldown: proxy.get_target_cooldown(),
+            next_index: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn get_primary(&self) -> &ForwardTarget {
+        &self.targets[0]
+    }
+
+    pub fn get_targets(&self) -> &[ForwardTarget] {
+        &self.targets
+    }
+
+    pub fn matches(&self, proxy: &TunnelProxy) -> bool {
+        let targets = proxy.get_targets();
+
+        self.selection == proxy.get_target_selection()
+            && self.cooldown == proxy.get_target_cooldown()
+            && self.targets.len() == targets.len()
+            && self
+                .targets
+                .iter()
+                .zip(targets.iter())
+                .all(|(a, b)| a.address == b.address && a.port == b.port)
+    }
+
+    /// Returns indexes of targets in the order they should be tried. Targets in cooldown are
+    /// tried last, only if all other targets failed.
+    fn get_order(&self) -> Vec<usize> {
+        let count = self.targets.len();
+
+        let start = match self.selection {
+            TargetSelection::Failover => 0,
+            TargetSelection::RoundRobin => self.next_index.fetch_add(1, Ordering::Relaxed) % count,
+        };
+
+        let now = Instant::now();
+        let cooldown_until = self.cooldown_until.lock().unwrap();
+
+        let (mut available, cooling): (Vec<usize>, Vec<usize>) = (0..count)
+            .map(|offset| (start + offset) % count)
+            .partition(|index| cooldown_until[*index].is_none_or(|until| until <= now));
+
+        available.extend(cooling);
+        available
+    }
+
+    fn set_cooldown(&self, index: usize, until: Option<Instant>) {
+        self.cooldown_until.lock().unwrap()[index] = until;
+    }
+
+    pub async fn connect(&self) -> Result<Connection> {
+        let mut last_error = None;
+
+        for index in self.get_order() {
+            let target = &self.targets[index];
+
+            match target.connect(target.port).await {
+                Ok(connection) => {
+                    self.set_cooldown(index, None);
+                    return Ok(connection);
+                }
+                Err(e) => {
+                    if self.targets.len() > 1 {
+                        warn!(
+                            "Forward target {}:{} failed, putting it in cooldown: {}",
+                            target.address, target.port, e
+                        );
+                    }
+
+                    self.set_cooldown(index, Some(Instant::now() + self.cooldown));
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| Error::other("No forward targets available")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::tunnel::configuration::{ProxyConfiguration, ProxyTarget};
+
+    fn create_proxy(ports: &[u16], selection: TargetSelection) -> TunnelProxy {
+        TunnelProxy {
+            endpoint_name: "tcp".to_string(),
+            address: "127.0.0.1".to_string(),
+            port: ports[0],
+            endpoint_config: ProxyConfiguration::Tcp {
+                desired_port: None,
+                pool: None,
+            },
+            tls: None,
+            health_check: None,
+            targets: Some(
+                ports[1..]
+                    .iter()
+                    .map(|port| ProxyTarget {
+                        address: "127.0.0.1".to_string(),
+                        port: *port,
+                    })
+                    .collect(),
+            ),
+            target_selection: Some(selection),
+            target_cooldown_seconds: None,
+        }
+    }
+
+    async fn get_closed_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    #[test]
+    fn test_failover_order() {
+        let targets = ForwardTargets::new(
+            &create_proxy(&[1000, 1001, 1002], TargetSelection::Failover),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(targets.get_order(), vec![0, 1, 2]);
+        assert_eq!(targets.get_order(), vec![0, 1, 2]);
+
+        targets.set_cooldown(0, Some(Instant::now() + Duration::from_secs(30)));
+        assert_eq!(targets.get_order(), vec![1, 2, 0]);
+
+        targets.set_cooldown(0, Some(Instant::now() - Duration::from_secs(1)));
+        assert_eq!(targets.get_order(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_round_robin_order() {
+        let targets = ForwardTargets::new(
+            &create_proxy(&[1000, 1001, 1002], TargetSelection::RoundRobin),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(targets.get_order(), vec![0, 1, 2]);
+        assert_eq!(targets.get_order(), vec![1, 2, 0]);
+
+        targets.set_cooldown(0, Some(Instant::now() + Duration::from_secs(30)));
+        assert_eq!(targets.get_order(), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_matches() {
+        let proxy = create_proxy(&[1000, 1001], TargetSelection::Failover);
+        let targets = ForwardTargets::new(&proxy, None).unwrap();
+
+        assert!(targets.matches(&proxy));
+        assert!(!targets.matches(&create_proxy(&[1000, 1002], TargetSelection::Failover)));
+        assert!(!targets.matches(&create_proxy(&[1000, 1001], TargetSelection::RoundRobin)));
+        assert!(!targets.matches(&create_proxy(&[1000], TargetSelection::Failover)));
+    }
+
+    #[tokio::test]
+    async fn test_connect_skips_refusing_target() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open_port = listener.local_addr().unwrap().port();
+        let closed_port = get_closed_port().await;
+
+        let targets = ForwardTargets::new(
+            &create_proxy(&[closed_port, open_port], TargetSelection::Failover),
+            None,
+        )
+        .unwrap();
+
+        assert!(targets.connect().await.is_ok());
+        assert_eq!(targets.get_order(), vec![1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_connect_fails_when_all_targets_refuse() {
+        let targets = ForwardTargets::new(
+            &create_proxy(&[get_closed_port().await], TargetSelection::Failover),
+            None,
+        )
+        .unwrap();
+
+        assert!(targets.connect().await.is_err());
+    }
+}
@@ -1,27 +1,53 @@
-use std::sync::Arc;
+use std::{fmt::Display, sync::Arc};
 
-use proxy_manager::ProxyManager;
-use tokio::sync::{Mutex, MutexGuard};
+pub use forward_targets::ForwardTarget;
+pub use proxy_manager::{LinkStats, Proxy, ProxyManager};
+use tokio::io::Result;
+use tokio::sync::{Mutex, MutexGuard, Notify};
+pub use tunnel_data::TunnelError;
 use tunnel_data::TunnelData;
 
 use super::configuration::TunnelConfiguration;
+use crate::common::cli::OutputFormat;
+use super::inspector::Inspector;
 
+mod forward_targets;
 mod proxy_manager;
 mod tunnel_data;
 
 pub struct Services {
     tunnel_data: Mutex<TunnelData>,
     proxy_manager: Mutex<ProxyManager>,
+    inspector: Option<Arc<Inspector>>,
     config: Arc<TunnelConfiguration>,
+    output: OutputFormat,
+    status_changed: Notify,
 }
 
 impl Services {
-    pub fn new(config: TunnelConfiguration) -> Self {
-        Self {
+    pub fn new(config: TunnelConfiguration) -> Result<Self> {
+        let mut proxy_manager = ProxyManager::new();
+
+        for proxy in config.proxies.iter() {
+            proxy_manager.add_proxy(proxy)?;
+        }
+
+        Ok(Self {
             tunnel_data: Mutex::new(TunnelData::new()),
-            proxy_manager: Mutex::new(ProxyManager::new()),
+            proxy_manager: Mutex::new(proxy_manager),
+            inspector: config
+                .inspector
+                .as_ref()
+                .map(|inspector| Arc::new(Inspector::new(inspector))),
             config: Arc::new(config),
-        }
+            output: OutputFormat::Text,
+            status_changed: Notify::new(),
+        })
+    }
+
+    pub fn with_output(mut self, output: OutputFormat) -> Self {
+        self.output = output;
+        self
     }
 
     pub async fn get_tunnel_data(&self) -> MutexGuard<'_, TunnelData> {
@@ -32,7 +58,31 @@ impl Services {
         self.proxy_manager.lock().await
     }
 
+    pub fn get_inspector(&self) -> Option<Arc<Inspector>> {
+        self.inspector.clone()
+    }
+
     pub fn get_config(&self) -> Arc<TunnelConfiguration> {
         self.config.clone()
     }
+
+    pub fn get_output(&self) -> OutputFormat {
+        self.output
+    }
+
+    /// Prints a message for the user. Messages are not printed when output is in JSON so that
+    /// only the status is written to stdout.
+    pub fn print(&self, message: impl Display) {
+        if self.output == OutputFormat::Text {
+            println!("{message}");
+        }
+    }
+
+    pub fn notify_status_changed(&self) {
+        self.status_changed.notify_one();
+    }
+
+    pub async fn wait_for_status_change(&self) {
+        self.status_changed.notified().await;
+    }
 }
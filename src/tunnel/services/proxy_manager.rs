@@ -1,35 +1,121 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 use log::error;
+use serde::{Deserialize, Serialize};
 use tokio::io::Result;
 use tokio::net::TcpStream;
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-use crate::common::protocol_socket::connect_to_address;
+use crate::common::encryption::ClientTlsEncryption;
+use crate::common::metered_stream::TransferStats;
 use crate::common::udp_client::UdpClient;
-use crate::tunnel::configuration::ProxyConfiguration;
+use crate::server::endpoints::messages::ResolvedEndpointInfo;
+use crate::server::incoming_requests::ProxyHealth;
+use crate::tunnel::services::forward_targets::ForwardTargets;
+use crate::tunnel::configuration::{
+    HealthCheckConfiguration, ProxyConfiguration, ProxyTlsConfiguration,
+};
 use crate::{common::connection::Connection, tunnel::configuration::TunnelProxy};
 
+#[derive(Clone)]
 pub struct Proxy {
     pub address: String,
     pub port: u16,
     pub endpoint_name: String,
+    pub endpoint_config: ProxyConfiguration,
     pub protocol: ProxyProtocol,
+    pub endpoint_info: Option<ResolvedEndpointInfo>,
+    pub tls: Option<ProxyTls>,
+    pub health_check: Option<HealthCheckConfiguration>,
+    pub health: ProxyHealth,
+    pub targets: Arc<ForwardTargets>,
+    pub stats: Arc<ProxyStats>,
+}
+
+#[derive(Default)]
+pub struct ProxyStats {
+    active_links: AtomicUsize,
+    total_links: AtomicU64,
+    pub transfer: Arc<TransferStats>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LinkStats {
+    pub active_links: usize,
+    pub total_links: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+}
+
+impl ProxyStats {
+    pub fn start_link(&self) {
+        self.active_links.fetch_add(1, Ordering::Relaxed);
+        self.total_links.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn end_link(&self) {
+        self.active_links.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn get_link_stats(&self) -> LinkStats {
+        let transfer = self.transfer.get_totals();
+
+        LinkStats {
+            active_links: self.active_links.load(Ordering::Relaxed),
+            total_links: self.total_links.load(Ordering::Relaxed),
+            bytes_received: transfer.bytes_received,
+            bytes_sent: transfer.bytes_sent,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ProxyTls {
+    pub config: ProxyTlsConfiguration,
+    pub server_name: String,
+    encryption: Arc<ClientTlsEncryption>,
+}
+
+impl ProxyTls {
+    pub fn new(config: &ProxyTlsConfiguration, address: &str) -> Result<Self> {
+        Ok(Self {
+            config: config.clone(),
+            server_name: config.get_server_name(address).to_owned(),
+            encryption: Arc::new(ClientTlsEncryption::new_upstream(
+                config.ca_path.clone(),
+                config.get_insecure_skip_verify(),
+            )?),
+        })
+    }
+
+    pub async fn connect(&self, stream: TcpStream) -> Result<Connection> {
+        self.encryption.connect(stream, &self.server_name).await
+    }
 }
 
 impl Proxy {
+    pub fn matches(&self, proxy: &TunnelProxy) -> bool {
+        self.address == proxy.address
+            && self.port == proxy.port
+            && self.endpoint_name == proxy.endpoint_name
+            && self.endpoint_config == proxy.endpoint_config
+            && self.tls.as_ref().map(|tls| &tls.config) == proxy.tls.as_ref()
+            && self.health_check == proxy.health_check
+            && self.targets.matches(proxy)
+    }
+
     pub async fn create_forward_connection(&self) -> Result<Connection> {
         Ok(match self.protocol {
-            ProxyProtocol::Tcp => {
-                match connect_to_address::<TcpStream>(&self.address, self.port, ()).await {
-                    Ok((stream, _)) => Connection::from(stream),
-                    Err(e) => {
-                        error!("Failed to connect to forward address: {}", e);
-                        return Err(e);
-                    }
+            ProxyProtocol::Tcp => match self.targets.connect().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    error!("Failed to connect to forward address: {}", e);
+                    return Err(e);
                 }
-            }
+            },
             ProxyProtocol::Udp { ref bind_address } => {
                 match UdpClient::new(
                     self.address.clone(),
@@ -50,6 +136,7 @@ impl Proxy {
     }
 }
 
+#[derive(Clone)]
 pub enum ProxyProtocol {
     Tcp,
     Udp { bind_address: Option<String> },
@@ -94,38 +181,78 @@ impl ProxyManager {
         self.proxy_map.get(id)
     }
 
-    pub fn add_proxy(&mut self, proxy: &TunnelProxy) -> Uuid {
+    pub fn get_proxies(&self) -> Vec<(Uuid, &Proxy)> {
+        let mut proxies: Vec<(Uuid, &Proxy)> =
+            self.proxy_map.iter().map(|(id, proxy)| (*id, proxy)).collect();
+
+        proxies.sort_by_key(|(id, _)| *id);
+
+        proxies
+    }
+
+    pub fn add_proxy(&mut self, proxy: &TunnelProxy) -> Result<Uuid> {
         let id = Uuid::new_v4();
 
+        let tls = proxy
+            .tls
+            .as_ref()
+            .map(|tls| ProxyTls::new(tls, &proxy.address))
+            .transpose()?;
+
         let proxy = Proxy {
             address: proxy.address.clone(),
             port: proxy.port,
             endpoint_name: proxy.endpoint_name.clone(),
+            endpoint_config: proxy.endpoint_config.clone(),
             protocol: ProxyProtocol::from(&proxy.endpoint_config),
+            endpoint_info: None,
+            targets: Arc::new(ForwardTargets::new(proxy, tls.clone())?),
+            tls,
+            health_check: proxy.health_check.clone(),
+            health: ProxyHealth::Unknown,
+            stats: Arc::new(ProxyStats::default()),
         };
 
         self.proxy_map.insert(id, proxy);
 
-        id
+        Ok(id)
+    }
+
+    pub fn remove_proxy(&mut self, id: &Uuid) -> Option<Proxy> {
+        self.proxy_map.remove(id)
     }
 
-    pub async fn create_forward_connection(&self, id: &Uuid) -> Result<Connection> {
-        if let Some(session) = self.proxy_map.get(id) {
-            return session.create_forward_connection().await;
+    pub fn set_endpoint_info(&mut self, id: &Uuid, endpoint_info: ResolvedEndpointInfo) {
+        if let Some(proxy) = self.proxy_map.get_mut(id) {
+            proxy.endpoint_info = Some(endpoint_info);
         }
+    }
+
+    /// Returns whether the health of the proxy changed.
+    pub fn set_health(&mut self, id: &Uuid, health: ProxyHealth) -> bool {
+        let Some(proxy) = self.proxy_map.get_mut(id) else {
+            return false;
+        };
 
-        error!("Failed to find proxy session with id: {}", id);
+        if proxy.health == health {
+            return false;
+        }
 
-        Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "Failed to find proxy session",
-        ))
+        proxy.health = health;
+        true
+    }
+
+    pub fn clear_endpoint_info(&mut self) {
+        for proxy in self.proxy_map.values_mut() {
+            proxy.endpoint_info = None;
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::server::endpoints::tcp::TcpEndpointInfo;
     use crate::tunnel::configuration::{ProxyConfiguration, TunnelProxy};
 
     fn create_test_proxy_configuration() -> TunnelProxy {
@@ -133,7 +260,15 @@ mod tests {
             address: "127.0.0.1".to_string(),
             port: 8080,
             endpoint_name: "test".to_string(),
-            endpoint_config: ProxyConfiguration::Tcp { desired_port: None },
+            endpoint_config: ProxyConfiguration::Tcp {
+                desired_port: None,
+                pool: None,
+            },
+            tls: None,
+            health_check: None,
+            targets: None,
+            target_selection: None,
+            target_cooldown_seconds: None,
         }
     }
 
@@ -141,16 +276,38 @@ mod tests {
     fn test_add_proxy() {
         let mut manager = ProxyManager::new();
         let proxy_config = create_test_proxy_configuration();
-        let id = manager.add_proxy(&proxy_config);
+        let id = manager.add_proxy(&proxy_config).unwrap();
 
         assert!(manager.get_proxy(&id).is_some());
     }
 
+    #[test]
+    fn test_add_proxy_with_bad_ca_path_fails() {
+        let ca_path = std::env::temp_dir().join(format!("tunnelize-ca-{}.pem", Uuid::new_v4()));
+        std::fs::write(&ca_path, "not a certificate").unwrap();
+
+        let mut manager = ProxyManager::new();
+        let mut proxy_config = create_test_proxy_configuration();
+        proxy_config.tls = Some(ProxyTlsConfiguration {
+            server_name: None,
+            ca_path: Some(ca_path.display().to_string()),
+            insecure_skip_verify: None,
+        });
+
+        assert!(manager.add_proxy(&proxy_config).is_err());
+        assert!(manager.get_proxies().is_empty());
+
+        proxy_config.tls.as_mut().unwrap().ca_path = Some("/nonexistent/ca.pem".to_string());
+        assert!(manager.add_proxy(&proxy_config).is_err());
+
+        std::fs::remove_file(&ca_path).unwrap();
+    }
+
     #[test]
     fn test_get_forward_address() {
         let mut manager = ProxyManager::new();
         let proxy_config = create_test_proxy_configuration();
-        let id = manager.add_proxy(&proxy_config);
+        let id = manager.add_proxy(&proxy_config).unwrap();
 
         let address = manager.get_forward_address(&id);
         assert!(address.is_some());
@@ -161,10 +318,125 @@ mod tests {
     fn test_get_proxy() {
         let mut manager = ProxyManager::new();
         let proxy_config = create_test_proxy_configuration();
-        let id = manager.add_proxy(&proxy_config);
+        let id = manager.add_proxy(&proxy_config).unwrap();
 
         let proxy = manager.get_proxy(&id);
         assert!(proxy.is_some());
         assert_eq!(proxy.unwrap().address, "127.0.0.1");
     }
+
+    #[test]
+    fn test_get_proxies() {
+        let mut manager = ProxyManager::new();
+        let proxy_config = create_test_proxy_configuration();
+        let first_id = manager.add_proxy(&proxy_config).unwrap();
+        let second_id = manager.add_proxy(&proxy_config).unwrap();
+
+        let ids: Vec<Uuid> = manager.get_proxies().iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&first_id));
+        assert!(ids.contains(&second_id));
+    }
+
+    #[test]
+    fn test_remove_proxy() {
+        let mut manager = ProxyManager::new();
+        let proxy_config = create_test_proxy_configuration();
+        let id = manager.add_proxy(&proxy_config).unwrap();
+
+        assert!(manager.remove_proxy(&id).is_some());
+        assert!(manager.remove_proxy(&id).is_none());
+        assert!(manager.get_proxy(&id).is_none());
+    }
+
+    #[test]
+    fn test_proxy_matches() {
+        let mut manager = ProxyManager::new();
+        let proxy_config = create_test_proxy_configuration();
+        let id = manager.add_proxy(&proxy_config).unwrap();
+        let proxy = manager.get_proxy(&id).unwrap();
+
+        assert!(proxy.matches(&proxy_config));
+        assert!(!proxy.matches(&TunnelProxy {
+            port: 9090,
+            ..create_test_proxy_configuration()
+        }));
+    }
+
+    #[test]
+    fn test_proxy_tls() {
+        let mut manager = ProxyManager::new();
+        let proxy_config = TunnelProxy {
+            tls: Some(ProxyTlsConfiguration {
+                server_name: Some("app.local".to_string()),
+                ca_path: None,
+                insecure_skip_verify: Some(true),
+            }),
+            ..create_test_proxy_configuration()
+        };
+        let id = manager.add_proxy(&proxy_config).unwrap();
+        let proxy = manager.get_proxy(&id).unwrap();
+
+        assert_eq!(proxy.tls.as_ref().unwrap().server_name, "app.local");
+        assert!(proxy.matches(&proxy_config));
+        assert!(!proxy.matches(&create_test_proxy_configuration()));
+    }
+
+    #[test]
+    fn test_set_health() {
+        let mut manager = ProxyManager::new();
+        let id = manager
+            .add_proxy(&create_test_proxy_configuration())
+            .unwrap();
+
+        assert_eq!(manager.get_proxy(&id).unwrap().health, ProxyHealth::Unknown);
+        assert!(manager.set_health(&id, ProxyHealth::Healthy));
+        assert!(!manager.set_health(&id, ProxyHealth::Healthy));
+        assert!(!manager.set_health(&Uuid::new_v4(), ProxyHealth::Healthy));
+        assert_eq!(manager.get_proxy(&id).unwrap().health, ProxyHealth::Healthy);
+    }
+
+    #[test]
+    fn test_endpoint_info() {
+        let mut manager = ProxyManager::new();
+        let id = manager
+            .add_proxy(&create_test_proxy_configuration())
+            .unwrap();
+
+        manager.set_endpoint_info(
+            &id,
+            ResolvedEndpointInfo::Tcp(TcpEndpointInfo {
+                assigned_hostname: "localhost:9000".to_string(),
+            }),
+        );
+        assert!(manager.get_proxy(&id).unwrap().endpoint_info.is_some());
+
+        manager.clear_endpoint_info();
+        assert!(manager.get_proxy(&id).unwrap().endpoint_info.is_none());
+    }
+
+    #[test]
+    fn test_proxy_stats() {
+        let mut manager = ProxyManager::new();
+        let id = manager
+            .add_proxy(&create_test_proxy_configuration())
+            .unwrap();
+        let stats = manager.get_proxy(&id).unwrap().stats.clone();
+
+        stats.start_link();
+        stats.start_link();
+        stats.end_link();
+        stats.transfer.add_received(10);
+        stats.transfer.add_sent(20);
+
+        assert_eq!(
+            stats.get_link_stats(),
+            LinkStats {
+                active_links: 1,
+                total_links: 2,
+                bytes_received: 10,
+                bytes_sent: 20,
+            }
+        );
+    }
 }
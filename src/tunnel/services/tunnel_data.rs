@@ -1,8 +1,27 @@
+use std::collections::VecDeque;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::common::{control_channel::ControlChannel, multiplexer::Multiplexer};
+
 pub struct TunnelData {
     pub tunnel_id: Option<Uuid>,
+    pub resume_token: Option<String>,
+    pub multiplexer: Option<Multiplexer>,
+    pub control: Option<ControlChannel>,
     pub failed_heartbeats: u16,
+    pub last_heartbeat_timestamp: Option<i64>,
+    pub recent_errors: VecDeque<TunnelError>,
+}
+
+const MAX_RECENT_ERRORS: usize = 20;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TunnelError {
+    pub timestamp: i64,
+    pub message: String,
 }
 
 impl Default for TunnelData {
@@ -15,24 +34,61 @@ impl TunnelData {
     pub fn new() -> Self {
         Self {
             tunnel_id: None,
+            resume_token: None,
+            multiplexer: None,
+            control: None,
             failed_heartbeats: 0,
+            last_heartbeat_timestamp: None,
+            recent_errors: VecDeque::new(),
         }
     }
 
     pub fn set_tunnel_id(&mut self, tunnel_id: Uuid) {
-        if self.tunnel_id.is_some() {
-            panic!("Tunnel ID already set");
-        }
-
         self.tunnel_id = Some(tunnel_id);
+        self.failed_heartbeats = 0;
+    }
+
+    pub fn clear_tunnel_id(&mut self) {
+        self.tunnel_id = None;
+        self.failed_heartbeats = 0;
+        self.control = None;
+
+        if let Some(multiplexer) = self.multiplexer.take() {
+            multiplexer.shutdown();
+        }
     }
 
     pub fn get_tunnel_id(&self) -> Option<Uuid> {
         self.tunnel_id
     }
 
+    pub fn set_resume_token(&mut self, resume_token: Option<String>) {
+        self.resume_token = resume_token;
+    }
+
+    pub fn get_resume_token(&self) -> Option<String> {
+        self.resume_token.clone()
+    }
+
+    pub fn set_multiplexer(&mut self, multiplexer: Multiplexer) {
+        self.multiplexer = Some(multiplexer);
+    }
+
+    pub fn get_multiplexer(&self) -> Option<Multiplexer> {
+        self.multiplexer.clone()
+    }
+
+    pub fn set_control(&mut self, control: ControlChannel) {
+        self.control = Some(control);
+    }
+
+    pub fn get_control(&self) -> Option<ControlChannel> {
+        self.control.clone()
+    }
+
     pub fn record_success_heartbeat(&mut self) {
         self.failed_heartbeats = 0;
+        self.last_heartbeat_timestamp = Some(Utc::now().timestamp());
     }
 
     pub fn record_failed_heartbeat(&mut self) {
@@ -42,6 +98,21 @@ impl TunnelData {
     pub fn too_many_failed_heartbeats(&self) -> bool {
         self.failed_heartbeats >= 5
     }
+
+    pub fn record_error(&mut self, message: String) {
+        if self.recent_errors.len() >= MAX_RECENT_ERRORS {
+            self.recent_errors.pop_front();
+        }
+
+        self.recent_errors.push_back(TunnelError {
+            timestamp: Utc::now().timestamp(),
+            message,
+        });
+    }
+
+    pub fn get_recent_errors(&self) -> Vec<TunnelError> {
+        self.recent_errors.iter().rev().cloned().collect()
+    }
 }
 
 #[cfg(test)]
@@ -70,10 +141,25 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Tunnel ID already set")]
     fn test_set_tunnel_id_twice() {
         let mut tunnel_data = create_tunnel_data_with_id();
-        tunnel_data.set_tunnel_id(Uuid::new_v4());
+        tunnel_data.record_failed_heartbeat();
+
+        let tunnel_id = Uuid::new_v4();
+        tunnel_data.set_tunnel_id(tunnel_id);
+
+        assert_eq!(tunnel_data.tunnel_id, Some(tunnel_id));
+        assert_eq!(tunnel_data.failed_heartbeats, 0);
+    }
+
+    #[test]
+    fn test_clear_tunnel_id() {
+        let mut tunnel_data = create_tunnel_data_with_id();
+        tunnel_data.record_failed_heartbeat();
+        tunnel_data.clear_tunnel_id();
+
+        assert!(tunnel_data.get_tunnel_id().is_none());
+        assert_eq!(tunnel_data.failed_heartbeats, 0);
     }
 
     #[test]
@@ -82,9 +168,45 @@ mod tests {
         assert!(tunnel_data.get_tunnel_id().is_some());
     }
 
+    #[test]
+    fn test_clear_tunnel_id_keeps_resume_token() {
+        let mut tunnel_data = create_tunnel_data_with_id();
+        tunnel_data.set_resume_token(Some("token".to_string()));
+        tunnel_data.clear_tunnel_id();
+
+        assert_eq!(tunnel_data.get_resume_token(), Some("token".to_string()));
+    }
+
     #[test]
     fn test_get_tunnel_id_none() {
         let tunnel_data = TunnelData::new();
         assert!(tunnel_data.get_tunnel_id().is_none());
     }
+
+    #[test]
+    fn test_record_success_heartbeat() {
+        let mut tunnel_data = create_tunnel_data_with_id();
+        tunnel_data.record_failed_heartbeat();
+        tunnel_data.record_success_heartbeat();
+
+        assert_eq!(tunnel_data.failed_heartbeats, 0);
+        assert!(tunnel_data.last_heartbeat_timestamp.is_some());
+    }
+
+    #[test]
+    fn test_record_error_keeps_latest_errors() {
+        let mut tunnel_data = TunnelData::new();
+
+        for index in 0..MAX_RECENT_ERRORS + 5 {
+            tunnel_data.record_error(format!("error {index}"));
+        }
+
+        let errors = tunnel_data.get_recent_errors();
+        assert_eq!(errors.len(), MAX_RECENT_ERRORS);
+        assert_eq!(
+            errors[0].message,
+            format!("error {}", MAX_RECENT_ERRORS + 4)
+        );
+        assert_eq!(errors[MAX_RECENT_ERRORS - 1].message, "error 5");
+    }
 }
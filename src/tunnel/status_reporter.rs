@@ -0,0 +1,157 @@
+use std::{io::Result, path::Path, sync::Arc};
+
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    common::{atomic_file::write_atomic, cli::OutputFormat},
+    server::{endpoints::messages::ResolvedEndpointInfo, incoming_requests::ProxyHealth},
+};
+
+use super::services::Services;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Stopped,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StatusReport {
+    pub name: Option<String>,
+    pub tunnel_id: Option<Uuid>,
+    pub state: ConnectionState,
+    pub server_address: String,
+    pub server_port: u16,
+    pub proxies: Vec<ProxyReport>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProxyReport {
+    pub id: Uuid,
+    pub endpoint_name: String,
+    pub forward_address: String,
+    pub forward_port: u16,
+    pub endpoint_info: Option<ResolvedEndpointInfo>,
+    pub health: ProxyHealth,
+}
+
+pub fn is_enabled(services: &Arc<Services>) -> bool {
+    services.get_output() == OutputFormat::Json || services.get_config().status_file.is_some()
+}
+
+async fn get_report(services: &Arc<Services>, is_stopped: bool) -> StatusReport {
+    let config = services.get_config();
+    let tunnel_id = services.get_tunnel_data().await.get_tunnel_id();
+
+    let proxies = services
+        .get_proxy_manager()
+        .await
+        .get_proxies()
+        .into_iter()
+        .map(|(id, proxy)| ProxyReport {
+            id,
+            endpoint_name: proxy.endpoint_name.clone(),
+            forward_address: proxy.address.clone(),
+            forward_port: proxy.port,
+            endpoint_info: proxy.endpoint_info.clone(),
+            health: proxy.health.clone(),
+        })
+        .collect();
+
+    StatusReport {
+        name: config.name.clone(),
+        tunnel_id,
+        state: match (is_stopped, tunnel_id.is_some()) {
+            (true, _) => ConnectionState::Stopped,
+            (false, true) => ConnectionState::Connected,
+            (false, false) => ConnectionState::Connecting,
+        },
+        server_address: config.server_address.clone(),
+        server_port: config.get_server_port(),
+        proxies,
+    }
+}
+
+async fn write_status_file(path: &Path, report: &StatusReport) -> Result<()> {
+    write_atomic(path, &serde_json::to_vec_pretty(report)?).await
+}
+
+/// Prints the status in JSON output mode and writes it into the status file, if configured.
+pub async fn report(services: &Arc<Services>, is_stopped: bool) {
+    let report = get_report(services, is_stopped).await;
+
+    if services.get_output() == OutputFormat::Json {
+        match serde_json::to_string(&report) {
+            Ok(json) => println!("{json}"),
+            Err(e) => error!("Failed to serialize tunnel status: {}", e),
+        }
+    }
+
+    if let Some(status_file) = &services.get_config().status_file
+        && let Err(e) = write_status_file(Path::new(status_file), &report).await
+    {
+        error!("Failed to write status file '{}': {}", status_file, e);
+    }
+}
+
+pub async fn start(services: Arc<Services>, cancel_token: CancellationToken) {
+    report(&services, false).await;
+
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                debug!("Status reporter stopped.");
+                return;
+            }
+            _ = services.wait_for_status_change() => {
+                report(&services, false).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::endpoints::tcp::TcpEndpointInfo;
+
+    #[tokio::test]
+    async fn test_write_status_file() {
+        let path = std::env::temp_dir().join(format!("tunnelize-status-{}.json", Uuid::new_v4()));
+
+        let report = StatusReport {
+            name: Some("test".to_string()),
+            tunnel_id: Some(Uuid::new_v4()),
+            state: ConnectionState::Connected,
+            server_address: "localhost".to_string(),
+            server_port: 3456,
+            proxies: vec![ProxyReport {
+                id: Uuid::new_v4(),
+                endpoint_name: "tcp".to_string(),
+                forward_address: "localhost".to_string(),
+                forward_port: 22,
+                endpoint_info: Some(ResolvedEndpointInfo::Tcp(TcpEndpointInfo {
+                    assigned_hostname: "localhost:4000".to_string(),
+                })),
+                health: ProxyHealth::Unknown,
+            }],
+        };
+
+        write_status_file(&path, &report).await.unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(written["state"], "connected");
+        assert_eq!(
+            written["proxies"][0]["endpoint_info"]["assigned_hostname"],
+            "localhost:4000"
+        );
+    }
+}